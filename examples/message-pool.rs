@@ -0,0 +1,95 @@
+//! Benchmarks heap allocations when receiving with and without a pool.
+//!
+//! A counting global allocator records every allocation made by Rust code.
+//! Allocations made by libzmq itself go through `malloc`, and are not
+//! counted.
+extern crate futures;
+extern crate tokio_core;
+extern crate zmq_tokio;
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::io;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+use futures::{future, Async, Future};
+use tokio_core::reactor::Core;
+
+use zmq_tokio::{Context, Socket, SocketRecv, PULL, PUSH};
+use zmq_tokio::pool::MessagePool;
+
+const TEST_ADDR: &str = "inproc://message-pool";
+const COUNT: usize = 100_000;
+const PAYLOAD: [u8; 256] = [0x2a; 256];
+
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+// Runs `COUNT` round-trips, printing the allocations made per message.
+fn bench<F, R>(name: &str, reactor: &mut Core, sendr: &Socket, mut recv: F)
+where
+    F: FnMut() -> R,
+    R: Future<Error = io::Error>,
+{
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    let start = Instant::now();
+    for _ in 0..COUNT {
+        let _ = reactor.run(sendr.send(&PAYLOAD[..]).and_then(|_| recv())).unwrap();
+    }
+    let elapsed = start.elapsed();
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - allocations;
+    println!(
+        "{}: {:.2} allocations per message, {} messages in {:?}",
+        name,
+        allocations as f64 / COUNT as f64,
+        COUNT,
+        elapsed
+    );
+}
+
+fn main() {
+    let mut reactor = Core::new().unwrap();
+    let context = Context::new();
+
+    let recvr = context.socket(PULL, &reactor.handle()).unwrap();
+    let _ = recvr.bind(TEST_ADDR).unwrap();
+
+    let sendr = context.socket(PUSH, &reactor.handle()).unwrap();
+    let _ = sendr.connect(TEST_ADDR).unwrap();
+
+    // Without a pool, every received frame is copied into a fresh `Vec<u8>`.
+    bench("unpooled", &mut reactor, &sendr, || {
+        future::poll_fn(|| match SocketRecv::recv_bytes(&recvr, 0) {
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => Ok(Async::NotReady),
+            Err(e) => Err(e),
+            Ok(bytes) => Ok(Async::Ready(bytes)),
+        })
+    });
+
+    // With a pool, frames are received into buffers that go back to the
+    // pool once dropped.
+    let pool = MessagePool::new(16, 1024);
+    bench("pooled", &mut reactor, &sendr, || recvr.recv_pooled(&pool));
+    println!(
+        "pooled: {} buffers allocated, {} reused",
+        pool.created(),
+        pool.reused()
+    );
+
+    ::std::process::exit(0);
+}
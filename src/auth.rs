@@ -0,0 +1,403 @@
+//! ZAP authentication.
+//!
+//! ØMQ delegates the authentication of incoming connections to a handler
+//! bound on the `inproc://zeromq.zap.01` endpoint of the same context,
+//! following the ZeroMQ Authentication Protocol. An `Authenticator` is such
+//! a handler, which asks a `Verifier` whether to accept each connection.
+//!
+//! The peers it accepts are kept in a `PeerRegistry`, by address, along
+//! with the security mechanism they used, so that the connections reported
+//! by a `Monitor` can be matched to them.
+//!
+//! An `AccessList` filters requests before they reach the `Verifier`, by IP
+//! address and by CURVE public key. It can be updated at any time, through
+//! any of its clones, and the changes apply to the next connections.
+//!
+//! See https://rfc.zeromq.org/spec:27/ZAP/
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::io;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+
+use futures::{Async, Future, Poll, Stream};
+use tokio_core::reactor::Handle;
+use zmq::{self, Message};
+
+use super::{Context, Multipart, Socket, REP};
+use super::monitor::MonitorEvent;
+use super::sink::flush;
+
+/// Endpoint of the ZAP handler of a context.
+pub const ZAP_ENDPOINT: &str = "inproc://zeromq.zap.01";
+
+const ZAP_VERSION: &[u8] = b"1.0";
+
+/// A ZAP request, for authenticating a connection.
+#[derive(Clone, Debug)]
+pub struct ZapRequest {
+    /// The ZAP domain of the socket being connected to.
+    pub domain: String,
+    /// The address of the peer.
+    pub address: String,
+    /// The routing id of the peer.
+    pub identity: Vec<u8>,
+    /// The security mechanism, such as `"NULL"`, `"PLAIN"`, or `"CURVE"`.
+    pub mechanism: String,
+    /// The credentials of the peer, which depend on the mechanism.
+    pub credentials: Vec<Vec<u8>>,
+}
+
+impl ZapRequest {
+    // Parses the frames that follow the version and request id.
+    fn parse(msgs: &[Message]) -> io::Result<ZapRequest> {
+        if msgs.len() < 4 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "invalid ZAP request"));
+        }
+        Ok(ZapRequest {
+            domain: String::from_utf8_lossy(&msgs[0]).into_owned(),
+            address: String::from_utf8_lossy(&msgs[1]).into_owned(),
+            identity: msgs[2].to_vec(),
+            mechanism: String::from_utf8_lossy(&msgs[3]).into_owned(),
+            credentials: msgs[4..].iter().map(|m| m.to_vec()).collect(),
+        })
+    }
+}
+
+/// An accepted peer, as authenticated.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PeerInfo {
+    /// The security mechanism the peer used, such as `"PLAIN"`.
+    pub mechanism: String,
+    /// The routing id of the peer.
+    pub identity: Vec<u8>,
+    /// The user id given by the `Verifier`.
+    pub user_id: String,
+}
+
+/// The peers accepted by an `Authenticator`, by address. It is cheap to
+/// clone, and every clone shares the same peers.
+///
+/// ZAP requests only carry the IP address of peers, so only the last peer
+/// accepted from a given address is kept.
+#[derive(Clone, Debug, Default)]
+pub struct PeerRegistry {
+    peers: Arc<Mutex<HashMap<String, PeerInfo>>>,
+}
+
+impl PeerRegistry {
+    /// Returns the last peer accepted from the given address.
+    pub fn get(&self, address: &str) -> Option<PeerInfo> {
+        self.peers.lock().unwrap().get(address).cloned()
+    }
+
+    /// Returns the peer of the connection a monitor event is about, for
+    /// `Accepted` events. Look it up once the handshake succeeded, as
+    /// peers are only authenticated during the handshake.
+    pub fn lookup(&self, event: &MonitorEvent) -> Option<PeerInfo> {
+        event
+            .peer_address()
+            .and_then(|addr| self.get(&addr.ip().to_string()))
+    }
+
+    fn insert(&self, address: String, peer: PeerInfo) {
+        self.peers.lock().unwrap().insert(address, peer);
+    }
+}
+
+/// A range of IP addresses, such as `"10.0.0.0/8"`, or a single address.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct IpRange {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+impl IpRange {
+    /// Parse an address, optionally followed by a prefix length.
+    pub fn parse(text: &str) -> io::Result<IpRange> {
+        let invalid = || io::Error::new(io::ErrorKind::InvalidInput, format!("invalid address range: {}", text));
+        let mut parts = text.splitn(2, '/');
+        let addr: IpAddr = parts.next().unwrap().parse().map_err(|_| invalid())?;
+        let max = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix = match parts.next() {
+            Some(prefix) => prefix.parse().map_err(|_| invalid())?,
+            None => max,
+        };
+        if prefix > max {
+            return Err(invalid());
+        }
+        Ok(IpRange { addr, prefix })
+    }
+
+    /// Returns true if the given address is in the range.
+    pub fn contains(&self, addr: &IpAddr) -> bool {
+        let (a, b, bits) = match (self.addr, *addr) {
+            (IpAddr::V4(a), IpAddr::V4(b)) => (u128::from(u32::from(a)), u128::from(u32::from(b)), 32),
+            (IpAddr::V6(a), IpAddr::V6(b)) => (u128::from(a), u128::from(b), 128),
+            _ => return false,
+        };
+        let shift = bits - u32::from(self.prefix);
+        shift == 128 || a >> shift == b >> shift
+    }
+}
+
+#[derive(Debug, Default)]
+struct AccessRules {
+    allowed: Vec<IpRange>,
+    denied: Vec<IpRange>,
+    keys: Option<HashSet<Vec<u8>>>,
+}
+
+/// Address and key rules, checked before the `Verifier` of an
+/// `Authenticator`. It is cheap to clone, and every clone shares the same
+/// rules.
+///
+/// Denied addresses are always refused. Once any address is allowed, the
+/// others are refused. Once any CURVE key is allowed, CURVE peers are
+/// accepted, with their Z85-encoded key as user id, if their key is
+/// allowed, and refused otherwise.
+#[derive(Clone, Debug, Default)]
+pub struct AccessList {
+    rules: Arc<Mutex<AccessRules>>,
+}
+
+impl AccessList {
+    /// Create an access list without any rules.
+    pub fn new() -> AccessList {
+        AccessList::default()
+    }
+
+    /// Allow an address range, such as `"10.0.0.0/8"`.
+    pub fn allow(&self, range: &str) -> io::Result<()> {
+        let range = IpRange::parse(range)?;
+        let mut rules = self.rules.lock().unwrap();
+        rules.denied.retain(|r| *r != range);
+        rules.allowed.push(range);
+        Ok(())
+    }
+
+    /// Deny an address range.
+    pub fn deny(&self, range: &str) -> io::Result<()> {
+        let range = IpRange::parse(range)?;
+        let mut rules = self.rules.lock().unwrap();
+        rules.allowed.retain(|r| *r != range);
+        rules.denied.push(range);
+        Ok(())
+    }
+
+    /// Remove any rule for an address range.
+    pub fn clear(&self, range: &str) -> io::Result<()> {
+        let range = IpRange::parse(range)?;
+        let mut rules = self.rules.lock().unwrap();
+        rules.allowed.retain(|r| *r != range);
+        rules.denied.retain(|r| *r != range);
+        Ok(())
+    }
+
+    /// Allow a CURVE public key, such as that of a `curve::Certificate`.
+    pub fn allow_key(&self, key: &[u8]) {
+        self.rules
+            .lock()
+            .unwrap()
+            .keys
+            .get_or_insert_with(HashSet::new)
+            .insert(key.to_vec());
+    }
+
+    /// Revoke a CURVE public key. CURVE peers stay restricted to the keys
+    /// that are left, if any.
+    pub fn revoke_key(&self, key: &[u8]) {
+        if let Some(ref mut keys) = self.rules.lock().unwrap().keys {
+            keys.remove(key);
+        }
+    }
+
+    // Returns `Some` result if the rules decide on the request, and `None`
+    // if it is up to the verifier.
+    fn check(&self, request: &ZapRequest) -> Option<Result<String, String>> {
+        let rules = self.rules.lock().unwrap();
+        let addr = request.address.parse::<IpAddr>().ok();
+        let matches = |ranges: &[IpRange]| addr.map_or(false, |addr| ranges.iter().any(|r| r.contains(&addr)));
+        if matches(&rules.denied) {
+            return Some(Err(format!("address {} is denied", request.address)));
+        }
+        if !rules.allowed.is_empty() && !matches(&rules.allowed) {
+            return Some(Err(format!("address {} is not allowed", request.address)));
+        }
+        match rules.keys {
+            Some(ref keys) if request.mechanism == "CURVE" => {
+                let key = request.credentials.first().map(|k| &k[..]).unwrap_or(&[]);
+                if keys.contains(key) {
+                    Some(zmq::z85_encode(key).map_err(|_| "invalid CURVE key".to_string()))
+                } else {
+                    Some(Err("CURVE key is not allowed".to_string()))
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Decides whether to accept a connection.
+pub trait Verifier {
+    /// Returns the user id of an accepted peer, or the reason for denying
+    /// it.
+    fn verify(&mut self, request: &ZapRequest) -> Result<String, String>;
+}
+
+/// A `Verifier` for the PLAIN mechanism, accepting a fixed set of
+/// usernames and passwords. Other mechanisms are denied.
+#[derive(Clone, Debug, Default)]
+pub struct PlainVerifier {
+    users: HashMap<String, String>,
+}
+
+impl PlainVerifier {
+    /// Create a new verifier, without any users.
+    pub fn new() -> PlainVerifier {
+        PlainVerifier::default()
+    }
+
+    /// Accept a username with the given password.
+    pub fn add_user(&mut self, username: &str, password: &str) -> &mut PlainVerifier {
+        self.users.insert(username.to_string(), password.to_string());
+        self
+    }
+
+    /// Stop accepting a username.
+    pub fn remove_user(&mut self, username: &str) -> &mut PlainVerifier {
+        self.users.remove(username);
+        self
+    }
+}
+
+impl Verifier for PlainVerifier {
+    fn verify(&mut self, request: &ZapRequest) -> Result<String, String> {
+        if request.mechanism != "PLAIN" {
+            return Err(format!("unsupported mechanism {}", request.mechanism));
+        }
+        if request.credentials.len() != 2 {
+            return Err("invalid credentials".to_string());
+        }
+        let username = String::from_utf8_lossy(&request.credentials[0]);
+        let password = String::from_utf8_lossy(&request.credentials[1]);
+        match self.users.get(&*username) {
+            Some(expected) if *expected == *password => Ok(username.into_owned()),
+            _ => Err("invalid username or password".to_string()),
+        }
+    }
+}
+
+/// A Future that handles the ZAP requests of a context, until its socket
+/// fails.
+///
+/// Only one authenticator can run per context.
+pub struct Authenticator<V> {
+    socket: Socket,
+    verifier: V,
+    peers: PeerRegistry,
+    access: AccessList,
+    outgoing: VecDeque<Multipart>,
+}
+
+impl<V: Verifier> Authenticator<V> {
+    /// Create a new authenticator for the sockets of the given context.
+    /// It should be created before any socket that needs authentication
+    /// starts accepting connections.
+    pub fn new(context: &Context, verifier: V, handle: &Handle) -> io::Result<Authenticator<V>> {
+        let socket = context.socket(REP, handle)?;
+        socket.bind(ZAP_ENDPOINT)?;
+        Ok(Authenticator {
+            socket,
+            verifier,
+            peers: PeerRegistry::default(),
+            access: AccessList::new(),
+            outgoing: VecDeque::new(),
+        })
+    }
+
+    /// A reference to the verifier.
+    pub fn verifier(&self) -> &V {
+        &self.verifier
+    }
+
+    /// A mutable reference to the verifier.
+    pub fn verifier_mut(&mut self) -> &mut V {
+        &mut self.verifier
+    }
+
+    /// Returns the access list checked before the verifier, which can be
+    /// updated while the authenticator runs.
+    pub fn access(&self) -> AccessList {
+        self.access.clone()
+    }
+
+    /// Returns the registry of the accepted peers.
+    pub fn peers(&self) -> PeerRegistry {
+        self.peers.clone()
+    }
+
+    fn handle_request(&mut self, msgs: Multipart) -> Multipart {
+        let request_id = msgs.get(1).map(|m| m.to_vec()).unwrap_or_default();
+        let result = if msgs.len() < 2 || &msgs[0][..] != ZAP_VERSION {
+            Err(("400", "invalid ZAP request".to_string()))
+        } else {
+            match ZapRequest::parse(&msgs[2..]) {
+                Ok(request) => {
+                    let result = match self.access.check(&request) {
+                        Some(result) => result,
+                        None => self.verifier.verify(&request),
+                    };
+                    match result {
+                        Ok(ref user) => {
+                            debug!("ZAP allowed {} as {:?}", request.address, user);
+                            self.peers.insert(
+                                request.address.clone(),
+                                PeerInfo {
+                                    mechanism: request.mechanism.clone(),
+                                    identity: request.identity.clone(),
+                                    user_id: user.clone(),
+                                },
+                            );
+                        }
+                        Err(ref reason) => info!("ZAP denied {}: {}", request.address, reason),
+                    }
+                    result.map_err(|reason| ("400", reason))
+                }
+                Err(e) => Err(("400", e.to_string())),
+            }
+        };
+        let (status, text, user) = match result {
+            Ok(user) => ("200", "OK".to_string(), user),
+            Err((status, reason)) => (status, reason, String::new()),
+        };
+        vec![
+            Message::from_slice(ZAP_VERSION),
+            Message::from_slice(&request_id),
+            Message::from_slice(status.as_bytes()),
+            Message::from_slice(text.as_bytes()),
+            Message::from_slice(user.as_bytes()),
+            Message::new(),
+        ]
+    }
+}
+
+impl<V: Verifier> Future for Authenticator<V> {
+    type Item = ();
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        loop {
+            // a `REP` socket must reply before it receives again.
+            try_ready!(flush(&self.socket, &mut self.outgoing));
+            let msgs = {
+                let mut stream = &self.socket;
+                match try_ready!(stream.poll()) {
+                    Some(msgs) => msgs,
+                    None => return Ok(Async::Ready(())),
+                }
+            };
+            let reply = self.handle_request(msgs);
+            self.outgoing.push_back(reply);
+        }
+    }
+}
@@ -0,0 +1,277 @@
+//! Measuring the overhead of sockets on a reactor.
+//!
+//! A `LatencyProbe` bounces messages between two connected sockets, such as
+//! a `PAIR` pair or a `DEALER` pair, one message at a time, and collects the
+//! round-trip times in a `Histogram`. Comparing it with the same loop over
+//! plain rust-zmq sockets shows what the reactor adds.
+//!
+//! `throughput` pushes messages from a `PUSH` socket to a `PULL` socket, as
+//! fast as they go, and reports the rate at which they were received.
+use std::collections::VecDeque;
+use std::io;
+use std::time::{Duration, Instant};
+
+use futures::{Async, AsyncSink, Future, Poll, Sink, Stream};
+use tokio_core::reactor::Handle;
+use zmq::Message;
+
+use super::{Context, Multipart, Socket, PULL, PUSH};
+use super::sink::flush;
+
+/// Sorted samples of durations.
+#[derive(Clone, Debug)]
+pub struct Histogram {
+    samples: Vec<Duration>,
+}
+
+impl Histogram {
+    /// Create a histogram of the given samples.
+    pub fn new(mut samples: Vec<Duration>) -> Histogram {
+        samples.sort();
+        Histogram { samples }
+    }
+
+    /// Returns the number of samples.
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    /// Returns true if there are no samples.
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    /// Returns the smallest sample.
+    pub fn min(&self) -> Duration {
+        self.samples.first().cloned().unwrap_or_default()
+    }
+
+    /// Returns the largest sample.
+    pub fn max(&self) -> Duration {
+        self.samples.last().cloned().unwrap_or_default()
+    }
+
+    /// Returns the mean of the samples.
+    pub fn mean(&self) -> Duration {
+        if self.samples.is_empty() {
+            return Duration::default();
+        }
+        let total = self.samples.iter().fold(Duration::default(), |acc, d| acc + *d);
+        total / self.samples.len() as u32
+    }
+
+    /// Returns the sample below which the given fraction of the samples
+    /// fall, such as `0.99`.
+    pub fn percentile(&self, fraction: f64) -> Duration {
+        if self.samples.is_empty() {
+            return Duration::default();
+        }
+        let idx = (fraction * self.samples.len() as f64).ceil() as usize;
+        self.samples[idx.max(1).min(self.samples.len()) - 1]
+    }
+
+    /// Returns the median.
+    pub fn p50(&self) -> Duration {
+        self.percentile(0.5)
+    }
+
+    /// Returns the 99th percentile.
+    pub fn p99(&self) -> Duration {
+        self.percentile(0.99)
+    }
+
+    /// Returns the 99.9th percentile.
+    pub fn p999(&self) -> Duration {
+        self.percentile(0.999)
+    }
+}
+
+/// A Future that bounces messages between two connected sockets, and
+/// resolves into the `Histogram` of their round-trip times.
+pub struct LatencyProbe {
+    client: Socket,
+    echo: Socket,
+    payload: Vec<u8>,
+    count: usize,
+    sent: Option<Instant>,
+    bounced: VecDeque<Multipart>,
+    samples: Vec<Duration>,
+}
+
+impl LatencyProbe {
+    /// Bounce `count` messages of `size` bytes, sent from `client`, and
+    /// sent back by `echo`.
+    pub fn new(client: Socket, echo: Socket, size: usize, count: usize) -> LatencyProbe {
+        LatencyProbe {
+            client,
+            echo,
+            payload: vec![0; size],
+            count,
+            sent: None,
+            bounced: VecDeque::new(),
+            samples: Vec::with_capacity(count),
+        }
+    }
+
+    /// Consumes the probe, returning the client and echo sockets.
+    pub fn into_inner(self) -> (Socket, Socket) {
+        (self.client, self.echo)
+    }
+}
+
+impl Future for LatencyProbe {
+    type Item = Histogram;
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        loop {
+            if self.samples.len() == self.count {
+                let samples = ::std::mem::replace(&mut self.samples, Vec::new());
+                return Ok(Async::Ready(Histogram::new(samples)));
+            }
+            let mut progress = false;
+            if self.sent.is_none() {
+                let mut client = &self.client;
+                if let AsyncSink::Ready = client.start_send(vec![Message::from_slice(&self.payload)])? {
+                    self.sent = Some(Instant::now());
+                    progress = true;
+                }
+            }
+            let mut echo = &self.echo;
+            if let Async::Ready(Some(msgs)) = echo.poll()? {
+                self.bounced.push_back(msgs);
+                progress = true;
+            }
+            flush(&self.echo, &mut self.bounced)?;
+            let mut client = &self.client;
+            if let Async::Ready(Some(_)) = client.poll()? {
+                if let Some(sent) = self.sent.take() {
+                    self.samples.push(sent.elapsed());
+                }
+                progress = true;
+            }
+            if !progress {
+                return Ok(Async::NotReady);
+            }
+        }
+    }
+}
+
+/// Settings of a throughput run.
+#[derive(Clone, Debug)]
+pub struct ThroughputConfig {
+    /// The endpoint the `PULL` socket binds to.
+    pub endpoint: String,
+    /// The size of messages, in bytes.
+    pub size: usize,
+    /// The number of messages.
+    pub count: usize,
+}
+
+impl Default for ThroughputConfig {
+    /// Send 100000 messages of 100 bytes, over `inproc`.
+    fn default() -> ThroughputConfig {
+        ThroughputConfig {
+            endpoint: "inproc://zmq-tokio-throughput".to_string(),
+            size: 100,
+            count: 100_000,
+        }
+    }
+}
+
+/// The outcome of a throughput run.
+#[derive(Clone, Copy, Debug)]
+pub struct Report {
+    /// The number of messages received.
+    pub messages: usize,
+    /// The number of bytes received.
+    pub bytes: usize,
+    /// The time from the first message sent to the last one received.
+    pub elapsed: Duration,
+}
+
+impl Report {
+    /// Returns the number of messages received per second.
+    pub fn messages_per_sec(&self) -> f64 {
+        self.messages as f64 / secs(self.elapsed)
+    }
+
+    /// Returns the number of bytes received per second.
+    pub fn bytes_per_sec(&self) -> f64 {
+        self.bytes as f64 / secs(self.elapsed)
+    }
+}
+
+fn secs(duration: Duration) -> f64 {
+    let secs = duration.as_secs() as f64 + f64::from(duration.subsec_nanos()) / 1e9;
+    secs.max(1e-9)
+}
+
+/// Create a `PUSH` and a `PULL` socket in a new context, connected over the
+/// configured endpoint, and return a `Throughput` future that sends them the
+/// configured messages.
+pub fn throughput(config: ThroughputConfig, handle: &Handle) -> io::Result<Throughput> {
+    let context = Context::new();
+    let pull = context.socket(PULL, handle)?;
+    pull.bind(&config.endpoint)?;
+    let push = context.socket(PUSH, handle)?;
+    push.connect(&config.endpoint)?;
+    Ok(Throughput {
+        push,
+        pull,
+        payload: vec![0; config.size],
+        count: config.count,
+        sent: 0,
+        received: 0,
+        bytes: 0,
+        start: None,
+    })
+}
+
+/// A Future that pushes messages as fast as they go, and resolves into a
+/// `Report` once they were all received. This is returned by `throughput`.
+pub struct Throughput {
+    push: Socket,
+    pull: Socket,
+    payload: Vec<u8>,
+    count: usize,
+    sent: usize,
+    received: usize,
+    bytes: usize,
+    start: Option<Instant>,
+}
+
+impl Future for Throughput {
+    type Item = Report;
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        let start = *self.start.get_or_insert_with(Instant::now);
+        loop {
+            let mut progress = false;
+            if self.sent < self.count {
+                let mut push = &self.push;
+                if let AsyncSink::Ready = push.start_send(vec![Message::from_slice(&self.payload)])? {
+                    self.sent += 1;
+                    progress = true;
+                }
+            }
+            let mut pull = &self.pull;
+            if let Async::Ready(Some(msgs)) = pull.poll()? {
+                self.received += 1;
+                self.bytes += msgs.iter().map(|m| m.len()).sum::<usize>();
+                progress = true;
+            }
+            if self.received == self.count {
+                return Ok(Async::Ready(Report {
+                    messages: self.received,
+                    bytes: self.bytes,
+                    elapsed: start.elapsed(),
+                }));
+            }
+            if !progress {
+                return Ok(Async::NotReady);
+            }
+        }
+    }
+}
@@ -0,0 +1,246 @@
+//! Binary Star high-availability pairs.
+//!
+//! Two servers, a primary and a backup, exchange their state over `PUB` and
+//! `SUB` sockets. Only one of them is active at a time, accepting requests
+//! from clients on its voter socket; the other stays passive, and takes
+//! over when the active peer stops sending its state and a client request
+//! arrives.
+//!
+//! See http://zguide.zeromq.org/page:all#High-Availability-Pair-Binary-Star-Pattern
+use std::io;
+use std::time::{Duration, Instant};
+
+use futures::{Async, Poll, Stream};
+use tokio_core::reactor::{Handle, Interval};
+
+use super::{Context, Multipart, Socket, SocketSend, PUB, ROUTER, SUB};
+
+/// Default interval between state updates, in milliseconds.
+pub const HEARTBEAT_INTERVAL: u64 = 1000;
+
+/// States of a Binary Star server.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum State {
+    /// Primary, waiting for its peer to connect.
+    Primary,
+    /// Backup, waiting for its peer to connect.
+    Backup,
+    /// Active, accepting client requests.
+    Active,
+    /// Passive, rejecting client requests while its peer is active.
+    Passive,
+}
+
+impl State {
+    fn to_byte(&self) -> u8 {
+        match *self {
+            State::Primary => 1,
+            State::Backup => 2,
+            State::Active => 3,
+            State::Passive => 4,
+        }
+    }
+
+    fn from_byte(byte: u8) -> Option<State> {
+        match byte {
+            1 => Some(State::Primary),
+            2 => Some(State::Backup),
+            3 => Some(State::Active),
+            4 => Some(State::Passive),
+            _ => None,
+        }
+    }
+}
+
+/// Events yielded by a `BinaryStar` server.
+#[derive(Debug)]
+pub enum BinaryStarEvent {
+    /// The server has become active.
+    Active,
+    /// The server has become passive.
+    Passive,
+    /// A client request was accepted on the voter socket. The message
+    /// includes the routing envelope, for replying through
+    /// `BinaryStar::voter`.
+    Request(Multipart),
+}
+
+// Inputs to the state machine.
+enum Input {
+    Peer(State),
+    ClientRequest,
+}
+
+/// A Binary Star server, yielding a `Stream` of `BinaryStarEvent`s.
+///
+/// The stream fails when both peers claim to be active, or passive, which
+/// means the pair is misconfigured.
+pub struct BinaryStar {
+    state: State,
+    statepub: Socket,
+    statesub: Socket,
+    voter: Socket,
+    heartbeat: Interval,
+    interval: Duration,
+    peer_expiry: Instant,
+    accepted: Option<Multipart>,
+}
+
+impl BinaryStar {
+    /// Create a new server, publishing its state on the `local` endpoint,
+    /// subscribing to the state of its peer at the `remote` endpoint, and
+    /// accepting client requests on a `ROUTER` socket bound to `voter`.
+    pub fn new(
+        context: &Context,
+        primary: bool,
+        local: &str,
+        remote: &str,
+        voter: &str,
+        handle: &Handle,
+    ) -> io::Result<BinaryStar> {
+        let statepub = context.socket(PUB, handle)?;
+        statepub.bind(local)?;
+        let statesub = context.socket(SUB, handle)?;
+        statesub.set_subscribe(b"")?;
+        statesub.connect(remote)?;
+        let voter_socket = context.socket(ROUTER, handle)?;
+        voter_socket.bind(voter)?;
+        let interval = Duration::from_millis(HEARTBEAT_INTERVAL);
+        Ok(BinaryStar {
+            state: if primary { State::Primary } else { State::Backup },
+            statepub,
+            statesub,
+            voter: voter_socket,
+            heartbeat: Interval::new(interval, handle)?,
+            interval,
+            peer_expiry: Instant::now() + interval * 2,
+            accepted: None,
+        })
+    }
+
+    /// Returns the current state of the server.
+    pub fn state(&self) -> State {
+        self.state
+    }
+
+    /// A reference to the voter socket, for replying to client requests.
+    pub fn voter(&self) -> &Socket {
+        &self.voter
+    }
+
+    // Runs the state machine. Returns `Ok(true)` when a client request is
+    // accepted, and fails when the pair is in an invalid state.
+    fn execute(&mut self, input: Input) -> io::Result<bool> {
+        let peer_dead = Instant::now() >= self.peer_expiry;
+        let next = match (self.state, input) {
+            (State::Primary, Input::Peer(State::Backup)) => Some(State::Active),
+            (State::Primary, Input::Peer(State::Active)) => Some(State::Passive),
+            (State::Primary, Input::ClientRequest) => {
+                if !peer_dead {
+                    return Ok(false);
+                }
+                Some(State::Active)
+            }
+            (State::Backup, Input::Peer(State::Active)) => Some(State::Passive),
+            (State::Backup, Input::ClientRequest) => return Ok(false),
+            (State::Active, Input::Peer(State::Active)) => {
+                return Err(io::Error::new(io::ErrorKind::Other, "dual active servers"));
+            }
+            (State::Active, Input::ClientRequest) => return Ok(true),
+            (State::Passive, Input::Peer(State::Primary)) |
+            (State::Passive, Input::Peer(State::Backup)) => Some(State::Active),
+            (State::Passive, Input::Peer(State::Passive)) => {
+                return Err(io::Error::new(io::ErrorKind::Other, "dual passive servers"));
+            }
+            (State::Passive, Input::ClientRequest) => {
+                if !peer_dead {
+                    return Ok(false);
+                }
+                info!("failover successful, ready as active");
+                Some(State::Active)
+            }
+            _ => None,
+        };
+        if let Some(state) = next {
+            self.state = state;
+            return Ok(state == State::Active);
+        }
+        Ok(false)
+    }
+
+    fn transition(&self) -> BinaryStarEvent {
+        match self.state {
+            State::Active => BinaryStarEvent::Active,
+            _ => BinaryStarEvent::Passive,
+        }
+    }
+
+    fn publish_state(&self) -> io::Result<()> {
+        match SocketSend::send(&self.statepub, &[self.state.to_byte()][..], 0) {
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => Ok(()),
+            r => r,
+        }
+    }
+}
+
+impl Stream for BinaryStar {
+    type Item = BinaryStarEvent;
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        while let Async::Ready(Some(())) = self.heartbeat.poll()? {
+            self.publish_state()?;
+        }
+        if let Some(msgs) = self.accepted.take() {
+            return Ok(Async::Ready(Some(BinaryStarEvent::Request(msgs))));
+        }
+        loop {
+            let msgs = {
+                let mut stream = &self.statesub;
+                match stream.poll()? {
+                    Async::Ready(Some(msgs)) => msgs,
+                    _ => break,
+                }
+            };
+            let peer = match msgs.first() {
+                Some(frame) if frame.len() == 1 => State::from_byte(frame[0]),
+                _ => None,
+            };
+            let peer = match peer {
+                Some(peer) => peer,
+                None => {
+                    warn!("dropping invalid state from peer");
+                    continue;
+                }
+            };
+            self.peer_expiry = Instant::now() + self.interval * 2;
+            let was = self.state;
+            self.execute(Input::Peer(peer))?;
+            if self.state != was {
+                return Ok(Async::Ready(Some(self.transition())));
+            }
+        }
+        loop {
+            let msgs: Multipart = {
+                let mut stream = &self.voter;
+                match stream.poll()? {
+                    Async::Ready(Some(msgs)) => msgs,
+                    Async::Ready(None) => return Ok(Async::Ready(None)),
+                    Async::NotReady => return Ok(Async::NotReady),
+                }
+            };
+            let was = self.state;
+            if self.execute(Input::ClientRequest)? {
+                if self.state != was {
+                    // let the peer know right away that we've taken over,
+                    // and yield the request after the transition.
+                    self.publish_state()?;
+                    self.accepted = Some(msgs);
+                    return Ok(Async::Ready(Some(BinaryStarEvent::Active)));
+                }
+                return Ok(Async::Ready(Some(BinaryStarEvent::Request(msgs))));
+            }
+            debug!("rejecting client request in {:?} state", self.state);
+        }
+    }
+}
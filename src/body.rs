@@ -0,0 +1,209 @@
+//! Streaming bodies for large payloads.
+//!
+//! A streaming body is a single logical payload that travels as a
+//! sequence of bounded, one-part messages. Every frame starts with a
+//! one-byte continuation marker: `BODY_MORE` when more frames follow,
+//! and `BODY_LAST` for the frame that ends the body.
+//!
+//! Unlike multi-part messages, which ØMQ delivers atomically (and thus
+//! fully buffered in memory), each body frame is delivered on its own,
+//! so a receiver can process gigabyte-scale transfers chunk by chunk.
+//!
+//! Frames carry no body identifier, so they only make sense between two
+//! peers sending one body at a time: the frames of concurrent bodies would
+//! interleave. Streaming bodies are thus limited to `PAIR` sockets, which
+//! have a single peer, and a socket must not send a body before the
+//! previous one is sent.
+use std::io;
+
+use futures::{Async, Future, Poll, Stream};
+
+use super::{SocketRecv, SocketSend, PAIR};
+use super::Socket;
+use super::future::check_received_size;
+
+/// Marker byte for a body frame that is followed by more frames.
+pub const BODY_MORE: u8 = 0x01;
+
+/// Marker byte for the final frame of a body.
+pub const BODY_LAST: u8 = 0x00;
+
+/// Default maximum payload size of a single body frame, in bytes.
+pub const DEFAULT_BODY_FRAME_SIZE: usize = 64 * 1024;
+
+/// A Future that sends every chunk yielded by a `Stream` as a streaming
+/// body. This is returned by `Socket::send_stream_body`.
+///
+/// Chunks larger than the frame size are split into several frames.
+pub struct SendStreamBody<'a, S> {
+    socket: &'a Socket,
+    body: S,
+    frame_size: usize,
+    buffered: Vec<u8>,
+    // Offset of the bytes of `buffered` not yet moved into a frame.
+    offset: usize,
+    pending: Option<Vec<u8>>,
+    checked: bool,
+    done: bool,
+}
+
+impl<'a, S> SendStreamBody<'a, S>
+where
+    S: Stream<Item = Vec<u8>, Error = io::Error>,
+{
+    pub fn new(socket: &'a Socket, body: S, frame_size: usize) -> SendStreamBody<'a, S> {
+        assert!(frame_size > 0, "body frame size must be non-zero");
+        SendStreamBody {
+            socket,
+            body,
+            frame_size,
+            buffered: Vec::new(),
+            offset: 0,
+            pending: None,
+            checked: false,
+            done: false,
+        }
+    }
+
+    // Sends the pending frame, if any. Returns `Async::NotReady` when
+    // the socket would block.
+    fn flush_pending(&mut self) -> Poll<(), io::Error> {
+        if let Some(frame) = self.pending.take() {
+            match SocketSend::send(self.socket, &frame[..], 0) {
+                Err(e) => {
+                    if e.kind() == io::ErrorKind::WouldBlock {
+                        self.pending = Some(frame);
+                        return Ok(Async::NotReady);
+                    } else {
+                        return Err(e);
+                    }
+                }
+                Ok(_) => {}
+            }
+        }
+        Ok(Async::Ready(()))
+    }
+
+    // Moves the next frame out of the buffered bytes. The final frame
+    // is only produced once the body stream has ended, since we need to
+    // know that no more data follows in order to mark it as `BODY_LAST`.
+    fn next_frame(&mut self) -> Option<Vec<u8>> {
+        if self.buffered.len() - self.offset > self.frame_size {
+            let end = self.offset + self.frame_size;
+            let frame = body_frame(BODY_MORE, &self.buffered[self.offset..end]);
+            self.offset = end;
+            Some(frame)
+        } else if self.done {
+            let frame = body_frame(BODY_LAST, &self.buffered[self.offset..]);
+            self.buffered = Vec::new();
+            self.offset = 0;
+            Some(frame)
+        } else {
+            None
+        }
+    }
+
+    // Appends a chunk of the body, dropping the bytes already moved into
+    // frames first. At most a frame's worth of them is left by then.
+    fn buffer(&mut self, chunk: &[u8]) {
+        self.buffered.drain(..self.offset);
+        self.offset = 0;
+        self.buffered.extend_from_slice(chunk);
+    }
+}
+
+impl<'a, S> Future for SendStreamBody<'a, S>
+where
+    S: Stream<Item = Vec<u8>, Error = io::Error>,
+{
+    type Item = ();
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        if !self.checked {
+            self.socket.check_socket_type("stream_body", &[PAIR])?;
+            self.checked = true;
+        }
+        loop {
+            try_ready!(self.flush_pending());
+            if let Some(frame) = self.next_frame() {
+                let last = frame[0] == BODY_LAST;
+                self.pending = Some(frame);
+                if last {
+                    try_ready!(self.flush_pending());
+                    return Ok(Async::Ready(()));
+                }
+                continue;
+            }
+            match try_ready!(self.body.poll()) {
+                Some(chunk) => self.buffer(&chunk),
+                None => self.done = true,
+            }
+        }
+    }
+}
+
+/// A `Stream` of the chunks that make up an incoming streaming body.
+/// This is returned by `Socket::recv_stream_body`.
+///
+/// The stream ends after the frame marked as `BODY_LAST` is received.
+pub struct ReceiveStreamBody<'a> {
+    socket: &'a Socket,
+    checked: bool,
+    done: bool,
+}
+
+impl<'a> ReceiveStreamBody<'a> {
+    pub fn new(socket: &'a Socket) -> ReceiveStreamBody<'a> {
+        ReceiveStreamBody {
+            socket,
+            checked: false,
+            done: false,
+        }
+    }
+}
+
+impl<'a> Stream for ReceiveStreamBody<'a> {
+    type Item = Vec<u8>;
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        if self.done {
+            return Ok(Async::Ready(None));
+        }
+        if !self.checked {
+            self.socket.check_socket_type("stream_body", &[PAIR])?;
+            self.checked = true;
+        }
+        let msg = match SocketRecv::recv_msg(self.socket, 0) {
+            Err(e) => {
+                if e.kind() == io::ErrorKind::WouldBlock {
+                    return Ok(Async::NotReady);
+                } else {
+                    return Err(e);
+                }
+            }
+            Ok(msg) => msg,
+        };
+        check_received_size(self.socket, msg.len())?;
+        match msg.first() {
+            Some(&BODY_MORE) => Ok(Async::Ready(Some(msg[1..].to_vec()))),
+            Some(&BODY_LAST) => {
+                self.done = true;
+                Ok(Async::Ready(Some(msg[1..].to_vec())))
+            }
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "frame is missing a valid body continuation marker",
+            )),
+        }
+    }
+}
+
+// Prepends the continuation marker to a chunk of body data.
+fn body_frame(marker: u8, chunk: &[u8]) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(chunk.len() + 1);
+    frame.push(marker);
+    frame.extend_from_slice(chunk);
+    frame
+}
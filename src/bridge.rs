@@ -0,0 +1,115 @@
+//! Bridges between sockets and `futures::sync::mpsc` channels.
+//!
+//! Both bridges own their socket, so they can be spawned on a reactor, and
+//! both apply backpressure: a bridge only takes a message from one side
+//! when the other side can accept it.
+use std::io;
+
+use futures::{Async, AsyncSink, Future, Poll, Sink, Stream};
+use futures::sync::mpsc;
+
+use super::{Multipart, Socket};
+
+/// A Future that sends every message received on a channel through a
+/// socket. It resolves once every sender of the channel is dropped.
+/// This is returned by `Socket::bridge_from`.
+pub struct BridgeFrom {
+    socket: Socket,
+    rx: mpsc::Receiver<Multipart>,
+    buffered: Option<Multipart>,
+}
+
+impl BridgeFrom {
+    pub fn new(socket: Socket, rx: mpsc::Receiver<Multipart>) -> BridgeFrom {
+        BridgeFrom {
+            socket,
+            rx,
+            buffered: None,
+        }
+    }
+
+    /// Consumes the bridge, returning the socket.
+    pub fn into_inner(self) -> Socket {
+        self.socket
+    }
+
+    fn try_start_send(&mut self, item: Multipart) -> Poll<(), io::Error> {
+        let mut sink = &self.socket;
+        if let AsyncSink::NotReady(item) = sink.start_send(item)? {
+            self.buffered = Some(item);
+            return Ok(Async::NotReady);
+        }
+        Ok(Async::Ready(()))
+    }
+}
+
+impl Future for BridgeFrom {
+    type Item = ();
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        if let Some(item) = self.buffered.take() {
+            try_ready!(self.try_start_send(item));
+        }
+        loop {
+            // `mpsc::Receiver` never fails.
+            match self.rx.poll() {
+                Ok(Async::Ready(Some(item))) => try_ready!(self.try_start_send(item)),
+                Ok(Async::NotReady) => return Ok(Async::NotReady),
+                Ok(Async::Ready(None)) | Err(_) => return Ok(Async::Ready(())),
+            }
+        }
+    }
+}
+
+/// A Future that sends every message received on a socket into a channel.
+/// It resolves once the receiver of the channel is dropped.
+/// This is returned by `Socket::bridge_to`.
+pub struct BridgeTo {
+    socket: Socket,
+    tx: mpsc::Sender<Multipart>,
+    buffered: Option<Multipart>,
+}
+
+impl BridgeTo {
+    pub fn new(socket: Socket, tx: mpsc::Sender<Multipart>) -> BridgeTo {
+        BridgeTo {
+            socket,
+            tx,
+            buffered: None,
+        }
+    }
+
+    /// Consumes the bridge, returning the socket.
+    pub fn into_inner(self) -> Socket {
+        self.socket
+    }
+}
+
+impl Future for BridgeTo {
+    type Item = ();
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        loop {
+            if let Some(item) = self.buffered.take() {
+                match self.tx.start_send(item) {
+                    Ok(AsyncSink::Ready) => {}
+                    Ok(AsyncSink::NotReady(item)) => {
+                        self.buffered = Some(item);
+                        return Ok(Async::NotReady);
+                    }
+                    Err(_) => return Ok(Async::Ready(())),
+                }
+            }
+            if self.tx.poll_complete().is_err() {
+                return Ok(Async::Ready(()));
+            }
+            let mut stream = &self.socket;
+            match try_ready!(stream.poll()) {
+                Some(item) => self.buffered = Some(item),
+                None => return Ok(Async::Ready(())),
+            }
+        }
+    }
+}
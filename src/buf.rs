@@ -0,0 +1,49 @@
+//! Interoperability with the `bytes` crate.
+//!
+//! Enabled with the `bytes` feature.
+//!
+//! Both `Bytes` and `zmq::Message` are foreign to this crate, so instead of
+//! `From` conversions, `Bytes` payloads are sent with
+//! `Socket::send_zero_copy`, which hands the reference-counted buffer over
+//! to ØMQ without copying it, and messages are received into a `BytesMut`
+//! with `Socket::recv_bytes_mut`.
+use std::io;
+
+use bytes::BytesMut;
+use futures::{Async, Future, Poll};
+
+use super::{Socket, SocketRecv};
+use super::future::check_received_size;
+
+/// A Future that receives a one-part message into a `BytesMut`.
+/// This is returned by `Socket::recv_bytes_mut`
+pub struct ReceiveBytesMut<'a> {
+    socket: &'a Socket,
+}
+
+impl<'a> ReceiveBytesMut<'a> {
+    pub fn new(socket: &'a Socket) -> ReceiveBytesMut<'a> {
+        ReceiveBytesMut { socket }
+    }
+}
+
+impl<'a> Future for ReceiveBytesMut<'a> {
+    type Item = BytesMut;
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        match SocketRecv::recv_msg(self.socket, 0) {
+            Err(e) => {
+                if e.kind() == io::ErrorKind::WouldBlock {
+                    Ok(Async::NotReady)
+                } else {
+                    Err(e)
+                }
+            }
+            Ok(msg) => {
+                check_received_size(self.socket, msg.len())?;
+                Ok(Async::Ready(BytesMut::from(&msg[..])))
+            }
+        }
+    }
+}
@@ -0,0 +1,159 @@
+//! Buffered sending for `PUB` and `PUSH` sockets.
+//!
+//! ØMQ silently drops, or refuses, messages sent while a `PUB` or `PUSH`
+//! socket has no connected peers. A `BufferedSink` keeps those messages in
+//! a bounded queue instead, and replays them once the socket is writable
+//! and connected again.
+use std::collections::VecDeque;
+use std::io;
+
+use futures::{Async, AsyncSink, Poll, Sink, StartSend, Stream};
+use zmq;
+
+use super::{Multipart, Socket};
+use super::monitor::{Event, Monitor};
+
+/// Counters of a `BufferedSink`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BufferStats {
+    /// Messages that were queued, instead of being sent right away.
+    pub buffered: u64,
+    /// Messages that were dropped because the queue was full.
+    pub dropped: u64,
+    /// Queued messages that were sent later.
+    pub replayed: u64,
+}
+
+/// A `Sink` of multipart-messages, that queues them while the socket is not
+/// writable, or while its monitor reports no connected peers.
+///
+/// When the queue is full, the oldest message is dropped. `poll_complete`
+/// resolves once the queue has been replayed.
+pub struct BufferedSink {
+    socket: Socket,
+    monitor: Option<Monitor>,
+    peers: usize,
+    queue: VecDeque<Multipart>,
+    capacity: usize,
+    stats: BufferStats,
+}
+
+impl BufferedSink {
+    /// Create a new sink, queueing up to `capacity` messages.
+    pub fn new(socket: Socket, capacity: usize) -> BufferedSink {
+        assert!(capacity > 0, "buffer capacity must be non-zero");
+        BufferedSink {
+            socket,
+            monitor: None,
+            peers: 0,
+            queue: VecDeque::new(),
+            capacity,
+            stats: BufferStats::default(),
+        }
+    }
+
+    /// Track the connected peers with the monitor of the socket. Messages
+    /// are queued until the monitor reports a connection, so the monitor
+    /// should have been created before the socket was bound or connected.
+    pub fn with_monitor(mut self, monitor: Monitor) -> BufferedSink {
+        self.monitor = Some(monitor);
+        self
+    }
+
+    /// A reference to the underlying socket.
+    pub fn get_ref(&self) -> &Socket {
+        &self.socket
+    }
+
+    /// Returns the number of queued messages.
+    pub fn queued(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// Returns the counters of the sink.
+    pub fn stats(&self) -> BufferStats {
+        self.stats
+    }
+
+    /// Consumes the sink, returning the underlying socket. Queued messages
+    /// are dropped.
+    pub fn into_inner(self) -> Socket {
+        self.socket
+    }
+
+    // Updates the number of connected peers from the monitor events.
+    fn poll_monitor(&mut self) -> io::Result<()> {
+        if let Some(ref mut monitor) = self.monitor {
+            while let Async::Ready(Some(event)) = monitor.poll()? {
+                match event.event {
+                    Event::Connected | Event::Accepted => self.peers += 1,
+                    Event::Disconnected => self.peers = self.peers.saturating_sub(1),
+                    _ => {}
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn is_ready(&self) -> io::Result<bool> {
+        if self.monitor.is_some() && self.peers == 0 {
+            return Ok(false);
+        }
+        Ok(self.socket.get_events()?.contains(zmq::POLLOUT))
+    }
+
+    fn enqueue(&mut self, msgs: Multipart) {
+        if self.queue.len() == self.capacity {
+            self.queue.pop_front();
+            self.stats.dropped += 1;
+        }
+        self.queue.push_back(msgs);
+        self.stats.buffered += 1;
+    }
+
+    // Sends a message, handing it back when the socket would block.
+    fn try_send(&self, msgs: Multipart) -> io::Result<Option<Multipart>> {
+        let mut sink = &self.socket;
+        match sink.start_send(msgs)? {
+            AsyncSink::Ready => Ok(None),
+            AsyncSink::NotReady(msgs) => Ok(Some(msgs)),
+        }
+    }
+}
+
+impl Sink for BufferedSink {
+    type SinkItem = Multipart;
+    type SinkError = io::Error;
+
+    fn start_send(&mut self, item: Multipart) -> StartSend<Multipart, Self::SinkError> {
+        self.poll_monitor()?;
+        if !self.queue.is_empty() || !self.is_ready()? {
+            self.enqueue(item);
+            return Ok(AsyncSink::Ready);
+        }
+        if let Some(item) = self.try_send(item)? {
+            self.enqueue(item);
+        }
+        Ok(AsyncSink::Ready)
+    }
+
+    fn poll_complete(&mut self) -> Poll<(), Self::SinkError> {
+        self.poll_monitor()?;
+        while !self.queue.is_empty() {
+            if !self.is_ready()? {
+                // the monitor, or the socket registration, wakes us up.
+                if self.monitor.is_none() || self.peers > 0 {
+                    self.socket.registration()?.need_write();
+                }
+                return Ok(Async::NotReady);
+            }
+            let msgs = self.queue.pop_front().unwrap();
+            if let Some(msgs) = self.try_send(msgs)? {
+                self.queue.push_front(msgs);
+                return Ok(Async::NotReady);
+            }
+            self.stats.replayed += 1;
+        }
+        Ok(Async::Ready(()))
+    }
+}
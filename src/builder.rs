@@ -0,0 +1,231 @@
+//! Builders for contexts and sockets.
+use std::io;
+
+use futures_cpupool::CpuPool;
+use tokio_core::reactor::Handle;
+use zmq::SocketType;
+
+use super::{Context, Socket};
+use super::options::{MulticastOptions, TcpOptions};
+use super::reconnect::ReconnectPolicy;
+
+/// Builder for a `Context` with custom options. This is returned by
+/// `Context::builder`.
+///
+/// Options are applied before any socket is created.
+#[derive(Clone, Debug, Default)]
+pub struct ContextBuilder {
+    io_threads: Option<i32>,
+    max_sockets: Option<usize>,
+    ipv6: bool,
+    blocking_pool: Option<CpuPool>,
+    metrics: bool,
+}
+
+impl ContextBuilder {
+    /// Create a new builder with the default options.
+    pub fn new() -> ContextBuilder {
+        ContextBuilder::default()
+    }
+
+    /// Set the number of ØMQ I/O threads. Defaults to one.
+    pub fn io_threads(mut self, threads: i32) -> ContextBuilder {
+        self.io_threads = Some(threads);
+        self
+    }
+
+    /// Set the maximum number of sockets that the context keeps open at
+    /// once. Past it, `Context::socket` fails with `EMFILE`, until a socket
+    /// is dropped.
+    pub fn max_sockets(mut self, max: usize) -> ContextBuilder {
+        self.max_sockets = Some(max);
+        self
+    }
+
+    /// Enable IPv6 on every socket created by the context. It can still be
+    /// disabled on each socket with `Socket::set_ipv6`.
+    pub fn ipv6(mut self, enabled: bool) -> ContextBuilder {
+        self.ipv6 = enabled;
+        self
+    }
+
+    /// Run the potentially blocking operations of the context and its
+    /// sockets on the given pool: destroying the context when it is
+    /// terminated, and `Socket::bind_offloaded`.
+    pub fn blocking_pool(mut self, pool: CpuPool) -> ContextBuilder {
+        self.blocking_pool = Some(pool);
+        self
+    }
+
+    /// Record metrics for every socket created by the context. See
+    /// `Socket::metrics`.
+    pub fn metrics(mut self, enabled: bool) -> ContextBuilder {
+        self.metrics = enabled;
+        self
+    }
+
+    /// Build the configured `Context`.
+    pub fn build(self) -> io::Result<Context> {
+        let context = Context::with_options(
+            self.max_sockets,
+            self.ipv6,
+            self.blocking_pool,
+            self.metrics,
+        );
+        if let Some(threads) = self.io_threads {
+            context.get_inner().set_io_threads(threads)?;
+        }
+        Ok(context)
+    }
+}
+
+/// Builder for a `Socket` with custom options. This is returned by
+/// `Context::socket_builder`.
+///
+/// Many options only apply to connections made after they are set, so
+/// every option is applied before the socket binds or connects.
+#[derive(Clone, Debug)]
+pub struct SocketBuilder {
+    context: Context,
+    typ: SocketType,
+    sndhwm: Option<i32>,
+    rcvhwm: Option<i32>,
+    linger: Option<i32>,
+    identity: Option<Vec<u8>>,
+    conflate: Option<bool>,
+    reconnect_policy: Option<ReconnectPolicy>,
+    tcp_options: Option<TcpOptions>,
+    multicast_options: Option<MulticastOptions>,
+    subscriptions: Vec<Vec<u8>>,
+    binds: Vec<String>,
+    connects: Vec<String>,
+}
+
+impl SocketBuilder {
+    /// Create a new builder for a socket of the given type, in `context`.
+    pub fn new(context: &Context, typ: SocketType) -> SocketBuilder {
+        SocketBuilder {
+            context: context.clone(),
+            typ,
+            sndhwm: None,
+            rcvhwm: None,
+            linger: None,
+            identity: None,
+            conflate: None,
+            reconnect_policy: None,
+            tcp_options: None,
+            multicast_options: None,
+            subscriptions: Vec::new(),
+            binds: Vec::new(),
+            connects: Vec::new(),
+        }
+    }
+
+    /// Set the high-water mark for outbound messages.
+    pub fn sndhwm(mut self, value: i32) -> SocketBuilder {
+        self.sndhwm = Some(value);
+        self
+    }
+
+    /// Set the high-water mark for inbound messages.
+    pub fn rcvhwm(mut self, value: i32) -> SocketBuilder {
+        self.rcvhwm = Some(value);
+        self
+    }
+
+    /// Set the linger period, in milliseconds.
+    pub fn linger(mut self, value: i32) -> SocketBuilder {
+        self.linger = Some(value);
+        self
+    }
+
+    /// Set the routing id of the socket.
+    pub fn identity(mut self, identity: &[u8]) -> SocketBuilder {
+        self.identity = Some(identity.to_vec());
+        self
+    }
+
+    /// Keep only the last message in the queues, with the restrictions of
+    /// `Socket::set_conflate`.
+    pub fn conflate(mut self, value: bool) -> SocketBuilder {
+        self.conflate = Some(value);
+        self
+    }
+
+    /// Set the reconnection intervals, with `Socket::set_reconnect_policy`.
+    pub fn reconnect_policy(mut self, policy: ReconnectPolicy) -> SocketBuilder {
+        self.reconnect_policy = Some(policy);
+        self
+    }
+
+    /// Set the TCP keepalive and type-of-service options, with
+    /// `Socket::set_tcp_options`.
+    pub fn tcp_options(mut self, options: TcpOptions) -> SocketBuilder {
+        self.tcp_options = Some(options);
+        self
+    }
+
+    /// Set the rate and multicast options, with
+    /// `Socket::set_multicast_options`.
+    pub fn multicast_options(mut self, options: MulticastOptions) -> SocketBuilder {
+        self.multicast_options = Some(options);
+        self
+    }
+
+    /// Subscribe a `SUB` socket to the given prefix.
+    pub fn subscribe(mut self, prefix: &[u8]) -> SocketBuilder {
+        self.subscriptions.push(prefix.to_vec());
+        self
+    }
+
+    /// Bind the socket to the given endpoint, once it is configured.
+    pub fn bind<T: Into<String>>(mut self, endpoint: T) -> SocketBuilder {
+        self.binds.push(endpoint.into());
+        self
+    }
+
+    /// Connect the socket to the given endpoint, once it is configured.
+    pub fn connect<T: Into<String>>(mut self, endpoint: T) -> SocketBuilder {
+        self.connects.push(endpoint.into());
+        self
+    }
+
+    /// Build the configured `Socket`, registered with the given reactor.
+    pub fn build(self, handle: &Handle) -> io::Result<Socket> {
+        let socket = self.context.socket(self.typ, handle)?;
+        if let Some(value) = self.sndhwm {
+            socket.set_sndhwm(value)?;
+        }
+        if let Some(value) = self.rcvhwm {
+            socket.set_rcvhwm(value)?;
+        }
+        if let Some(value) = self.linger {
+            socket.set_linger(value)?;
+        }
+        if let Some(ref identity) = self.identity {
+            socket.set_identity(identity)?;
+        }
+        if let Some(value) = self.conflate {
+            socket.set_conflate(value)?;
+        }
+        if let Some(policy) = self.reconnect_policy {
+            socket.set_reconnect_policy(policy)?;
+        }
+        if let Some(ref options) = self.tcp_options {
+            socket.set_tcp_options(options)?;
+        }
+        if let Some(ref options) = self.multicast_options {
+            socket.set_multicast_options(options)?;
+        }
+        for prefix in &self.subscriptions {
+            socket.set_subscribe(prefix)?;
+        }
+        for endpoint in &self.binds {
+            socket.bind(endpoint)?;
+        }
+        for endpoint in &self.connects {
+            socket.connect(endpoint)?;
+        }
+        Ok(socket)
+    }
+}
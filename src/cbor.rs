@@ -0,0 +1,48 @@
+//! CBOR codec for sockets.
+//!
+//! Enabled with the `cbor` feature.
+use std::io;
+use std::marker::PhantomData;
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_cbor;
+use zmq::Message;
+
+use super::Multipart;
+use super::codec::{single_frame, MessageCodec};
+
+/// Codec for values of type `T`, encoded as one-part CBOR messages.
+/// Use it with `Socket::framed_with`.
+pub struct CborCodec<T> {
+    _item: PhantomData<T>,
+}
+
+impl<T> CborCodec<T> {
+    pub fn new() -> CborCodec<T> {
+        CborCodec { _item: PhantomData }
+    }
+}
+
+impl<T> Default for CborCodec<T> {
+    fn default() -> CborCodec<T> {
+        CborCodec::new()
+    }
+}
+
+impl<T> MessageCodec for CborCodec<T>
+where
+    T: Serialize + DeserializeOwned,
+{
+    type Item = T;
+
+    fn encode(&mut self, item: T) -> io::Result<Multipart> {
+        let bytes = serde_cbor::to_vec(&item).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        Ok(vec![Message::from(bytes)])
+    }
+
+    fn decode(&mut self, msgs: Multipart) -> io::Result<T> {
+        let msg = single_frame(msgs)?;
+        serde_cbor::from_slice(&msg).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
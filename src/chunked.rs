@@ -0,0 +1,253 @@
+//! Chunked transfer of large payloads, with credit-based flow control.
+//!
+//! Enabled with the `bytes` feature.
+//!
+//! Following the file transfer design of the ØMQ guide, the receiver grants
+//! the sender credit, in chunks, and the sender never sends more chunks than
+//! it was granted. The receiver grants one more chunk for every chunk it
+//! yields, so at most a window of chunks is in flight, whatever the size of
+//! the payload and the high-water mark of the sockets.
+//!
+//! Messages are:
+//!
+//! * `["CREDIT", n]`, from the receiver, granting `n` more chunks, where `n`
+//!   is a big-endian `u32`;
+//! * `["CHUNK", data]`, from the sender, carrying a chunk of the payload;
+//! * `["END"]`, from the sender, after the last chunk.
+//!
+//! Both ends are meant for `DEALER` sockets. On a `ROUTER` socket, the
+//! routing id of the peer is given with `to` or `from`, and prefixes every
+//! message.
+use std::collections::VecDeque;
+use std::io::{self, Read};
+
+use bytes::Bytes;
+use futures::{Async, Future, Poll, Stream};
+use zmq::Message;
+
+use super::{Multipart, RoutingId, Socket};
+use super::sink::flush;
+
+/// Default number of chunks in flight.
+pub const DEFAULT_WINDOW: u32 = 8;
+
+const CREDIT: &[u8] = b"CREDIT";
+const CHUNK: &[u8] = b"CHUNK";
+const END: &[u8] = b"END";
+
+// Prefixes a message with the routing id of its peer, if any.
+fn route(peer: &Option<RoutingId>, mut msgs: Multipart) -> Multipart {
+    if let Some(ref peer) = *peer {
+        msgs.insert(0, Message::from_slice(peer));
+    }
+    msgs
+}
+
+// Strips the routing id of a received message, returning `None` for
+// messages from other peers.
+fn unroute(peer: &Option<RoutingId>, mut msgs: Multipart) -> Option<Multipart> {
+    if let Some(ref peer) = *peer {
+        if msgs.is_empty() || &msgs[0][..] != &peer[..] {
+            return None;
+        }
+        msgs.remove(0);
+    }
+    Some(msgs)
+}
+
+fn invalid_message() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "invalid chunked transfer message")
+}
+
+/// A Future that sends everything read from a reader in chunks, as the
+/// receiver grants credit. It resolves into the number of bytes sent.
+/// This is returned by `Socket::send_chunked`.
+pub struct SendChunked<'a, R> {
+    socket: &'a Socket,
+    reader: R,
+    chunk_size: usize,
+    peer: Option<RoutingId>,
+    credit: u32,
+    outgoing: VecDeque<Multipart>,
+    sent: u64,
+    eof: bool,
+}
+
+impl<'a, R: Read> SendChunked<'a, R> {
+    pub fn new(socket: &'a Socket, reader: R, chunk_size: usize) -> SendChunked<'a, R> {
+        assert!(chunk_size > 0, "chunk size must be non-zero");
+        SendChunked {
+            socket,
+            reader,
+            chunk_size,
+            peer: None,
+            credit: 0,
+            outgoing: VecDeque::new(),
+            sent: 0,
+            eof: false,
+        }
+    }
+
+    /// Send to the given peer, from a `ROUTER` socket.
+    pub fn to(mut self, peer: RoutingId) -> SendChunked<'a, R> {
+        self.peer = Some(peer);
+        self
+    }
+
+    // Reads the next chunk, which is empty at the end of the reader.
+    fn read_chunk(&mut self) -> io::Result<Vec<u8>> {
+        let mut chunk = vec![0u8; self.chunk_size];
+        let mut len = 0;
+        while len < chunk.len() {
+            match self.reader.read(&mut chunk[len..]) {
+                Ok(0) => break,
+                Ok(n) => len += n,
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        chunk.truncate(len);
+        Ok(chunk)
+    }
+
+    // Takes the credit granted so far, without blocking.
+    fn poll_credit(&mut self) -> io::Result<()> {
+        loop {
+            let msgs = {
+                let mut stream = self.socket;
+                match stream.poll()? {
+                    Async::Ready(Some(msgs)) => msgs,
+                    _ => return Ok(()),
+                }
+            };
+            let msgs = match unroute(&self.peer, msgs) {
+                Some(msgs) => msgs,
+                None => continue,
+            };
+            if msgs.len() != 2 || &msgs[0][..] != CREDIT || msgs[1].len() != 4 {
+                return Err(invalid_message());
+            }
+            let credit = msgs[1].iter().fold(0u32, |acc, b| (acc << 8) | u32::from(*b));
+            self.credit = self.credit.saturating_add(credit);
+        }
+    }
+}
+
+impl<'a, R: Read> Future for SendChunked<'a, R> {
+    type Item = u64;
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        loop {
+            try_ready!(flush(self.socket, &mut self.outgoing));
+            if self.eof {
+                return Ok(Async::Ready(self.sent));
+            }
+            self.poll_credit()?;
+            if self.credit == 0 {
+                // `poll_credit` registered the task for the next grant.
+                return Ok(Async::NotReady);
+            }
+            let chunk = self.read_chunk()?;
+            if chunk.is_empty() {
+                self.eof = true;
+                let end = route(&self.peer, vec![Message::from_slice(END)]);
+                self.outgoing.push_back(end);
+            } else {
+                self.credit -= 1;
+                self.sent += chunk.len() as u64;
+                let msgs = vec![Message::from_slice(CHUNK), Message::from(chunk)];
+                let msgs = route(&self.peer, msgs);
+                self.outgoing.push_back(msgs);
+            }
+        }
+    }
+}
+
+/// A `Stream` of the chunks of a payload, granting the sender credit as
+/// chunks are consumed. The stream ends after the last chunk.
+/// This is returned by `Socket::recv_chunked`.
+pub struct RecvChunked<'a> {
+    socket: &'a Socket,
+    window: u32,
+    peer: Option<RoutingId>,
+    outgoing: VecDeque<Multipart>,
+    started: bool,
+    done: bool,
+}
+
+impl<'a> RecvChunked<'a> {
+    pub fn new(socket: &'a Socket) -> RecvChunked<'a> {
+        RecvChunked {
+            socket,
+            window: DEFAULT_WINDOW,
+            peer: None,
+            outgoing: VecDeque::new(),
+            started: false,
+            done: false,
+        }
+    }
+
+    /// Set the number of chunks in flight. Defaults to `DEFAULT_WINDOW`.
+    pub fn window(mut self, window: u32) -> RecvChunked<'a> {
+        assert!(window > 0, "window must be non-zero");
+        self.window = window;
+        self
+    }
+
+    /// Receive from the given peer, on a `ROUTER` socket.
+    pub fn from(mut self, peer: RoutingId) -> RecvChunked<'a> {
+        self.peer = Some(peer);
+        self
+    }
+
+    fn grant(&mut self, credit: u32) {
+        let credit = [(credit >> 24) as u8, (credit >> 16) as u8, (credit >> 8) as u8, credit as u8];
+        let msgs = vec![Message::from_slice(CREDIT), Message::from_slice(&credit)];
+        let msgs = route(&self.peer, msgs);
+        self.outgoing.push_back(msgs);
+    }
+}
+
+impl<'a> Stream for RecvChunked<'a> {
+    type Item = Bytes;
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        if self.done {
+            return Ok(Async::Ready(None));
+        }
+        if !self.started {
+            self.started = true;
+            let window = self.window;
+            self.grant(window);
+        }
+        // grants are sent in the background of receiving.
+        flush(self.socket, &mut self.outgoing)?;
+        loop {
+            let msgs = {
+                let mut stream = self.socket;
+                match try_ready!(stream.poll()) {
+                    Some(msgs) => msgs,
+                    None => return Ok(Async::Ready(None)),
+                }
+            };
+            let msgs = match unroute(&self.peer, msgs) {
+                Some(msgs) => msgs,
+                None => continue,
+            };
+            match msgs.first().map(|msg| &msg[..]) {
+                Some(CHUNK) if msgs.len() == 2 => {
+                    self.grant(1);
+                    flush(self.socket, &mut self.outgoing)?;
+                    return Ok(Async::Ready(Some(Bytes::from(&msgs[1][..]))));
+                }
+                Some(END) if msgs.len() == 1 => {
+                    self.done = true;
+                    return Ok(Async::Ready(None));
+                }
+                _ => return Err(invalid_message()),
+            }
+        }
+    }
+}
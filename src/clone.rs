@@ -0,0 +1,287 @@
+//! Reliable key-value state distribution, after the Clone pattern.
+//!
+//! A `CloneServer` holds a key-value map. It serves snapshots of the map
+//! over a `ROUTER` socket, and publishes every update over a `PUB` socket.
+//! A `CloneClient` subscribes to the updates first, then requests a
+//! snapshot, and applies the updates that are newer than the snapshot, so
+//! no update is lost in between.
+//!
+//! Every entry travels as a `[key, sequence, value]` message, where the
+//! sequence is an 8-byte big-endian number. An empty value deletes the key.
+//!
+//! See http://zguide.zeromq.org/page:all#Reliable-Pub-Sub-Clone-Pattern
+use std::collections::{HashMap, VecDeque};
+use std::io;
+
+use futures::{Async, Future, Poll, Stream};
+use tokio_core::reactor::Handle;
+use zmq::Message;
+
+use super::{Context, Multipart, Socket, SocketSend, DEALER, PUB, ROUTER, SUB};
+use super::sink::flush;
+
+/// Frame that starts a snapshot request.
+pub const SNAPSHOT_REQUEST: &[u8] = b"ICANHAZ?";
+
+/// Frame that ends a snapshot.
+pub const SNAPSHOT_END: &[u8] = b"KTHXBAI";
+
+/// A Future that serves a key-value map to `CloneClient`s, until its
+/// snapshot socket fails.
+pub struct CloneServer {
+    snapshot: Socket,
+    publisher: Socket,
+    map: HashMap<Vec<u8>, (u64, Vec<u8>)>,
+    sequence: u64,
+    outgoing: VecDeque<Multipart>,
+}
+
+impl CloneServer {
+    /// Create a new server, serving snapshots on a `ROUTER` socket bound to
+    /// the `snapshot` endpoint, and publishing updates on a `PUB` socket
+    /// bound to the `publisher` endpoint.
+    pub fn new(context: &Context, snapshot: &str, publisher: &str, handle: &Handle) -> io::Result<CloneServer> {
+        let snapshot_socket = context.socket(ROUTER, handle)?;
+        snapshot_socket.bind(snapshot)?;
+        let publisher_socket = context.socket(PUB, handle)?;
+        publisher_socket.bind(publisher)?;
+        Ok(CloneServer {
+            snapshot: snapshot_socket,
+            publisher: publisher_socket,
+            map: HashMap::new(),
+            sequence: 0,
+            outgoing: VecDeque::new(),
+        })
+    }
+
+    /// Returns the sequence number of the latest update.
+    pub fn sequence(&self) -> u64 {
+        self.sequence
+    }
+
+    /// Returns the value stored for a key.
+    pub fn get(&self, key: &[u8]) -> Option<&[u8]> {
+        self.map.get(key).map(|&(_, ref value)| &value[..])
+    }
+
+    /// Store a value for a key, publishing the update. An empty value
+    /// deletes the key.
+    pub fn set(&mut self, key: &[u8], value: &[u8]) -> io::Result<()> {
+        self.sequence += 1;
+        if value.is_empty() {
+            self.map.remove(key);
+        } else {
+            self.map.insert(key.to_vec(), (self.sequence, value.to_vec()));
+        }
+        let sequence = encode_sequence(self.sequence);
+        let update = [key, &sequence[..], value];
+        // `PUB` sockets drop messages instead of blocking.
+        match SocketSend::send_multipart(&self.publisher, update.iter().cloned(), 0) {
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => Ok(()),
+            r => r,
+        }
+    }
+
+    /// Remove a key, publishing the update.
+    pub fn remove(&mut self, key: &[u8]) -> io::Result<()> {
+        self.set(key, b"")
+    }
+
+    fn handle_request(&mut self, msgs: Multipart) {
+        if msgs.len() < 2 || &msgs[1][..] != SNAPSHOT_REQUEST {
+            warn!("dropping invalid snapshot request");
+            return;
+        }
+        let identity = &msgs[0];
+        let subtree: &[u8] = msgs.get(2).map(|m| &m[..]).unwrap_or(b"");
+        for (key, &(sequence, ref value)) in &self.map {
+            if key.starts_with(subtree) {
+                self.outgoing.push_back(vec![
+                    Message::from_slice(identity),
+                    Message::from_slice(key),
+                    Message::from_slice(&encode_sequence(sequence)),
+                    Message::from_slice(value),
+                ]);
+            }
+        }
+        self.outgoing.push_back(vec![
+            Message::from_slice(identity),
+            Message::from_slice(SNAPSHOT_END),
+            Message::from_slice(&encode_sequence(self.sequence)),
+            Message::from_slice(subtree),
+        ]);
+    }
+}
+
+impl Future for CloneServer {
+    type Item = ();
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        loop {
+            let msgs = {
+                let mut stream = &self.snapshot;
+                match stream.poll()? {
+                    Async::Ready(Some(msgs)) => msgs,
+                    Async::Ready(None) => return Ok(Async::Ready(())),
+                    Async::NotReady => break,
+                }
+            };
+            self.handle_request(msgs);
+        }
+        flush(&self.snapshot, &mut self.outgoing)?;
+        Ok(Async::NotReady)
+    }
+}
+
+/// A change to the map of a `CloneClient`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Change {
+    /// The key that changed.
+    pub key: Vec<u8>,
+    /// The new value, or `None` when the key was deleted.
+    pub value: Option<Vec<u8>>,
+}
+
+/// A client that keeps a local copy of the map of a `CloneServer`, yielding
+/// every `Change` to it as a `Stream`.
+///
+/// The entries of the initial snapshot are yielded as changes too.
+pub struct CloneClient {
+    snapshot: Socket,
+    subscriber: Socket,
+    map: HashMap<Vec<u8>, Vec<u8>>,
+    sequence: u64,
+    synced: bool,
+    outgoing: VecDeque<Multipart>,
+}
+
+impl CloneClient {
+    /// Create a new client for the keys starting with `subtree`, requesting
+    /// a snapshot from the server's `snapshot` endpoint, and subscribing to
+    /// its `publisher` endpoint.
+    pub fn new(
+        context: &Context,
+        snapshot: &str,
+        publisher: &str,
+        subtree: &[u8],
+        handle: &Handle,
+    ) -> io::Result<CloneClient> {
+        // subscribe first, so no update is missed while the snapshot arrives.
+        let subscriber = context.socket(SUB, handle)?;
+        subscriber.set_subscribe(subtree)?;
+        subscriber.connect(publisher)?;
+        let snapshot_socket = context.socket(DEALER, handle)?;
+        snapshot_socket.connect(snapshot)?;
+        let mut outgoing = VecDeque::new();
+        outgoing.push_back(vec![
+            Message::from_slice(SNAPSHOT_REQUEST),
+            Message::from_slice(subtree),
+        ]);
+        Ok(CloneClient {
+            snapshot: snapshot_socket,
+            subscriber,
+            map: HashMap::new(),
+            sequence: 0,
+            synced: false,
+            outgoing,
+        })
+    }
+
+    /// Returns the value stored for a key.
+    pub fn get(&self, key: &[u8]) -> Option<&[u8]> {
+        self.map.get(key).map(|value| &value[..])
+    }
+
+    /// A reference to the local copy of the map.
+    pub fn map(&self) -> &HashMap<Vec<u8>, Vec<u8>> {
+        &self.map
+    }
+
+    /// Returns the sequence number of the latest update applied.
+    pub fn sequence(&self) -> u64 {
+        self.sequence
+    }
+
+    /// Returns true once the snapshot has been fully received.
+    pub fn is_synced(&self) -> bool {
+        self.synced
+    }
+
+    // Applies an entry to the map, returning the change.
+    fn apply(&mut self, msgs: Multipart) -> io::Result<(u64, Change)> {
+        if msgs.len() != 3 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "invalid key-value message"));
+        }
+        let sequence = decode_sequence(&msgs[1])?;
+        let key = msgs[0].to_vec();
+        let value = if msgs[2].is_empty() {
+            self.map.remove(&key);
+            None
+        } else {
+            self.map.insert(key.clone(), msgs[2].to_vec());
+            Some(msgs[2].to_vec())
+        };
+        Ok((sequence, Change { key, value }))
+    }
+}
+
+impl Stream for CloneClient {
+    type Item = Change;
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        if !self.synced {
+            flush(&self.snapshot, &mut self.outgoing)?;
+            let msgs = {
+                let mut stream = &self.snapshot;
+                match try_ready!(stream.poll()) {
+                    Some(msgs) => msgs,
+                    None => return Ok(Async::Ready(None)),
+                }
+            };
+            if !msgs.is_empty() && &msgs[0][..] == SNAPSHOT_END {
+                self.sequence = match msgs.get(1) {
+                    Some(frame) => decode_sequence(frame)?,
+                    None => return Err(io::Error::new(io::ErrorKind::InvalidData, "invalid snapshot end")),
+                };
+                self.synced = true;
+                debug!("received snapshot at sequence {}", self.sequence);
+            } else {
+                let (_, change) = self.apply(msgs)?;
+                return Ok(Async::Ready(Some(change)));
+            }
+        }
+        loop {
+            let msgs = {
+                let mut stream = &self.subscriber;
+                match try_ready!(stream.poll()) {
+                    Some(msgs) => msgs,
+                    None => return Ok(Async::Ready(None)),
+                }
+            };
+            if msgs.len() != 3 || decode_sequence(&msgs[1])? <= self.sequence {
+                // stale updates are already part of the snapshot.
+                continue;
+            }
+            let (sequence, change) = self.apply(msgs)?;
+            self.sequence = sequence;
+            return Ok(Async::Ready(Some(change)));
+        }
+    }
+}
+
+fn encode_sequence(sequence: u64) -> [u8; 8] {
+    let mut buf = [0u8; 8];
+    for (idx, b) in buf.iter_mut().enumerate() {
+        *b = (sequence >> (56 - 8 * idx)) as u8;
+    }
+    buf
+}
+
+fn decode_sequence(frame: &[u8]) -> io::Result<u64> {
+    if frame.len() != 8 {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "invalid sequence number"));
+    }
+    Ok(frame.iter().fold(0, |acc, b| (acc << 8) | u64::from(*b)))
+}
@@ -0,0 +1,136 @@
+//! Codecs for sockets, mapping multi-part messages to typed items.
+use std::io;
+
+use futures::{Async, AsyncSink, Poll, Sink, StartSend, Stream};
+
+use super::{Multipart, Socket};
+
+/// Encodes items into multi-part messages, and decodes them back.
+pub trait MessageCodec {
+    /// The type of items sent and received with this codec.
+    type Item;
+
+    /// Encode an item into a multi-part message.
+    fn encode(&mut self, item: Self::Item) -> io::Result<Multipart>;
+
+    /// Decode a received multi-part message into an item.
+    fn decode(&mut self, msgs: Multipart) -> io::Result<Self::Item>;
+}
+
+/// A transport for `Socket`, implementing `Stream` and `Sink` for the items
+/// of a `MessageCodec`. This is returned by `Socket::framed_with`.
+pub struct CodecFramed<C> {
+    socket: Socket,
+    codec: C,
+    buffered: Option<Multipart>,
+}
+
+impl<C> CodecFramed<C>
+where
+    C: MessageCodec,
+{
+    pub fn new(socket: Socket, codec: C) -> CodecFramed<C> {
+        CodecFramed {
+            socket,
+            codec,
+            buffered: None,
+        }
+    }
+
+    /// A reference to the underlying socket.
+    pub fn get_ref(&self) -> &Socket {
+        &self.socket
+    }
+
+    /// A reference to the codec.
+    pub fn codec(&self) -> &C {
+        &self.codec
+    }
+
+    /// A mutable reference to the codec.
+    pub fn codec_mut(&mut self) -> &mut C {
+        &mut self.codec
+    }
+
+    /// Consumes the transport, returning the underlying socket.
+    pub fn into_inner(self) -> Socket {
+        self.socket
+    }
+
+    // Sends the encoded message waiting to go out, if any.
+    fn poll_flush(&mut self) -> Poll<(), io::Error> {
+        if let Some(msgs) = self.buffered.take() {
+            let mut sink = &self.socket;
+            if let AsyncSink::NotReady(msgs) = sink.start_send(msgs)? {
+                self.buffered = Some(msgs);
+                return Ok(Async::NotReady);
+            }
+        }
+        Ok(Async::Ready(()))
+    }
+}
+
+impl<C> Stream for CodecFramed<C>
+where
+    C: MessageCodec,
+{
+    type Item = C::Item;
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        let mut stream = &self.socket;
+        match try_ready!(stream.poll()) {
+            Some(msgs) => Ok(Async::Ready(Some(self.codec.decode(msgs)?))),
+            None => Ok(Async::Ready(None)),
+        }
+    }
+}
+
+impl<C> Sink for CodecFramed<C>
+where
+    C: MessageCodec,
+{
+    type SinkItem = C::Item;
+    type SinkError = io::Error;
+
+    fn start_send(&mut self, item: C::Item) -> StartSend<C::Item, Self::SinkError> {
+        if let Async::NotReady = self.poll_flush()? {
+            return Ok(AsyncSink::NotReady(item));
+        }
+        self.buffered = Some(self.codec.encode(item)?);
+        self.poll_flush()?;
+        Ok(AsyncSink::Ready)
+    }
+
+    fn poll_complete(&mut self) -> Poll<(), Self::SinkError> {
+        self.poll_flush()
+    }
+}
+
+/// Codec for raw multi-part messages, which are passed through unchanged.
+/// Useful as the inner codec of codec wrappers.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MultipartCodec;
+
+impl MessageCodec for MultipartCodec {
+    type Item = Multipart;
+
+    fn encode(&mut self, item: Multipart) -> io::Result<Multipart> {
+        Ok(item)
+    }
+
+    fn decode(&mut self, msgs: Multipart) -> io::Result<Multipart> {
+        Ok(msgs)
+    }
+}
+
+// Returns the only frame of a message, for codecs of one-part messages.
+pub(crate) fn single_frame(mut msgs: Multipart) -> io::Result<::zmq::Message> {
+    if msgs.len() != 1 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("expected a one-part message, got {} parts", msgs.len()),
+        ));
+    }
+    Ok(msgs.remove(0))
+}
@@ -0,0 +1,222 @@
+//! Compression of message frames.
+//!
+//! A `CompressedCodec` wraps another codec, compressing every outgoing
+//! frame and decompressing every incoming one. Each frame starts with a
+//! header byte, which is `0` for frames sent as-is, and `1` for compressed
+//! frames. Frames shorter than the threshold are sent as-is, since they
+//! don't gain from compression.
+//!
+//! A small frame can decompress into a huge one, so incoming frames are
+//! only decompressed up to a maximum size, failing with `InvalidData`
+//! past it.
+//!
+//! The `zstd` and `lz4` features enable the `Zstd` and `Lz4` algorithms.
+use std::io;
+#[cfg(feature = "zstd")]
+use std::io::Read;
+
+#[cfg(feature = "lz4")]
+use lz4;
+#[cfg(feature = "zstd")]
+use zstd;
+use zmq::Message;
+
+use super::Multipart;
+use super::codec::{MessageCodec, MultipartCodec};
+
+/// Default size, in bytes, below which frames are not compressed.
+pub const DEFAULT_THRESHOLD: usize = 256;
+
+/// Default size, in bytes, up to which incoming frames are decompressed.
+pub const DEFAULT_MAX_DECOMPRESSED_SIZE: usize = 16 * 1024 * 1024;
+
+const RAW: u8 = 0;
+const COMPRESSED: u8 = 1;
+
+/// A compression algorithm.
+pub trait Compression {
+    /// Compress a frame.
+    fn compress(&mut self, data: &[u8]) -> io::Result<Vec<u8>>;
+
+    /// Decompress a frame, failing with `InvalidData` if it decompresses
+    /// into more than `max_size` bytes, without allocating them.
+    fn decompress(&mut self, data: &[u8], max_size: usize) -> io::Result<Vec<u8>>;
+}
+
+/// The zstd algorithm, enabled with the `zstd` feature.
+#[cfg(feature = "zstd")]
+#[derive(Clone, Copy, Debug)]
+pub struct Zstd {
+    level: i32,
+}
+
+#[cfg(feature = "zstd")]
+impl Zstd {
+    /// Use the given compression level, from 1 to 21.
+    pub fn new(level: i32) -> Zstd {
+        Zstd { level }
+    }
+}
+
+#[cfg(feature = "zstd")]
+impl Default for Zstd {
+    fn default() -> Zstd {
+        Zstd::new(3)
+    }
+}
+
+#[cfg(feature = "zstd")]
+impl Compression for Zstd {
+    fn compress(&mut self, data: &[u8]) -> io::Result<Vec<u8>> {
+        zstd::encode_all(data, self.level)
+    }
+
+    fn decompress(&mut self, data: &[u8], max_size: usize) -> io::Result<Vec<u8>> {
+        let decoder = zstd::stream::Decoder::new(data)?;
+        let mut frame = Vec::new();
+        // reading one more byte than allowed tells a frame that fits from
+        // one that doesn't.
+        decoder.take(max_size as u64 + 1).read_to_end(&mut frame)?;
+        if frame.len() > max_size {
+            return Err(too_large(max_size));
+        }
+        Ok(frame)
+    }
+}
+
+/// The LZ4 algorithm, enabled with the `lz4` feature.
+#[cfg(feature = "lz4")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Lz4;
+
+#[cfg(feature = "lz4")]
+impl Compression for Lz4 {
+    fn compress(&mut self, data: &[u8]) -> io::Result<Vec<u8>> {
+        lz4::block::compress(data, None, true)
+    }
+
+    fn decompress(&mut self, data: &[u8], max_size: usize) -> io::Result<Vec<u8>> {
+        // compressed blocks start with their size, as a little-endian i32.
+        if data.len() < 4 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "lz4 block is missing its size",
+            ));
+        }
+        let size = data[..4]
+            .iter()
+            .rev()
+            .fold(0u32, |size, &byte| size << 8 | u32::from(byte));
+        if size as usize > max_size {
+            return Err(too_large(max_size));
+        }
+        lz4::block::decompress(data, None)
+    }
+}
+
+#[cfg(any(feature = "zstd", feature = "lz4"))]
+fn too_large(max_size: usize) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("frame decompresses into more than {} bytes", max_size),
+    )
+}
+
+/// A codec that compresses the frames encoded by an inner codec, and
+/// decompresses frames before the inner codec decodes them. Use it with
+/// `Socket::framed_with`.
+pub struct CompressedCodec<A, C = MultipartCodec> {
+    algorithm: A,
+    inner: C,
+    threshold: usize,
+    max_size: usize,
+}
+
+impl<A: Compression> CompressedCodec<A, MultipartCodec> {
+    /// Compress raw multi-part messages with the given algorithm.
+    pub fn new(algorithm: A) -> CompressedCodec<A, MultipartCodec> {
+        CompressedCodec::wrap(MultipartCodec, algorithm)
+    }
+}
+
+impl<A: Compression, C: MessageCodec> CompressedCodec<A, C> {
+    /// Compress the messages of an inner codec with the given algorithm.
+    pub fn wrap(inner: C, algorithm: A) -> CompressedCodec<A, C> {
+        CompressedCodec {
+            algorithm,
+            inner,
+            threshold: DEFAULT_THRESHOLD,
+            max_size: DEFAULT_MAX_DECOMPRESSED_SIZE,
+        }
+    }
+
+    /// Set the size, in bytes, below which frames are not compressed.
+    pub fn threshold(mut self, threshold: usize) -> CompressedCodec<A, C> {
+        self.threshold = threshold;
+        self
+    }
+
+    /// Set the size, in bytes, up to which incoming frames are
+    /// decompressed, such as the `ZMQ_MAXMSGSIZE` of the socket. Defaults to
+    /// `DEFAULT_MAX_DECOMPRESSED_SIZE`.
+    pub fn max_decompressed_size(mut self, max_size: usize) -> CompressedCodec<A, C> {
+        self.max_size = max_size;
+        self
+    }
+
+    /// A reference to the inner codec.
+    pub fn get_ref(&self) -> &C {
+        &self.inner
+    }
+
+    /// A mutable reference to the inner codec.
+    pub fn get_mut(&mut self) -> &mut C {
+        &mut self.inner
+    }
+
+    fn encode_frame(&mut self, frame: &[u8]) -> io::Result<Message> {
+        let (header, body) = if frame.len() < self.threshold {
+            (RAW, None)
+        } else {
+            let compressed = self.algorithm.compress(frame)?;
+            // keep incompressible frames as they are.
+            if compressed.len() < frame.len() {
+                (COMPRESSED, Some(compressed))
+            } else {
+                (RAW, None)
+            }
+        };
+        let body = body.as_ref().map(|body| &body[..]).unwrap_or(frame);
+        let mut bytes = Vec::with_capacity(body.len() + 1);
+        bytes.push(header);
+        bytes.extend_from_slice(body);
+        Ok(Message::from(bytes))
+    }
+
+    fn decode_frame(&mut self, frame: &[u8]) -> io::Result<Message> {
+        match frame.split_first() {
+            Some((&RAW, body)) => Ok(Message::from_slice(body)),
+            Some((&COMPRESSED, body)) => Ok(Message::from(self.algorithm.decompress(body, self.max_size)?)),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "invalid compression header",
+            )),
+        }
+    }
+}
+
+impl<A: Compression, C: MessageCodec> MessageCodec for CompressedCodec<A, C> {
+    type Item = C::Item;
+
+    fn encode(&mut self, item: C::Item) -> io::Result<Multipart> {
+        let msgs = self.inner.encode(item)?;
+        msgs.iter().map(|msg| self.encode_frame(msg)).collect()
+    }
+
+    fn decode(&mut self, msgs: Multipart) -> io::Result<C::Item> {
+        let msgs = msgs.iter()
+            .map(|msg| self.decode_frame(msg))
+            .collect::<io::Result<Multipart>>()?;
+        self.inner.decode(msgs)
+    }
+}
@@ -0,0 +1,278 @@
+//! Sockets described by configuration.
+//!
+//! A `SocketConfig` describes a socket: its type, the endpoints it binds or
+//! connects to, its options, and its security mechanism. With the `serde`
+//! feature, it can be deserialized, so applications can describe their
+//! sockets in YAML or TOML files, such as:
+//!
+//! ```toml
+//! type = "PUB"
+//! bind = ["tcp://*:5556"]
+//!
+//! [options]
+//! sndhwm = 100000
+//! linger = 0
+//!
+//! [security]
+//! mechanism = "curve"
+//! server = true
+//! secret_key = "<the 40-character Z85 secret key>"
+//! ```
+//!
+//! Options have the names of the endpoint query options, in
+//! `endpoint::EndpointOptions`. Keys are given in their Z85 form.
+use std::collections::BTreeMap;
+#[cfg(feature = "serde")]
+use std::fmt;
+use std::io;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Deserializer};
+#[cfg(feature = "serde")]
+use serde::de::{self, MapAccess, Visitor};
+use tokio_core::reactor::Handle;
+use zmq::SocketType;
+
+use super::{Context, Socket};
+use super::endpoint::EndpointOptions;
+
+/// The security mechanism of a configured socket.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Security {
+    /// No security, the default.
+    Null,
+    /// A PLAIN server, whose clients are checked by an `auth::Authenticator`.
+    PlainServer,
+    /// A PLAIN client.
+    PlainClient { username: String, password: String },
+    /// A CURVE server.
+    CurveServer { secret_key: String },
+    /// A CURVE client, with its own keys and the public key of the server.
+    CurveClient {
+        public_key: String,
+        secret_key: String,
+        server_key: String,
+    },
+}
+
+impl Default for Security {
+    fn default() -> Security {
+        Security::Null
+    }
+}
+
+impl Security {
+    /// Set the security options of a socket.
+    pub fn apply(&self, socket: &Socket) -> io::Result<()> {
+        match *self {
+            Security::Null => Ok(()),
+            Security::PlainServer => socket.set_plain_server(true),
+            Security::PlainClient {
+                ref username,
+                ref password,
+            } => {
+                socket.set_plain_username(Some(username.as_str()))?;
+                socket.set_plain_password(Some(password.as_str()))
+            }
+            Security::CurveServer { ref secret_key } => {
+                socket.set_curve_server(true)?;
+                socket.set_curve_secretkey(secret_key.as_bytes())
+            }
+            Security::CurveClient {
+                ref public_key,
+                ref secret_key,
+                ref server_key,
+            } => {
+                socket.set_curve_publickey(public_key.as_bytes())?;
+                socket.set_curve_secretkey(secret_key.as_bytes())?;
+                socket.set_curve_serverkey(server_key.as_bytes())
+            }
+        }
+    }
+
+    // Builds the security settings from the keys of a `security` section.
+    #[cfg(feature = "serde")]
+    fn from_map(mut map: BTreeMap<String, String>) -> Result<Security, String> {
+        let mut take = |key: &str| map.remove(key).ok_or_else(|| format!("missing security setting: {}", key));
+        let mechanism = take("mechanism")?;
+        let server = take("server").map(|s| s == "true").unwrap_or(false);
+        let security = match (mechanism.as_str(), server) {
+            ("null", _) => Security::Null,
+            ("plain", true) => Security::PlainServer,
+            ("plain", false) => Security::PlainClient {
+                username: take("username")?,
+                password: take("password")?,
+            },
+            ("curve", true) => Security::CurveServer {
+                secret_key: take("secret_key")?,
+            },
+            ("curve", false) => Security::CurveClient {
+                public_key: take("public_key")?,
+                secret_key: take("secret_key")?,
+                server_key: take("server_key")?,
+            },
+            (mechanism, _) => return Err(format!("unknown security mechanism: {}", mechanism)),
+        };
+        Ok(security)
+    }
+}
+
+/// The configuration of a socket.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SocketConfig {
+    /// The type of the socket, such as `"PUB"`.
+    pub socket_type: String,
+    /// The endpoints the socket binds to.
+    pub bind: Vec<String>,
+    /// The endpoints the socket connects to.
+    pub connect: Vec<String>,
+    /// The prefixes a `SUB` socket subscribes to.
+    pub subscribe: Vec<String>,
+    /// Socket options, by name, such as `"sndhwm"`.
+    pub options: BTreeMap<String, String>,
+    /// The security mechanism.
+    pub security: Security,
+}
+
+impl SocketConfig {
+    /// Create the configuration of a socket of the given type, such as
+    /// `"PUB"`, without endpoints or options.
+    pub fn new(socket_type: &str) -> SocketConfig {
+        SocketConfig {
+            socket_type: socket_type.to_string(),
+            ..SocketConfig::default()
+        }
+    }
+
+    /// Returns the socket type, failing with `InvalidInput` for unknown
+    /// types.
+    pub fn socket_type(&self) -> io::Result<SocketType> {
+        use zmq::SocketType::*;
+        let typ = match self.socket_type.to_uppercase().as_str() {
+            "PAIR" => PAIR,
+            "PUB" => PUB,
+            "SUB" => SUB,
+            "REQ" => REQ,
+            "REP" => REP,
+            "DEALER" => DEALER,
+            "ROUTER" => ROUTER,
+            "PULL" => PULL,
+            "PUSH" => PUSH,
+            "XPUB" => XPUB,
+            "XSUB" => XSUB,
+            "STREAM" => STREAM,
+            typ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("unknown socket type: {}", typ),
+                ))
+            }
+        };
+        Ok(typ)
+    }
+
+    /// Create the configured socket in `context`. Security and options are
+    /// set before the socket binds and connects.
+    pub fn build(&self, context: &Context, handle: &Handle) -> io::Result<Socket> {
+        let socket = context.socket(self.socket_type()?, handle)?;
+        self.security.apply(&socket)?;
+        let options = self.options
+            .iter()
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect::<EndpointOptions>();
+        options.apply(&socket)?;
+        for prefix in &self.subscribe {
+            socket.set_subscribe(prefix.as_bytes())?;
+        }
+        for endpoint in &self.bind {
+            socket.bind(endpoint)?;
+        }
+        for endpoint in &self.connect {
+            socket.connect(endpoint)?;
+        }
+        Ok(socket)
+    }
+}
+
+// A scalar setting, such as a number, a boolean, or a string, kept as text.
+#[cfg(feature = "serde")]
+struct Scalar(String);
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for Scalar {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Scalar, D::Error> {
+        struct ScalarVisitor;
+
+        impl<'de> Visitor<'de> for ScalarVisitor {
+            type Value = Scalar;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a string, a number, or a boolean")
+            }
+
+            fn visit_str<E: de::Error>(self, value: &str) -> Result<Scalar, E> {
+                Ok(Scalar(value.to_string()))
+            }
+
+            fn visit_bool<E: de::Error>(self, value: bool) -> Result<Scalar, E> {
+                Ok(Scalar(value.to_string()))
+            }
+
+            fn visit_i64<E: de::Error>(self, value: i64) -> Result<Scalar, E> {
+                Ok(Scalar(value.to_string()))
+            }
+
+            fn visit_u64<E: de::Error>(self, value: u64) -> Result<Scalar, E> {
+                Ok(Scalar(value.to_string()))
+            }
+        }
+
+        deserializer.deserialize_any(ScalarVisitor)
+    }
+}
+
+#[cfg(feature = "serde")]
+fn scalars(map: BTreeMap<String, Scalar>) -> BTreeMap<String, String> {
+    map.into_iter().map(|(key, value)| (key, value.0)).collect()
+}
+
+#[cfg(feature = "serde")]
+const FIELDS: &[&str] = &["type", "bind", "connect", "subscribe", "options", "security"];
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for SocketConfig {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<SocketConfig, D::Error> {
+        struct SocketConfigVisitor;
+
+        impl<'de> Visitor<'de> for SocketConfigVisitor {
+            type Value = SocketConfig;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a socket configuration")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<SocketConfig, A::Error> {
+                let mut socket_type = None;
+                let mut config = SocketConfig::default();
+                while let Some(key) = map.next_key::<String>()? {
+                    match key.as_str() {
+                        "type" => socket_type = Some(map.next_value()?),
+                        "bind" => config.bind = map.next_value()?,
+                        "connect" => config.connect = map.next_value()?,
+                        "subscribe" => config.subscribe = map.next_value()?,
+                        "options" => config.options = scalars(map.next_value()?),
+                        "security" => {
+                            config.security = Security::from_map(scalars(map.next_value()?))
+                                .map_err(de::Error::custom)?
+                        }
+                        key => return Err(de::Error::unknown_field(key, FIELDS)),
+                    }
+                }
+                config.socket_type = socket_type.ok_or_else(|| de::Error::missing_field("type"))?;
+                Ok(config)
+            }
+        }
+
+        deserializer.deserialize_map(SocketConfigVisitor)
+    }
+}
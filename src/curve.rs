@@ -0,0 +1,260 @@
+//! CURVE certificates.
+//!
+//! Certificates are stored in the ZPL format used by CZMQ's `zcert`, so
+//! keys provisioned with CZMQ or pyzmq tools can be loaded directly. A
+//! certificate is saved as two files: the public one, holding the public
+//! key, and a secret one, with the `_secret` suffix, holding both keys.
+//! Keys are encoded with Z85.
+//!
+//! See https://rfc.zeromq.org/spec:4/ZPL/
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+use zmq;
+
+use super::Socket;
+
+/// Length of CURVE keys, in bytes.
+pub const KEY_SIZE: usize = 32;
+
+/// A CURVE key pair, with optional metadata. The secret key is missing for
+/// certificates loaded from a public file.
+#[derive(Clone)]
+pub struct Certificate {
+    public_key: [u8; KEY_SIZE],
+    secret_key: Option<[u8; KEY_SIZE]>,
+    metadata: BTreeMap<String, String>,
+}
+
+impl Certificate {
+    /// Generate a new certificate, with a fresh key pair.
+    pub fn new() -> io::Result<Certificate> {
+        let pair = zmq::CurveKeyPair::new()?;
+        Ok(Certificate {
+            public_key: pair.public_key,
+            secret_key: Some(pair.secret_key),
+            metadata: BTreeMap::new(),
+        })
+    }
+
+    /// Create a certificate from existing keys.
+    pub fn from_keys(public_key: [u8; KEY_SIZE], secret_key: Option<[u8; KEY_SIZE]>) -> Certificate {
+        Certificate {
+            public_key,
+            secret_key,
+            metadata: BTreeMap::new(),
+        }
+    }
+
+    /// Load a certificate from a file. When a secret file exists next to the
+    /// given path, it is loaded instead, so the secret key is available.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Certificate> {
+        let secret = secret_path(path.as_ref());
+        if secret.exists() {
+            Certificate::load_file(&secret)
+        } else {
+            Certificate::load_file(path.as_ref())
+        }
+    }
+
+    // Reads and parses a single certificate file.
+    fn load_file(path: &Path) -> io::Result<Certificate> {
+        let mut text = String::new();
+        File::open(path)?.read_to_string(&mut text)?;
+        Certificate::parse(&text)
+    }
+
+    /// Parse a certificate in the ZPL format.
+    pub fn parse(text: &str) -> io::Result<Certificate> {
+        let entries = parse_zpl(text)?;
+        let mut public_key = None;
+        let mut secret_key = None;
+        let mut metadata = BTreeMap::new();
+        for (section, name, value) in entries {
+            match (&section[..], &name[..]) {
+                ("curve", "public-key") => public_key = Some(decode_key(&value)?),
+                ("curve", "secret-key") => secret_key = Some(decode_key(&value)?),
+                ("metadata", _) => {
+                    metadata.insert(name, value);
+                }
+                _ => {}
+            }
+        }
+        let public_key = public_key.ok_or_else(|| invalid_data("certificate has no public key"))?;
+        Ok(Certificate {
+            public_key,
+            secret_key,
+            metadata,
+        })
+    }
+
+    /// Returns the public key.
+    pub fn public_key(&self) -> &[u8; KEY_SIZE] {
+        &self.public_key
+    }
+
+    /// Returns the secret key, if known.
+    pub fn secret_key(&self) -> Option<&[u8; KEY_SIZE]> {
+        self.secret_key.as_ref()
+    }
+
+    /// Returns the public key, encoded with Z85.
+    pub fn public_txt(&self) -> String {
+        encode_key(&self.public_key)
+    }
+
+    /// Returns the secret key, encoded with Z85, if known.
+    pub fn secret_txt(&self) -> Option<String> {
+        self.secret_key.as_ref().map(|key| encode_key(key))
+    }
+
+    /// Returns the value of a metadata entry.
+    pub fn meta(&self, name: &str) -> Option<&str> {
+        self.metadata.get(name).map(|value| &value[..])
+    }
+
+    /// Set a metadata entry.
+    pub fn set_meta(&mut self, name: &str, value: &str) {
+        self.metadata.insert(name.to_string(), value.to_string());
+    }
+
+    /// Returns every metadata entry.
+    pub fn metadata(&self) -> &BTreeMap<String, String> {
+        &self.metadata
+    }
+
+    /// Save the certificate to the public file at the given path, and, when
+    /// the secret key is known, to the secret file next to it.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        self.save_public(path.as_ref())?;
+        if self.secret_key.is_some() {
+            self.save_secret(secret_path(path.as_ref()))?;
+        }
+        Ok(())
+    }
+
+    /// Save the public key and metadata to the given path.
+    pub fn save_public<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        File::create(path)?.write_all(self.to_zpl(false).as_bytes())
+    }
+
+    /// Save both keys and metadata to the given path. Fails when the secret
+    /// key is unknown.
+    pub fn save_secret<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        if self.secret_key.is_none() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "certificate has no secret key",
+            ));
+        }
+        File::create(path)?.write_all(self.to_zpl(true).as_bytes())
+    }
+
+    /// Returns the certificate in the ZPL format, with the secret key only
+    /// when `secret` is true.
+    pub fn to_zpl(&self, secret: bool) -> String {
+        let mut text = String::new();
+        if secret {
+            text.push_str("#   ZeroMQ CURVE **Secret** Certificate\n");
+            text.push_str("#   DO NOT PROVIDE THIS FILE TO OTHER USERS nor change its permissions.\n");
+        } else {
+            text.push_str("#   ZeroMQ CURVE Public Certificate\n");
+            text.push_str("#   Exchange securely, or use a secure mechanism to verify the contents\n");
+            text.push_str("#   of this file after exchange. Store public certificates in your home\n");
+            text.push_str("#   directory, in the .curve subdirectory.\n");
+        }
+        text.push_str("\nmetadata\n");
+        for (name, value) in &self.metadata {
+            text.push_str(&format!("    {} = \"{}\"\n", name, value));
+        }
+        text.push_str("curve\n");
+        text.push_str(&format!("    public-key = \"{}\"\n", self.public_txt()));
+        if secret {
+            if let Some(key) = self.secret_txt() {
+                text.push_str(&format!("    secret-key = \"{}\"\n", key));
+            }
+        }
+        text
+    }
+
+    /// Use the keys of this certificate on a socket. Fails when the secret
+    /// key is unknown.
+    pub fn apply(&self, socket: &Socket) -> io::Result<()> {
+        let secret_key = self.secret_key.as_ref().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "certificate has no secret key")
+        })?;
+        socket.set_curve_publickey(&self.public_key)?;
+        socket.set_curve_secretkey(secret_key)
+    }
+}
+
+// Returns the path of the secret file for a public certificate file.
+fn secret_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push("_secret");
+    PathBuf::from(name)
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn encode_key(key: &[u8; KEY_SIZE]) -> String {
+    // 32 bytes are always a valid Z85 input, as a multiple of 4.
+    zmq::z85_encode(key).expect("keys have a valid Z85 length")
+}
+
+fn decode_key(text: &str) -> io::Result<[u8; KEY_SIZE]> {
+    let bytes = zmq::z85_decode(text).map_err(|_| invalid_data("invalid Z85 key"))?;
+    if bytes.len() != KEY_SIZE {
+        return Err(invalid_data("invalid key length"));
+    }
+    let mut key = [0u8; KEY_SIZE];
+    key.copy_from_slice(&bytes);
+    Ok(key)
+}
+
+// Parses the name-value entries of a ZPL document, as
+// `(section, name, value)`, where the section is the top-level name. Only
+// the two levels used by certificates are supported.
+fn parse_zpl(text: &str) -> io::Result<Vec<(String, String, String)>> {
+    let mut entries = Vec::new();
+    let mut section = String::new();
+    for line in text.lines() {
+        let content = line.trim_right();
+        let trimmed = content.trim_left();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        let indented = trimmed.len() < content.len();
+        let (name, value) = match trimmed.find('=') {
+            Some(idx) => (trimmed[..idx].trim(), Some(trimmed[idx + 1..].trim())),
+            None => (trimmed, None),
+        };
+        if !indented {
+            section = name.to_string();
+            continue;
+        }
+        let value = match value {
+            Some(value) => unquote(value),
+            None => continue,
+        };
+        entries.push((section.clone(), name.to_string(), value));
+    }
+    Ok(entries)
+}
+
+fn unquote(value: &str) -> String {
+    let value = match value.find('#') {
+        Some(idx) if !value.starts_with('"') && !value.starts_with('\'') => value[..idx].trim(),
+        _ => value,
+    };
+    for quote in &['"', '\''] {
+        if value.len() >= 2 && value.starts_with(*quote) && value.ends_with(*quote) {
+            return value[1..value.len() - 1].to_string();
+        }
+    }
+    value.to_string()
+}
@@ -0,0 +1,198 @@
+//! Demultiplexing a `SUB` socket by topic.
+//!
+//! A `Demux` owns a `SUB` socket, and routes each incoming message to the
+//! streams taken for the topic prefixes it matches, through bounded
+//! channels. Taking a topic subscribes the socket to it, and a dropped
+//! stream unsubscribes it again, once a message for it arrives. A message waits until every
+//! matching stream has room for it, so a slow consumer slows the socket
+//! down, which then drops messages at its high-water mark.
+use std::io;
+use std::sync::{Arc, Mutex};
+
+use futures::{Async, AsyncSink, Future, Poll, Sink, Stream};
+use futures::sync::mpsc;
+use futures::task::AtomicTask;
+use zmq::Message;
+
+use super::{Multipart, Socket};
+
+struct Route {
+    prefix: Vec<u8>,
+    tx: mpsc::Sender<Multipart>,
+}
+
+#[derive(Default)]
+struct Routes {
+    routes: Vec<Route>,
+    // prefixes taken since the last poll, not subscribed yet.
+    added: Vec<Vec<u8>>,
+}
+
+struct Shared {
+    routes: Mutex<Routes>,
+    task: AtomicTask,
+}
+
+/// A Future routing the messages of a `SUB` socket to the streams taken
+/// from its `DemuxHandle`s. It runs until the socket fails. This is
+/// returned by `Socket::demux`.
+pub struct Demux {
+    socket: Socket,
+    capacity: usize,
+    shared: Arc<Shared>,
+    // a message, with the routes it still has to go to.
+    pending: Option<(Multipart, Vec<usize>)>,
+}
+
+impl Demux {
+    pub fn new(socket: Socket, capacity: usize) -> Demux {
+        Demux {
+            socket,
+            capacity,
+            shared: Arc::new(Shared {
+                routes: Mutex::new(Routes::default()),
+                task: AtomicTask::new(),
+            }),
+            pending: None,
+        }
+    }
+
+    /// Returns a handle, for taking topic streams.
+    pub fn handle(&self) -> DemuxHandle {
+        DemuxHandle {
+            capacity: self.capacity,
+            shared: self.shared.clone(),
+        }
+    }
+
+    // Subscribes to the prefixes taken since the last poll.
+    fn update_subscriptions(&mut self) -> io::Result<()> {
+        let added: Vec<Vec<u8>> = self.shared.routes.lock().unwrap().added.drain(..).collect();
+        for prefix in added {
+            self.socket.set_subscribe(&prefix)?;
+        }
+        Ok(())
+    }
+
+    // Sends the pending message to its remaining routes.
+    fn send_pending(&mut self) -> Poll<(), io::Error> {
+        let (msgs, mut targets) = match self.pending.take() {
+            Some(pending) => pending,
+            None => return Ok(Async::Ready(())),
+        };
+        let mut routes = self.shared.routes.lock().unwrap();
+        let mut closed = Vec::new();
+        targets.retain(|&idx| {
+            let route = &mut routes.routes[idx];
+            match route.tx.start_send(copy(&msgs)) {
+                Ok(AsyncSink::Ready) => {
+                    let _ = route.tx.poll_complete();
+                    false
+                }
+                Ok(AsyncSink::NotReady(_)) => true,
+                Err(_) => {
+                    closed.push(idx);
+                    false
+                }
+            }
+        });
+        let remaining = !targets.is_empty();
+        if remaining {
+            self.pending = Some((msgs, targets));
+        }
+        // removing routes shifts the indices, so it comes last.
+        for idx in closed.into_iter().rev() {
+            let route = routes.routes.remove(idx);
+            self.socket.set_unsubscribe(&route.prefix)?;
+        }
+        if remaining {
+            Ok(Async::NotReady)
+        } else {
+            Ok(Async::Ready(()))
+        }
+    }
+}
+
+impl Future for Demux {
+    type Item = ();
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        self.shared.task.register();
+        self.update_subscriptions()?;
+        loop {
+            try_ready!(self.send_pending());
+            let msgs = {
+                let mut stream = &self.socket;
+                match try_ready!(stream.poll()) {
+                    Some(msgs) => msgs,
+                    None => return Ok(Async::Ready(())),
+                }
+            };
+            let topic = msgs.first().map(|msg| msg.to_vec()).unwrap_or_default();
+            let targets: Vec<usize> = self.shared
+                .routes
+                .lock()
+                .unwrap()
+                .routes
+                .iter()
+                .enumerate()
+                .filter(|&(_, route)| topic.starts_with(&route.prefix))
+                .map(|(idx, _)| idx)
+                .collect();
+            if !targets.is_empty() {
+                self.pending = Some((msgs, targets));
+            }
+        }
+    }
+}
+
+/// A handle to a `Demux`, for taking topic streams. It can be cloned.
+#[derive(Clone)]
+pub struct DemuxHandle {
+    capacity: usize,
+    shared: Arc<Shared>,
+}
+
+impl DemuxHandle {
+    /// Returns a stream of the messages whose topic, the first frame,
+    /// starts with the given prefix. Up to `capacity` messages wait in the
+    /// stream. A topic can be taken many times, and every stream gets every
+    /// matching message.
+    pub fn take_topic(&self, prefix: &[u8]) -> TopicStream {
+        let (tx, rx) = mpsc::channel(self.capacity);
+        {
+            let mut routes = self.shared.routes.lock().unwrap();
+            routes.routes.push(Route {
+                prefix: prefix.to_vec(),
+                tx,
+            });
+            routes.added.push(prefix.to_vec());
+        }
+        self.shared.task.notify();
+        TopicStream { rx }
+    }
+}
+
+/// A `Stream` of the messages of a topic. It ends once the `Demux` is gone.
+/// This is returned by `DemuxHandle::take_topic`.
+pub struct TopicStream {
+    rx: mpsc::Receiver<Multipart>,
+}
+
+impl Stream for TopicStream {
+    type Item = Multipart;
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        // `mpsc::Receiver` never fails.
+        match self.rx.poll() {
+            Ok(polled) => Ok(polled),
+            Err(()) => Ok(Async::Ready(None)),
+        }
+    }
+}
+
+fn copy(msgs: &[Message]) -> Multipart {
+    msgs.iter().map(|msg| Message::from_slice(msg)).collect()
+}
@@ -0,0 +1,165 @@
+//! Dumping message traffic in the pcapng format.
+//!
+//! A `DumpWriter` writes a pcapng file with one interface, of a user link
+//! type, where every packet is a multipart-message. Tools that read pcapng,
+//! such as Wireshark with a dissector for the payload below, can then
+//! analyze the traffic.
+//!
+//! Every packet holds, with integers in big-endian order:
+//!
+//! * the version of the payload format, `1`, as a `u8`;
+//! * the direction, `0` for incoming and `1` for outgoing, as a `u8`;
+//! * the length of the endpoint as a `u16`, followed by the endpoint, in
+//!   UTF-8;
+//! * the number of frames, as a `u32`;
+//! * every frame, as its flags as a `u8`, where bit 0 is set when more
+//!   frames follow, then its length as a `u32`, followed by its bytes.
+//!
+//! Timestamps are in microseconds, the pcapng default.
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::Message;
+
+/// The `LINKTYPE_USER0` link type, used by default.
+pub const LINKTYPE_USER0: u16 = 147;
+
+/// Version of the packet payload format.
+pub const PAYLOAD_VERSION: u8 = 1;
+
+const SECTION_HEADER_BLOCK: u32 = 0x0A0D_0D0A;
+const INTERFACE_DESCRIPTION_BLOCK: u32 = 0x0000_0001;
+const ENHANCED_PACKET_BLOCK: u32 = 0x0000_0006;
+const BYTE_ORDER_MAGIC: u32 = 0x1A2B_3C4D;
+
+/// The direction of a dumped message.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    /// Received by the socket.
+    Incoming,
+    /// Sent by the socket.
+    Outgoing,
+}
+
+/// Writes messages to a pcapng file.
+pub struct DumpWriter<W: Write> {
+    writer: W,
+}
+
+impl DumpWriter<BufWriter<File>> {
+    /// Create a dump at the given path, replacing any existing file, with
+    /// the `LINKTYPE_USER0` link type.
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<DumpWriter<BufWriter<File>>> {
+        DumpWriter::new(BufWriter::new(File::create(path)?), LINKTYPE_USER0)
+    }
+}
+
+impl<W: Write> DumpWriter<W> {
+    /// Start a dump on the given writer, with the given link type, such as
+    /// `LINKTYPE_USER0`.
+    pub fn new(mut writer: W, link_type: u16) -> io::Result<DumpWriter<W>> {
+        // section header block, without options, of unspecified length.
+        let mut shb = Vec::with_capacity(16);
+        shb.extend_from_slice(&u32_le(BYTE_ORDER_MAGIC));
+        shb.extend_from_slice(&u16_le(1));
+        shb.extend_from_slice(&u16_le(0));
+        shb.extend_from_slice(&[0xff; 8]);
+        write_block(&mut writer, SECTION_HEADER_BLOCK, &shb)?;
+        // interface description block, without a snapshot length.
+        let mut idb = Vec::with_capacity(8);
+        idb.extend_from_slice(&u16_le(link_type));
+        idb.extend_from_slice(&u16_le(0));
+        idb.extend_from_slice(&u32_le(0));
+        write_block(&mut writer, INTERFACE_DESCRIPTION_BLOCK, &idb)?;
+        Ok(DumpWriter { writer })
+    }
+
+    /// Dump a message, timestamped with the current time.
+    pub fn write(&mut self, direction: Direction, endpoint: &str, msgs: &[Message]) -> io::Result<()> {
+        let since_epoch = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        let timestamp = since_epoch.as_secs() * 1_000_000 + u64::from(since_epoch.subsec_nanos() / 1_000);
+        self.write_at(timestamp, direction, endpoint, msgs)
+    }
+
+    /// Dump a message with the given timestamp, in microseconds since the
+    /// UNIX epoch.
+    pub fn write_at(
+        &mut self,
+        timestamp: u64,
+        direction: Direction,
+        endpoint: &str,
+        msgs: &[Message],
+    ) -> io::Result<()> {
+        let packet = encode_packet(direction, endpoint, msgs)?;
+        let mut epb = Vec::with_capacity(20 + packet.len() + 3);
+        epb.extend_from_slice(&u32_le(0));
+        epb.extend_from_slice(&u32_le((timestamp >> 32) as u32));
+        epb.extend_from_slice(&u32_le(timestamp as u32));
+        epb.extend_from_slice(&u32_le(packet.len() as u32));
+        epb.extend_from_slice(&u32_le(packet.len() as u32));
+        epb.extend_from_slice(&packet);
+        while epb.len() % 4 != 0 {
+            epb.push(0);
+        }
+        write_block(&mut self.writer, ENHANCED_PACKET_BLOCK, &epb)
+    }
+
+    /// Flush the underlying writer.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    /// Consumes the writer, returning the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+/// Encode a message as the payload of a packet.
+pub fn encode_packet(direction: Direction, endpoint: &str, msgs: &[Message]) -> io::Result<Vec<u8>> {
+    if endpoint.len() > u16::max_value() as usize {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "endpoint is too long"));
+    }
+    let mut packet = Vec::new();
+    packet.push(PAYLOAD_VERSION);
+    packet.push(match direction {
+        Direction::Incoming => 0,
+        Direction::Outgoing => 1,
+    });
+    packet.extend_from_slice(&u16_be(endpoint.len() as u16));
+    packet.extend_from_slice(endpoint.as_bytes());
+    packet.extend_from_slice(&u32_be(msgs.len() as u32));
+    for (idx, msg) in msgs.iter().enumerate() {
+        packet.push(if idx + 1 < msgs.len() { 1 } else { 0 });
+        packet.extend_from_slice(&u32_be(msg.len() as u32));
+        packet.extend_from_slice(msg);
+    }
+    Ok(packet)
+}
+
+// Writes a pcapng block, whose body is already padded to 32 bits.
+fn write_block<W: Write>(writer: &mut W, block_type: u32, body: &[u8]) -> io::Result<()> {
+    let total = u32_le(12 + body.len() as u32);
+    writer.write_all(&u32_le(block_type))?;
+    writer.write_all(&total)?;
+    writer.write_all(body)?;
+    writer.write_all(&total)
+}
+
+fn u16_le(value: u16) -> [u8; 2] {
+    [value as u8, (value >> 8) as u8]
+}
+
+fn u32_le(value: u32) -> [u8; 4] {
+    [value as u8, (value >> 8) as u8, (value >> 16) as u8, (value >> 24) as u8]
+}
+
+fn u16_be(value: u16) -> [u8; 2] {
+    [(value >> 8) as u8, value as u8]
+}
+
+fn u32_be(value: u32) -> [u8; 4] {
+    [(value >> 24) as u8, (value >> 16) as u8, (value >> 8) as u8, value as u8]
+}
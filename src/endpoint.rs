@@ -0,0 +1,490 @@
+//! Endpoint helpers for sockets.
+//!
+//! String endpoints may end with a query of socket options, as in
+//! `tcp://127.0.0.1:5555?sndhwm=1000&linger=0`. `Socket::bind` and
+//! `Socket::connect` apply them before binding or connecting, so sockets can
+//! be tuned from configuration strings alone.
+use std::fmt;
+use std::io;
+use std::iter::FromIterator;
+use std::net::SocketAddr;
+use std::str::FromStr;
+
+use super::{has, Socket};
+
+/// Parses a `tcp://` endpoint, as reported by `Socket::get_last_endpoint`,
+/// into a `SocketAddr`.
+pub fn parse_tcp_endpoint(endpoint: &str) -> io::Result<SocketAddr> {
+    let addr = match endpoint.find("://") {
+        Some(idx) if &endpoint[..idx] == "tcp" => &endpoint[idx + 3..],
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("not a tcp endpoint: {}", endpoint),
+            ))
+        }
+    };
+    addr.parse().map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("invalid tcp endpoint address: {}", addr),
+        )
+    })
+}
+
+// Interprets the raw last-endpoint value, which might not be valid UTF-8.
+pub(crate) fn last_endpoint_string(endpoint: Result<String, Vec<u8>>) -> io::Result<String> {
+    endpoint.map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            "last endpoint is not valid UTF-8",
+        )
+    })
+}
+
+/// A ØMQ endpoint, as given to `Socket::bind` and `Socket::connect`.
+///
+/// Parsing an endpoint checks its transport and address, so malformed
+/// endpoints are reported before they reach libzmq. A port of `0`, written
+/// as `*` or `0`, asks for an ephemeral port when binding.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Endpoint {
+    /// A `tcp://` endpoint. The host is an address, a hostname, an
+    /// interface name, or `*`. When connecting, a `source` address may be
+    /// given, as in `tcp://source;host:port`.
+    Tcp {
+        source: Option<String>,
+        host: String,
+        port: u16,
+    },
+    /// An `ipc://` endpoint, with its path or abstract name.
+    Ipc(String),
+    /// An `inproc://` endpoint, with its name.
+    Inproc(String),
+    /// A `pgm://` or `epgm://` endpoint: an interface, a multicast address,
+    /// and a port. `epgm` selects the UDP encapsulated protocol.
+    Pgm {
+        epgm: bool,
+        interface: String,
+        address: String,
+        port: u16,
+    },
+    /// A `vmci://` endpoint. A missing context id, written as `*`, binds to
+    /// every context.
+    Vmci { cid: Option<u32>, port: u16 },
+    /// A `udp://` endpoint, for `RADIO` and `DISH` sockets, with an optional
+    /// interface for multicast, as in `udp://interface;address:port`.
+    Udp {
+        interface: Option<String>,
+        host: String,
+        port: u16,
+    },
+}
+
+impl Endpoint {
+    /// Create a `tcp://` endpoint.
+    pub fn tcp(host: &str, port: u16) -> Endpoint {
+        Endpoint::Tcp {
+            source: None,
+            host: host.to_string(),
+            port,
+        }
+    }
+
+    /// Create an `ipc://` endpoint.
+    pub fn ipc(path: &str) -> Endpoint {
+        Endpoint::Ipc(path.to_string())
+    }
+
+    /// Create an `inproc://` endpoint.
+    pub fn inproc(name: &str) -> Endpoint {
+        Endpoint::Inproc(name.to_string())
+    }
+
+    /// Create a `pgm://` endpoint, for multicast over raw IP, on the given
+    /// interface.
+    pub fn pgm(interface: &str, address: &str, port: u16) -> Endpoint {
+        Endpoint::Pgm {
+            epgm: false,
+            interface: interface.to_string(),
+            address: address.to_string(),
+            port,
+        }
+    }
+
+    /// Create an `epgm://` endpoint, for multicast encapsulated in UDP, on
+    /// the given interface.
+    pub fn epgm(interface: &str, address: &str, port: u16) -> Endpoint {
+        Endpoint::Pgm {
+            epgm: true,
+            interface: interface.to_string(),
+            address: address.to_string(),
+            port,
+        }
+    }
+
+    /// Fails with `InvalidInput` for the multicast transports, when libzmq
+    /// was built without them.
+    pub fn check_supported(&self) -> io::Result<()> {
+        match *self {
+            Endpoint::Pgm { .. } if has("pgm") != Some(true) => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("the {} transport is not supported by libzmq", self.transport()),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns the name of the transport, such as `"tcp"`.
+    pub fn transport(&self) -> &'static str {
+        match *self {
+            Endpoint::Tcp { .. } => "tcp",
+            Endpoint::Ipc(_) => "ipc",
+            Endpoint::Inproc(_) => "inproc",
+            Endpoint::Pgm { epgm: false, .. } => "pgm",
+            Endpoint::Pgm { epgm: true, .. } => "epgm",
+            Endpoint::Vmci { .. } => "vmci",
+            Endpoint::Udp { .. } => "udp",
+        }
+    }
+
+    /// Returns the port, for transports that have one.
+    pub fn port(&self) -> Option<u16> {
+        match *self {
+            Endpoint::Tcp { port, .. }
+            | Endpoint::Pgm { port, .. }
+            | Endpoint::Vmci { port, .. }
+            | Endpoint::Udp { port, .. } => Some(port),
+            Endpoint::Ipc(_) | Endpoint::Inproc(_) => None,
+        }
+    }
+
+    /// Returns true if binding to this endpoint picks an ephemeral port.
+    pub fn is_wildcard_port(&self) -> bool {
+        self.port() == Some(0)
+    }
+}
+
+impl FromStr for Endpoint {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> io::Result<Endpoint> {
+        let (transport, address) = match s.find("://") {
+            Some(idx) => (&s[..idx], &s[idx + 3..]),
+            None => return Err(invalid_endpoint(s, "missing transport")),
+        };
+        if address.is_empty() || address.chars().any(char::is_whitespace) {
+            return Err(invalid_endpoint(s, "invalid address"));
+        }
+        match transport {
+            "tcp" => {
+                let (source, addr) = split_prefix(address);
+                let (host, port) = split_host_port(s, addr)?;
+                if source == Some("") {
+                    return Err(invalid_endpoint(s, "empty source address"));
+                }
+                Ok(Endpoint::Tcp {
+                    source: source.map(|src| src.to_string()),
+                    host: host.to_string(),
+                    port,
+                })
+            }
+            "ipc" => Ok(Endpoint::Ipc(address.to_string())),
+            "inproc" => Ok(Endpoint::Inproc(address.to_string())),
+            "pgm" | "epgm" => {
+                let (interface, addr) = match split_prefix(address) {
+                    (Some(interface), addr) if !interface.is_empty() => (interface, addr),
+                    _ => return Err(invalid_endpoint(s, "missing interface")),
+                };
+                let (address, port) = split_host_port(s, addr)?;
+                Ok(Endpoint::Pgm {
+                    epgm: transport == "epgm",
+                    interface: interface.to_string(),
+                    address: address.to_string(),
+                    port,
+                })
+            }
+            "vmci" => {
+                let (cid, port) = split_host_port(s, address)?;
+                let cid = match cid {
+                    "*" => None,
+                    cid => Some(cid.parse().map_err(|_| invalid_endpoint(s, "invalid context id"))?),
+                };
+                Ok(Endpoint::Vmci { cid, port })
+            }
+            "udp" => {
+                let (interface, addr) = split_prefix(address);
+                let (host, port) = split_host_port(s, addr)?;
+                if interface == Some("") {
+                    return Err(invalid_endpoint(s, "empty interface"));
+                }
+                Ok(Endpoint::Udp {
+                    interface: interface.map(|iface| iface.to_string()),
+                    host: host.to_string(),
+                    port,
+                })
+            }
+            _ => Err(invalid_endpoint(s, "unknown transport")),
+        }
+    }
+}
+
+impl fmt::Display for Endpoint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}://", self.transport())?;
+        match *self {
+            Endpoint::Tcp {
+                ref source,
+                ref host,
+                port,
+            } => {
+                if let Some(ref source) = *source {
+                    write!(f, "{};", source)?;
+                }
+                write!(f, "{}:{}", host, port)
+            }
+            Endpoint::Ipc(ref path) => write!(f, "{}", path),
+            Endpoint::Inproc(ref name) => write!(f, "{}", name),
+            Endpoint::Pgm {
+                ref interface,
+                ref address,
+                port,
+                ..
+            } => write!(f, "{};{}:{}", interface, address, port),
+            Endpoint::Vmci { cid, port } => match cid {
+                Some(cid) => write!(f, "{}:{}", cid, port),
+                None => write!(f, "*:{}", port),
+            },
+            Endpoint::Udp {
+                ref interface,
+                ref host,
+                port,
+            } => {
+                if let Some(ref interface) = *interface {
+                    write!(f, "{};", interface)?;
+                }
+                write!(f, "{}:{}", host, port)
+            }
+        }
+    }
+}
+
+impl From<SocketAddr> for Endpoint {
+    fn from(addr: SocketAddr) -> Endpoint {
+        let host = match addr {
+            SocketAddr::V4(ref addr) => addr.ip().to_string(),
+            SocketAddr::V6(ref addr) => format!("[{}]", addr.ip()),
+        };
+        Endpoint::Tcp {
+            source: None,
+            host,
+            port: addr.port(),
+        }
+    }
+}
+
+/// Socket options given in the query of a string endpoint.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EndpointOptions {
+    options: Vec<(String, String)>,
+}
+
+impl EndpointOptions {
+    /// Parse a query, such as `sndhwm=1000&linger=0`. Option names are
+    /// checked when the options are applied.
+    pub fn parse(query: &str) -> io::Result<EndpointOptions> {
+        let mut options = Vec::new();
+        for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+            let mut parts = pair.splitn(2, '=');
+            let name = parts.next().unwrap();
+            match parts.next() {
+                Some(value) if !name.is_empty() => options.push((name.to_string(), value.to_string())),
+                _ => return Err(invalid_endpoint(query, "socket options must be given as name=value")),
+            }
+        }
+        Ok(EndpointOptions { options })
+    }
+
+    /// Returns true if there are no options.
+    pub fn is_empty(&self) -> bool {
+        self.options.is_empty()
+    }
+
+    /// Returns the options, as names and values, in the order given.
+    pub fn iter(&self) -> ::std::slice::Iter<(String, String)> {
+        self.options.iter()
+    }
+
+    /// Set the options on a socket. Unknown options, and invalid values,
+    /// fail with `InvalidInput`.
+    pub fn apply(&self, socket: &Socket) -> io::Result<()> {
+        for &(ref name, ref value) in &self.options {
+            match name.as_str() {
+                "sndhwm" => socket.set_sndhwm(parse_value(name, value)?)?,
+                "rcvhwm" => socket.set_rcvhwm(parse_value(name, value)?)?,
+                "linger" => socket.set_linger(parse_value(name, value)?)?,
+                "sndtimeo" => socket.set_sndtimeo(parse_value(name, value)?)?,
+                "rcvtimeo" => socket.set_rcvtimeo(parse_value(name, value)?)?,
+                "reconnect_ivl" => socket.set_reconnect_ivl(parse_value(name, value)?)?,
+                "reconnect_ivl_max" => socket.set_reconnect_ivl_max(parse_value(name, value)?)?,
+                "heartbeat_ivl" => socket.set_heartbeat_ivl(parse_value(name, value)?)?,
+                "heartbeat_timeout" => socket.set_heartbeat_timeout(parse_value(name, value)?)?,
+                "heartbeat_ttl" => socket.set_heartbeat_ttl(parse_value(name, value)?)?,
+                "ipv6" => socket.set_ipv6(parse_flag(name, value)?)?,
+                "conflate" => socket.set_conflate(parse_flag(name, value)?)?,
+                "identity" => socket.set_identity(value.as_bytes())?,
+                "subscribe" => socket.set_subscribe(value.as_bytes())?,
+                "zap_domain" => socket.set_zap_domain(value)?,
+                "rate" => socket.set_rate(parse_value(name, value)?)?,
+                "recovery_ivl" => socket.set_recovery_ivl(parse_value(name, value)?)?,
+                "multicast_hops" => socket.set_multicast_hops(parse_value(name, value)?)?,
+                "multicast_maxtpdu" => socket.set_multicast_maxtpdu(parse_value(name, value)?)?,
+                _ => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!("unknown endpoint option: {}", name),
+                    ))
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromIterator<(String, String)> for EndpointOptions {
+    fn from_iter<I: IntoIterator<Item = (String, String)>>(iter: I) -> EndpointOptions {
+        EndpointOptions {
+            options: iter.into_iter().collect(),
+        }
+    }
+}
+
+fn parse_value(name: &str, value: &str) -> io::Result<i32> {
+    value.parse().map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("invalid value for endpoint option {}: {}", name, value),
+        )
+    })
+}
+
+fn parse_flag(name: &str, value: &str) -> io::Result<bool> {
+    match value {
+        "1" | "true" => Ok(true),
+        "0" | "false" => Ok(false),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("invalid value for endpoint option {}: {}", name, value),
+        )),
+    }
+}
+
+// Splits the query of socket options from a string endpoint.
+fn split_query(endpoint: &str) -> io::Result<(Endpoint, EndpointOptions)> {
+    match endpoint.find('?') {
+        Some(idx) => Ok((endpoint[..idx].parse()?, EndpointOptions::parse(&endpoint[idx + 1..])?)),
+        None => Ok((endpoint.parse()?, EndpointOptions::default())),
+    }
+}
+
+/// Conversion into an `Endpoint`, as accepted by `Socket::bind` and
+/// `Socket::connect`. Strings are parsed, so the conversion can fail.
+pub trait IntoEndpoint {
+    fn into_endpoint(self) -> io::Result<Endpoint>;
+
+    /// Convert into an `Endpoint`, along with the socket options given
+    /// with it. Only string endpoints have options, in their query.
+    fn into_endpoint_with_options(self) -> io::Result<(Endpoint, EndpointOptions)>
+    where
+        Self: Sized,
+    {
+        Ok((self.into_endpoint()?, EndpointOptions::default()))
+    }
+}
+
+impl IntoEndpoint for Endpoint {
+    fn into_endpoint(self) -> io::Result<Endpoint> {
+        Ok(self)
+    }
+}
+
+impl<'a> IntoEndpoint for &'a Endpoint {
+    fn into_endpoint(self) -> io::Result<Endpoint> {
+        Ok(self.clone())
+    }
+}
+
+impl IntoEndpoint for SocketAddr {
+    fn into_endpoint(self) -> io::Result<Endpoint> {
+        Ok(Endpoint::from(self))
+    }
+}
+
+impl<'a> IntoEndpoint for &'a str {
+    fn into_endpoint(self) -> io::Result<Endpoint> {
+        self.parse()
+    }
+
+    fn into_endpoint_with_options(self) -> io::Result<(Endpoint, EndpointOptions)> {
+        split_query(self)
+    }
+}
+
+impl IntoEndpoint for String {
+    fn into_endpoint(self) -> io::Result<Endpoint> {
+        self.parse()
+    }
+
+    fn into_endpoint_with_options(self) -> io::Result<(Endpoint, EndpointOptions)> {
+        split_query(&self)
+    }
+}
+
+impl<'a> IntoEndpoint for &'a String {
+    fn into_endpoint(self) -> io::Result<Endpoint> {
+        self.parse()
+    }
+
+    fn into_endpoint_with_options(self) -> io::Result<(Endpoint, EndpointOptions)> {
+        split_query(self)
+    }
+}
+
+fn invalid_endpoint(endpoint: &str, reason: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("invalid endpoint {}: {}", endpoint, reason),
+    )
+}
+
+// Splits an optional `prefix;` from an address, such as the source address
+// of a TCP endpoint or the interface of a multicast endpoint.
+fn split_prefix(address: &str) -> (Option<&str>, &str) {
+    match address.find(';') {
+        Some(idx) => (Some(&address[..idx]), &address[idx + 1..]),
+        None => (None, address),
+    }
+}
+
+// Splits `host:port`, where the port is a number or `*`. IPv6 hosts must be
+// enclosed in brackets.
+fn split_host_port<'a>(endpoint: &str, address: &'a str) -> io::Result<(&'a str, u16)> {
+    let idx = match address.rfind(':') {
+        Some(idx) => idx,
+        None => return Err(invalid_endpoint(endpoint, "missing port")),
+    };
+    let (host, port) = (&address[..idx], &address[idx + 1..]);
+    if host.is_empty() {
+        return Err(invalid_endpoint(endpoint, "missing host"));
+    }
+    if host.contains(':') && !(host.starts_with('[') && host.ends_with(']')) {
+        return Err(invalid_endpoint(endpoint, "IPv6 addresses must be enclosed in brackets"));
+    }
+    let port = match port {
+        "*" => 0,
+        port => port
+            .parse()
+            .map_err(|_| invalid_endpoint(endpoint, "invalid port"))?,
+    };
+    Ok((host, port))
+}
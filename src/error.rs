@@ -0,0 +1,196 @@
+//! Error helpers for sockets.
+//!
+//! All socket operations return `io::Error`. Errors that originate in
+//! ØMQ keep the original `zmq::Error` as their inner error, which can be
+//! inspected with the functions in this module.
+use std::error;
+use std::fmt;
+use std::io;
+
+use zmq;
+
+use super::{Endpoint, Multipart, RoutingId};
+
+/// Returns the `zmq::Error` wrapped by the given `io::Error`, if any.
+pub fn zmq_error(e: &io::Error) -> Option<zmq::Error> {
+    e.get_ref()
+        .and_then(|inner| inner.downcast_ref::<zmq::Error>())
+        .cloned()
+}
+
+/// Returns true if the error was caused by sending to an unknown peer on
+/// a `ROUTER` socket with `ROUTER_MANDATORY` enabled.
+pub fn is_host_unreachable(e: &io::Error) -> bool {
+    zmq_error(e) == Some(zmq::Error::EHOSTUNREACH) || host_unreachable(e).is_some()
+}
+
+/// Returns the `HostUnreachable` error wrapped by the given `io::Error`, if
+/// any.
+pub fn host_unreachable(e: &io::Error) -> Option<&HostUnreachable> {
+    e.get_ref()
+        .and_then(|inner| inner.downcast_ref::<HostUnreachable>())
+}
+
+// Names the peer a `ROUTER` socket failed to reach, given the routing id
+// the message was sent to.
+pub(crate) fn with_peer(e: io::Error, peer: &[u8]) -> io::Error {
+    if zmq_error(&e) == Some(zmq::Error::EHOSTUNREACH) {
+        HostUnreachable {
+            peer: RoutingId::from(peer.to_vec()),
+        }.into()
+    } else {
+        e
+    }
+}
+
+/// Returns true if the operation can be tried again after the error: the
+/// socket would have blocked, the call was interrupted by a signal, or the
+/// message was too large.
+pub fn is_recoverable(e: &io::Error) -> bool {
+    match e.kind() {
+        io::ErrorKind::WouldBlock | io::ErrorKind::Interrupted => true,
+        _ => zmq_error(e) == Some(zmq::Error::EMSGSIZE) || message_too_large(e).is_some(),
+    }
+}
+
+/// Returns true if the socket can't be used anymore after the error: its
+/// context was terminated, or the socket was closed.
+pub fn is_fatal(e: &io::Error) -> bool {
+    match zmq_error(e) {
+        Some(zmq::Error::ETERM) | Some(zmq::Error::ENOTSOCK) => true,
+        _ => false,
+    }
+}
+
+/// Returns the `MessageTooLarge` error wrapped by the given `io::Error`, if
+/// any.
+pub fn message_too_large(e: &io::Error) -> Option<&MessageTooLarge> {
+    e.get_ref()
+        .and_then(|inner| inner.downcast_ref::<MessageTooLarge>())
+}
+
+/// Error returned by `Socket::try_send`.
+#[derive(Debug)]
+pub enum TrySendError {
+    /// The socket can't accept the message right now. The message is
+    /// handed back, so it can be sent later.
+    Full(Multipart),
+    /// Sending failed.
+    Io(io::Error),
+}
+
+impl fmt::Display for TrySendError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TrySendError::Full(_) => write!(f, "socket would block"),
+            TrySendError::Io(ref e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl error::Error for TrySendError {
+    fn description(&self) -> &str {
+        match *self {
+            TrySendError::Full(_) => "socket would block",
+            TrySendError::Io(ref e) => e.description(),
+        }
+    }
+}
+
+impl From<io::Error> for TrySendError {
+    fn from(e: io::Error) -> TrySendError {
+        TrySendError::Io(e)
+    }
+}
+
+/// Error returned when a `ROUTER` socket with `ROUTER_MANDATORY` enabled
+/// sends to a peer that is not connected. The message is dropped.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HostUnreachable {
+    /// The routing id the message was sent to.
+    pub peer: RoutingId,
+}
+
+impl fmt::Display for HostUnreachable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "peer {} is unreachable", self.peer)
+    }
+}
+
+impl error::Error for HostUnreachable {
+    fn description(&self) -> &str {
+        "host unreachable"
+    }
+}
+
+impl From<HostUnreachable> for io::Error {
+    fn from(e: HostUnreachable) -> io::Error {
+        io::Error::new(io::ErrorKind::NotConnected, e)
+    }
+}
+
+/// Error returned when receiving a message larger than the limit set with
+/// `Socket::set_maxmsgsize`. The message is dropped.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MessageTooLarge {
+    /// The largest message accepted, in bytes.
+    pub limit: i64,
+    /// The size of the largest frame of the dropped message, in bytes.
+    pub size: usize,
+}
+
+impl fmt::Display for MessageTooLarge {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "message of {} bytes exceeds the limit of {} bytes", self.size, self.limit)
+    }
+}
+
+impl error::Error for MessageTooLarge {
+    fn description(&self) -> &str {
+        "message too large"
+    }
+}
+
+impl From<MessageTooLarge> for io::Error {
+    fn from(e: MessageTooLarge) -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidData, e)
+    }
+}
+
+/// Error returned by `Socket::bind_all` and `Socket::connect_all` when some
+/// endpoints fail. The endpoints that succeeded stay bound or connected.
+#[derive(Debug)]
+pub struct EndpointsError {
+    /// The endpoints that succeeded.
+    pub succeeded: Vec<Endpoint>,
+    /// The endpoints that failed, as given, with their errors.
+    pub failed: Vec<(String, io::Error)>,
+}
+
+impl fmt::Display for EndpointsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} of {} endpoints failed", self.failed.len(), self.failed.len() + self.succeeded.len())?;
+        for &(ref endpoint, ref e) in &self.failed {
+            write!(f, "; {}: {}", endpoint, e)?;
+        }
+        Ok(())
+    }
+}
+
+impl error::Error for EndpointsError {
+    fn description(&self) -> &str {
+        "some endpoints failed"
+    }
+}
+
+impl From<EndpointsError> for io::Error {
+    fn from(e: EndpointsError) -> io::Error {
+        // Keep the kind of the first failure, which is the most useful one
+        // when every endpoint failed for the same reason.
+        let kind = e.failed
+            .first()
+            .map(|&(_, ref e)| e.kind())
+            .unwrap_or(io::ErrorKind::Other);
+        io::Error::new(kind, e)
+    }
+}
@@ -0,0 +1,88 @@
+//! Typed readiness of sockets.
+use std::fmt;
+use std::ops::{BitAnd, BitOr};
+
+use zmq;
+
+/// The readiness of a socket, as reported by `ZMQ_EVENTS`. This is returned
+/// by `Socket::events`.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct EventFlags(u8);
+
+/// The socket has a message to receive, `ZMQ_POLLIN`.
+pub const READABLE: EventFlags = EventFlags(1);
+
+/// The socket can send a message without blocking, `ZMQ_POLLOUT`.
+pub const WRITABLE: EventFlags = EventFlags(2);
+
+impl EventFlags {
+    /// No readiness.
+    pub fn empty() -> EventFlags {
+        EventFlags(0)
+    }
+
+    /// Returns true if every flag of `other` is set.
+    pub fn contains(self, other: EventFlags) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Returns true if no flag is set.
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Returns true if the socket has a message to receive.
+    pub fn is_readable(self) -> bool {
+        self.contains(READABLE)
+    }
+
+    /// Returns true if the socket can send a message without blocking.
+    pub fn is_writable(self) -> bool {
+        self.contains(WRITABLE)
+    }
+
+    /// Returns the flags of `self` that are not in `other`.
+    pub fn difference(self, other: EventFlags) -> EventFlags {
+        EventFlags(self.0 & !other.0)
+    }
+}
+
+impl From<zmq::PollEvents> for EventFlags {
+    fn from(events: zmq::PollEvents) -> EventFlags {
+        let mut flags = EventFlags::empty();
+        if events.contains(zmq::POLLIN) {
+            flags = flags | READABLE;
+        }
+        if events.contains(zmq::POLLOUT) {
+            flags = flags | WRITABLE;
+        }
+        flags
+    }
+}
+
+impl BitOr for EventFlags {
+    type Output = EventFlags;
+
+    fn bitor(self, other: EventFlags) -> EventFlags {
+        EventFlags(self.0 | other.0)
+    }
+}
+
+impl BitAnd for EventFlags {
+    type Output = EventFlags;
+
+    fn bitand(self, other: EventFlags) -> EventFlags {
+        EventFlags(self.0 & other.0)
+    }
+}
+
+impl fmt::Debug for EventFlags {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.is_readable(), self.is_writable()) {
+            (true, true) => write!(f, "READABLE | WRITABLE"),
+            (true, false) => write!(f, "READABLE"),
+            (false, true) => write!(f, "WRITABLE"),
+            (false, false) => write!(f, "(empty)"),
+        }
+    }
+}
@@ -0,0 +1,242 @@
+//! Freelance clients, with failover across several servers.
+//!
+//! A `FreelanceClient` connects one `DEALER` socket to each server, and
+//! pings them regularly to know which ones are alive. Requests go to a live
+//! server, and are resent to the next one when no reply arrives in time.
+//!
+//! Requests are sent as `["", sequence, body...]`, and servers must echo
+//! the sequence frame back as the first frame after the delimiter, so
+//! `REP` sockets work as servers. Pings are sent as `["", "PING"]`, and
+//! servers should reply with `["", "PONG"]`; any message from a server
+//! counts as a sign of life. Pings are only sent while a request is
+//! polled, so after the client sat idle, servers are presumed alive again,
+//! until they miss the next pings.
+//!
+//! See http://zguide.zeromq.org/page:all#Brokerless-Reliability-Freelance-Pattern
+use std::io;
+use std::time::{Duration, Instant};
+
+use futures::{Async, AsyncSink, Future, Poll, Sink, Stream};
+use tokio_core::reactor::{Handle, Interval, Timeout};
+use zmq::Message;
+
+use super::{Context, Multipart, Socket, DEALER};
+
+/// Frame sent by clients to ping a server.
+pub const PING: &[u8] = b"PING";
+
+/// Frame sent by servers in reply to a ping.
+pub const PONG: &[u8] = b"PONG";
+
+/// Default interval between pings, in milliseconds.
+pub const PING_INTERVAL: u64 = 1000;
+
+struct Server {
+    endpoint: String,
+    socket: Socket,
+    expiry: Instant,
+}
+
+impl Server {
+    fn is_alive(&self) -> bool {
+        Instant::now() < self.expiry
+    }
+
+    // Sends without blocking, returning `false` when the socket would block.
+    fn try_send(&self, msgs: Multipart) -> io::Result<bool> {
+        let mut sink = &self.socket;
+        match sink.start_send(msgs)? {
+            AsyncSink::Ready => Ok(true),
+            AsyncSink::NotReady(_) => Ok(false),
+        }
+    }
+}
+
+/// A client that sends requests to any of several servers, failing over to
+/// another server when one stops replying.
+pub struct FreelanceClient {
+    servers: Vec<Server>,
+    handle: Handle,
+    ping: Interval,
+    ping_interval: Duration,
+    // When the servers were last polled, to find out the client sat idle.
+    polled: Instant,
+    timeout: Duration,
+    next: usize,
+    sequence: u64,
+}
+
+impl FreelanceClient {
+    /// Create a new client, connected to each of the given server endpoints.
+    /// By default, each server has one second to reply to a request.
+    pub fn new<I, T>(context: &Context, endpoints: I, handle: &Handle) -> io::Result<FreelanceClient>
+    where
+        I: IntoIterator<Item = T>,
+        T: AsRef<str>,
+    {
+        let ping_interval = Duration::from_millis(PING_INTERVAL);
+        // new servers are presumed alive until they miss their first pings.
+        let expiry = Instant::now() + ping_interval * 2;
+        let mut servers = Vec::new();
+        for endpoint in endpoints {
+            let socket = context.socket(DEALER, handle)?;
+            socket.connect(endpoint.as_ref())?;
+            servers.push(Server {
+                endpoint: endpoint.as_ref().to_string(),
+                socket,
+                expiry,
+            });
+        }
+        let client = FreelanceClient {
+            servers,
+            handle: handle.clone(),
+            ping: Interval::new(ping_interval, handle)?,
+            ping_interval,
+            polled: Instant::now(),
+            timeout: Duration::from_millis(PING_INTERVAL),
+            next: 0,
+            sequence: 0,
+        };
+        client.ping_servers()?;
+        Ok(client)
+    }
+
+    /// Set the time a server has to reply to a request, before the request
+    /// is resent to another server.
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    /// Returns the endpoints of the servers that were alive when a request
+    /// was last polled.
+    pub fn live_servers(&self) -> Vec<&str> {
+        self.servers
+            .iter()
+            .filter(|s| s.is_alive())
+            .map(|s| &s.endpoint[..])
+            .collect()
+    }
+
+    /// Send a request to a live server, returning a future that resolves into
+    /// the reply body. The request fails with `TimedOut` once every server
+    /// has had its chance to reply.
+    pub fn call<I, T>(&mut self, body: I) -> Call
+    where
+        I: IntoIterator<Item = T>,
+        T: AsRef<[u8]>,
+    {
+        self.sequence = self.sequence.wrapping_add(1);
+        let sequence = self.sequence.to_string().into_bytes();
+        let mut frames = vec![Vec::new(), sequence];
+        frames.extend(body.into_iter().map(|f| f.as_ref().to_vec()));
+        let attempts = self.servers.len();
+        Call {
+            client: self,
+            frames,
+            attempts,
+            timer: None,
+        }
+    }
+
+    fn ping_servers(&self) -> io::Result<()> {
+        for server in &self.servers {
+            let ping = vec![Message::new(), Message::from_slice(PING)];
+            server.try_send(ping)?;
+        }
+        Ok(())
+    }
+
+    // Drives the pings, and reads incoming messages from every server.
+    // Returns the body of the reply with the given sequence, if it arrived.
+    fn poll_servers(&mut self, sequence: &[u8]) -> io::Result<Option<Multipart>> {
+        let now = Instant::now();
+        if now - self.polled > self.ping_interval {
+            // no pings went out while idle, so the silence of the servers
+            // says nothing: they get a fresh chance, like new servers.
+            for server in &mut self.servers {
+                server.expiry = now + self.ping_interval * 2;
+            }
+            self.ping_servers()?;
+        }
+        self.polled = now;
+        while let Async::Ready(Some(())) = self.ping.poll()? {
+            self.ping_servers()?;
+        }
+        let mut reply = None;
+        for server in &mut self.servers {
+            loop {
+                let mut msgs = {
+                    let mut stream = &server.socket;
+                    match stream.poll()? {
+                        Async::Ready(Some(msgs)) => msgs,
+                        _ => break,
+                    }
+                };
+                server.expiry = Instant::now() + self.ping_interval * 2;
+                if msgs.len() >= 2 && msgs[0].is_empty() && &msgs[1][..] == sequence {
+                    reply = Some(msgs.split_off(2));
+                }
+            }
+        }
+        Ok(reply)
+    }
+
+    // Sends the request to the next live server. Returns `false` when no
+    // server could take it.
+    fn send_request(&mut self, frames: &[Vec<u8>]) -> io::Result<bool> {
+        for _ in 0..self.servers.len() {
+            let idx = self.next % self.servers.len();
+            self.next = self.next.wrapping_add(1);
+            let server = &self.servers[idx];
+            if !server.is_alive() {
+                continue;
+            }
+            let msgs = frames.iter().map(|f| Message::from_slice(f)).collect();
+            if server.try_send(msgs)? {
+                debug!("request sent to {}", server.endpoint);
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+}
+
+/// A Future that resolves into the reply to a request made with
+/// `FreelanceClient`. This is returned by `FreelanceClient::call`.
+pub struct Call<'a> {
+    client: &'a mut FreelanceClient,
+    frames: Vec<Vec<u8>>,
+    attempts: usize,
+    timer: Option<Timeout>,
+}
+
+impl<'a> Future for Call<'a> {
+    type Item = Multipart;
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        loop {
+            if let Some(reply) = self.client.poll_servers(&self.frames[1])? {
+                return Ok(Async::Ready(reply));
+            }
+            if let Some(ref mut timer) = self.timer {
+                if let Async::NotReady = timer.poll()? {
+                    return Ok(Async::NotReady);
+                }
+            }
+            if self.attempts == 0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    "no reply from any server",
+                ));
+            }
+            // when no server is alive, we wait for the pings to find one,
+            // which still costs an attempt.
+            self.attempts -= 1;
+            if !self.client.send_request(&self.frames)? {
+                debug!("no live server available");
+            }
+            self.timer = Some(Timeout::new(self.client.timeout, &self.client.handle)?);
+        }
+    }
+}
@@ -1,10 +1,18 @@
 //! Futures for ØMQ sockets.
 use std::io;
+use std::thread;
+use std::time::{Duration, Instant};
 
-use futures::{Async, Future, Poll};
+use futures::{task, Async, AsyncSink, Future, Poll, Sink, Stream};
+use futures::sync::oneshot;
+use futures_cpupool::{CpuFuture, CpuPool};
+use tokio_core::reactor::{Handle, Timeout};
+use zmq;
 
 use super::{SocketSend, SocketRecv};
-use super::{Message, Socket};
+use super::{Context, Endpoint, Message, Multipart, RoutingId, Socket};
+use super::error::{with_peer, MessageTooLarge};
+use super::events::{EventFlags, READABLE, WRITABLE};
 
 /// A Future that sends a `Message` asynchronously. This is returned by `Socket::send`
 pub struct SendMessage<'a> {
@@ -23,7 +31,47 @@ impl<'a> Future for SendMessage<'a> {
     type Error = io::Error;
 
     fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
-        match SocketSend::send(self.socket.get_ref(), &*self.message, 0) {
+        match SocketSend::send(self.socket, &*self.message, 0) {
+            Err(e) => {
+                if e.kind() == io::ErrorKind::WouldBlock {
+                    Ok(Async::NotReady)
+                } else {
+                    Err(e)
+                }
+            }
+            Ok(_) => Ok(Async::Ready(())),
+        }
+    }
+}
+
+/// A Future that sends a shared payload asynchronously, without copying it.
+/// This is returned by `Socket::send_zero_copy`
+///
+/// Every attempt to send hands a clone of the payload to ØMQ, so cloning
+/// should be cheap, as it is for `Arc<[u8]>`.
+pub struct SendZeroCopy<'a, T> {
+    socket: &'a Socket,
+    data: T,
+}
+
+impl<'a, T> SendZeroCopy<'a, T>
+where
+    T: AsRef<[u8]> + Clone + Send + 'static,
+{
+    pub fn new(socket: &'a Socket, data: T) -> SendZeroCopy<'a, T> {
+        SendZeroCopy { socket, data }
+    }
+}
+
+impl<'a, T> Future for SendZeroCopy<'a, T>
+where
+    T: AsRef<[u8]> + Clone + Send + 'static,
+{
+    type Item = ();
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        match SocketSend::send_zero_copy(self.socket, self.data.clone(), 0) {
             Err(e) => {
                 if e.kind() == io::ErrorKind::WouldBlock {
                     Ok(Async::NotReady)
@@ -59,12 +107,13 @@ impl<'a> Future for SendMultipartMessage<'a> {
     type Error = io::Error;
 
     fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
-        match SocketSend::send_multipart(self.socket.get_ref(), &self.messages, 0) {
+        self.socket.check_conflate(self.messages.len())?;
+        match SocketSend::send_multipart(self.socket, &self.messages, 0) {
             Err(e) => {
                 if e.kind() == io::ErrorKind::WouldBlock {
                     Ok(Async::NotReady)
                 } else {
-                    Err(e)
+                    Err(with_peer(e, &self.messages[0]))
                 }
             }
             Ok(_) => Ok(Async::Ready(())),
@@ -89,7 +138,7 @@ impl<'a> Future for ReceiveMultipartMessage<'a> {
     type Error = io::Error;
 
     fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
-        match SocketRecv::recv_multipart(self.socket.get_ref(), 0) {
+        match recv_multipart_msgs(self.socket) {
             Err(e) => {
                 if e.kind() == io::ErrorKind::WouldBlock {
                     Ok(Async::NotReady)
@@ -97,12 +146,86 @@ impl<'a> Future for ReceiveMultipartMessage<'a> {
                     Err(e)
                 }
             }
-            Ok(msgs) => {
-                let m_out = msgs.iter().map(|v| v.into()).collect::<Vec<Message>>();
-                Ok(Async::Ready(m_out))
+            Ok(msgs) => Ok(Async::Ready(msgs)),
+        }
+    }
+}
+
+/// A Future that receives a batch of up to `max` multi-part messages, draining
+/// the socket once it becomes readable. It resolves as soon as at least one
+/// message is available. This is returned by `Socket::recv_batch`
+pub struct ReceiveBatch<'a> {
+    socket: &'a Socket,
+    max: usize,
+}
+
+impl<'a> ReceiveBatch<'a> {
+    pub fn new(socket: &'a Socket, max: usize) -> ReceiveBatch<'a> {
+        assert!(max > 0, "batch size must be non-zero");
+        ReceiveBatch { socket, max }
+    }
+}
+
+impl<'a> Future for ReceiveBatch<'a> {
+    type Item = Vec<Multipart>;
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        recv_batch(self.socket, self.max)
+    }
+}
+
+// Receives up to `max` multi-part messages, returning `Async::NotReady` only
+// when none is available.
+pub(crate) fn recv_batch<T: SocketRecv>(socket: &T, max: usize) -> Poll<Vec<Multipart>, io::Error> {
+    let mut batch = Vec::new();
+    while batch.len() < max {
+        match recv_multipart_msgs(socket) {
+            Err(e) => {
+                if e.kind() != io::ErrorKind::WouldBlock {
+                    return Err(e);
+                }
+                break;
             }
+            Ok(msgs) => batch.push(msgs),
         }
     }
+    if batch.is_empty() {
+        Ok(Async::NotReady)
+    } else {
+        Ok(Async::Ready(batch))
+    }
+}
+
+// Receives the frames of a multi-part message straight into `Message`s,
+// without copying them. Only the first frame can fail with `WouldBlock`,
+// as the others arrive along with it.
+pub(crate) fn recv_multipart_msgs<T: SocketRecv>(socket: &T) -> io::Result<Multipart> {
+    let mut msgs = vec![SocketRecv::recv_msg(socket, 0)?];
+    while SocketRecv::get_rcvmore(socket)? {
+        msgs.push(SocketRecv::recv_msg(socket, 0)?);
+    }
+    check_message_size(socket, &msgs)?;
+    Ok(msgs)
+}
+
+// Fails on a received message with a frame larger than the largest
+// message accepted by the socket, which, as in ØMQ, limits single frames.
+// The whole message is received first, so the socket can still be read.
+pub(crate) fn check_message_size<T: SocketRecv + ?Sized>(socket: &T, msgs: &[Message]) -> io::Result<()> {
+    match msgs.iter().map(|msg| msg.len()).max() {
+        Some(size) => check_received_size(socket, size),
+        None => Ok(()),
+    }
+}
+
+// Same as `check_message_size`, given the size of a single frame.
+pub(crate) fn check_received_size<T: SocketRecv + ?Sized>(socket: &T, size: usize) -> io::Result<()> {
+    let limit = socket.max_message_size()?;
+    if limit >= 0 && size > limit as usize {
+        return Err(MessageTooLarge { limit, size }.into());
+    }
+    Ok(())
 }
 
 /// A Future that receives a `Message` asynchronously. This is returned by `Socket::recv`
@@ -121,7 +244,163 @@ impl<'a> Future for ReceiveMessage<'a> {
     type Error = io::Error;
 
     fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
-        match SocketRecv::recv_msg(self.socket.get_ref(), 0) {
+        match SocketRecv::recv_msg(self.socket, 0) {
+            Err(e) => {
+                if e.kind() == io::ErrorKind::WouldBlock {
+                    Ok(Async::NotReady)
+                } else {
+                    Err(e)
+                }
+            }
+            Ok(msg) => {
+                check_received_size(self.socket, msg.len())?;
+                Ok(Async::Ready(msg))
+            }
+        }
+    }
+}
+
+/// A Future that sends a multi-part `Message` to a peer of a `ROUTER` socket.
+/// The identity frame and the empty delimiter are prepended automatically.
+/// This is returned by `Socket::send_to`
+pub struct SendTo<'a> {
+    socket: &'a Socket,
+    messages: Vec<Vec<u8>>,
+}
+
+impl<'a> SendTo<'a> {
+    pub fn new<I, T>(socket: &'a Socket, routing_id: &RoutingId, iter: I) -> SendTo<'a>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<Vec<u8>>,
+    {
+        let mut messages: Vec<Vec<u8>> = vec![routing_id.to_vec(), Vec::new()];
+        messages.extend(iter.into_iter().map(|m| m.into()));
+        SendTo { socket, messages }
+    }
+}
+
+impl<'a> Future for SendTo<'a> {
+    type Item = ();
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        match SocketSend::send_multipart(self.socket, &self.messages, 0) {
+            Err(e) => {
+                if e.kind() == io::ErrorKind::WouldBlock {
+                    Ok(Async::NotReady)
+                } else {
+                    Err(with_peer(e, &self.messages[0]))
+                }
+            }
+            Ok(_) => Ok(Async::Ready(())),
+        }
+    }
+}
+
+/// A Future that receives a multi-part `Message` on a `ROUTER` socket,
+/// resolving into the peer's identity and the message body. The empty
+/// delimiter frame, if present, is stripped.
+/// This is returned by `Socket::recv_from`
+pub struct ReceiveFrom<'a> {
+    socket: &'a Socket,
+}
+
+impl<'a> ReceiveFrom<'a> {
+    pub fn new(socket: &'a Socket) -> ReceiveFrom<'a> {
+        ReceiveFrom { socket }
+    }
+}
+
+impl<'a> Future for ReceiveFrom<'a> {
+    type Item = (RoutingId, Multipart);
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        match SocketRecv::recv_multipart(self.socket, 0) {
+            Err(e) => {
+                if e.kind() == io::ErrorKind::WouldBlock {
+                    Ok(Async::NotReady)
+                } else {
+                    Err(e)
+                }
+            }
+            Ok(mut msgs) => {
+                if msgs.is_empty() {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "received a message without an identity frame",
+                    ));
+                }
+                let routing_id = RoutingId::from(msgs.remove(0));
+                if msgs.first().map(|m| m.is_empty()).unwrap_or(false) {
+                    msgs.remove(0);
+                }
+                let m_out = msgs.iter().map(|v| v.into()).collect::<Multipart>();
+                Ok(Async::Ready((routing_id, m_out)))
+            }
+        }
+    }
+}
+
+/// A Future that sends a multi-part request from a `DEALER` socket to a
+/// `REP` or `ROUTER` peer, prepending the empty delimiter frame.
+/// This is returned by `Socket::send_request`
+pub struct SendRequest<'a> {
+    socket: &'a Socket,
+    messages: Vec<Vec<u8>>,
+}
+
+impl<'a> SendRequest<'a> {
+    pub fn new<I, T>(socket: &'a Socket, iter: I) -> SendRequest<'a>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<Vec<u8>>,
+    {
+        let mut messages: Vec<Vec<u8>> = vec![Vec::new()];
+        messages.extend(iter.into_iter().map(|m| m.into()));
+        SendRequest { socket, messages }
+    }
+}
+
+impl<'a> Future for SendRequest<'a> {
+    type Item = ();
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        match SocketSend::send_multipart(self.socket, &self.messages, 0) {
+            Err(e) => {
+                if e.kind() == io::ErrorKind::WouldBlock {
+                    Ok(Async::NotReady)
+                } else {
+                    Err(e)
+                }
+            }
+            Ok(_) => Ok(Async::Ready(())),
+        }
+    }
+}
+
+/// A Future that receives a multi-part reply on a `DEALER` socket, from a
+/// `REP` or `ROUTER` peer, stripping the empty delimiter frame. Replies
+/// without the delimiter are reported as `io::ErrorKind::InvalidData`.
+/// This is returned by `Socket::recv_reply`
+pub struct ReceiveReply<'a> {
+    socket: &'a Socket,
+}
+
+impl<'a> ReceiveReply<'a> {
+    pub fn new(socket: &'a Socket) -> ReceiveReply<'a> {
+        ReceiveReply { socket }
+    }
+}
+
+impl<'a> Future for ReceiveReply<'a> {
+    type Item = Multipart;
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        match recv_multipart_msgs(self.socket) {
             Err(e) => {
                 if e.kind() == io::ErrorKind::WouldBlock {
                     Ok(Async::NotReady)
@@ -129,7 +408,423 @@ impl<'a> Future for ReceiveMessage<'a> {
                     Err(e)
                 }
             }
-            Ok(msg) => Ok(Async::Ready(msg)),
+            Ok(mut msgs) => {
+                match msgs.first().map(|delimiter| delimiter.is_empty()) {
+                    Some(true) => {
+                        msgs.remove(0);
+                        Ok(Async::Ready(msgs))
+                    }
+                    _ => Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "reply is missing the empty delimiter frame",
+                    )),
+                }
+            }
+        }
+    }
+}
+
+/// A Future that sends a multi-part request on a `REQ` socket, and
+/// resolves into its reply, resending the request when the socket is
+/// relaxed and no reply arrives in time. This is returned by
+/// `Socket::request`
+pub struct Request<'a> {
+    socket: &'a Socket,
+    messages: Vec<Vec<u8>>,
+    sent: bool,
+    timeout: Duration,
+    timer: Timeout,
+    retries: u32,
+}
+
+impl<'a> Request<'a> {
+    pub fn new<I, T>(socket: &'a Socket, iter: I, timeout: Duration, retries: u32, handle: &Handle) -> io::Result<Request<'a>>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<Vec<u8>>,
+    {
+        Ok(Request {
+            socket,
+            messages: iter.into_iter().map(|m| m.into()).collect(),
+            sent: false,
+            timeout,
+            timer: Timeout::new(timeout, handle)?,
+            retries,
+        })
+    }
+}
+
+impl<'a> Future for Request<'a> {
+    type Item = Multipart;
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        loop {
+            if !self.sent {
+                match SocketSend::send_multipart(self.socket, &self.messages, 0) {
+                    Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(Async::NotReady),
+                    Err(e) => return Err(e),
+                    Ok(_) => {
+                        self.sent = true;
+                        self.timer.reset(Instant::now() + self.timeout);
+                    }
+                }
+            }
+            match recv_multipart_msgs(self.socket) {
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {}
+                Err(e) => return Err(e),
+                Ok(msgs) => return Ok(Async::Ready(msgs)),
+            }
+            try_ready!(self.timer.poll());
+            if self.retries == 0 || !self.socket.is_req_relaxed() {
+                return Err(io::Error::new(io::ErrorKind::TimedOut, "request timed out"));
+            }
+            debug!("resending request");
+            self.retries -= 1;
+            self.sent = false;
+        }
+    }
+}
+
+/// A Future that destroys a `Context`, resolving once all of its sockets
+/// are closed. This is returned by `Context::terminate`
+///
+/// Destroying the context makes operations on its sockets fail with
+/// `ETERM`, and blocks until they are all closed, delivering their
+/// lingering messages. So it runs on the blocking pool of the context, or
+/// else on a thread of its own.
+pub struct Terminate {
+    context: Option<Context>,
+    destroying: Option<Box<Future<Item = (), Error = io::Error> + Send>>,
+}
+
+impl Terminate {
+    pub fn new(context: Context) -> Terminate {
+        Terminate {
+            context: Some(context),
+            destroying: None,
+        }
+    }
+}
+
+impl Future for Terminate {
+    type Item = ();
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        if let Some(ref mut destroying) = self.destroying {
+            return destroying.poll();
+        }
+        let mut context = self.context.take().expect("polled Terminate after completion");
+        let mut destroying: Box<Future<Item = (), Error = io::Error> + Send> =
+            match context.state.blocking_pool.clone() {
+                Some(pool) => Box::new(pool.spawn_fn(move || context.destroy())),
+                None => {
+                    let (tx, rx) = oneshot::channel();
+                    thread::spawn(move || {
+                        let _ = tx.send(context.destroy());
+                    });
+                    Box::new(rx.then(|res| match res {
+                        Ok(res) => res,
+                        Err(_) => Err(io::Error::new(
+                            io::ErrorKind::Other,
+                            "context was dropped while destroying",
+                        )),
+                    }))
+                }
+            };
+        let poll = destroying.poll();
+        self.destroying = Some(destroying);
+        poll
+    }
+}
+
+/// A Future that waits until a `Socket` is writable, then closes it with
+/// its linger period. This is returned by `Socket::close`
+///
+/// The socket is kept open until `ZMQ_EVENTS` reports it writable, or until
+/// its linger period expires. Being writable only means that its outbound
+/// pipe is attached to a peer and below its high-water mark: queued
+/// messages may still be in the process. The socket is then deregistered
+/// from the reactor and dropped, leaving its queued messages to the ØMQ
+/// I/O threads for the linger period.
+///
+/// Nothing here waits for the messages to leave the process: that is what
+/// `Context::terminate` does, as destroying the context blocks until the
+/// lingering messages are sent or dropped.
+pub struct Close {
+    socket: Option<Socket>,
+    linger: Option<i32>,
+    started: bool,
+    expires: Option<Timeout>,
+}
+
+impl Close {
+    pub fn new(socket: Socket, linger: Option<i32>) -> Close {
+        Close {
+            socket: Some(socket),
+            linger,
+            started: false,
+            expires: None,
+        }
+    }
+}
+
+impl Future for Close {
+    type Item = ();
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        let registered = match self.socket {
+            Some(ref socket) => socket.is_registered(),
+            None => panic!("polled Close after completion"),
+        };
+        // a deregistered socket is just dropped.
+        if !registered {
+            let socket = self.socket.take().unwrap();
+            if let Some(linger) = self.linger {
+                socket.set_linger(linger)?;
+            }
+            return Ok(Async::Ready(()));
+        }
+        {
+            let socket = self.socket.as_ref().unwrap();
+            if !self.started {
+                if let Some(linger) = self.linger {
+                    socket.set_linger(linger)?;
+                }
+                // a negative linger period waits forever.
+                let linger = socket.get_linger()?;
+                if linger >= 0 {
+                    let linger = Duration::from_millis(linger as u64);
+                    self.expires = Some(Timeout::new(linger, &socket.reactor_handle()?)?);
+                }
+                self.started = true;
+            }
+            let expired = match self.expires {
+                Some(ref mut expires) => expires.poll()?.is_ready(),
+                None => false,
+            };
+            if !expired && !socket.get_events()?.contains(zmq::POLLOUT) {
+                socket.registration()?.need_write();
+                // the events may have changed before the readiness was cleared.
+                if !socket.get_events()?.contains(zmq::POLLOUT) {
+                    return Ok(Async::NotReady);
+                }
+            }
+        }
+        let mut socket = self.socket.take().unwrap();
+        let handle = socket.reactor_handle()?;
+        let io = socket.io.take().unwrap();
+        // the underlying socket is closed once deregistered.
+        io.deregister(&handle)?;
+        Ok(Async::Ready(()))
+    }
+}
+
+enum BindState {
+    Inline(Option<io::Result<Socket>>),
+    Pool(CpuFuture<Socket, io::Error>),
+}
+
+/// A Future that binds a `Socket`, resolving into the socket once it is
+/// bound. This is returned by `Socket::bind_offloaded`
+///
+/// When the context has a blocking pool, the socket is moved to the pool
+/// for binding, so slow transports don't stall the reactor.
+pub struct BindOffloaded {
+    state: BindState,
+}
+
+impl BindOffloaded {
+    pub fn new(socket: Socket, endpoint: io::Result<Endpoint>) -> BindOffloaded {
+        let endpoint = match endpoint {
+            Ok(endpoint) => endpoint,
+            Err(e) => {
+                return BindOffloaded {
+                    state: BindState::Inline(Some(Err(e))),
+                }
+            }
+        };
+        let state = match socket.blocking_pool() {
+            Some(pool) => BindState::Pool(spawn_bind(&pool, socket, endpoint)),
+            None => BindState::Inline(Some(socket.bind(endpoint).map(|_| socket))),
+        };
+        BindOffloaded { state }
+    }
+}
+
+fn spawn_bind(pool: &CpuPool, socket: Socket, endpoint: Endpoint) -> CpuFuture<Socket, io::Error> {
+    pool.spawn_fn(move || {
+        socket.bind(endpoint)?;
+        Ok(socket)
+    })
+}
+
+impl Future for BindOffloaded {
+    type Item = Socket;
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        match self.state {
+            BindState::Inline(ref mut result) => {
+                let socket = result.take().expect("polled BindOffloaded after completion")?;
+                Ok(Async::Ready(socket))
+            }
+            BindState::Pool(ref mut binding) => binding.poll(),
+        }
+    }
+}
+
+/// A Future that sends every multi-part message yielded by a `Stream`,
+/// respecting the socket's high-water mark: the stream is not polled again
+/// until its last message has been accepted by the socket.
+/// This is returned by `Socket::send_all` and `Socket::forward_to`
+pub struct SendAll<'a, S> {
+    socket: &'a Socket,
+    stream: S,
+    buffered: Option<Multipart>,
+}
+
+impl<'a, S> SendAll<'a, S>
+where
+    S: Stream<Item = Multipart, Error = io::Error>,
+{
+    pub fn new(socket: &'a Socket, stream: S) -> SendAll<'a, S> {
+        SendAll {
+            socket,
+            stream,
+            buffered: None,
+        }
+    }
+
+    // Tries to send the buffered message, keeping it if the socket is not
+    // writable.
+    fn try_start_send(&mut self, item: Multipart) -> Poll<(), io::Error> {
+        let mut sink = self.socket;
+        if let AsyncSink::NotReady(item) = sink.start_send(item)? {
+            self.buffered = Some(item);
+            return Ok(Async::NotReady);
+        }
+        Ok(Async::Ready(()))
+    }
+}
+
+impl<'a, S> Future for SendAll<'a, S>
+where
+    S: Stream<Item = Multipart, Error = io::Error>,
+{
+    type Item = ();
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        if let Some(item) = self.buffered.take() {
+            try_ready!(self.try_start_send(item));
+        }
+        loop {
+            match try_ready!(self.stream.poll()) {
+                Some(item) => try_ready!(self.try_start_send(item)),
+                None => return Ok(Async::Ready(())),
+            }
+        }
+    }
+}
+
+/// A Future that resolves once the socket has a message to receive, as
+/// reported by `ZMQ_EVENTS`. This is returned by `Socket::readable`
+///
+/// Nothing is received, so the message is left for the caller.
+pub struct Readable<'a> {
+    socket: &'a Socket,
+}
+
+impl<'a> Readable<'a> {
+    pub fn new(socket: &'a Socket) -> Readable<'a> {
+        Readable { socket }
+    }
+}
+
+impl<'a> Future for Readable<'a> {
+    type Item = ();
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        if let Async::NotReady = self.socket.registration()?.poll_read() {
+            return Ok(Async::NotReady);
+        }
+        // the ØMQ file descriptor only signals that `ZMQ_EVENTS` changed.
+        if self.socket.get_events()?.contains(zmq::POLLIN) {
+            Ok(Async::Ready(()))
+        } else {
+            self.socket.registration()?.need_read();
+            Ok(Async::NotReady)
+        }
+    }
+}
+
+/// A Future that resolves once the socket can accept a message without
+/// blocking, as reported by `ZMQ_EVENTS`. This is returned by
+/// `Socket::writable`
+pub struct Writable<'a> {
+    socket: &'a Socket,
+}
+
+impl<'a> Writable<'a> {
+    pub fn new(socket: &'a Socket) -> Writable<'a> {
+        Writable { socket }
+    }
+}
+
+impl<'a> Future for Writable<'a> {
+    type Item = ();
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        if let Async::NotReady = self.socket.registration()?.poll_write() {
+            return Ok(Async::NotReady);
+        }
+        if self.socket.get_events()?.contains(zmq::POLLOUT) {
+            Ok(Async::Ready(()))
+        } else {
+            self.socket.registration()?.need_write();
+            Ok(Async::NotReady)
+        }
+    }
+}
+
+/// A Future that resolves into the events of the socket, once they contain
+/// every requested flag. This is returned by `Socket::wait_events`.
+pub struct WaitEvents<'a> {
+    socket: &'a Socket,
+    flags: EventFlags,
+}
+
+impl<'a> WaitEvents<'a> {
+    pub fn new(socket: &'a Socket, flags: EventFlags) -> WaitEvents<'a> {
+        WaitEvents { socket, flags }
+    }
+}
+
+impl<'a> Future for WaitEvents<'a> {
+    type Item = EventFlags;
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        let events = self.socket.events()?;
+        if events.contains(self.flags) {
+            return Ok(Async::Ready(events));
+        }
+        let missing = self.flags.difference(events);
+        if missing.contains(READABLE) {
+            self.socket.registration()?.need_read();
+        }
+        if missing.contains(WRITABLE) {
+            self.socket.registration()?.need_write();
+        }
+        // the events may have changed before the readiness was cleared.
+        if self.socket.events()?.contains(self.flags) {
+            task::current().notify();
         }
+        Ok(Async::NotReady)
     }
 }
@@ -0,0 +1,237 @@
+//! Gap detection for subscribers.
+//!
+//! `PUB` sockets drop messages for slow subscribers, and subscribers miss
+//! everything published before they joined. When publishers number their
+//! messages, a `GapDetector` reports the messages that went missing.
+//!
+//! Sequence numbers are carried in a designated frame, as 8-byte big-endian
+//! numbers. Use `sequence_frame` to build them on the publishing side, or
+//! wrap the publishing sink in a `SeqPub`, which numbers the messages of
+//! each topic, and pair it with `GapDetector::per_topic`.
+use std::collections::HashMap;
+use std::io;
+
+use futures::{Async, AsyncSink, Poll, Sink, StartSend, Stream};
+use zmq::Message;
+
+use super::Multipart;
+
+/// A message with its sequence number. The sequence frame is removed from
+/// the message.
+#[derive(Debug)]
+pub struct SequencedMessage {
+    pub sequence: u64,
+    pub message: Multipart,
+}
+
+/// A range of missed sequence numbers, inclusive.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Gap {
+    pub from: u64,
+    pub to: u64,
+}
+
+impl Gap {
+    /// Returns the number of missed messages.
+    pub fn len(&self) -> u64 {
+        self.to - self.from + 1
+    }
+}
+
+/// Items yielded by a `GapDetector`.
+#[derive(Debug)]
+pub enum Sequenced {
+    /// A received message.
+    Message(SequencedMessage),
+    /// Messages were missed, before the next received message.
+    Gap(Gap),
+}
+
+/// Returns the frame carrying a sequence number.
+pub fn sequence_frame(sequence: u64) -> Message {
+    let mut buf = [0u8; 8];
+    for (idx, b) in buf.iter_mut().enumerate() {
+        *b = (sequence >> (56 - 8 * idx)) as u8;
+    }
+    Message::from_slice(&buf)
+}
+
+/// A `Stream` adapter that reads the sequence numbers of incoming messages,
+/// and yields a `Gap` before any message that follows missed ones.
+///
+/// A sequence number lower than or equal to the last one is taken as a
+/// restart of the publisher, and starts a new sequence. Messages without a
+/// valid sequence frame fail the stream with `InvalidData`.
+pub struct GapDetector<S> {
+    stream: S,
+    frame: usize,
+    last: Option<u64>,
+    topics: Option<HashMap<Vec<u8>, u64>>,
+    pending: Option<SequencedMessage>,
+    gaps: u64,
+}
+
+impl<S> GapDetector<S>
+where
+    S: Stream<Item = Multipart, Error = io::Error>,
+{
+    /// Wrap a stream of messages, reading their sequence numbers from the
+    /// frame at the given index, which is usually `1`, after the topic.
+    pub fn new(stream: S, frame: usize) -> GapDetector<S> {
+        GapDetector {
+            stream,
+            frame,
+            last: None,
+            topics: None,
+            pending: None,
+            gaps: 0,
+        }
+    }
+
+    /// Track a separate sequence for each topic, the first frame, as
+    /// numbered by `SeqPub`.
+    pub fn per_topic(mut self) -> GapDetector<S> {
+        self.topics = Some(HashMap::new());
+        self
+    }
+
+    /// Returns the sequence number of the last received message.
+    pub fn last_sequence(&self) -> Option<u64> {
+        self.last
+    }
+
+    /// Returns the total number of missed messages.
+    pub fn missed(&self) -> u64 {
+        self.gaps
+    }
+
+    /// Consumes the adapter, returning the underlying stream.
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+}
+
+impl<S> Stream for GapDetector<S>
+where
+    S: Stream<Item = Multipart, Error = io::Error>,
+{
+    type Item = Sequenced;
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        if let Some(msg) = self.pending.take() {
+            return Ok(Async::Ready(Some(Sequenced::Message(msg))));
+        }
+        let mut message = match try_ready!(self.stream.poll()) {
+            Some(message) => message,
+            None => return Ok(Async::Ready(None)),
+        };
+        if message.len() <= self.frame || message[self.frame].len() != 8 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "message is missing a valid sequence frame",
+            ));
+        }
+        let sequence = message
+            .remove(self.frame)
+            .iter()
+            .fold(0, |acc, b| (acc << 8) | u64::from(*b));
+        let last = match self.topics {
+            Some(ref mut topics) => {
+                let topic = message.first().map(|m| m.to_vec()).unwrap_or_default();
+                topics.insert(topic, sequence)
+            }
+            None => self.last,
+        };
+        self.last = Some(sequence);
+        let msg = SequencedMessage { sequence, message };
+        match last {
+            Some(last) if sequence > last + 1 => {
+                let gap = Gap {
+                    from: last + 1,
+                    to: sequence - 1,
+                };
+                self.gaps += gap.len();
+                self.pending = Some(msg);
+                Ok(Async::Ready(Some(Sequenced::Gap(gap))))
+            }
+            _ => Ok(Async::Ready(Some(Sequenced::Message(msg)))),
+        }
+    }
+}
+
+/// A `Sink` adapter that inserts a sequence frame after the topic, the
+/// first frame, of every message. Each topic is numbered separately,
+/// starting from 0.
+pub struct SeqPub<S> {
+    inner: S,
+    counters: HashMap<Vec<u8>, u64>,
+}
+
+impl<S> SeqPub<S>
+where
+    S: Sink<SinkItem = Multipart, SinkError = io::Error>,
+{
+    /// Wrap a sink, such as that of a `PUB` socket.
+    pub fn new(inner: S) -> SeqPub<S> {
+        SeqPub {
+            inner,
+            counters: HashMap::new(),
+        }
+    }
+
+    /// Returns the number of messages sent with the given topic, which is
+    /// also the sequence number of the next one.
+    pub fn sent(&self, topic: &[u8]) -> u64 {
+        self.counters.get(topic).cloned().unwrap_or(0)
+    }
+
+    /// Returns the number of messages sent for every topic.
+    pub fn counters(&self) -> &HashMap<Vec<u8>, u64> {
+        &self.counters
+    }
+
+    /// A reference to the wrapped sink.
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+
+    /// Consumes the adapter, returning the wrapped sink.
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S> Sink for SeqPub<S>
+where
+    S: Sink<SinkItem = Multipart, SinkError = io::Error>,
+{
+    type SinkItem = Multipart;
+    type SinkError = io::Error;
+
+    fn start_send(&mut self, mut item: Multipart) -> StartSend<Self::SinkItem, Self::SinkError> {
+        if item.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "message has no topic frame",
+            ));
+        }
+        let topic = item[0].to_vec();
+        let sequence = self.sent(&topic);
+        item.insert(1, sequence_frame(sequence));
+        match self.inner.start_send(item)? {
+            AsyncSink::Ready => {
+                self.counters.insert(topic, sequence + 1);
+                Ok(AsyncSink::Ready)
+            }
+            AsyncSink::NotReady(mut item) => {
+                item.remove(1);
+                Ok(AsyncSink::NotReady(item))
+            }
+        }
+    }
+
+    fn poll_complete(&mut self) -> Poll<(), Self::SinkError> {
+        self.inner.poll_complete()
+    }
+}
@@ -0,0 +1,230 @@
+//! Sharing a socket between tasks.
+//!
+//! ØMQ sockets must not be used from several threads at once, and a
+//! `Socket` can only be polled by one task at a time. A `SocketHandle`
+//! moves the socket into a task of its own, and forwards the calls made
+//! through any of its clones over a channel.
+use std::collections::VecDeque;
+use std::io;
+
+use futures::{Async, AsyncSink, Future, Poll, Sink, Stream};
+use futures::sync::{mpsc, oneshot};
+use tokio_core::reactor::Handle;
+
+use super::{Multipart, Socket};
+use super::error::{is_fatal, is_recoverable};
+
+enum Command {
+    Send(Multipart, oneshot::Sender<io::Result<()>>),
+    Request(Multipart, oneshot::Sender<io::Result<Multipart>>),
+}
+
+/// A cheap, cloneable handle to a socket owned by a task of its own.
+///
+/// Replies are matched to requests in order, so `request` is meant for
+/// peers that answer every request in turn, such as `REP` sockets. Incoming
+/// messages that answer no request are dropped.
+///
+/// A call that fails, such as a multipart-message sent through a
+/// conflating socket, fails on its own, and the task goes on with the
+/// other calls. Only errors that leave the socket unusable, such as the
+/// termination of its context, stop the task and fail every pending call.
+#[derive(Clone)]
+pub struct SocketHandle {
+    commands: mpsc::UnboundedSender<Command>,
+}
+
+impl SocketHandle {
+    /// Move the socket into a task spawned on the given reactor handle. The
+    /// task stops once every clone of the handle is dropped, every message
+    /// is sent, and the remaining requests are answered, or their responses
+    /// dropped.
+    pub fn spawn(socket: Socket, handle: &Handle) -> SocketHandle {
+        let (tx, rx) = mpsc::unbounded();
+        let driver = Driver {
+            socket,
+            commands: rx,
+            commands_done: false,
+            outgoing: VecDeque::new(),
+            replies: VecDeque::new(),
+        };
+        handle.spawn(driver.map_err(|e| error!("socket handle failed: {}", e)));
+        SocketHandle { commands: tx }
+    }
+
+    /// Send a multipart-message, returning a future that resolves once the
+    /// socket has accepted it.
+    pub fn send(&self, messages: Multipart) -> Response<()> {
+        let (tx, rx) = oneshot::channel();
+        let _ = self.commands.unbounded_send(Command::Send(messages, tx));
+        Response { rx }
+    }
+
+    /// Send a request, returning a future that resolves into its reply.
+    pub fn request(&self, messages: Multipart) -> Response<Multipart> {
+        let (tx, rx) = oneshot::channel();
+        let _ = self.commands.unbounded_send(Command::Request(messages, tx));
+        Response { rx }
+    }
+}
+
+/// A Future that resolves into the result of a call made through a
+/// `SocketHandle`.
+pub struct Response<T> {
+    rx: oneshot::Receiver<io::Result<T>>,
+}
+
+impl<T> Future for Response<T> {
+    type Item = T;
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        match self.rx.poll() {
+            Ok(Async::Ready(Ok(item))) => Ok(Async::Ready(item)),
+            Ok(Async::Ready(Err(e))) => Err(e),
+            Ok(Async::NotReady) => Ok(Async::NotReady),
+            Err(_) => Err(io::Error::new(
+                io::ErrorKind::BrokenPipe,
+                "socket task has stopped",
+            )),
+        }
+    }
+}
+
+// Where the result of an outgoing message goes, once it is sent.
+enum Pending {
+    Sent(oneshot::Sender<io::Result<()>>),
+    Reply(oneshot::Sender<io::Result<Multipart>>),
+}
+
+// Task that owns the socket of a `SocketHandle`.
+struct Driver {
+    socket: Socket,
+    commands: mpsc::UnboundedReceiver<Command>,
+    commands_done: bool,
+    outgoing: VecDeque<(Multipart, Pending)>,
+    replies: VecDeque<oneshot::Sender<io::Result<Multipart>>>,
+}
+
+impl Driver {
+    fn poll_commands(&mut self) {
+        while !self.commands_done {
+            match self.commands.poll() {
+                Ok(Async::Ready(Some(Command::Send(msgs, tx)))) => {
+                    self.outgoing.push_back((msgs, Pending::Sent(tx)));
+                }
+                Ok(Async::Ready(Some(Command::Request(msgs, tx)))) => {
+                    self.outgoing.push_back((msgs, Pending::Reply(tx)));
+                }
+                Ok(Async::NotReady) => break,
+                Ok(Async::Ready(None)) | Err(_) => self.commands_done = true,
+            }
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        let mut sink = &self.socket;
+        while let Some((msgs, pending)) = self.outgoing.pop_front() {
+            let sent = match sink.start_send(msgs) {
+                Ok(AsyncSink::NotReady(msgs)) => {
+                    self.outgoing.push_front((msgs, pending));
+                    break;
+                }
+                Ok(AsyncSink::Ready) => Ok(()),
+                Err(e) => Err(e),
+            };
+            let fatal = match sent {
+                Err(ref e) if is_fatal(e) => Some(io::Error::new(e.kind(), e.to_string())),
+                _ => None,
+            };
+            match (pending, sent) {
+                (Pending::Sent(tx), sent) => {
+                    let _ = tx.send(sent);
+                }
+                (Pending::Reply(tx), Ok(())) => self.replies.push_back(tx),
+                (Pending::Reply(tx), Err(e)) => {
+                    let _ = tx.send(Err(e));
+                }
+            }
+            if let Some(e) = fatal {
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+
+    fn poll_socket(&mut self) -> Poll<(), io::Error> {
+        self.poll_commands();
+        self.flush()?;
+        loop {
+            let (msgs, more) = {
+                let mut stream = &self.socket;
+                match stream.poll() {
+                    Ok(Async::Ready(Some(msgs))) => (Ok(msgs), true),
+                    Ok(_) => break,
+                    Err(e) => {
+                        if is_fatal(&e) {
+                            return Err(e);
+                        }
+                        // the failed reply, such as one that is too large,
+                        // fails its request. Only recoverable errors leave
+                        // more messages to receive right away.
+                        let more = is_recoverable(&e);
+                        (Err(e), more)
+                    }
+                }
+            };
+            match self.replies.pop_front() {
+                Some(tx) => {
+                    let _ = tx.send(msgs);
+                }
+                None => warn!("dropping message that answers no request"),
+            }
+            if !more {
+                break;
+            }
+        }
+        if self.commands_done && self.outgoing.is_empty() && self.replies_dropped() {
+            Ok(Async::Ready(()))
+        } else {
+            Ok(Async::NotReady)
+        }
+    }
+
+    // Returns true when no pending request has a response to answer
+    // anymore. Otherwise, the task is notified once the next one is
+    // dropped.
+    fn replies_dropped(&mut self) -> bool {
+        self.replies
+            .iter_mut()
+            .all(|tx| tx.poll_cancel() == Ok(Async::Ready(())))
+    }
+}
+
+impl Future for Driver {
+    type Item = ();
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        match self.poll_socket() {
+            Err(e) => {
+                for (_, pending) in self.outgoing.drain(..) {
+                    let err = io::Error::new(e.kind(), e.to_string());
+                    match pending {
+                        Pending::Sent(tx) => {
+                            let _ = tx.send(Err(err));
+                        }
+                        Pending::Reply(tx) => {
+                            let _ = tx.send(Err(err));
+                        }
+                    }
+                }
+                for tx in self.replies.drain(..) {
+                    let _ = tx.send(Err(io::Error::new(e.kind(), e.to_string())));
+                }
+                Err(e)
+            }
+            r => r,
+        }
+    }
+}
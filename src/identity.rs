@@ -0,0 +1,96 @@
+//! Socket identities, a.k.a. routing-ids.
+use std::fmt;
+use std::ops::Deref;
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering, ATOMIC_USIZE_INIT};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+static NEXT_ID: AtomicUsize = ATOMIC_USIZE_INIT;
+
+/// The identity of a peer, as used by `ROUTER` sockets to address messages.
+#[derive(Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct RoutingId(Vec<u8>);
+
+impl RoutingId {
+    /// Create a new `RoutingId` from raw bytes.
+    pub fn new<T: Into<Vec<u8>>>(id: T) -> RoutingId {
+        RoutingId(id.into())
+    }
+
+    /// Generate a printable `RoutingId` that is unique within this process,
+    /// and very likely to be unique across processes and hosts.
+    ///
+    /// The generated identity never starts with a zero byte, which ØMQ
+    /// reserves for its own use.
+    pub fn generate() -> RoutingId {
+        let count = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs() * 1_000_000_000 + u64::from(d.subsec_nanos()))
+            .unwrap_or(0);
+        RoutingId(format!("id-{:x}-{:x}-{:x}", process::id(), nanos, count).into_bytes())
+    }
+
+    /// Returns the identity as a byte slice.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Consumes the identity, returning the underlying bytes.
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.0
+    }
+}
+
+impl Deref for RoutingId {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl AsRef<[u8]> for RoutingId {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl From<Vec<u8>> for RoutingId {
+    fn from(id: Vec<u8>) -> RoutingId {
+        RoutingId(id)
+    }
+}
+
+impl<'a> From<&'a [u8]> for RoutingId {
+    fn from(id: &'a [u8]) -> RoutingId {
+        RoutingId(id.to_vec())
+    }
+}
+
+impl<'a> From<&'a str> for RoutingId {
+    fn from(id: &'a str) -> RoutingId {
+        RoutingId(id.as_bytes().to_vec())
+    }
+}
+
+impl fmt::Debug for RoutingId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "RoutingId({})", self)
+    }
+}
+
+/// Printable identities are shown as-is, anything else is shown as hex.
+impl fmt::Display for RoutingId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let printable = !self.0.is_empty() && self.0.iter().all(|b| *b >= 0x20 && *b < 0x7f);
+        if printable {
+            write!(f, "{}", String::from_utf8_lossy(&self.0))
+        } else {
+            for b in &self.0 {
+                write!(f, "{:02x}", b)?;
+            }
+            Ok(())
+        }
+    }
+}
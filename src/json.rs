@@ -0,0 +1,119 @@
+//! JSON-typed sockets.
+//!
+//! Enabled with the `serde` feature.
+use std::io;
+use std::marker::PhantomData;
+
+use futures::{Async, Future, Poll};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json;
+use zmq::Message;
+
+use super::Socket;
+use super::codec::{single_frame, CodecFramed, MessageCodec};
+use super::future::{ReceiveMessage, SendMessage};
+
+/// A socket transport whose `Stream` and `Sink` items are values of type
+/// `T`, sent as one-part JSON messages. This is returned by `Socket::typed`.
+pub type TypedSocket<T> = CodecFramed<JsonCodec<T>>;
+
+/// Codec for values of type `T`, encoded as one-part JSON messages.
+pub struct JsonCodec<T> {
+    _item: PhantomData<T>,
+}
+
+impl<T> JsonCodec<T> {
+    pub fn new() -> JsonCodec<T> {
+        JsonCodec { _item: PhantomData }
+    }
+}
+
+impl<T> Default for JsonCodec<T> {
+    fn default() -> JsonCodec<T> {
+        JsonCodec::new()
+    }
+}
+
+impl<T> MessageCodec for JsonCodec<T>
+where
+    T: Serialize + DeserializeOwned,
+{
+    type Item = T;
+
+    fn encode(&mut self, item: T) -> io::Result<Vec<Message>> {
+        Ok(vec![Message::from(to_json(&item)?)])
+    }
+
+    fn decode(&mut self, msgs: Vec<Message>) -> io::Result<T> {
+        from_json(&single_frame(msgs)?)
+    }
+}
+
+/// A Future that sends a value as a JSON message.
+/// This is returned by `Socket::send_json`
+pub struct SendJson<'a> {
+    inner: Result<SendMessage<'a>, Option<io::Error>>,
+}
+
+impl<'a> SendJson<'a> {
+    pub fn new<T: Serialize>(socket: &'a Socket, item: &T) -> SendJson<'a> {
+        let inner = match to_json(item) {
+            Ok(bytes) => Ok(SendMessage::new(socket, Message::from(bytes))),
+            Err(e) => Err(Some(e)),
+        };
+        SendJson { inner }
+    }
+}
+
+impl<'a> Future for SendJson<'a> {
+    type Item = ();
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        match self.inner {
+            Ok(ref mut send) => send.poll(),
+            Err(ref mut e) => Err(e.take().expect("polled SendJson after completion")),
+        }
+    }
+}
+
+/// A Future that receives a JSON message, and deserializes it into a value.
+/// This is returned by `Socket::recv_json`
+pub struct ReceiveJson<'a, T> {
+    inner: ReceiveMessage<'a>,
+    _item: PhantomData<T>,
+}
+
+impl<'a, T> ReceiveJson<'a, T>
+where
+    T: DeserializeOwned,
+{
+    pub fn new(socket: &'a Socket) -> ReceiveJson<'a, T> {
+        ReceiveJson {
+            inner: ReceiveMessage::new(socket),
+            _item: PhantomData,
+        }
+    }
+}
+
+impl<'a, T> Future for ReceiveJson<'a, T>
+where
+    T: DeserializeOwned,
+{
+    type Item = T;
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        let msg = try_ready!(self.inner.poll());
+        Ok(Async::Ready(from_json(&msg)?))
+    }
+}
+
+fn to_json<T: Serialize>(item: &T) -> io::Result<Vec<u8>> {
+    serde_json::to_vec(item).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))
+}
+
+fn from_json<T: DeserializeOwned>(bytes: &[u8]) -> io::Result<T> {
+    serde_json::from_slice(bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
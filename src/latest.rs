@@ -0,0 +1,102 @@
+//! Keeping only the latest message per topic.
+//!
+//! `ZMQ_CONFLATE` only keeps the last message of a socket, and only works
+//! with one-part messages. A `Latest` stream adapter keeps the latest
+//! message for each topic key instead, among those that arrived since the
+//! last poll, so a consumer that falls behind skips stale values rather
+//! than processing every one of them.
+use std::collections::{HashMap, VecDeque};
+use std::io;
+
+use futures::{Async, Poll, Stream};
+
+use super::Multipart;
+
+/// Keep the latest message per topic, the topic being the first frame.
+pub fn latest<S>(inner: S) -> Latest<S, fn(&Multipart) -> Vec<u8>>
+where
+    S: Stream<Item = Multipart, Error = io::Error>,
+{
+    Latest::by_key(inner, first_frame)
+}
+
+fn first_frame(msgs: &Multipart) -> Vec<u8> {
+    msgs.first().map(|msg| msg.to_vec()).unwrap_or_default()
+}
+
+/// A `Stream` adapter that yields only the latest message for each topic
+/// key, in the order the keys first arrived. This is returned by `latest`.
+pub struct Latest<S, F> {
+    inner: S,
+    key: F,
+    order: VecDeque<Vec<u8>>,
+    latest: HashMap<Vec<u8>, Multipart>,
+    done: bool,
+    conflated: u64,
+}
+
+impl<S, F> Latest<S, F>
+where
+    S: Stream<Item = Multipart, Error = io::Error>,
+    F: FnMut(&Multipart) -> Vec<u8>,
+{
+    /// Keep the latest message per topic, with the topic of each message
+    /// given by `key`.
+    pub fn by_key(inner: S, key: F) -> Latest<S, F> {
+        Latest {
+            inner,
+            key,
+            order: VecDeque::new(),
+            latest: HashMap::new(),
+            done: false,
+            conflated: 0,
+        }
+    }
+
+    /// Returns the number of messages dropped for newer ones.
+    pub fn conflated(&self) -> u64 {
+        self.conflated
+    }
+
+    /// Consumes the adapter, returning the wrapped stream. Pending messages
+    /// are lost.
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S, F> Stream for Latest<S, F>
+where
+    S: Stream<Item = Multipart, Error = io::Error>,
+    F: FnMut(&Multipart) -> Vec<u8>,
+{
+    type Item = Multipart;
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        // take everything that is ready, so only the newest values remain.
+        while !self.done {
+            match self.inner.poll()? {
+                Async::Ready(Some(msgs)) => {
+                    let key = (self.key)(&msgs);
+                    if self.latest.insert(key.clone(), msgs).is_some() {
+                        self.conflated += 1;
+                    } else {
+                        self.order.push_back(key);
+                    }
+                }
+                Async::Ready(None) => self.done = true,
+                Async::NotReady => break,
+            }
+        }
+        if let Some(key) = self.order.pop_front() {
+            let msgs = self.latest.remove(&key).expect("queued topic has a message");
+            return Ok(Async::Ready(Some(msgs)));
+        }
+        if self.done {
+            Ok(Async::Ready(None))
+        } else {
+            Ok(Async::NotReady)
+        }
+    }
+}
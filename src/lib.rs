@@ -183,114 +183,1404 @@
 //!     ::std::process::exit(0);
 //! }
 //! ```
+#[cfg(feature = "bytes")]
+extern crate bytes;
+#[cfg(feature = "ed25519")]
+extern crate ed25519_dalek;
+#[macro_use]
 extern crate futures;
 extern crate futures_cpupool;
 #[macro_use]
 extern crate log;
+#[cfg(feature = "lz4")]
+extern crate lz4;
 extern crate mio;
+#[cfg(feature = "prometheus")]
+extern crate prometheus;
+#[cfg(feature = "prost")]
+extern crate prost;
+#[cfg(feature = "msgpack")]
+extern crate rmp_serde;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(feature = "cbor")]
+extern crate serde_cbor;
+#[cfg(feature = "serde")]
+extern crate serde_json;
 extern crate tokio_core;
 extern crate tokio_io;
+#[cfg(feature = "tokio-signal")]
+extern crate tokio_signal;
+#[cfg(feature = "tower-service")]
+extern crate tower_service;
 pub extern crate zmq;
 extern crate zmq_mio;
+#[cfg(feature = "zstd")]
+extern crate zstd;
+
+pub mod auth;
+pub mod bench;
+pub mod binary_star;
+pub mod body;
+pub mod bridge;
+#[cfg(feature = "bytes")]
+pub mod buf;
+pub mod buffer;
+pub mod builder;
+#[cfg(feature = "cbor")]
+pub mod cbor;
+#[cfg(feature = "bytes")]
+pub mod chunked;
+pub mod clone;
+pub mod codec;
+pub mod compress;
+pub mod config;
+pub mod curve;
+pub mod demux;
+pub mod dump;
+pub mod endpoint;
+pub mod error;
+pub mod events;
+pub mod freelance;
+pub mod future;
+pub mod gap;
+pub mod handle;
+pub mod identity;
+#[cfg(feature = "serde")]
+pub mod json;
+pub mod latest;
+pub mod liveness;
+pub mod load_balancer;
+pub mod mdp;
+pub mod metrics;
+pub mod monitor;
+#[cfg(feature = "msgpack")]
+pub mod msgpack;
+mod options;
+pub mod pipeline;
+pub mod pirate;
+mod poll_evented;
+pub mod pool;
+#[cfg(feature = "prometheus")]
+pub mod prom;
+#[cfg(feature = "prost")]
+pub mod proto;
+pub mod proxy;
+pub mod reactor;
+pub mod reconnect;
+pub mod recorder;
+pub mod reliable;
+pub mod resolve;
+pub mod resubscribe;
+pub mod select;
+#[cfg(feature = "tower-service")]
+pub mod service;
+pub mod shutdown;
+#[cfg(feature = "tokio-signal")]
+pub mod signal;
+#[cfg(feature = "ed25519")]
+pub mod signing;
+pub mod sink;
+pub mod spill;
+pub mod stream;
+#[cfg(feature = "bytes")]
+pub mod stream_codec;
+pub mod subscription;
+pub mod tap;
+pub mod tcp;
+pub mod testing;
+pub mod throttle;
+pub mod topology;
+pub mod tracked;
+pub mod transport;
+
+use std::fmt;
+use std::io;
+use std::io::{Read, Write};
+use std::net::SocketAddr;
+use std::os::raw::c_void;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::Duration;
+
+use futures::{Poll, Stream};
+use futures::sync::mpsc;
+use futures_cpupool::CpuPool;
+
+use tokio_core::reactor::{Handle, PollEvented, Remote};
+use tokio_io::{AsyncRead, AsyncWrite};
+
+#[cfg(feature = "bytes")]
+use bytes::Buf;
+
+use self::bridge::{BridgeFrom, BridgeTo};
+use self::builder::{ContextBuilder, SocketBuilder};
+use self::endpoint::IntoEndpoint;
+use self::error::{EndpointsError, TrySendError};
+use self::reconnect::ReconnectPolicy;
+use self::resolve::ConnectResolved;
+use self::codec::{CodecFramed, MessageCodec};
+use self::config::SocketConfig;
+use self::reactor::Reactor;
+use self::body::{ReceiveStreamBody, SendStreamBody, DEFAULT_BODY_FRAME_SIZE};
+use self::demux::Demux;
+use self::events::EventFlags;
+use self::resubscribe::Resubscriber;
+use self::subscription::WaitForSubscribers;
+use self::metrics::{Metrics, MetricsSnapshot};
+use self::future::{BindOffloaded, Close, Readable, ReceiveBatch, ReceiveFrom, ReceiveMessage, ReceiveMultipartMessage,
+                   ReceiveReply, Request, SendAll, SendMessage, SendMultipartMessage, SendRequest, SendTo,
+                   SendZeroCopy, Terminate, WaitEvents, Writable};
+use self::future::check_message_size;
+use self::stream::{MessageStream, MultipartBatchStream, MultipartMessageStream, ResultStream};
+use self::sink::{MessageSink, MultipartMessageSink};
+
+pub use io::Error;
+pub use self::endpoint::Endpoint;
+pub use self::identity::RoutingId;
+pub use self::options::{MulticastOptions, SocketOptionsSnapshot, TcpOptions};
+pub use zmq::Message;
+/// A multi-part message, as received from a socket.
+pub type Multipart = Vec<Message>;
+/// Supported socket types are: `DEALER`, `PAIR`, `PUB`, `PULL`, `PUSH`, `REP`, `REQ`, `ROUTER`, `STREAM`, `SUB`, `XPUB`, `XSUB`.
+pub use zmq::SocketType::*;
+
+// Re-export custom transport to keep backwards-compatibility with examples
+// TODO: move this someplace else once the API is stable
+pub use self::transport::SocketFramed;
+
+/// A ØMQ context, creating sockets driven by a tokio reactor.
+///
+/// Clones share the underlying `zmq_mio::Context`, along with the options
+/// set by `ContextBuilder` and the count of open sockets.
+#[derive(Clone, Default)]
+pub struct Context {
+    inner: zmq_mio::Context,
+    state: Arc<ContextState>,
+}
+
+impl Context {
+    /// Create a new ØMQ context for the `tokio` framework.
+    pub fn new() -> Context {
+        Context {
+            inner: zmq_mio::Context::new(),
+            state: Arc::new(ContextState::default()),
+        }
+    }
+
+    /// Returns a `ContextBuilder`, for creating a context with custom options.
+    pub fn builder() -> ContextBuilder {
+        ContextBuilder::new()
+    }
+
+    // Create a new context with the options set by `ContextBuilder`.
+    fn with_options(
+        max_sockets: Option<usize>,
+        ipv6: bool,
+        blocking_pool: Option<CpuPool>,
+        metrics: bool,
+    ) -> Context {
+        Context {
+            inner: zmq_mio::Context::new(),
+            state: Arc::new(ContextState {
+                max_sockets,
+                ipv6,
+                blocking_pool,
+                metrics,
+                ..ContextState::default()
+            }),
+        }
+    }
+
+    /// Create a socket as described by a `SocketConfig`, such as one
+    /// deserialized from a configuration file.
+    pub fn socket_from_config(&self, config: &SocketConfig, handle: &Handle) -> io::Result<Socket> {
+        config.build(self, handle)
+    }
+
+    /// Returns a `SocketBuilder`, for creating a socket of the given type
+    /// with custom options, applied before it binds or connects.
+    pub fn socket_builder(&self, typ: zmq::SocketType) -> SocketBuilder {
+        SocketBuilder::new(self, typ)
+    }
+
+    /// Create a new ØMQ socket for the `tokio` framework.
+    pub fn socket(&self, typ: zmq::SocketType, handle: &Handle) -> io::Result<Socket> {
+        let slot = try!(SocketSlot::acquire(&self.state));
+        let mio_socket = try!(self.inner.socket(typ));
+        if self.state.ipv6 {
+            try!(mio_socket.set_ipv6(true));
+        }
+        let mut socket = try!(Socket::new(mio_socket, handle));
+        socket.slot = Some(slot);
+        if self.state.metrics {
+            socket.enable_metrics();
+        }
+        Ok(socket)
+    }
+
+    /// Try to destroy the underlying context. This is different than the destructor;
+    /// the destructor will loop when zmq_ctx_destroy returns EINTR.
+    pub fn destroy(&mut self) -> io::Result<()> {
+        self.inner.destroy()
+    }
+
+    /// Gracefully terminate the context. Returns a `Future` that resolves
+    /// once every socket created by this context has been dropped, and the
+    /// underlying context has been destroyed.
+    ///
+    /// From now on, the context refuses to create new sockets. Once polled,
+    /// the future terminates the context: operations on its sockets fail
+    /// with `ETERM`, so the tasks using them end, and drop them.
+    ///
+    /// The given linger period bounds the time spent delivering the pending
+    /// messages of the open sockets. As ØMQ sockets are not thread-safe,
+    /// each socket applies it on its own thread, the next time it is
+    /// polled or when it is dropped, whichever comes first.
+    pub fn terminate(&self, linger: Option<Duration>) -> Terminate {
+        let linger = linger.map(duration_to_millis);
+        *self.state.close_linger.lock().unwrap() = linger;
+        self.state.terminating.store(true, Ordering::SeqCst);
+        Terminate::new(self.clone())
+    }
+
+    /// Returns the pool that runs the blocking operations of this context
+    /// and its sockets, if one was set with `ContextBuilder::blocking_pool`.
+    pub fn blocking_pool(&self) -> Option<&CpuPool> {
+        self.state.blocking_pool.as_ref()
+    }
+
+    /// Get a cloned instance of the underlying `zmq_mio::Context`.
+    pub fn get_inner(&self) -> zmq_mio::Context {
+        self.inner.clone()
+    }
+
+    // Returns the number of sockets created by this context that are still open.
+    fn open_sockets(&self) -> usize {
+        self.state.open_sockets.load(Ordering::SeqCst)
+    }
+}
+
+impl fmt::Debug for Context {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Context")
+            .field("open_sockets", &self.open_sockets())
+            .field("max_sockets", &self.state.max_sockets)
+            .field("terminating", &self.state.terminating.load(Ordering::SeqCst))
+            .finish()
+    }
+}
+
+// Options and bookkeeping shared by all clones of a `Context`.
+struct ContextState {
+    max_sockets: Option<usize>,
+    ipv6: bool,
+    blocking_pool: Option<CpuPool>,
+    metrics: bool,
+    open_sockets: AtomicUsize,
+    terminating: AtomicBool,
+    close_linger: Mutex<Option<i32>>,
+}
+
+impl ContextState {
+    // The linger period to apply to sockets that are closed while the
+    // context is terminating.
+    fn close_linger(&self) -> Option<i32> {
+        if self.terminating.load(Ordering::SeqCst) {
+            *self.close_linger.lock().unwrap()
+        } else {
+            None
+        }
+    }
+}
+
+impl Default for ContextState {
+    fn default() -> ContextState {
+        ContextState {
+            max_sockets: None,
+            ipv6: false,
+            blocking_pool: None,
+            metrics: false,
+            open_sockets: AtomicUsize::new(0),
+            terminating: AtomicBool::new(false),
+            close_linger: Mutex::new(None),
+        }
+    }
+}
+
+// Keeps count of the sockets created by a `Context`, releasing its slot
+// when the socket is dropped.
+struct SocketSlot {
+    state: Arc<ContextState>,
+}
+
+impl SocketSlot {
+    fn acquire(state: &Arc<ContextState>) -> io::Result<SocketSlot> {
+        if state.terminating.load(Ordering::SeqCst) {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                "context is terminating",
+            ));
+        }
+        let open = state.open_sockets.fetch_add(1, Ordering::SeqCst);
+        let slot = SocketSlot {
+            state: state.clone(),
+        };
+        match state.max_sockets {
+            // like libzmq, when it runs out of sockets.
+            Some(max) if open >= max => Err(zmq::Error::EMFILE.into()),
+            _ => Ok(slot),
+        }
+    }
+}
+
+impl Drop for SocketSlot {
+    fn drop(&mut self) {
+        self.state.open_sockets.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Poll-evented ØMQ socket. Can be used directly on transports implementing
+/// `futures::stream::Stream` and `futures::sink::Sink`.
+///
+/// A socket is registered with the reactor it was created with. To drive
+/// it from another `Core`, such as one on a worker thread, deregister it
+/// with `Socket::deregister`, send it to the other thread, and register it
+/// there with `Socket::register`.
+pub struct Socket {
+    // The registration with the reactor. Taken by `Close` and
+    // `Socket::deregister`.
+    io: Option<PollEvented<zmq_mio::Socket>>,
+    // The socket while deregistered.
+    detached: Option<zmq_mio::Socket>,
+    remote: Remote,
+    slot: Option<SocketSlot>,
+    metrics: Option<Arc<Metrics>>,
+    endpoints: Mutex<Vec<String>>,
+    subscriptions: Mutex<Vec<Vec<u8>>>,
+    conflate: AtomicBool,
+    req_relaxed: AtomicBool,
+    // Whether the linger period of a terminating context was applied.
+    close_linger_applied: AtomicBool,
+}
+
+impl Socket {
+    /// Create a new poll-evented ØMQ socket, along with a tokio reactor handle
+    /// to drive its event-loop.
+    fn new(socket: zmq_mio::Socket, handle: &Handle) -> io::Result<Self> {
+        let io = try!(handle.register(socket));
+        let socket = Socket {
+            io: Some(io),
+            detached: None,
+            remote: handle.remote().clone(),
+            slot: None,
+            metrics: None,
+            endpoints: Mutex::new(Vec::new()),
+            subscriptions: Mutex::new(Vec::new()),
+            conflate: AtomicBool::new(false),
+            req_relaxed: AtomicBool::new(false),
+            close_linger_applied: AtomicBool::new(false),
+        };
+        Ok(socket)
+    }
+
+    /// Wrap a socket created and configured by other code, to drive it
+    /// with the given reactor. The socket must not be used through other
+    /// handles from now on.
+    ///
+    /// The endpoints and subscriptions of the socket made before it was
+    /// wrapped are not known to `endpoints` and `subscriptions`.
+    pub fn from_zmq(socket: zmq::Socket, handle: &Handle) -> io::Result<Socket> {
+        Socket::new(zmq_mio::Socket::new(socket), handle)
+    }
+
+    /// Deregister the socket from its reactor, and return the underlying
+    /// `zmq::Socket`, for code expecting a plain rust-zmq socket. Like
+    /// `deregister`, this must run on the thread of the reactor, unless the
+    /// socket is already deregistered.
+    ///
+    /// The socket no longer counts against the maximum number of sockets of
+    /// its context.
+    pub fn into_inner(mut self) -> io::Result<zmq::Socket> {
+        if self.io.is_some() {
+            self.deregister()?;
+        }
+        match self.detached.take() {
+            Some(socket) => socket.into_inner(),
+            None => Err(io::Error::new(io::ErrorKind::Other, "socket is closed")),
+        }
+    }
+
+    /// A reference to the underlying `zmq_mio::Socket`. Useful
+    /// for building futures.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the socket is deregistered. The futures and streams of a
+    /// deregistered socket fail with `io::ErrorKind::NotConnected` instead.
+    pub fn get_ref(&self) -> &PollEvented<zmq_mio::Socket> {
+        self.registration()
+            .expect("socket is deregistered from its reactor, see `Socket::register`")
+    }
+
+    // The registration of the socket with its reactor, through which its
+    // futures and streams are polled.
+    pub(crate) fn registration(&self) -> io::Result<&PollEvented<zmq_mio::Socket>> {
+        self.apply_close_linger();
+        self.io.as_ref().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotConnected,
+                "socket is deregistered from its reactor",
+            )
+        })
+    }
+
+    /// Deregister the socket from its reactor, so it can be sent to another
+    /// thread, and registered with another reactor with `Socket::register`.
+    /// This must run on the thread of the reactor.
+    ///
+    /// Since it borrows the socket mutably, no future or stream of the
+    /// socket can be outstanding. Until it is registered again, the options
+    /// of the socket can be set, but its futures and streams fail with
+    /// `io::ErrorKind::NotConnected`.
+    pub fn deregister(&mut self) -> io::Result<()> {
+        let mut io = match self.io.take() {
+            Some(io) => io,
+            None => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Other,
+                    "socket is already deregistered",
+                ))
+            }
+        };
+        let handle = match self.reactor_handle() {
+            Ok(handle) => handle,
+            Err(e) => {
+                self.io = Some(io);
+                return Err(e);
+            }
+        };
+        // the reactor drops what it deregisters, so it gets a shell that
+        // doesn't own the underlying socket.
+        match io.get_mut().detach() {
+            Ok(socket) => self.detached = Some(socket),
+            Err(e) => {
+                self.io = Some(io);
+                return Err(e);
+            }
+        }
+        handle.deregister(io)
+    }
+
+    /// Register a deregistered socket with the given reactor, which drives
+    /// it from now on.
+    pub fn register(&mut self, handle: &Handle) -> io::Result<()> {
+        let socket = match self.detached.take() {
+            Some(socket) => socket,
+            None => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Other,
+                    "socket is already registered",
+                ))
+            }
+        };
+        self.io = Some(handle.register(socket)?);
+        self.remote = handle.remote().clone();
+        Ok(())
+    }
+
+    /// Returns true if the socket is registered with a reactor.
+    pub fn is_registered(&self) -> bool {
+        self.io.is_some()
+    }
+
+    // Returns the reactor of the socket, which must be reached from its
+    // thread.
+    pub(crate) fn reactor_handle(&self) -> io::Result<Handle> {
+        self.remote.handle().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::Other,
+                "socket is not on the thread of its reactor",
+            )
+        })
+    }
+
+    /// Start recording metrics for this socket, if not already recording.
+    pub fn enable_metrics(&mut self) {
+        if self.metrics.is_none() {
+            self.metrics = Some(Arc::new(Metrics::new()));
+        }
+    }
+
+    /// Returns a snapshot of the metrics of this socket, if they are
+    /// recorded.
+    pub fn metrics(&self) -> Option<MetricsSnapshot> {
+        self.metrics.as_ref().map(|metrics| metrics.snapshot())
+    }
+
+    /// Returns the shared counters of this socket, if metrics are recorded,
+    /// for observers such as `Monitor::record_reconnects`.
+    pub fn metrics_handle(&self) -> Option<Arc<Metrics>> {
+        self.metrics.clone()
+    }
+
+    // The blocking pool of the context that created this socket.
+    fn blocking_pool(&self) -> Option<CpuPool> {
+        self.slot.as_ref().and_then(|slot| slot.state.blocking_pool.clone())
+    }
+
+    // Sets the linger period given to `Context::terminate`, once the
+    // context is terminating. This runs on the thread using the socket,
+    // before the termination of the context reaches it, since the socket
+    // refuses new options from then on.
+    fn apply_close_linger(&self) {
+        if self.close_linger_applied.load(Ordering::SeqCst) {
+            return;
+        }
+        let linger = match self.slot.as_ref().and_then(|slot| slot.state.close_linger()) {
+            Some(linger) => linger,
+            None => return,
+        };
+        self.close_linger_applied.store(true, Ordering::SeqCst);
+        if let Some(socket) = self.mio_socket() {
+            if let Err(e) = socket.set_linger(linger) {
+                debug!("failed to set linger on close: {}", e);
+            }
+        }
+    }
+
+    /// A reference to the underlying `zmq_mio::Socket`. Useful
+    /// for building futures.
+    fn get_mio_ref(&self) -> &zmq_mio::Socket {
+        self.mio_socket().expect("socket is closed")
+    }
+
+    // The underlying `zmq_mio::Socket`, registered or not, unless `Close`
+    // took it.
+    fn mio_socket(&self) -> Option<&zmq_mio::Socket> {
+        match self.io {
+            Some(ref io) => Some(io.get_ref()),
+            None => self.detached.as_ref(),
+        }
+    }
+
+    /// A reference to the underlying `zmq::Socket`, for code expecting a
+    /// plain rust-zmq socket. Operations on it bypass the reactor, so they
+    /// should not block.
+    pub fn get_zmq_ref(&self) -> &zmq::Socket {
+        self.get_mio_ref().get_ref()
+    }
+
+    /// Returns the raw `void *` handle of the underlying libzmq socket, for
+    /// passing to C code. The handle is only valid while this socket lives.
+    pub fn as_raw_socket(&self) -> *mut c_void {
+        self.get_mio_ref().as_raw_socket()
+    }
+
+    /// Bind the underlying socket to the given endpoint. String endpoints
+    /// are parsed into an `Endpoint` first, failing with `InvalidInput` when
+    /// they are malformed, and the socket options in their query are set
+    /// before binding.
+    pub fn bind<E: IntoEndpoint>(&self, endpoint: E) -> io::Result<()> {
+        let (endpoint, options) = endpoint.into_endpoint_with_options()?;
+        endpoint.check_supported()?;
+        options.apply(self)?;
+        self.bind_str(&endpoint.to_string())
+    }
+
+    /// Connect the underlying socket to the given endpoint. String endpoints
+    /// are parsed into an `Endpoint` first, failing with `InvalidInput` when
+    /// they are malformed, and the socket options in their query are set
+    /// before connecting.
+    pub fn connect<E: IntoEndpoint>(&self, endpoint: E) -> io::Result<()> {
+        let (endpoint, options) = endpoint.into_endpoint_with_options()?;
+        endpoint.check_supported()?;
+        options.apply(self)?;
+        self.connect_str(&endpoint.to_string())
+    }
+
+    // Binds, remembering the endpoint, with the port assigned for wildcards.
+    fn bind_str(&self, endpoint: &str) -> io::Result<()> {
+        self.get_mio_ref().bind(endpoint)?;
+        let bound = match self.get_last_endpoint()? {
+            Ok(bound) => bound,
+            Err(_) => endpoint.to_string(),
+        };
+        self.endpoints.lock().unwrap().push(bound);
+        Ok(())
+    }
+
+    // Connects, remembering the endpoint.
+    fn connect_str(&self, endpoint: &str) -> io::Result<()> {
+        self.get_mio_ref().connect(endpoint)?;
+        self.endpoints.lock().unwrap().push(endpoint.to_string());
+        Ok(())
+    }
+
+    /// Returns the endpoints the underlying socket is bound or connected to,
+    /// with the ports assigned when binding to wildcard ports.
+    pub fn endpoints(&self) -> Vec<String> {
+        self.endpoints.lock().unwrap().clone()
+    }
+
+    /// Connect the underlying socket to the given endpoint, resolving its
+    /// hostname on the given pool first, instead of letting libzmq block the
+    /// reactor on DNS. Returns a future that resolves into the endpoint the
+    /// socket connected to.
+    pub fn connect_resolved<E: IntoEndpoint>(&self, endpoint: E, pool: &CpuPool) -> ConnectResolved {
+        ConnectResolved::new(self, endpoint.into_endpoint(), pool)
+    }
+
+    /// Bind the underlying socket to every given endpoint, such as both a
+    /// `tcp` and an `ipc` endpoint. Every endpoint is attempted, even after a
+    /// failure. Returns the bound endpoints, or an `EndpointsError` telling
+    /// which endpoints failed and which are bound.
+    pub fn bind_all<E>(&self, endpoints: &[E]) -> Result<Vec<Endpoint>, EndpointsError>
+    where
+        E: IntoEndpoint + Clone + fmt::Display,
+    {
+        self.for_all_endpoints(endpoints, |endpoint| self.bind_str(endpoint))
+    }
+
+    /// Connect the underlying socket to every given endpoint, such as a `SUB`
+    /// socket to many publishers. Every endpoint is attempted, even after a
+    /// failure. Returns the connected endpoints, or an `EndpointsError`
+    /// telling which endpoints failed and which are connected.
+    pub fn connect_all<E>(&self, endpoints: &[E]) -> Result<Vec<Endpoint>, EndpointsError>
+    where
+        E: IntoEndpoint + Clone + fmt::Display,
+    {
+        self.for_all_endpoints(endpoints, |endpoint| self.connect_str(endpoint))
+    }
+
+    // Applies `f` to every endpoint, collecting the results.
+    fn for_all_endpoints<E, F>(&self, endpoints: &[E], mut f: F) -> Result<Vec<Endpoint>, EndpointsError>
+    where
+        E: IntoEndpoint + Clone + fmt::Display,
+        F: FnMut(&str) -> io::Result<()>,
+    {
+        let mut succeeded = Vec::new();
+        let mut failed = Vec::new();
+        for endpoint in endpoints {
+            let result = endpoint
+                .clone()
+                .into_endpoint()
+                .and_then(|parsed| f(&parsed.to_string()).map(|_| parsed));
+            match result {
+                Ok(parsed) => succeeded.push(parsed),
+                Err(e) => failed.push((endpoint.to_string(), e)),
+            }
+        }
+        if failed.is_empty() {
+            Ok(succeeded)
+        } else {
+            Err(EndpointsError { succeeded, failed })
+        }
+    }
+
+    /// Bind the underlying socket to the given endpoint, on the blocking pool
+    /// of its context when there is one, so binding to slow transports
+    /// doesn't stall the reactor. Returns a future that resolves into the
+    /// socket once it is bound.
+    pub fn bind_offloaded<E: IntoEndpoint>(self, endpoint: E) -> BindOffloaded {
+        let endpoint = endpoint.into_endpoint();
+        BindOffloaded::new(self, endpoint)
+    }
+
+    /// Unbind the underlying socket from the given endpoint.
+    pub fn unbind(&self, endpoint: &str) -> io::Result<()> {
+        self.get_mio_ref().unbind(endpoint)?;
+        self.endpoints.lock().unwrap().retain(|e| e != endpoint);
+        Ok(())
+    }
+
+    /// Get the last endpoint the underlying socket was bound or connected to.
+    /// Useful for finding out the port assigned when binding to a wildcard port.
+    pub fn get_last_endpoint(&self) -> io::Result<Result<String, Vec<u8>>> {
+        self.get_mio_ref().get_last_endpoint()
+    }
+
+    /// Bind the underlying socket to a wildcard port on the given `tcp://`
+    /// interface, e.g. `"tcp://127.0.0.1"`, returning the address that was
+    /// actually bound.
+    pub fn bind_ephemeral(&self, interface: &str) -> io::Result<SocketAddr> {
+        self.bind(&format!("{}:0", interface))?;
+        let endpoint = endpoint::last_endpoint_string(self.get_last_endpoint()?)?;
+        endpoint::parse_tcp_endpoint(&endpoint)
+    }
+
+    /// Set the identity of the underlying socket, as seen by `ROUTER` peers.
+    /// Must be set before binding or connecting.
+    pub fn set_identity(&self, identity: &[u8]) -> io::Result<()> {
+        self.get_mio_ref().set_identity(identity)
+    }
+
+    /// Get the identity of the underlying socket.
+    pub fn get_identity(&self) -> io::Result<RoutingId> {
+        self.get_mio_ref().get_identity().map(RoutingId::from)
+    }
+
+    /// Make a `ROUTER` socket report messages sent to unknown peers as errors,
+    /// instead of silently dropping them. Such errors can be recognized with
+    /// `error::is_host_unreachable`, and carry an `error::HostUnreachable`
+    /// with the routing id of the peer. Other socket types fail with
+    /// `InvalidInput`.
+    pub fn set_router_mandatory(&self, value: bool) -> io::Result<()> {
+        self.check_socket_type("router_mandatory", &[ROUTER])?;
+        self.get_mio_ref().set_router_mandatory(value)
+    }
+
+    // Fails with `InvalidInput` for options that the type of the socket
+    // doesn't support.
+    pub(crate) fn check_socket_type(&self, option: &str, types: &[zmq::SocketType]) -> io::Result<()> {
+        let typ = self.get_socket_type()?;
+        if !types.contains(&typ) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} is not supported by {:?} sockets", option, typ),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Enable or disable IPv6 on the underlying socket.
+    pub fn set_ipv6(&self, value: bool) -> io::Result<()> {
+        self.get_mio_ref().set_ipv6(value)
+    }
+
+    /// Return true if IPv6 is enabled on the underlying socket.
+    pub fn is_ipv6(&self) -> io::Result<bool> {
+        self.get_mio_ref().is_ipv6()
+    }
+
+    /// Set the linger period of the underlying socket, in milliseconds. A value
+    /// of `-1` means an infinite period.
+    pub fn set_linger(&self, value: i32) -> io::Result<()> {
+        self.get_mio_ref().set_linger(value)
+    }
+
+    /// Get the linger period of the underlying socket, in milliseconds.
+    pub fn get_linger(&self) -> io::Result<i32> {
+        self.get_mio_ref().get_linger()
+    }
+
+    /// Close the socket, with the given linger period for its pending
+    /// outbound messages. A `linger` of `None` keeps the current setting.
+    /// Returns a `Future` that waits until the socket is writable, then
+    /// deregisters it from the reactor and closes it. The future must run on
+    /// the reactor of the socket.
+    ///
+    /// This doesn't wait for pending messages to leave the process, see
+    /// `Close`, and `Context::terminate` for that.
+    pub fn close(self, linger: Option<Duration>) -> Close {
+        Close::new(self, linger.map(duration_to_millis))
+    }
+
+    /// Set the interval between ZMTP heartbeats sent by the underlying socket, in milliseconds.
+    /// A value of `0` disables heartbeats.
+    pub fn set_heartbeat_ivl(&self, value: i32) -> io::Result<()> {
+        self.get_mio_ref().set_heartbeat_ivl(value)
+    }
+
+    /// Get the interval between ZMTP heartbeats sent by the underlying socket, in milliseconds.
+    pub fn get_heartbeat_ivl(&self) -> io::Result<i32> {
+        self.get_mio_ref().get_heartbeat_ivl()
+    }
+
+    /// Set how long the underlying socket waits for traffic after sending a ZMTP heartbeat,
+    /// before timing out the connection, in milliseconds.
+    pub fn set_heartbeat_timeout(&self, value: i32) -> io::Result<()> {
+        self.get_mio_ref().set_heartbeat_timeout(value)
+    }
+
+    /// Get the ZMTP heartbeat timeout of the underlying socket, in milliseconds.
+    pub fn get_heartbeat_timeout(&self) -> io::Result<i32> {
+        self.get_mio_ref().get_heartbeat_timeout()
+    }
+
+    /// Set the time-to-live advertised to the peers of the underlying socket, in milliseconds.
+    /// Peers time out the connection if they receive no traffic within this period.
+    pub fn set_heartbeat_ttl(&self, value: i32) -> io::Result<()> {
+        self.get_mio_ref().set_heartbeat_ttl(value)
+    }
+
+    /// Get the ZMTP heartbeat time-to-live of the underlying socket, in milliseconds.
+    pub fn get_heartbeat_ttl(&self) -> io::Result<i32> {
+        self.get_mio_ref().get_heartbeat_ttl()
+    }
+
+    /// Set the initial interval the underlying socket waits before reconnecting, in milliseconds.
+    /// A value of `-1` disables reconnection.
+    pub fn set_reconnect_ivl(&self, value: i32) -> io::Result<()> {
+        self.get_mio_ref().set_reconnect_ivl(value)
+    }
+
+    /// Get the initial interval the underlying socket waits before reconnecting, in milliseconds.
+    pub fn get_reconnect_ivl(&self) -> io::Result<i32> {
+        self.get_mio_ref().get_reconnect_ivl()
+    }
+
+    /// Set the maximum interval the underlying socket waits before reconnecting, in milliseconds.
+    /// The interval doubles after each failed attempt, up to this maximum. A value of `0` keeps it fixed.
+    pub fn set_reconnect_ivl_max(&self, value: i32) -> io::Result<()> {
+        self.get_mio_ref().set_reconnect_ivl_max(value)
+    }
+
+    /// Get the maximum interval the underlying socket waits before reconnecting, in milliseconds.
+    pub fn get_reconnect_ivl_max(&self) -> io::Result<i32> {
+        self.get_mio_ref().get_reconnect_ivl_max()
+    }
+
+    /// Apply the intervals of a `ReconnectPolicy` to the native reconnection
+    /// of the underlying socket.
+    pub fn set_reconnect_policy(&self, policy: ReconnectPolicy) -> io::Result<()> {
+        self.set_reconnect_ivl(duration_to_millis(policy.initial()))?;
+        self.set_reconnect_ivl_max(duration_to_millis(policy.max()))
+    }
+
+    /// Apply the keepalive and type-of-service options of a `TcpOptions` to
+    /// the underlying socket, leaving the unset ones unchanged.
+    pub fn set_tcp_options(&self, options: &TcpOptions) -> io::Result<()> {
+        if let Some(value) = options.keepalive_raw() {
+            self.set_tcp_keepalive(value)?;
+        }
+        if let Some(idle) = options.keepalive_idle {
+            self.set_tcp_keepalive_idle(idle.as_secs() as i32)?;
+        }
+        if let Some(cnt) = options.keepalive_cnt {
+            self.set_tcp_keepalive_cnt(cnt as i32)?;
+        }
+        if let Some(intvl) = options.keepalive_intvl {
+            self.set_tcp_keepalive_intvl(intvl.as_secs() as i32)?;
+        }
+        if let Some(tos) = options.tos {
+            self.set_tos(i32::from(tos))?;
+        }
+        Ok(())
+    }
+
+    /// Returns the keepalive and type-of-service options of the underlying
+    /// socket. Options left to the OS defaults are `None`.
+    pub fn tcp_options(&self) -> io::Result<TcpOptions> {
+        Ok(TcpOptions::from_raw(
+            self.get_tcp_keepalive()?,
+            self.get_tcp_keepalive_idle()?,
+            self.get_tcp_keepalive_cnt()?,
+            self.get_tcp_keepalive_intvl()?,
+            self.get_tos()?,
+        ))
+    }
+
+    /// Apply the rate and multicast options of a `MulticastOptions` to the
+    /// underlying socket, leaving the unset ones unchanged.
+    pub fn set_multicast_options(&self, options: &MulticastOptions) -> io::Result<()> {
+        if let Some(rate) = options.rate {
+            self.set_rate(rate as i32)?;
+        }
+        if let Some(ivl) = options.recovery_ivl {
+            self.set_recovery_ivl(duration_to_millis(ivl))?;
+        }
+        if let Some(hops) = options.hops {
+            self.set_multicast_hops(hops as i32)?;
+        }
+        if let Some(maxtpdu) = options.maxtpdu {
+            self.set_multicast_maxtpdu(maxtpdu as i32)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the rate and multicast options of the underlying socket.
+    pub fn multicast_options(&self) -> io::Result<MulticastOptions> {
+        Ok(MulticastOptions {
+            rate: Some(self.get_rate()? as u32),
+            recovery_ivl: Some(Duration::from_millis(self.get_recovery_ivl()? as u64)),
+            hops: Some(self.get_multicast_hops()? as u32),
+            maxtpdu: Some(self.get_multicast_maxtpdu()? as u32),
+        })
+    }
+
+    /// Start monitoring the underlying socket, publishing the selected
+    /// `events` on a `PAIR` socket bound to the given `inproc` endpoint.
+    /// See `monitor::Monitor` for reading the events.
+    pub fn monitor(&self, endpoint: &str, events: i32) -> io::Result<()> {
+        self.get_mio_ref().monitor(endpoint, events)
+    }
+
+    /// Get the `ZMQ_EVENTS` of the underlying socket, telling whether it is
+    /// currently readable or writable.
+    pub fn get_events(&self) -> io::Result<zmq::PollEvents> {
+        self.get_mio_ref().get_events()
+    }
+
+    /// Get the `ZMQ_EVENTS` of the underlying socket, as typed flags.
+    pub fn events(&self) -> io::Result<EventFlags> {
+        self.get_events().map(EventFlags::from)
+    }
+
+    /// Set whether the underlying socket acts as a server for the PLAIN security mechanism.
+    pub fn set_plain_server(&self, value: bool) -> io::Result<()> {
+        self.get_mio_ref().set_plain_server(value)
+    }
+
+    /// Returns true if the underlying socket acts as a server for the PLAIN security mechanism.
+    pub fn is_plain_server(&self) -> io::Result<bool> {
+        self.get_mio_ref().is_plain_server()
+    }
+
+    /// Set the username used by the underlying socket for the PLAIN security mechanism.
+    /// Setting `None` resets the socket to the NULL mechanism.
+    pub fn set_plain_username(&self, value: Option<&str>) -> io::Result<()> {
+        self.get_mio_ref().set_plain_username(value)
+    }
+
+    /// Get the username used by the underlying socket for the PLAIN security mechanism.
+    pub fn get_plain_username(&self) -> io::Result<Result<String, Vec<u8>>> {
+        self.get_mio_ref().get_plain_username()
+    }
+
+    /// Set the password used by the underlying socket for the PLAIN security mechanism.
+    /// Setting `None` resets the socket to the NULL mechanism.
+    pub fn set_plain_password(&self, value: Option<&str>) -> io::Result<()> {
+        self.get_mio_ref().set_plain_password(value)
+    }
+
+    /// Get the password used by the underlying socket for the PLAIN security mechanism.
+    pub fn get_plain_password(&self) -> io::Result<Result<String, Vec<u8>>> {
+        self.get_mio_ref().get_plain_password()
+    }
+
+    /// Set the ZAP domain of the underlying socket, passed to the authenticator in every request.
+    pub fn set_zap_domain(&self, value: &str) -> io::Result<()> {
+        self.get_mio_ref().set_zap_domain(value)
+    }
+
+    /// Get the ZAP domain of the underlying socket.
+    pub fn get_zap_domain(&self) -> io::Result<Result<String, Vec<u8>>> {
+        self.get_mio_ref().get_zap_domain()
+    }
+
+    /// Set whether the underlying socket acts as a server for the GSSAPI security mechanism.
+    /// Fails when libzmq was built without GSSAPI support.
+    pub fn set_gssapi_server(&self, value: bool) -> io::Result<()> {
+        require_capability("gssapi")?;
+        self.get_mio_ref().set_gssapi_server(value)
+    }
+
+    /// Returns true if the underlying socket acts as a server for the GSSAPI security mechanism.
+    pub fn is_gssapi_server(&self) -> io::Result<bool> {
+        self.get_mio_ref().is_gssapi_server()
+    }
+
+    /// Set the name of the principal for which GSSAPI credentials of the underlying socket are retrieved.
+    pub fn set_gssapi_principal(&self, value: &str) -> io::Result<()> {
+        require_capability("gssapi")?;
+        self.get_mio_ref().set_gssapi_principal(value)
+    }
+
+    /// Get the name of the principal for which GSSAPI credentials of the underlying socket are retrieved.
+    pub fn get_gssapi_principal(&self) -> io::Result<Result<String, Vec<u8>>> {
+        self.get_mio_ref().get_gssapi_principal()
+    }
+
+    /// Set the name of the principal of the GSSAPI server the underlying socket connects to.
+    pub fn set_gssapi_service_principal(&self, value: &str) -> io::Result<()> {
+        require_capability("gssapi")?;
+        self.get_mio_ref().set_gssapi_service_principal(value)
+    }
+
+    /// Get the name of the principal of the GSSAPI server the underlying socket connects to.
+    pub fn get_gssapi_service_principal(&self) -> io::Result<Result<String, Vec<u8>>> {
+        self.get_mio_ref().get_gssapi_service_principal()
+    }
+
+    /// Set whether the underlying socket disables the encryption of GSSAPI connections.
+    pub fn set_gssapi_plaintext(&self, value: bool) -> io::Result<()> {
+        require_capability("gssapi")?;
+        self.get_mio_ref().set_gssapi_plaintext(value)
+    }
+
+    /// Returns true if the underlying socket disables the encryption of GSSAPI connections.
+    pub fn is_gssapi_plaintext(&self) -> io::Result<bool> {
+        self.get_mio_ref().is_gssapi_plaintext()
+    }
+
+    /// Set whether the underlying socket acts as a server for the CURVE security mechanism.
+    pub fn set_curve_server(&self, value: bool) -> io::Result<()> {
+        self.get_mio_ref().set_curve_server(value)
+    }
+
+    /// Returns true if the underlying socket acts as a server for the CURVE security mechanism.
+    pub fn is_curve_server(&self) -> io::Result<bool> {
+        self.get_mio_ref().is_curve_server()
+    }
+
+    /// Set the long-term public key of the underlying socket, for the CURVE security mechanism.
+    pub fn set_curve_publickey(&self, value: &[u8]) -> io::Result<()> {
+        self.get_mio_ref().set_curve_publickey(value)
+    }
+
+    /// Get the long-term public key of the underlying socket, for the CURVE security mechanism.
+    pub fn get_curve_publickey(&self) -> io::Result<Vec<u8>> {
+        self.get_mio_ref().get_curve_publickey()
+    }
+
+    /// Set the long-term secret key of the underlying socket, for the CURVE security mechanism.
+    pub fn set_curve_secretkey(&self, value: &[u8]) -> io::Result<()> {
+        self.get_mio_ref().set_curve_secretkey(value)
+    }
+
+    /// Get the long-term secret key of the underlying socket, for the CURVE security mechanism.
+    pub fn get_curve_secretkey(&self) -> io::Result<Vec<u8>> {
+        self.get_mio_ref().get_curve_secretkey()
+    }
+
+    /// Set the long-term public key of the server the underlying socket connects to, for the CURVE security mechanism.
+    pub fn set_curve_serverkey(&self, value: &[u8]) -> io::Result<()> {
+        self.get_mio_ref().set_curve_serverkey(value)
+    }
+
+    /// Get the long-term public key of the server the underlying socket connects to, for the CURVE security mechanism.
+    pub fn get_curve_serverkey(&self) -> io::Result<Vec<u8>> {
+        self.get_mio_ref().get_curve_serverkey()
+    }
+
+    /// Set the high-water mark for outbound messages of the underlying socket.
+    pub fn set_sndhwm(&self, value: i32) -> io::Result<()> {
+        self.get_mio_ref().set_sndhwm(value)
+    }
+
+    /// Get the high-water mark for outbound messages of the underlying socket.
+    pub fn get_sndhwm(&self) -> io::Result<i32> {
+        self.get_mio_ref().get_sndhwm()
+    }
+
+    /// Set the high-water mark for inbound messages of the underlying socket.
+    pub fn set_rcvhwm(&self, value: i32) -> io::Result<()> {
+        self.get_mio_ref().set_rcvhwm(value)
+    }
+
+    /// Get the high-water mark for inbound messages of the underlying socket.
+    pub fn get_rcvhwm(&self) -> io::Result<i32> {
+        self.get_mio_ref().get_rcvhwm()
+    }
+
+    /// Returns true if the underlying socket can't accept another outbound
+    /// message right now, as reported by its `ZMQ_EVENTS`.
+    ///
+    /// This is an approximation of the outbound queue reaching its
+    /// high-water mark, as libzmq doesn't expose queue depths. Note that
+    /// `PUB` sockets never block, they drop messages for peers that reached
+    /// the high-water mark instead, so they are never reported as full.
+    pub fn outbound_queue_full(&self) -> io::Result<bool> {
+        Ok(!self.events()?.contains(events::WRITABLE))
+    }
+
+    /// Get the security mechanism of the underlying socket.
+    pub fn get_mechanism(&self) -> io::Result<zmq::Mechanism> {
+        self.get_mio_ref().get_mechanism()
+    }
+
+    /// Set whether the underlying socket inverts the matching of
+    /// subscriptions. Inverted `SUB` sockets receive the messages matching
+    /// none of their subscriptions, which then act as a block-list, and
+    /// inverted `PUB` and `XPUB` sockets send messages to the subscribers
+    /// with no matching subscription. Every `PUB` and `SUB` socket of a
+    /// topology must agree on this option.
+    pub fn set_invert_matching(&self, value: bool) -> io::Result<()> {
+        self.get_mio_ref().set_invert_matching(value)
+    }
+
+    /// Returns true if the underlying socket inverts the matching of
+    /// subscriptions.
+    pub fn is_invert_matching(&self) -> io::Result<bool> {
+        self.get_mio_ref().is_invert_matching()
+    }
+
+    /// Returns true if a message with the given topic would be received by
+    /// the underlying socket, given its subscriptions and whether it
+    /// inverts matching.
+    pub fn accepts_topic(&self, topic: &[u8]) -> io::Result<bool> {
+        let matched = self.subscriptions
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|prefix| topic.starts_with(prefix));
+        Ok(matched != self.is_invert_matching()?)
+    }
+
+    /// Set whether the underlying socket keeps only the last message in its queues, `ZMQ_CONFLATE`.
+    ///
+    /// Conflation only works on `PUB`, `SUB`, `PUSH`, `PULL`, and `DEALER`
+    /// sockets, with single-part messages, as libzmq may keep a partial
+    /// message otherwise. Other socket types fail with `InvalidInput`, and so
+    /// does sending a multipart-message through a conflating socket.
+    pub fn set_conflate(&self, value: bool) -> io::Result<()> {
+        if value {
+            self.check_socket_type("conflate", &[PUB, SUB, PUSH, PULL, DEALER])?;
+        }
+        self.get_mio_ref().set_conflate(value)?;
+        self.conflate.store(value, Ordering::SeqCst);
+        Ok(())
+    }
+
+    // Fails to send multipart-messages through conflating sockets.
+    fn check_conflate(&self, frames: usize) -> io::Result<()> {
+        if frames > 1 && self.conflate.load(Ordering::SeqCst) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "conflating sockets only support single-part messages",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Returns true if the underlying socket keeps only the last message in its queues.
+    pub fn is_conflate(&self) -> io::Result<bool> {
+        self.get_mio_ref().is_conflate()
+    }
+
+    /// Set the timeout of blocking receives on the underlying socket, in milliseconds, `ZMQ_RCVTIMEO`.
+    pub fn set_rcvtimeo(&self, value: i32) -> io::Result<()> {
+        self.get_mio_ref().set_rcvtimeo(value)
+    }
+
+    /// Get the timeout of blocking receives on the underlying socket, in milliseconds.
+    pub fn get_rcvtimeo(&self) -> io::Result<i32> {
+        self.get_mio_ref().get_rcvtimeo()
+    }
+
+    /// Set the timeout of blocking sends on the underlying socket, in milliseconds, `ZMQ_SNDTIMEO`.
+    pub fn set_sndtimeo(&self, value: i32) -> io::Result<()> {
+        self.get_mio_ref().set_sndtimeo(value)
+    }
+
+    /// Get the timeout of blocking sends on the underlying socket, in milliseconds.
+    pub fn get_sndtimeo(&self) -> io::Result<i32> {
+        self.get_mio_ref().get_sndtimeo()
+    }
+
+    /// Set whether the underlying socket passes every subscription message upstream, instead of only new ones, `ZMQ_XPUB_VERBOSE`.
+    pub fn set_xpub_verbose(&self, value: bool) -> io::Result<()> {
+        self.get_mio_ref().set_xpub_verbose(value)
+    }
+
+    /// Set the largest message the underlying socket accepts, in bytes, `ZMQ_MAXMSGSIZE`. `-1` means no limit.
+    ///
+    /// The limit applies to single frames. Over `tcp` and `ipc`, ØMQ
+    /// enforces it on connections made after it is set, by disconnecting
+    /// peers that send a larger frame: watch for `Disconnected` events of a
+    /// `Monitor` to find out. ØMQ never enforces it over `inproc`, so this
+    /// crate also checks every received message, whatever the transport:
+    /// messages with a larger frame are dropped, and reported as an
+    /// `error::MessageTooLarge` error, after which the socket can still be
+    /// read. This applies to every receive path of the socket, its futures,
+    /// streams, and transports. The limit is cached when set through this
+    /// socket, so receives don't query it.
+    pub fn set_maxmsgsize(&self, value: i64) -> io::Result<()> {
+        self.get_mio_ref().set_maxmsgsize(value)
+    }
+
+    /// Get the largest message the underlying socket accepts, in bytes.
+    pub fn get_maxmsgsize(&self) -> io::Result<i64> {
+        self.get_mio_ref().get_maxmsgsize()
+    }
+
+    /// Set whether the underlying socket enables `SO_KEEPALIVE` on its TCP connections, `ZMQ_TCP_KEEPALIVE`: `1` to enable, `0` to disable, `-1` for the OS default.
+    pub fn set_tcp_keepalive(&self, value: i32) -> io::Result<()> {
+        self.get_mio_ref().set_tcp_keepalive(value)
+    }
+
+    /// Get whether the underlying socket enables `SO_KEEPALIVE` on its TCP connections.
+    pub fn get_tcp_keepalive(&self) -> io::Result<i32> {
+        self.get_mio_ref().get_tcp_keepalive()
+    }
+
+    /// Set the idle time before the underlying socket sends TCP keepalive probes, in seconds, `ZMQ_TCP_KEEPALIVE_IDLE`. `-1` is the OS default.
+    pub fn set_tcp_keepalive_idle(&self, value: i32) -> io::Result<()> {
+        self.get_mio_ref().set_tcp_keepalive_idle(value)
+    }
 
-pub mod future;
-mod poll_evented;
-pub mod sink;
-pub mod stream;
-pub mod transport;
+    /// Get the idle time before the underlying socket sends TCP keepalive probes, in seconds.
+    pub fn get_tcp_keepalive_idle(&self) -> io::Result<i32> {
+        self.get_mio_ref().get_tcp_keepalive_idle()
+    }
 
-use std::io;
-use std::io::{Read, Write};
+    /// Set the number of unanswered TCP keepalive probes after which the underlying socket drops a connection, `ZMQ_TCP_KEEPALIVE_CNT`. `-1` is the OS default.
+    pub fn set_tcp_keepalive_cnt(&self, value: i32) -> io::Result<()> {
+        self.get_mio_ref().set_tcp_keepalive_cnt(value)
+    }
 
-use futures::Poll;
+    /// Get the number of unanswered TCP keepalive probes after which the underlying socket drops a connection.
+    pub fn get_tcp_keepalive_cnt(&self) -> io::Result<i32> {
+        self.get_mio_ref().get_tcp_keepalive_cnt()
+    }
 
-use tokio_core::reactor::{Handle, PollEvented};
-use tokio_io::{AsyncRead, AsyncWrite};
+    /// Set the interval between the TCP keepalive probes of the underlying socket, in seconds, `ZMQ_TCP_KEEPALIVE_INTVL`. `-1` is the OS default.
+    pub fn set_tcp_keepalive_intvl(&self, value: i32) -> io::Result<()> {
+        self.get_mio_ref().set_tcp_keepalive_intvl(value)
+    }
 
-use self::future::{ReceiveMessage, ReceiveMultipartMessage, SendMessage, SendMultipartMessage};
-use self::stream::{MessageStream, MultipartMessageStream};
-use self::sink::{MessageSink, MultipartMessageSink};
+    /// Get the interval between the TCP keepalive probes of the underlying socket, in seconds.
+    pub fn get_tcp_keepalive_intvl(&self) -> io::Result<i32> {
+        self.get_mio_ref().get_tcp_keepalive_intvl()
+    }
 
-pub use io::Error;
-pub use zmq::Message;
-/// Supported socket types are: `DEALER`, `PAIR`, `PUB`, `PULL`, `PUSH`, `REP`, `REQ`, `ROUTER`, `STREAM`, `SUB`, `XPUB`, `XSUB`.
-pub use zmq::SocketType::*;
+    /// Set the type-of-service field of the IP packets sent by the underlying socket, such as a DSCP marking, `ZMQ_TOS`.
+    pub fn set_tos(&self, value: i32) -> io::Result<()> {
+        self.get_mio_ref().set_tos(value)
+    }
 
-// Re-export custom transport to keep backwards-compatibility with examples
-// TODO: move this someplace else once the API is stable
-pub use self::transport::SocketFramed;
+    /// Get the type-of-service field of the IP packets sent by the underlying socket.
+    pub fn get_tos(&self) -> io::Result<i32> {
+        self.get_mio_ref().get_tos()
+    }
 
-/// Wrapper for `zmq::Context`.
-#[derive(Clone, Default)]
-pub struct Context {
-    inner: zmq_mio::Context,
-}
+    /// Set whether the underlying socket only queues messages for completed connections, `ZMQ_IMMEDIATE`, so that messages are not queued for peers that are not connected yet.
+    pub fn set_immediate(&self, value: bool) -> io::Result<()> {
+        self.get_mio_ref().set_immediate(value)
+    }
 
-impl Context {
-    /// Create a new ØMQ context for the `tokio` framework.
-    pub fn new() -> Context {
-        Context {
-            inner: zmq_mio::Context::new(),
-        }
+    /// Return true if the underlying socket only queues messages for completed connections.
+    pub fn is_immediate(&self) -> io::Result<bool> {
+        self.get_mio_ref().is_immediate()
     }
 
-    /// Create a new ØMQ socket for the `tokio` framework.
-    pub fn socket(&self, typ: zmq::SocketType, handle: &Handle) -> io::Result<Socket> {
-        Ok(Socket::new(try!(self.inner.socket(typ)), handle)?)
+    /// Make the underlying socket send an empty message to every `ROUTER` peer it connects to, `ZMQ_PROBE_ROUTER`, so the peer learns its routing id.
+    ///
+    /// Only `ROUTER`, `DEALER`, and `REQ` sockets support probing, others
+    /// fail with `InvalidInput`.
+    pub fn set_probe_router(&self, value: bool) -> io::Result<()> {
+        self.check_socket_type("probe_router", &[ROUTER, DEALER, REQ])?;
+        self.get_mio_ref().set_probe_router(value)
     }
 
-    /// Try to destroy the underlying context. This is different than the destructor;
-    /// the destructor will loop when zmq_ctx_destroy returns EINTR.
-    pub fn destroy(&mut self) -> io::Result<()> {
-        self.inner.destroy()
+    /// Make a `ROUTER` socket hand the routing id of a peer over to a new connection with the same id, instead of refusing it, `ZMQ_ROUTER_HANDOVER`.
+    /// Other socket types fail with `InvalidInput`.
+    pub fn set_router_handover(&self, value: bool) -> io::Result<()> {
+        self.check_socket_type("router_handover", &[ROUTER])?;
+        self.get_mio_ref().set_router_handover(value)
     }
 
-    /// Get a cloned instance of the underlying `zmq_mio::Context`.
-    pub fn get_inner(&self) -> zmq_mio::Context {
-        self.inner.clone()
+    /// Make a `REQ` socket accept a new request before the reply to the previous one arrived, `ZMQ_REQ_RELAXED`.
+    ///
+    /// `Socket::request` then resends requests that time out. Enable
+    /// `ZMQ_REQ_CORRELATE` as well, so that late replies to earlier attempts
+    /// are dropped. Other socket types fail with `InvalidInput`.
+    pub fn set_req_relaxed(&self, value: bool) -> io::Result<()> {
+        self.check_socket_type("req_relaxed", &[REQ])?;
+        self.get_mio_ref().set_req_relaxed(value)?;
+        self.req_relaxed.store(value, Ordering::SeqCst);
+        Ok(())
     }
-}
 
-/// Poll-evented ØMQ socket. Can be used directly on transports implementing
-/// `futures::stream::Stream` and `futures::sink::Sink`.
-pub struct Socket {
-    io: PollEvented<zmq_mio::Socket>,
-}
+    /// Return true if `ZMQ_REQ_RELAXED` was enabled with `set_req_relaxed`.
+    pub fn is_req_relaxed(&self) -> bool {
+        self.req_relaxed.load(Ordering::SeqCst)
+    }
 
-impl Socket {
-    /// Create a new poll-evented ØMQ socket, along with a tokio reactor handle
-    /// to drive its event-loop.
-    fn new(socket: zmq_mio::Socket, handle: &Handle) -> io::Result<Self> {
-        let io = try!(PollEvented::new(socket, handle));
-        let socket = Socket { io };
-        Ok(socket)
+    /// Make a `REQ` socket tag its requests with an id, and drop the replies that do not match the last request, `ZMQ_REQ_CORRELATE`.
+    /// Other socket types fail with `InvalidInput`.
+    pub fn set_req_correlate(&self, value: bool) -> io::Result<()> {
+        self.check_socket_type("req_correlate", &[REQ])?;
+        self.get_mio_ref().set_req_correlate(value)
     }
 
-    /// A reference to the underlying `zmq_mio::Socket`. Useful
-    /// for building futures.
-    pub fn get_ref(&self) -> &PollEvented<zmq_mio::Socket> {
-        &self.io
+    /// Set the maximum send rate of the underlying socket over multicast transports, in kilobits per second, `ZMQ_RATE`.
+    pub fn set_rate(&self, value: i32) -> io::Result<()> {
+        self.get_mio_ref().set_rate(value)
     }
 
-    /// A reference to the underlying `zmq_mio::Socket`. Useful
-    /// for building futures.
-    fn get_mio_ref(&self) -> &zmq_mio::Socket {
-        self.io.get_ref()
+    /// Get the maximum send rate of the underlying socket over multicast transports, in kilobits per second.
+    pub fn get_rate(&self) -> io::Result<i32> {
+        self.get_mio_ref().get_rate()
     }
 
-    /// Bind the underlying socket to the given address.
-    pub fn bind(&self, address: &str) -> io::Result<()> {
-        self.get_mio_ref().bind(address)
+    /// Set how long the underlying socket keeps multicast data for receivers that recover from a loss, in milliseconds, `ZMQ_RECOVERY_IVL`.
+    pub fn set_recovery_ivl(&self, value: i32) -> io::Result<()> {
+        self.get_mio_ref().set_recovery_ivl(value)
     }
 
-    /// Connect the underlying socket to the given address.
-    pub fn connect(&self, address: &str) -> io::Result<()> {
-        self.get_mio_ref().connect(address)
+    /// Get how long the underlying socket keeps multicast data for receivers that recover from a loss, in milliseconds.
+    pub fn get_recovery_ivl(&self) -> io::Result<i32> {
+        self.get_mio_ref().get_recovery_ivl()
     }
 
-    /// Subscribe the underlying socket to the given prefix.
+    /// Set the time-to-live of the multicast packets sent by the underlying socket, in hops, `ZMQ_MULTICAST_HOPS`.
+    pub fn set_multicast_hops(&self, value: i32) -> io::Result<()> {
+        self.get_mio_ref().set_multicast_hops(value)
+    }
+
+    /// Get the time-to-live of the multicast packets sent by the underlying socket, in hops.
+    pub fn get_multicast_hops(&self) -> io::Result<i32> {
+        self.get_mio_ref().get_multicast_hops()
+    }
+
+    /// Set the largest transport data unit the underlying socket sends over multicast transports, in bytes, `ZMQ_MULTICAST_MAXTPDU`.
+    pub fn set_multicast_maxtpdu(&self, value: i32) -> io::Result<()> {
+        self.get_mio_ref().set_multicast_maxtpdu(value)
+    }
+
+    /// Get the largest transport data unit the underlying socket sends over multicast transports, in bytes.
+    pub fn get_multicast_maxtpdu(&self) -> io::Result<i32> {
+        self.get_mio_ref().get_multicast_maxtpdu()
+    }
+
+    /// Subscribe the underlying socket to the given prefix. When matching is
+    /// inverted, messages with this prefix are blocked instead.
     pub fn set_subscribe(&self, prefix: &[u8]) -> io::Result<()> {
-        self.get_mio_ref().set_subscribe(prefix)
+        self.get_mio_ref().set_subscribe(prefix)?;
+        self.subscriptions.lock().unwrap().push(prefix.to_vec());
+        Ok(())
+    }
+
+    /// Unsubscribe the underlying socket from the given prefix. Each
+    /// subscription to a prefix needs its own unsubscription.
+    pub fn set_unsubscribe(&self, prefix: &[u8]) -> io::Result<()> {
+        self.get_mio_ref().set_unsubscribe(prefix)?;
+        let mut subscriptions = self.subscriptions.lock().unwrap();
+        if let Some(idx) = subscriptions.iter().position(|s| &s[..] == prefix) {
+            subscriptions.remove(idx);
+        }
+        Ok(())
+    }
+
+    /// Returns the prefixes the underlying socket is subscribed to, once
+    /// per subscription.
+    pub fn subscriptions(&self) -> Vec<Vec<u8>> {
+        self.subscriptions.lock().unwrap().clone()
+    }
+
+    /// Re-applies the tracked subscriptions of the underlying socket, so
+    /// they are sent to its publishers again. Each prefix is unsubscribed
+    /// and subscribed back, leaving the subscriptions unchanged.
+    pub fn resubscribe(&self) -> io::Result<()> {
+        for prefix in self.subscriptions() {
+            self.get_mio_ref().set_unsubscribe(&prefix)?;
+            self.get_mio_ref().set_subscribe(&prefix)?;
+        }
+        Ok(())
+    }
+
+    /// Returns a snapshot of the options of the underlying socket, for
+    /// logging its configuration.
+    pub fn dump_options(&self) -> io::Result<SocketOptionsSnapshot> {
+        let mechanism = match self.get_mechanism()? {
+            zmq::Mechanism::ZMQ_NULL => "NULL",
+            zmq::Mechanism::ZMQ_PLAIN => "PLAIN",
+            zmq::Mechanism::ZMQ_CURVE => "CURVE",
+            zmq::Mechanism::ZMQ_GSSAPI => "GSSAPI",
+        };
+        Ok(SocketOptionsSnapshot {
+            socket_type: format!("{:?}", self.get_socket_type()?),
+            endpoints: self.endpoints(),
+            sndhwm: self.get_sndhwm()?,
+            rcvhwm: self.get_rcvhwm()?,
+            linger: self.get_linger()?,
+            identity: self.get_identity()?.to_string(),
+            mechanism: mechanism.to_string(),
+            subscriptions: self.subscriptions.lock().unwrap().len(),
+        })
     }
 
     /// Sends a type implementing `Into<zmq::Message>` as a `Future`.
@@ -298,6 +1588,47 @@ impl Socket {
         SendMessage::new(self, message.into())
     }
 
+    /// Consumes a `SUB` socket, returning a `Demux` future that routes its
+    /// messages to per-topic streams, taken from `Demux::handle`. Up to
+    /// `capacity` messages wait in each stream.
+    pub fn demux(self, capacity: usize) -> Demux {
+        Demux::new(self, capacity)
+    }
+
+    /// Consumes a `SUB` socket, returning a `Resubscriber` stream of its
+    /// messages, which re-applies its subscriptions each time it connects.
+    pub fn auto_resubscribe(self, context: &Context, handle: &Handle) -> io::Result<Resubscriber> {
+        Resubscriber::new(context, self, handle)
+    }
+
+    /// Returns a `Future` that resolves once `n` subscribers subscribed to
+    /// this `XPUB` socket, or fails with `TimedOut` after `timeout`. This
+    /// enables `ZMQ_XPUB_VERBOSE`, so every subscription is seen.
+    pub fn wait_for_subscribers(&self, n: usize, timeout: Duration, handle: &Handle) -> io::Result<WaitForSubscribers> {
+        WaitForSubscribers::new(self, n, timeout, handle)
+    }
+
+    /// Sends the message subscribing an `XSUB` socket to a prefix, as a
+    /// `Future`.
+    pub fn send_subscription(&self, prefix: &[u8]) -> SendMessage {
+        self.send(subscription::subscribe_message(prefix))
+    }
+
+    /// Sends the message unsubscribing an `XSUB` socket from a prefix, as a
+    /// `Future`.
+    pub fn send_unsubscription(&self, prefix: &[u8]) -> SendMessage {
+        self.send(subscription::unsubscribe_message(prefix))
+    }
+
+    /// Sends a shared payload, such as `Arc<[u8]>`, as a `Future`, without
+    /// copying its bytes into a fresh `zmq::Message`.
+    pub fn send_zero_copy<T>(&self, data: T) -> SendZeroCopy<T>
+    where
+        T: AsRef<[u8]> + Clone + Send + 'static,
+    {
+        SendZeroCopy::new(self, data)
+    }
+
     /// Sends a type implementing `Into<zmq::Message>` as a `Future`.
     pub fn send_multipart<I, T>(&self, messages: I) -> SendMultipartMessage
     where
@@ -307,16 +1638,128 @@ impl Socket {
         SendMultipartMessage::new(self, messages)
     }
 
+    /// Sends the given buffers as the frames of one multipart-message,
+    /// without copying them into an intermediate buffer. Returns the total
+    /// number of bytes written, or fails with `WouldBlock`.
+    pub fn write_vectored(&self, bufs: &[&[u8]]) -> io::Result<usize> {
+        self.check_conflate(bufs.len())?;
+        SocketSend::send_multipart(self, bufs.iter().cloned(), 0)?;
+        Ok(bufs.iter().map(|buf| buf.len()).sum())
+    }
+
     /// Returns a `Future` that resolves into a `zmq::Message`
     pub fn recv(&self) -> ReceiveMessage {
         ReceiveMessage::new(self)
     }
 
+    /// Returns a `Future` that resolves into a `bytes::BytesMut`.
+    #[cfg(feature = "bytes")]
+    pub fn recv_bytes_mut(&self) -> buf::ReceiveBytesMut {
+        buf::ReceiveBytesMut::new(self)
+    }
+
+    /// Send everything read from `reader`, in chunks of `chunk_size` bytes,
+    /// to a peer receiving with `recv_chunked`, as it grants credit. Returns
+    /// a future that resolves into the number of bytes sent.
+    #[cfg(feature = "bytes")]
+    pub fn send_chunked<R: Read>(&self, reader: R, chunk_size: usize) -> chunked::SendChunked<R> {
+        chunked::SendChunked::new(self, reader, chunk_size)
+    }
+
+    /// Returns a stream of the chunks sent by a peer with `send_chunked`,
+    /// granting credit as chunks are consumed.
+    #[cfg(feature = "bytes")]
+    pub fn recv_chunked(&self) -> chunked::RecvChunked {
+        chunked::RecvChunked::new(self)
+    }
+
+    /// Returns a `Future` that resolves into a frame received into a buffer
+    /// taken from the given pool, which it goes back to when dropped. Frames
+    /// larger than the buffers of the pool fail with `error::MessageTooLarge`.
+    pub fn recv_pooled(&self, pool: &pool::MessagePool) -> pool::ReceivePooled {
+        pool::ReceivePooled::new(self, pool)
+    }
+
     /// Returns a `Future` that resolves into a `Vec<zmq::Message>`
     pub fn recv_multipart(&self) -> ReceiveMultipartMessage {
         ReceiveMultipartMessage::new(self)
     }
 
+    /// Returns a `Future` that resolves into up to `max` multipart-messages,
+    /// all received on a single readiness notification.
+    pub fn recv_batch(&self, max: usize) -> ReceiveBatch {
+        ReceiveBatch::new(self, max)
+    }
+
+    /// Sends every chunk of the given `Stream` as a streaming body, split
+    /// into frames of at most `DEFAULT_BODY_FRAME_SIZE` bytes. Returns a
+    /// `Future` that resolves once the final frame has been sent, or that
+    /// fails with `InvalidInput` on sockets other than `PAIR`.
+    pub fn send_stream_body<S>(&self, body: S) -> SendStreamBody<S>
+    where
+        S: Stream<Item = Vec<u8>, Error = io::Error>,
+    {
+        SendStreamBody::new(self, body, DEFAULT_BODY_FRAME_SIZE)
+    }
+
+    /// Like `send_stream_body`, with a custom maximum frame size.
+    pub fn send_stream_body_with_frame_size<S>(&self, body: S, frame_size: usize) -> SendStreamBody<S>
+    where
+        S: Stream<Item = Vec<u8>, Error = io::Error>,
+    {
+        SendStreamBody::new(self, body, frame_size)
+    }
+
+    /// Returns a `Stream` of the chunks of an incoming streaming body, which
+    /// fails with `InvalidInput` on sockets other than `PAIR`.
+    pub fn recv_stream_body(&self) -> ReceiveStreamBody {
+        ReceiveStreamBody::new(self)
+    }
+
+    /// Sends a multi-part message to the peer with the given identity, on
+    /// a `ROUTER` socket. Returns a `Future`.
+    pub fn send_to<I, T>(&self, routing_id: &RoutingId, messages: I) -> SendTo
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<Vec<u8>>,
+    {
+        SendTo::new(self, routing_id, messages)
+    }
+
+    /// Returns a `Future` that resolves into the identity of the sending
+    /// peer and its multi-part message, on a `ROUTER` socket.
+    pub fn recv_from(&self) -> ReceiveFrom {
+        ReceiveFrom::new(self)
+    }
+
+    /// Sends a multi-part request on a `DEALER` socket, prepending the empty
+    /// delimiter expected by `REP` and `ROUTER` peers. Returns a `Future`.
+    pub fn send_request<I, T>(&self, messages: I) -> SendRequest
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<Vec<u8>>,
+    {
+        SendRequest::new(self, messages)
+    }
+
+    /// Returns a `Future` that resolves into a multi-part reply on a `DEALER`
+    /// socket, with the empty delimiter stripped.
+    pub fn recv_reply(&self) -> ReceiveReply {
+        ReceiveReply::new(self)
+    }
+
+    /// Sends a multi-part request on a `REQ` socket, returning a `Future`
+    /// that resolves into the reply. Once `timeout` elapses without a reply,
+    /// the request is sent again, up to `retries` times, if the socket is
+    /// relaxed, with `set_req_relaxed`, and fails with `TimedOut` otherwise.
+    pub fn request<I, T>(&self, messages: I, timeout: Duration, retries: u32, handle: &Handle) -> io::Result<Request>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<Vec<u8>>,
+    {
+        Request::new(self, messages, timeout, retries, handle)
+    }
+
     /// Get the SocketType
     pub fn get_socket_type(&self) -> io::Result<zmq::SocketType> {
         self.get_mio_ref().get_socket_type()
@@ -326,38 +1769,297 @@ impl Socket {
         SocketFramed::new(self)
     }
 
+    /// Returns a transport implementing `Stream` and `Sink` for the items of
+    /// the given codec.
+    pub fn framed_with<C: MessageCodec>(self, codec: C) -> CodecFramed<C> {
+        CodecFramed::new(self, codec)
+    }
+
+    /// Returns a transport implementing `Stream` and `Sink` for values of type
+    /// `T`, sent as JSON messages.
+    #[cfg(feature = "serde")]
+    pub fn typed<T>(self) -> json::TypedSocket<T>
+    where
+        T: serde::Serialize + serde::de::DeserializeOwned,
+    {
+        CodecFramed::new(self, json::JsonCodec::new())
+    }
+
+    /// Sends a value as a JSON message. Returns a `Future`.
+    #[cfg(feature = "serde")]
+    pub fn send_json<T: serde::Serialize>(&self, item: &T) -> json::SendJson {
+        json::SendJson::new(self, item)
+    }
+
+    /// Returns a `Future` that resolves into a value, received as a JSON message.
+    #[cfg(feature = "serde")]
+    pub fn recv_json<T: serde::de::DeserializeOwned>(&self) -> json::ReceiveJson<T> {
+        json::ReceiveJson::new(self)
+    }
+
+    /// Sends a protobuf message. Returns a `Future`.
+    #[cfg(feature = "prost")]
+    pub fn send_proto<M: prost::Message>(&self, item: &M) -> proto::SendProto {
+        proto::SendProto::new(self, item)
+    }
+
+    /// Returns a `Future` that resolves into a decoded protobuf message.
+    #[cfg(feature = "prost")]
+    pub fn recv_proto<M: prost::Message + Default>(&self) -> proto::ReceiveProto<M> {
+        proto::ReceiveProto::new(self)
+    }
+
+    /// Send a multipart-message if the socket can accept it right away,
+    /// without creating a future. When the socket would block, the message
+    /// is handed back in `TrySendError::Full`, and the current task is
+    /// notified once the socket is writable, so this must be called from a
+    /// task, like the futures of the socket.
+    ///
+    /// # Panics
+    ///
+    /// Panics when called outside a futures task. Poll loops that don't run
+    /// futures can use the `zmq_mio::Socket` of `Socket::get_ref` instead.
+    pub fn try_send(&self, messages: Multipart) -> Result<(), TrySendError> {
+        let last = match messages.len() {
+            0 => return Ok(()),
+            n => n - 1,
+        };
+        let flags = if last > 0 { zmq::SNDMORE } else { 0 };
+        match SocketSend::send(self, &*messages[0], flags) {
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                if let Some(ref metrics) = self.metrics {
+                    metrics.record_send_blocked();
+                }
+                return Err(TrySendError::Full(messages));
+            }
+            r => r?,
+        }
+        // the remaining frames never block once the first one is queued.
+        for (idx, part) in messages.iter().enumerate().skip(1) {
+            let flags = if idx < last { zmq::SNDMORE } else { 0 };
+            SocketSend::send(self, &**part, flags)?;
+        }
+        if let Some(ref metrics) = self.metrics {
+            metrics.record_sent(&messages);
+        }
+        Ok(())
+    }
+
+    /// Receive a multipart-message if one is ready, without creating a
+    /// future. Returns `None` when no message is ready, and the current task
+    /// is notified once one arrives, so this must be called from a task, like
+    /// the futures of the socket.
+    ///
+    /// Messages larger than `ZMQ_MAXMSGSIZE` fail as with `recv_multipart`.
+    ///
+    /// # Panics
+    ///
+    /// Panics when called outside a futures task, like `Socket::try_send`.
+    pub fn try_recv(&self) -> io::Result<Option<Multipart>> {
+        let first = match SocketRecv::recv_msg(self, 0) {
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                if let Some(ref metrics) = self.metrics {
+                    metrics.record_recv_blocked();
+                }
+                return Ok(None);
+            }
+            r => r?,
+        };
+        let mut messages = vec![first];
+        while SocketRecv::get_rcvmore(self)? {
+            messages.push(SocketRecv::recv_msg(self, 0)?);
+        }
+        check_message_size(self, &messages)?;
+        if let Some(ref metrics) = self.metrics {
+            metrics.record_received(&messages);
+        }
+        Ok(Some(messages))
+    }
+
+    /// Sends every multipart-message received on the channel through this
+    /// socket. Returns a `Future` that owns the socket, so it can be spawned,
+    /// and resolves once every sender of the channel is dropped.
+    pub fn bridge_from(self, rx: mpsc::Receiver<Multipart>) -> BridgeFrom {
+        BridgeFrom::new(self, rx)
+    }
+
+    /// Sends every multipart-message received on this socket into the
+    /// channel. Returns a `Future` that owns the socket, so it can be
+    /// spawned, and resolves once the receiver of the channel is dropped.
+    pub fn bridge_to(self, tx: mpsc::Sender<Multipart>) -> BridgeTo {
+        BridgeTo::new(self, tx)
+    }
+
+    /// Returns a `Future` that resolves once the socket has a message to
+    /// receive, without receiving it.
+    pub fn readable(&self) -> Readable {
+        Readable::new(self)
+    }
+
+    /// Returns a `Future` that resolves once the socket can send a message
+    /// without blocking.
+    pub fn writable(&self) -> Writable {
+        Writable::new(self)
+    }
+
+    /// Returns a `Future` that resolves into the events of the socket, once
+    /// they contain every given flag, such as `events::READABLE`.
+    pub fn wait_events(&self, flags: EventFlags) -> WaitEvents {
+        WaitEvents::new(self, flags)
+    }
+
+    /// Sends every multipart-message yielded by the given `Stream`, such as
+    /// the receiving end of a `futures::sync::mpsc` channel. The stream is only
+    /// polled when the socket can accept another message. Returns a `Future`
+    /// that resolves once the stream is exhausted.
+    pub fn send_all<S>(&self, stream: S) -> SendAll<S>
+    where
+        S: Stream<Item = Multipart, Error = io::Error>,
+    {
+        SendAll::new(self, stream)
+    }
+
+    /// Forwards every incoming multipart-message to another socket, with the
+    /// same backpressure as `send_all`. Returns a `Future` that runs for as
+    /// long as the incoming stream lasts.
+    pub fn forward_to<'a>(&'a self, socket: &'a Socket) -> SendAll<'a, &'a Socket> {
+        SendAll::new(socket, self)
+    }
+
     /// Returns a `Stream` of incoming one-part messages.
-    pub fn incoming<'a>(&'a self) -> MessageStream<'a, PollEvented<zmq_mio::Socket>> {
-        MessageStream::new(self.get_ref())
+    pub fn incoming<'a>(&'a self) -> MessageStream<'a, Socket> {
+        MessageStream::new(self)
     }
 
     /// Returns a `Stream` of incoming multipart-messages.
-    pub fn incoming_multipart<'a>(&'a self) -> MultipartMessageStream<'a, PollEvented<zmq_mio::Socket>> {
-        MultipartMessageStream::new(self.get_ref())
+    ///
+    /// A borrowed socket, `&Socket`, is also a `Stream` of every incoming
+    /// `Multipart`, receiving each frame natively.
+    pub fn incoming_multipart<'a>(&'a self) -> MultipartMessageStream<'a, Socket> {
+        MultipartMessageStream::new(self)
+    }
+
+    /// Returns a `Stream` of batches of up to `max` incoming multipart-messages,
+    /// amortizing the cost of polling over every message in a batch.
+    pub fn incoming_batches<'a>(&'a self, max: usize) -> MultipartBatchStream<'a, Socket> {
+        MultipartBatchStream::new(self, max)
+    }
+
+    /// Returns a `Stream` of incoming multipart-messages, as `Result`s, which
+    /// goes on after recoverable errors instead of ending.
+    pub fn incoming_results<'a>(&'a self) -> ResultStream<MultipartMessageStream<'a, Socket>> {
+        ResultStream::new(self.incoming_multipart())
     }
 
     /// Returns a `Sink` for outgoing one-part messages.
-    pub fn outgoing<'a>(&'a self) -> MessageSink<'a, PollEvented<zmq_mio::Socket>> {
-        MessageSink::new(self.get_ref())
+    pub fn outgoing<'a>(&'a self) -> MessageSink<'a, Socket> {
+        MessageSink::new(self)
     }
 
     /// Returns a `Sink` for outgoing multipart-messages.
-    pub fn outgoing_multipart<'a>(&'a self) -> MultipartMessageSink<'a, PollEvented<zmq_mio::Socket>> {
-        MultipartMessageSink::new(self.get_ref())
+    ///
+    /// A borrowed socket, `&Socket`, is also a `Sink` for outgoing `Multipart`
+    /// messages, sending each frame natively.
+    pub fn outgoing_multipart<'a>(&'a self) -> MultipartMessageSink<'a, Socket> {
+        MultipartMessageSink::new(self)
     }
 }
 
 unsafe impl Send for Socket {}
 
+impl fmt::Debug for Socket {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut debug = f.debug_struct("Socket");
+        match self.get_socket_type() {
+            Ok(typ) => debug.field("type", &typ),
+            Err(_) => debug.field("type", &"?"),
+        };
+        debug.field("endpoints", &*self.endpoints.lock().unwrap()).finish()
+    }
+}
+
+/// Sockets dropped while their context is terminating get the linger period
+/// given to `Context::terminate`.
+impl Drop for Socket {
+    fn drop(&mut self) {
+        self.apply_close_linger();
+    }
+}
+
+/// Sends through the reactor, like `Write`, failing with `WouldBlock`
+/// when the socket can't accept a message.
+impl SocketSend for Socket {
+    fn send<T>(&self, msg: T, flags: i32) -> io::Result<()>
+    where
+        T: zmq::Sendable,
+    {
+        SocketSend::send(self.registration()?, msg, flags)
+    }
+
+    fn send_multipart<I, T>(&self, iter: I, flags: i32) -> io::Result<()>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<Message>,
+    {
+        SocketSend::send_multipart(self.registration()?, iter, flags)
+    }
+
+    fn send_zero_copy<T>(&self, data: T, flags: i32) -> io::Result<()>
+    where
+        T: AsRef<[u8]> + Send + 'static,
+    {
+        SocketSend::send_zero_copy(self.registration()?, data, flags)
+    }
+}
+
+/// Receives through the reactor, like `Read`, failing with `WouldBlock`
+/// when no message is ready.
+impl SocketRecv for Socket {
+    fn get_rcvmore(&self) -> io::Result<bool> {
+        SocketRecv::get_rcvmore(self.registration()?)
+    }
+
+    fn recv(&self, buf: &mut Message, flags: i32) -> io::Result<()> {
+        SocketRecv::recv(self.registration()?, buf, flags)
+    }
+
+    fn recv_into(&self, buf: &mut [u8], flags: i32) -> io::Result<usize> {
+        SocketRecv::recv_into(self.registration()?, buf, flags)
+    }
+
+    fn recv_msg(&self, flags: i32) -> io::Result<Message> {
+        SocketRecv::recv_msg(self.registration()?, flags)
+    }
+
+    fn recv_bytes(&self, flags: i32) -> io::Result<Vec<u8>> {
+        SocketRecv::recv_bytes(self.registration()?, flags)
+    }
+
+    fn recv_string(&self, flags: i32) -> io::Result<Result<String, Vec<u8>>> {
+        SocketRecv::recv_string(self.registration()?, flags)
+    }
+
+    fn recv_multipart(&self, flags: i32) -> io::Result<Vec<Vec<u8>>> {
+        SocketRecv::recv_multipart(self.registration()?, flags)
+    }
+
+    fn max_message_size(&self) -> io::Result<i64> {
+        SocketRecv::max_message_size(self.registration()?)
+    }
+}
+
+// Reads and writes go through `SocketRecv` and `SocketSend`, which recheck
+// `ZMQ_EVENTS` after every operation, so transports don't miss wakeups.
 impl Read for Socket {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        self.io.read(buf)
+        SocketRecv::recv_into(&*self, buf, 0)
     }
 }
 
 impl Write for Socket {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        self.io.write(buf)
+        SocketSend::send(&*self, buf, 0)?;
+        Ok(buf.len())
     }
 
     fn flush(&mut self) -> io::Result<()> {
@@ -365,18 +2067,123 @@ impl Write for Socket {
     }
 }
 
+/// Returns the `ZMQ_FD` file descriptor of the socket, which signals that
+/// `ZMQ_EVENTS` may have changed. It is edge-triggered, and must not be read
+/// from or written to.
+impl AsRawFd for Socket {
+    fn as_raw_fd(&self) -> RawFd {
+        // the reactor registered this descriptor, so getting it can't fail.
+        self.get_mio_ref()
+            .as_raw_fd()
+            .expect("registered sockets have a file descriptor")
+    }
+}
+
 impl AsyncRead for Socket {}
 
+/// Writes every chunk of the buffer as a frame of one multipart-message,
+/// such as the header and body of a `Buf` chain, without coalescing them.
 impl AsyncWrite for Socket {
     fn shutdown(&mut self) -> Poll<(), io::Error> {
         Ok(().into())
     }
+
+    #[cfg(feature = "bytes")]
+    fn write_buf<B: Buf>(&mut self, buf: &mut B) -> Poll<usize, io::Error> {
+        let mut written = 0;
+        while buf.has_remaining() {
+            let len = buf.bytes().len();
+            let flags = if len < buf.remaining() { zmq::SNDMORE } else { 0 };
+            // only the first frame can block, the others are queued with it.
+            match SocketSend::send(&*self, buf.bytes(), flags) {
+                Ok(()) => {}
+                Err(ref e) if written == 0 && e.kind() == io::ErrorKind::WouldBlock => {
+                    return Ok(Async::NotReady)
+                }
+                Err(e) => return Err(e),
+            }
+            buf.advance(len);
+            written += len;
+        }
+        Ok(Async::Ready(written))
+    }
+}
+
+/// Returns whether libzmq supports the given capability, such as `"curve"`
+/// or `"ipc"`, or `None` when libzmq is too old to tell.
+pub fn has(capability: &str) -> Option<bool> {
+    zmq::has(capability)
+}
+
+/// Returns the version of libzmq, as `(major, minor, patch)`.
+pub fn version() -> (i32, i32, i32) {
+    zmq::version()
+}
+
+/// The optional features libzmq was built with. Features are reported as
+/// missing when libzmq is too old to tell.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Capabilities {
+    /// The CURVE security mechanism.
+    pub curve: bool,
+    /// The GSSAPI security mechanism.
+    pub gssapi: bool,
+    /// The draft API, with draft socket types and options.
+    pub draft: bool,
+    /// The `ipc://` transport.
+    pub ipc: bool,
+    /// The `pgm://` and `epgm://` transports.
+    pub pgm: bool,
+    /// The `tipc://` transport.
+    pub tipc: bool,
+    /// The `vmci://` transport.
+    pub vmci: bool,
+}
+
+impl Capabilities {
+    /// Detect the capabilities of the linked libzmq.
+    pub fn detect() -> Capabilities {
+        let supported = |capability: &str| has(capability).unwrap_or(false);
+        Capabilities {
+            curve: supported("curve"),
+            gssapi: supported("gssapi"),
+            draft: supported("draft"),
+            ipc: supported("ipc"),
+            pgm: supported("pgm"),
+            tipc: supported("tipc"),
+            vmci: supported("vmci"),
+        }
+    }
+}
+
+// Fails with a descriptive error when libzmq is known to lack the given
+// capability, such as a security mechanism.
+fn require_capability(capability: &str) -> io::Result<()> {
+    match has(capability) {
+        Some(false) => Err(io::Error::new(
+            io::ErrorKind::Other,
+            format!("libzmq was built without {} support", capability),
+        )),
+        _ => Ok(()),
+    }
+}
+
+// Converts a `Duration` into the milliseconds expected by ØMQ options,
+// saturating at `i32::MAX`.
+fn duration_to_millis(d: Duration) -> i32 {
+    let millis = d.as_secs()
+        .saturating_mul(1_000)
+        .saturating_add(u64::from(d.subsec_nanos() / 1_000_000));
+    if millis > i32::max_value() as u64 {
+        i32::max_value()
+    } else {
+        millis as i32
+    }
 }
 
 /// Convert an `zmq::Socket` instance into `zmq_tokio::Socket`.
 pub fn convert_into_tokio_socket(orig: zmq::Socket, handle: &Handle) -> io::Result<Socket> {
-    let mio_socket = zmq_mio::Socket::new(orig);
-    Socket::new(mio_socket, handle)
+    Socket::from_zmq(orig, handle)
 }
 
 /// API methods for sending messages with sockets.
@@ -393,6 +2200,11 @@ pub trait SocketSend {
     where
         I: IntoIterator<Item = T>,
         T: Into<Message>;
+    /// Send a message without copying its payload, which is dropped once
+    /// ØMQ is done with it.
+    fn send_zero_copy<T>(&self, T, i32) -> io::Result<()>
+    where
+        T: AsRef<[u8]> + Send + 'static;
 }
 
 /// API methods for receiving messages with sockets.
@@ -427,4 +2239,12 @@ pub trait SocketRecv {
     /// will be possible to process the different parts sequentially and reuse allocations that
     /// way.
     fn recv_multipart(&self, i32) -> io::Result<Vec<Vec<u8>>>;
+
+    /// The largest message accepted when receiving, in bytes, or `-1` for no
+    /// limit. The futures, streams, and transports of this crate fail with
+    /// an `error::MessageTooLarge` error on received messages with a larger
+    /// frame.
+    fn max_message_size(&self) -> io::Result<i64> {
+        Ok(-1)
+    }
 }
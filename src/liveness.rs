@@ -0,0 +1,144 @@
+//! Application-level heartbeating, after the Paranoid Pirate pattern.
+//!
+//! A `Liveness` wraps a `PAIR` or `DEALER` socket, sending a heartbeat to
+//! its peer at a regular interval, and watching for incoming traffic. Any
+//! incoming message counts as a sign of life; when nothing arrives for a
+//! number of consecutive intervals, the peer is considered dead.
+//!
+//! See http://zguide.zeromq.org/page:all#Robust-Reliable-Queuing-Paranoid-Pirate-Pattern
+use std::collections::VecDeque;
+use std::io;
+use std::time::Duration;
+
+use futures::{Async, Poll, Stream};
+use tokio_core::reactor::{Handle, Interval};
+
+use super::{Multipart, Socket, SocketSend};
+
+/// Frame sent as a heartbeat.
+pub const HEARTBEAT: &[u8] = b"\x02";
+
+/// Configuration for a `Liveness` wrapper.
+#[derive(Clone, Copy, Debug)]
+pub struct LivenessConfig {
+    /// Interval between heartbeats.
+    pub interval: Duration,
+    /// Number of intervals without incoming traffic after which the peer is
+    /// considered dead.
+    pub liveness: usize,
+}
+
+impl Default for LivenessConfig {
+    /// One heartbeat per second, with a liveness of three intervals.
+    fn default() -> LivenessConfig {
+        LivenessConfig {
+            interval: Duration::from_secs(1),
+            liveness: 3,
+        }
+    }
+}
+
+/// Events yielded by `Liveness`.
+#[derive(Debug)]
+pub enum LivenessEvent {
+    /// The peer has shown a sign of life, after being dead or unknown.
+    PeerAlive,
+    /// The peer hasn't sent anything for the configured number of intervals.
+    PeerDead,
+    /// The peer has sent a message, other than a heartbeat.
+    Message(Multipart),
+}
+
+/// A socket wrapper that sends heartbeats and tracks the liveness of its peer.
+/// It is a `Stream` of `LivenessEvent`s, which include the incoming messages.
+pub struct Liveness {
+    socket: Socket,
+    config: LivenessConfig,
+    interval: Interval,
+    remaining: usize,
+    alive: bool,
+    events: VecDeque<LivenessEvent>,
+}
+
+impl Liveness {
+    /// Wrap a connected socket, with its heartbeats driven by the given reactor.
+    pub fn new(socket: Socket, config: LivenessConfig, handle: &Handle) -> io::Result<Liveness> {
+        assert!(config.liveness > 0, "liveness must be non-zero");
+        let interval = Interval::new(config.interval, handle)?;
+        Ok(Liveness {
+            socket,
+            config,
+            interval,
+            remaining: config.liveness,
+            alive: false,
+            events: VecDeque::new(),
+        })
+    }
+
+    /// A reference to the underlying socket, for sending messages.
+    pub fn get_ref(&self) -> &Socket {
+        &self.socket
+    }
+
+    /// Returns true if the peer is currently considered alive.
+    pub fn is_alive(&self) -> bool {
+        self.alive
+    }
+
+    /// Consumes the wrapper, returning the underlying socket.
+    pub fn into_inner(self) -> Socket {
+        self.socket
+    }
+
+    fn tick(&mut self) -> io::Result<()> {
+        // a heartbeat that would block is simply skipped.
+        match SocketSend::send(&self.socket, HEARTBEAT, 0) {
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {}
+            Err(e) => return Err(e),
+            Ok(_) => trace!("heartbeat sent"),
+        }
+        self.remaining = self.remaining.saturating_sub(1);
+        if self.remaining == 0 && self.alive {
+            self.alive = false;
+            self.events.push_back(LivenessEvent::PeerDead);
+        }
+        Ok(())
+    }
+
+    fn received(&mut self, msgs: Multipart) {
+        self.remaining = self.config.liveness;
+        if !self.alive {
+            self.alive = true;
+            self.events.push_back(LivenessEvent::PeerAlive);
+        }
+        let heartbeat = msgs.len() == 1 && &msgs[0][..] == HEARTBEAT;
+        if !heartbeat {
+            self.events.push_back(LivenessEvent::Message(msgs));
+        }
+    }
+}
+
+impl Stream for Liveness {
+    type Item = LivenessEvent;
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        while let Async::Ready(Some(())) = self.interval.poll()? {
+            self.tick()?;
+        }
+        while self.events.is_empty() {
+            let msgs = {
+                let mut stream = &self.socket;
+                match stream.poll()? {
+                    Async::Ready(Some(msgs)) => msgs,
+                    _ => break,
+                }
+            };
+            self.received(msgs);
+        }
+        match self.events.pop_front() {
+            Some(event) => Ok(Async::Ready(Some(event))),
+            None => Ok(Async::NotReady),
+        }
+    }
+}
@@ -0,0 +1,265 @@
+//! Load-balancing broker.
+//!
+//! Following the load-balancing pattern of the ØMQ guide, a `LoadBalancer`
+//! sits between clients, connected to a frontend `ROUTER` socket, and
+//! workers, connected to a backend `ROUTER` socket. Workers are typically
+//! `REQ` sockets, which send `WORKER_READY` when they start, and then send
+//! each reply as their next request. Client requests are queued, and handed
+//! to the worker that has been idle the longest.
+//!
+//! Messages from workers, after their envelope, are either `WORKER_READY`,
+//! or the envelope of the client followed by the reply, which is forwarded
+//! to the client as-is.
+use std::collections::{HashSet, VecDeque};
+use std::io;
+
+use futures::{Async, Future, Poll, Stream};
+use zmq::Message;
+
+use super::{Multipart, Socket};
+use super::shutdown::Drain;
+use super::sink::flush;
+
+/// Message sent by workers when they are ready for their first request.
+pub const WORKER_READY: &[u8] = b"\x01";
+
+/// Default maximum number of queued client requests.
+pub const DEFAULT_MAX_QUEUED: usize = 1000;
+
+/// A Future that runs a load-balancing broker between bound frontend and
+/// backend `ROUTER` sockets, until either socket fails.
+///
+/// Once `max_queued` requests wait for a worker, the broker stops reading
+/// the frontend, so clients are held back by their high-water mark.
+pub struct LoadBalancer {
+    frontend: Socket,
+    backend: Socket,
+    workers: HashSet<Vec<u8>>,
+    idle: VecDeque<Vec<u8>>,
+    requests: VecDeque<Multipart>,
+    max_queued: usize,
+    frontend_out: VecDeque<Multipart>,
+    backend_out: VecDeque<Multipart>,
+    // Whether workers are `REQ` sockets, whose messages start with an
+    // empty delimiter frame.
+    delimited: bool,
+}
+
+impl LoadBalancer {
+    /// Create a new broker for bound frontend and backend `ROUTER` sockets.
+    pub fn new(frontend: Socket, backend: Socket) -> LoadBalancer {
+        LoadBalancer {
+            frontend,
+            backend,
+            workers: HashSet::new(),
+            idle: VecDeque::new(),
+            requests: VecDeque::new(),
+            max_queued: DEFAULT_MAX_QUEUED,
+            frontend_out: VecDeque::new(),
+            backend_out: VecDeque::new(),
+            delimited: true,
+        }
+    }
+
+    // Creates a broker for `DEALER` workers, whose messages have no empty
+    // delimiter frame.
+    pub(crate) fn undelimited(frontend: Socket, backend: Socket) -> LoadBalancer {
+        LoadBalancer {
+            delimited: false,
+            ..LoadBalancer::new(frontend, backend)
+        }
+    }
+
+    /// Set the maximum number of queued client requests. Defaults to
+    /// `DEFAULT_MAX_QUEUED`.
+    pub fn max_queued(mut self, max: usize) -> LoadBalancer {
+        self.max_queued = max;
+        self
+    }
+
+    /// A reference to the frontend socket.
+    pub fn frontend(&self) -> &Socket {
+        &self.frontend
+    }
+
+    /// A reference to the backend socket.
+    pub fn backend(&self) -> &Socket {
+        &self.backend
+    }
+
+    /// Returns the number of known workers.
+    pub fn workers(&self) -> usize {
+        self.workers.len()
+    }
+
+    /// Returns true if the worker is known.
+    pub fn has_worker(&self, worker: &[u8]) -> bool {
+        self.workers.contains(worker)
+    }
+
+    /// Returns the number of idle workers.
+    pub fn idle_workers(&self) -> usize {
+        self.idle.len()
+    }
+
+    /// Returns the number of client requests waiting for a worker.
+    pub fn queued(&self) -> usize {
+        self.requests.len()
+    }
+
+    /// Register a worker as ready for a request, as if it had sent
+    /// `WORKER_READY`. Liveness layers use this to bring workers back.
+    pub fn worker_ready(&mut self, worker: &[u8]) {
+        self.workers.insert(worker.to_vec());
+        if !self.idle.iter().any(|w| &w[..] == worker) {
+            self.idle.push_back(worker.to_vec());
+        }
+        self.dispatch();
+    }
+
+    /// Forget a worker, such as one that stopped responding. Returns true if
+    /// the worker was known. A request the worker was handling is lost.
+    pub fn remove_worker(&mut self, worker: &[u8]) -> bool {
+        self.idle.retain(|w| &w[..] != worker);
+        self.workers.remove(worker)
+    }
+
+    fn handle_request(&mut self, msgs: Multipart) {
+        // the envelope of the client ends with an empty delimiter frame.
+        if !msgs.iter().any(|msg| msg.is_empty()) {
+            warn!("dropping request without an envelope");
+            return;
+        }
+        self.requests.push_back(msgs);
+        self.dispatch();
+    }
+
+    fn handle_worker(&mut self, msgs: Multipart) {
+        let (worker, body) = match self.split_worker_message(msgs) {
+            Some(parts) => parts,
+            None => {
+                warn!("dropping worker message with an invalid envelope");
+                return;
+            }
+        };
+        if !(body.len() == 1 && &body[0][..] == WORKER_READY) {
+            self.forward_reply(body);
+        }
+        self.worker_ready(&worker);
+    }
+
+    // Splits a message from the backend into the id of the worker and the
+    // body of the message.
+    pub(crate) fn split_worker_message(&self, mut msgs: Multipart) -> Option<(Vec<u8>, Multipart)> {
+        let header = if self.delimited { 2 } else { 1 };
+        if msgs.len() <= header || (self.delimited && !msgs[1].is_empty()) {
+            return None;
+        }
+        let body = msgs.split_off(header);
+        Some((msgs[0].to_vec(), body))
+    }
+
+    // Queues a reply from a worker for its client.
+    pub(crate) fn forward_reply(&mut self, reply: Multipart) {
+        self.frontend_out.push_back(reply);
+    }
+
+    // Queues a message for a worker.
+    pub(crate) fn send_to_worker(&mut self, worker: &[u8], body: Multipart) {
+        let mut msgs = vec![Message::from_slice(worker)];
+        if self.delimited {
+            msgs.push(Message::new());
+        }
+        msgs.extend(body);
+        self.backend_out.push_back(msgs);
+    }
+
+    // Returns the ids of the idle workers, the longest idle first.
+    pub(crate) fn idle(&self) -> &VecDeque<Vec<u8>> {
+        &self.idle
+    }
+
+    fn dispatch(&mut self) {
+        while !self.requests.is_empty() {
+            let worker = match self.idle.pop_front() {
+                Some(worker) => worker,
+                None => break,
+            };
+            let request = self.requests.pop_front().unwrap();
+            self.send_to_worker(&worker, request);
+        }
+    }
+
+    fn poll_backend(&mut self) -> Poll<(), io::Error> {
+        loop {
+            let msgs = {
+                let mut stream = &self.backend;
+                match stream.poll()? {
+                    Async::Ready(Some(msgs)) => msgs,
+                    Async::Ready(None) => return Ok(Async::Ready(())),
+                    Async::NotReady => return Ok(Async::NotReady),
+                }
+            };
+            self.handle_worker(msgs);
+        }
+    }
+
+    pub(crate) fn poll_frontend(&mut self) -> Poll<(), io::Error> {
+        while self.requests.len() < self.max_queued {
+            let msgs = {
+                let mut stream = &self.frontend;
+                match stream.poll()? {
+                    Async::Ready(Some(msgs)) => msgs,
+                    Async::Ready(None) => return Ok(Async::Ready(())),
+                    Async::NotReady => return Ok(Async::NotReady),
+                }
+            };
+            self.handle_request(msgs);
+        }
+        Ok(Async::NotReady)
+    }
+
+    pub(crate) fn flush(&mut self) -> io::Result<()> {
+        flush(&self.backend, &mut self.backend_out)?;
+        flush(&self.frontend, &mut self.frontend_out)?;
+        Ok(())
+    }
+}
+
+impl Future for LoadBalancer {
+    type Item = ();
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        if let Async::Ready(()) = self.poll_backend()? {
+            return Ok(Async::Ready(()));
+        }
+        if let Async::Ready(()) = self.poll_frontend()? {
+            return Ok(Async::Ready(()));
+        }
+        self.flush()?;
+        Ok(Async::NotReady)
+    }
+}
+
+/// Once shut down, no request is read, and the queued ones are dropped,
+/// while the replies of the busy workers are forwarded to their clients.
+impl Drain for LoadBalancer {
+    fn poll_drain(&mut self) -> Poll<(), io::Error> {
+        self.requests.clear();
+        if let Async::Ready(()) = self.poll_backend()? {
+            return Ok(Async::Ready(()));
+        }
+        self.flush()?;
+        let busy = self.workers.len() - self.idle.len();
+        if busy == 0 && self.frontend_out.is_empty() && self.backend_out.is_empty() {
+            Ok(Async::Ready(()))
+        } else {
+            Ok(Async::NotReady)
+        }
+    }
+
+    fn sockets(&self) -> Vec<&Socket> {
+        vec![&self.frontend, &self.backend]
+    }
+}
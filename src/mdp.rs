@@ -0,0 +1,609 @@
+//! Majordomo Protocol (MDP/0.1) broker, worker, and client.
+//!
+//! The broker binds a `ROUTER` socket, to which workers connect with
+//! `DEALER` sockets, registering the name of the service they provide.
+//! Clients send requests for a named service through the broker, which
+//! queues them until a worker for that service is available.
+//!
+//! Brokers and workers exchange heartbeats, so dead workers are purged
+//! from the broker, and workers reconnect when the broker goes away.
+//!
+//! See https://rfc.zeromq.org/spec:7/MDP/
+use std::collections::{HashMap, VecDeque};
+use std::io;
+use std::time::{Duration, Instant};
+
+use futures::{Async, AsyncSink, Future, Poll, Sink, Stream};
+use tokio_core::reactor::{Handle, Interval, Timeout};
+use zmq::Message;
+
+use super::{Context, Multipart, Socket, DEALER};
+use super::shutdown::Drain;
+use super::sink::flush;
+
+/// Protocol header of client messages.
+pub const MDPC_CLIENT: &[u8] = b"MDPC01";
+
+/// Protocol header of worker messages.
+pub const MDPW_WORKER: &[u8] = b"MDPW01";
+
+/// Interval between heartbeats, in milliseconds.
+pub const HEARTBEAT_INTERVAL: u64 = 2500;
+
+/// Number of missed heartbeats after which a peer is considered dead.
+pub const HEARTBEAT_LIVENESS: u32 = 3;
+
+const READY: u8 = 0x01;
+const REQUEST: u8 = 0x02;
+const REPLY: u8 = 0x03;
+const HEARTBEAT: u8 = 0x04;
+const DISCONNECT: u8 = 0x05;
+
+// Prefix of the services implemented by the broker itself.
+const MMI_PREFIX: &[u8] = b"mmi.";
+
+/// A Future that runs a Majordomo broker on a bound `ROUTER` socket, until
+/// the socket fails.
+///
+/// The broker implements the `mmi.service` service, which replies with
+/// `"200"` when workers for the service in the request body are registered,
+/// and `"404"` otherwise.
+pub struct Broker {
+    socket: Socket,
+    services: HashMap<Vec<u8>, ServiceQueue>,
+    workers: HashMap<Vec<u8>, WorkerInfo>,
+    heartbeat: Interval,
+    outgoing: VecDeque<Multipart>,
+}
+
+#[derive(Default)]
+struct ServiceQueue {
+    requests: VecDeque<Multipart>,
+    waiting: VecDeque<Vec<u8>>,
+}
+
+struct WorkerInfo {
+    service: Vec<u8>,
+    expiry: Instant,
+}
+
+impl Broker {
+    /// Create a new broker for a bound `ROUTER` socket.
+    pub fn new(socket: Socket, handle: &Handle) -> io::Result<Broker> {
+        let heartbeat = Interval::new(Duration::from_millis(HEARTBEAT_INTERVAL), handle)?;
+        Ok(Broker {
+            socket,
+            services: HashMap::new(),
+            workers: HashMap::new(),
+            heartbeat,
+            outgoing: VecDeque::new(),
+        })
+    }
+
+    /// A reference to the underlying `ROUTER` socket.
+    pub fn get_ref(&self) -> &Socket {
+        &self.socket
+    }
+
+    /// Returns the number of registered workers.
+    pub fn workers(&self) -> usize {
+        self.workers.len()
+    }
+
+    fn handle_message(&mut self, mut msgs: Multipart) {
+        if msgs.len() < 3 || !msgs[1].is_empty() {
+            warn!("dropping message with an invalid envelope");
+            return;
+        }
+        let mut rest = msgs.split_off(2);
+        let sender = msgs[0].to_vec();
+        let body = rest.split_off(1);
+        if &rest[0][..] == MDPC_CLIENT {
+            self.handle_client(sender, body);
+        } else if &rest[0][..] == MDPW_WORKER {
+            self.handle_worker(sender, body);
+        } else {
+            warn!("dropping message with an unknown protocol header");
+        }
+    }
+
+    fn handle_client(&mut self, sender: Vec<u8>, mut msgs: Multipart) {
+        if msgs.is_empty() {
+            warn!("dropping client request without a service name");
+            return;
+        }
+        let body = msgs.split_off(1);
+        let service = msgs[0].to_vec();
+        if service.starts_with(MMI_PREFIX) {
+            self.handle_mmi(sender, service, body);
+            return;
+        }
+        let mut request = vec![Message::from_slice(&sender), Message::new()];
+        request.extend(body);
+        self.services
+            .entry(service.clone())
+            .or_insert_with(ServiceQueue::default)
+            .requests
+            .push_back(request);
+        self.dispatch(&service);
+    }
+
+    fn handle_mmi(&mut self, sender: Vec<u8>, service: Vec<u8>, body: Multipart) {
+        let code: &[u8] = if &service[..] == b"mmi.service" {
+            let registered = match body.first() {
+                Some(name) => self.workers.values().any(|w| &w.service[..] == &name[..]),
+                None => false,
+            };
+            if registered {
+                b"200"
+            } else {
+                b"404"
+            }
+        } else {
+            b"501"
+        };
+        let reply = vec![
+            Message::from_slice(&sender),
+            Message::new(),
+            Message::from_slice(MDPC_CLIENT),
+            Message::from_slice(&service),
+            Message::from_slice(code),
+        ];
+        self.outgoing.push_back(reply);
+    }
+
+    fn handle_worker(&mut self, sender: Vec<u8>, mut msgs: Multipart) {
+        if msgs.is_empty() || msgs[0].len() != 1 {
+            warn!("dropping worker message without a valid command");
+            return;
+        }
+        let body = msgs.split_off(1);
+        let command = msgs[0][0];
+        let known = self.workers.contains_key(&sender);
+        match command {
+            READY => {
+                let service = body.first().map(|s| s.to_vec()).unwrap_or_default();
+                if known || service.is_empty() || service.starts_with(MMI_PREFIX) {
+                    self.delete_worker(&sender, true);
+                } else {
+                    debug!("registering worker for service {:?}", String::from_utf8_lossy(&service));
+                    self.workers.insert(
+                        sender.clone(),
+                        WorkerInfo {
+                            service: service.clone(),
+                            expiry: expiry(),
+                        },
+                    );
+                    self.worker_waiting(sender, &service);
+                }
+            }
+            REPLY if known => {
+                let service = self.workers[&sender].service.clone();
+                let mut body = body;
+                if body.len() < 2 || !body[1].is_empty() {
+                    warn!("dropping worker reply with an invalid envelope");
+                } else {
+                    let payload = body.split_off(2);
+                    let mut reply = vec![
+                        body.swap_remove(0),
+                        Message::new(),
+                        Message::from_slice(MDPC_CLIENT),
+                        Message::from_slice(&service),
+                    ];
+                    reply.extend(payload);
+                    self.outgoing.push_back(reply);
+                }
+                self.worker_waiting(sender, &service);
+            }
+            HEARTBEAT if known => {
+                if let Some(worker) = self.workers.get_mut(&sender) {
+                    worker.expiry = expiry();
+                }
+            }
+            REPLY | HEARTBEAT => self.delete_worker(&sender, true),
+            DISCONNECT => self.delete_worker(&sender, false),
+            _ => warn!("dropping worker message with an unknown command"),
+        }
+    }
+
+    // Puts a worker back in the queue of its service, and hands it a request
+    // if any are pending.
+    fn worker_waiting(&mut self, worker: Vec<u8>, service: &[u8]) {
+        if let Some(info) = self.workers.get_mut(&worker) {
+            info.expiry = expiry();
+        }
+        self.services
+            .entry(service.to_vec())
+            .or_insert_with(ServiceQueue::default)
+            .waiting
+            .push_back(worker);
+        self.dispatch(service);
+    }
+
+    fn dispatch(&mut self, service: &[u8]) {
+        let queue = match self.services.get_mut(service) {
+            Some(queue) => queue,
+            None => return,
+        };
+        while !queue.requests.is_empty() {
+            let worker = match queue.waiting.pop_front() {
+                Some(worker) => worker,
+                None => break,
+            };
+            let request = queue.requests.pop_front().unwrap();
+            self.outgoing.push_back(worker_command(&worker, REQUEST, request));
+        }
+    }
+
+    fn delete_worker(&mut self, worker: &[u8], disconnect: bool) {
+        if disconnect {
+            self.outgoing.push_back(worker_command(worker, DISCONNECT, Vec::new()));
+        }
+        if let Some(info) = self.workers.remove(worker) {
+            if let Some(queue) = self.services.get_mut(&info.service) {
+                queue.waiting.retain(|w| &w[..] != worker);
+            }
+        }
+    }
+
+    // Removes the waiting workers that have expired, and sends heartbeats
+    // to the rest.
+    fn send_heartbeats(&mut self) {
+        let now = Instant::now();
+        let expired: Vec<Vec<u8>> = self.workers
+            .iter()
+            .filter(|&(_, info)| info.expiry < now)
+            .map(|(id, _)| id.clone())
+            .collect();
+        for worker in expired {
+            debug!("deleting expired worker");
+            self.delete_worker(&worker, false);
+        }
+        for queue in self.services.values() {
+            for worker in &queue.waiting {
+                self.outgoing
+                    .push_back(worker_command(worker, HEARTBEAT, Vec::new()));
+            }
+        }
+    }
+}
+
+impl Future for Broker {
+    type Item = ();
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        while let Async::Ready(Some(())) = self.heartbeat.poll()? {
+            self.send_heartbeats();
+        }
+        loop {
+            let msgs = {
+                let mut stream = &self.socket;
+                match stream.poll()? {
+                    Async::Ready(Some(msgs)) => msgs,
+                    Async::Ready(None) => return Ok(Async::Ready(())),
+                    Async::NotReady => break,
+                }
+            };
+            self.handle_message(msgs);
+        }
+        flush(&self.socket, &mut self.outgoing)?;
+        Ok(Async::NotReady)
+    }
+}
+
+/// Once shut down, the broker disconnects its workers, and drops the
+/// requests still waiting for one.
+impl Drain for Broker {
+    fn poll_drain(&mut self) -> Poll<(), io::Error> {
+        let workers: Vec<Vec<u8>> = self.workers.keys().cloned().collect();
+        for worker in workers {
+            self.delete_worker(&worker, true);
+        }
+        self.services.clear();
+        flush(&self.socket, &mut self.outgoing)?;
+        if self.outgoing.is_empty() {
+            Ok(Async::Ready(()))
+        } else {
+            Ok(Async::NotReady)
+        }
+    }
+
+    fn sockets(&self) -> Vec<&Socket> {
+        vec![&self.socket]
+    }
+}
+
+/// A request received by a `MdWorker`.
+#[derive(Debug)]
+pub struct MdRequest {
+    client: Vec<u8>,
+    body: Multipart,
+}
+
+impl MdRequest {
+    /// The address of the client that made the request.
+    pub fn client(&self) -> &[u8] {
+        &self.client
+    }
+
+    /// The request body.
+    pub fn body(&self) -> &Multipart {
+        &self.body
+    }
+
+    /// Consumes the request, returning its body.
+    pub fn into_body(self) -> Multipart {
+        self.body
+    }
+}
+
+/// A Majordomo worker, which registers a service with a broker, and yields
+/// the requests for it as a `Stream`.
+///
+/// Replies are sent with `MdWorker::reply`, and are flushed as the stream is
+/// polled. When the broker stops sending heartbeats, the worker reconnects
+/// on a new socket.
+pub struct MdWorker {
+    context: Context,
+    handle: Handle,
+    broker: String,
+    service: Vec<u8>,
+    socket: Socket,
+    heartbeat: Interval,
+    liveness: u32,
+    outgoing: VecDeque<Multipart>,
+}
+
+impl MdWorker {
+    /// Connect to the broker at the given endpoint, and register the service.
+    pub fn new(context: &Context, broker: &str, service: &[u8], handle: &Handle) -> io::Result<MdWorker> {
+        let socket = context.socket(DEALER, handle)?;
+        let heartbeat = Interval::new(Duration::from_millis(HEARTBEAT_INTERVAL), handle)?;
+        let mut worker = MdWorker {
+            context: context.clone(),
+            handle: handle.clone(),
+            broker: broker.to_string(),
+            service: service.to_vec(),
+            socket,
+            heartbeat,
+            liveness: HEARTBEAT_LIVENESS,
+            outgoing: VecDeque::new(),
+        };
+        worker.register()?;
+        Ok(worker)
+    }
+
+    /// A reference to the underlying `DEALER` socket.
+    pub fn get_ref(&self) -> &Socket {
+        &self.socket
+    }
+
+    /// Queue a reply to the given request. It is sent right away, unless the
+    /// socket would block, in which case it is sent when the worker is next
+    /// polled.
+    pub fn reply(&mut self, request: &MdRequest, body: Multipart) -> io::Result<()> {
+        let mut msgs = vec![Message::from_slice(&request.client), Message::new()];
+        msgs.extend(body);
+        self.outgoing.push_back(worker_frames(REPLY, msgs));
+        flush(&self.socket, &mut self.outgoing)?;
+        Ok(())
+    }
+
+    fn register(&mut self) -> io::Result<()> {
+        self.socket.connect(&self.broker)?;
+        let ready = worker_frames(READY, vec![Message::from_slice(&self.service)]);
+        self.outgoing.push_back(ready);
+        self.liveness = HEARTBEAT_LIVENESS;
+        Ok(())
+    }
+
+    fn reconnect(&mut self) -> io::Result<()> {
+        self.socket.set_linger(0)?;
+        self.socket = self.context.socket(DEALER, &self.handle)?;
+        self.outgoing.clear();
+        self.register()
+    }
+}
+
+impl Stream for MdWorker {
+    type Item = MdRequest;
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        while let Async::Ready(Some(())) = self.heartbeat.poll()? {
+            self.liveness = self.liveness.saturating_sub(1);
+            if self.liveness == 0 {
+                warn!("disconnected from broker, reconnecting");
+                self.reconnect()?;
+            } else {
+                self.outgoing.push_back(worker_frames(HEARTBEAT, Vec::new()));
+            }
+        }
+        flush(&self.socket, &mut self.outgoing)?;
+        loop {
+            let mut msgs = {
+                let mut stream = &self.socket;
+                match stream.poll()? {
+                    Async::Ready(Some(msgs)) => msgs,
+                    Async::Ready(None) => return Ok(Async::Ready(None)),
+                    Async::NotReady => return Ok(Async::NotReady),
+                }
+            };
+            self.liveness = HEARTBEAT_LIVENESS;
+            if msgs.len() < 3 || !msgs[0].is_empty() || &msgs[1][..] != MDPW_WORKER || msgs[2].len() != 1 {
+                warn!("dropping invalid message from broker");
+                continue;
+            }
+            let mut body = msgs.split_off(3);
+            match msgs[2][0] {
+                REQUEST => {
+                    if body.len() < 2 || !body[1].is_empty() {
+                        warn!("dropping request with an invalid envelope");
+                        continue;
+                    }
+                    let payload = body.split_off(2);
+                    return Ok(Async::Ready(Some(MdRequest {
+                        client: body[0].to_vec(),
+                        body: payload,
+                    })));
+                }
+                HEARTBEAT => {}
+                DISCONNECT => {
+                    self.reconnect()?;
+                    flush(&self.socket, &mut self.outgoing)?;
+                }
+                _ => warn!("dropping message with an unknown command"),
+            }
+        }
+    }
+}
+
+/// A Majordomo client, sending requests over a `DEALER` socket connected
+/// to a broker.
+///
+/// Requests are resent when no reply arrives within the timeout, up to the
+/// configured number of retries. Since replies carry no request id, only
+/// one request should be outstanding at a time.
+pub struct MdClient {
+    socket: Socket,
+    handle: Handle,
+    timeout: Duration,
+    retries: usize,
+}
+
+impl MdClient {
+    /// Create a new client for a `DEALER` socket connected to a broker. By
+    /// default, requests time out after 2.5 seconds, and are retried 3 times.
+    pub fn new(socket: Socket, handle: &Handle) -> MdClient {
+        MdClient {
+            socket,
+            handle: handle.clone(),
+            timeout: Duration::from_millis(HEARTBEAT_INTERVAL),
+            retries: 3,
+        }
+    }
+
+    /// A reference to the underlying `DEALER` socket.
+    pub fn get_ref(&self) -> &Socket {
+        &self.socket
+    }
+
+    /// Set the time to wait for a reply, before retrying a request.
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    /// Set the number of times a request is resent, before giving up.
+    pub fn set_retries(&mut self, retries: usize) {
+        self.retries = retries;
+    }
+
+    /// Send a request to the given service, returning a future that resolves
+    /// into the reply body.
+    pub fn send<I, T>(&self, service: &[u8], body: I) -> MdCall
+    where
+        I: IntoIterator<Item = T>,
+        T: AsRef<[u8]>,
+    {
+        let mut frames = vec![Vec::new(), MDPC_CLIENT.to_vec(), service.to_vec()];
+        frames.extend(body.into_iter().map(|f| f.as_ref().to_vec()));
+        MdCall {
+            client: self,
+            frames,
+            retries: self.retries,
+            timer: None,
+        }
+    }
+}
+
+/// A Future that resolves into the reply to a request made with `MdClient`.
+/// This is returned by `MdClient::send`.
+pub struct MdCall<'a> {
+    client: &'a MdClient,
+    frames: Vec<Vec<u8>>,
+    retries: usize,
+    timer: Option<Timeout>,
+}
+
+impl<'a> MdCall<'a> {
+    fn send_request(&mut self) -> Poll<(), io::Error> {
+        let msgs = self.frames.iter().map(|f| Message::from_slice(f)).collect();
+        let mut sink = &self.client.socket;
+        if let AsyncSink::NotReady(_) = sink.start_send(msgs)? {
+            return Ok(Async::NotReady);
+        }
+        self.timer = Some(Timeout::new(self.client.timeout, &self.client.handle)?);
+        Ok(Async::Ready(()))
+    }
+
+    // Returns the body of the reply, if the message is a reply from the
+    // requested service.
+    fn parse_reply(&self, mut msgs: Multipart) -> Option<Multipart> {
+        if msgs.len() < 3 || !msgs[0].is_empty() || &msgs[1][..] != MDPC_CLIENT || &msgs[2][..] != &self.frames[2][..] {
+            warn!("dropping unexpected message from broker");
+            return None;
+        }
+        Some(msgs.split_off(3))
+    }
+}
+
+impl<'a> Future for MdCall<'a> {
+    type Item = Multipart;
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        loop {
+            if self.timer.is_none() {
+                try_ready!(self.send_request());
+            }
+            loop {
+                let msgs = {
+                    let mut stream = &self.client.socket;
+                    match stream.poll()? {
+                        Async::Ready(Some(msgs)) => msgs,
+                        _ => break,
+                    }
+                };
+                if let Some(reply) = self.parse_reply(msgs) {
+                    return Ok(Async::Ready(reply));
+                }
+            }
+            match self.timer.as_mut().unwrap().poll()? {
+                Async::NotReady => return Ok(Async::NotReady),
+                Async::Ready(()) => {}
+            }
+            if self.retries == 0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    "no reply from the broker",
+                ));
+            }
+            warn!("no reply from the broker, retrying");
+            self.retries -= 1;
+            self.timer = None;
+        }
+    }
+}
+
+fn expiry() -> Instant {
+    Instant::now() + Duration::from_millis(HEARTBEAT_INTERVAL * u64::from(HEARTBEAT_LIVENESS))
+}
+
+// Builds a worker command, as sent by a worker.
+fn worker_frames(command: u8, body: Multipart) -> Multipart {
+    let mut msgs = vec![
+        Message::new(),
+        Message::from_slice(MDPW_WORKER),
+        Message::from_slice(&[command]),
+    ];
+    msgs.extend(body);
+    msgs
+}
+
+// Builds a worker command, as sent by the broker to the given worker.
+fn worker_command(worker: &[u8], command: u8, body: Multipart) -> Multipart {
+    let mut msgs = vec![Message::from_slice(worker)];
+    msgs.extend(worker_frames(command, body));
+    msgs
+}
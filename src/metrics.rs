@@ -0,0 +1,95 @@
+//! Per-socket metrics.
+//!
+//! Metrics are opt-in: they are recorded for sockets created by a context
+//! built with `ContextBuilder::metrics`, or after calling
+//! `Socket::enable_metrics`. Messages are counted when they go through the
+//! `Stream` and `Sink` of a borrowed socket, and through `Socket::try_send`
+//! and `Socket::try_recv`.
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use super::Message;
+
+/// Counters shared by a socket and its observers, such as a `Monitor`.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    messages_sent: AtomicUsize,
+    bytes_sent: AtomicUsize,
+    messages_received: AtomicUsize,
+    bytes_received: AtomicUsize,
+    eagain: AtomicUsize,
+    hwm_stalls: AtomicUsize,
+    reconnects: AtomicUsize,
+}
+
+impl Metrics {
+    /// Create a new set of counters, all at zero.
+    pub fn new() -> Metrics {
+        Metrics::default()
+    }
+
+    /// Returns the current value of every counter.
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        let load = |counter: &AtomicUsize| counter.load(Ordering::Relaxed) as u64;
+        MetricsSnapshot {
+            messages_sent: load(&self.messages_sent),
+            bytes_sent: load(&self.bytes_sent),
+            messages_received: load(&self.messages_received),
+            bytes_received: load(&self.bytes_received),
+            eagain: load(&self.eagain),
+            hwm_stalls: load(&self.hwm_stalls),
+            reconnects: load(&self.reconnects),
+        }
+    }
+
+    /// Count a reconnection of the socket. This is recorded by a `Monitor`
+    /// set up with `Monitor::record_reconnects`.
+    pub fn record_reconnect(&self) {
+        self.reconnects.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_sent(&self, msgs: &[Message]) {
+        self.messages_sent.fetch_add(1, Ordering::Relaxed);
+        self.bytes_sent.fetch_add(multipart_len(msgs), Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_received(&self, msgs: &[Message]) {
+        self.messages_received.fetch_add(1, Ordering::Relaxed);
+        self.bytes_received.fetch_add(multipart_len(msgs), Ordering::Relaxed);
+    }
+
+    // A send that would block means that the high-water mark is reached, or
+    // that there is no peer to send to.
+    pub(crate) fn record_send_blocked(&self) {
+        self.eagain.fetch_add(1, Ordering::Relaxed);
+        self.hwm_stalls.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_recv_blocked(&self) {
+        self.eagain.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// The counters of a socket at one point in time. This is returned by
+/// `Socket::metrics`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MetricsSnapshot {
+    /// Multi-part messages sent.
+    pub messages_sent: u64,
+    /// Bytes sent, over every frame.
+    pub bytes_sent: u64,
+    /// Multi-part messages received.
+    pub messages_received: u64,
+    /// Bytes received, over every frame.
+    pub bytes_received: u64,
+    /// Sends and receives that failed with `EAGAIN`.
+    pub eagain: u64,
+    /// Sends that failed with `EAGAIN`, mostly because the high-water mark
+    /// was reached.
+    pub hwm_stalls: u64,
+    /// Reconnections reported by a monitor.
+    pub reconnects: u64,
+}
+
+fn multipart_len(msgs: &[Message]) -> usize {
+    msgs.iter().map(|msg| msg.len()).sum()
+}
@@ -0,0 +1,292 @@
+//! Socket monitoring.
+//!
+//! ØMQ reports the connection events of a monitored socket as two-part
+//! messages over an `inproc` endpoint: the first frame holds the event id
+//! and its value, and the second frame holds the endpoint the event is
+//! about. A `Monitor` reads them as a `Stream` of `MonitorEvent`s.
+//!
+//! A monitor can also keep `EndpointStats`, the state of each endpoint of
+//! the socket, which can be queried at any time through its clones.
+use std::collections::BTreeMap;
+use std::io;
+use std::net::{SocketAddr, TcpStream};
+use std::os::unix::io::{FromRawFd, IntoRawFd};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering, ATOMIC_USIZE_INIT};
+use std::time::Instant;
+
+use futures::{Async, Poll, Stream};
+use tokio_core::reactor::Handle;
+
+use super::{Context, Socket, PAIR};
+use super::metrics::Metrics;
+
+static NEXT_MONITOR: AtomicUsize = ATOMIC_USIZE_INIT;
+
+/// Flags for monitoring every event.
+pub const EVENT_ALL: i32 = 0xFFFF;
+
+/// Events reported by a socket monitor.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Event {
+    Connected,
+    ConnectDelayed,
+    ConnectRetried,
+    Listening,
+    BindFailed,
+    Accepted,
+    AcceptFailed,
+    Closed,
+    CloseFailed,
+    Disconnected,
+    MonitorStopped,
+    HandshakeFailedNoDetail,
+    HandshakeSucceeded,
+    HandshakeFailedProtocol,
+    HandshakeFailedAuth,
+    /// An event unknown to this crate, with its raw id.
+    Unknown(u16),
+}
+
+impl Event {
+    /// Returns the event for a raw event id.
+    pub fn from_raw(raw: u16) -> Event {
+        match raw {
+            0x0001 => Event::Connected,
+            0x0002 => Event::ConnectDelayed,
+            0x0004 => Event::ConnectRetried,
+            0x0008 => Event::Listening,
+            0x0010 => Event::BindFailed,
+            0x0020 => Event::Accepted,
+            0x0040 => Event::AcceptFailed,
+            0x0080 => Event::Closed,
+            0x0100 => Event::CloseFailed,
+            0x0200 => Event::Disconnected,
+            0x0400 => Event::MonitorStopped,
+            0x0800 => Event::HandshakeFailedNoDetail,
+            0x1000 => Event::HandshakeSucceeded,
+            0x2000 => Event::HandshakeFailedProtocol,
+            0x4000 => Event::HandshakeFailedAuth,
+            raw => Event::Unknown(raw),
+        }
+    }
+
+    /// Returns the raw id of the event, which is also its flag for
+    /// selecting the monitored events.
+    pub fn to_raw(&self) -> u16 {
+        match *self {
+            Event::Connected => 0x0001,
+            Event::ConnectDelayed => 0x0002,
+            Event::ConnectRetried => 0x0004,
+            Event::Listening => 0x0008,
+            Event::BindFailed => 0x0010,
+            Event::Accepted => 0x0020,
+            Event::AcceptFailed => 0x0040,
+            Event::Closed => 0x0080,
+            Event::CloseFailed => 0x0100,
+            Event::Disconnected => 0x0200,
+            Event::MonitorStopped => 0x0400,
+            Event::HandshakeFailedNoDetail => 0x0800,
+            Event::HandshakeSucceeded => 0x1000,
+            Event::HandshakeFailedProtocol => 0x2000,
+            Event::HandshakeFailedAuth => 0x4000,
+            Event::Unknown(raw) => raw,
+        }
+    }
+}
+
+/// An event reported by a socket monitor.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MonitorEvent {
+    /// The event.
+    pub event: Event,
+    /// The value of the event, such as a file descriptor, an interval, or
+    /// an error number, depending on the event.
+    pub value: u32,
+    /// The endpoint the event is about.
+    pub endpoint: String,
+}
+
+impl MonitorEvent {
+    /// Returns the address of the peer of a TCP connection, for `Accepted`
+    /// and `Connected` events, whose value is the file descriptor of the
+    /// connection. This is `None` once the connection has been closed.
+    pub fn peer_address(&self) -> Option<SocketAddr> {
+        match self.event {
+            Event::Accepted | Event::Connected => {}
+            _ => return None,
+        }
+        // the descriptor belongs to libzmq, so it is given back unclosed.
+        let stream = unsafe { TcpStream::from_raw_fd(self.value as i32) };
+        let addr = stream.peer_addr().ok();
+        stream.into_raw_fd();
+        addr
+    }
+}
+
+/// The state of an endpoint of a monitored socket.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EndpointState {
+    /// The number of open connections on the endpoint.
+    pub connections: usize,
+    /// When the endpoint got its first open connection, if it has any.
+    pub connected_since: Option<Instant>,
+    /// The number of times the socket retried connecting to the endpoint.
+    pub retries: u64,
+    /// The last failed handshake on the endpoint, whose value is an error
+    /// number, or a protocol or authentication error code.
+    pub last_handshake_error: Option<MonitorEvent>,
+}
+
+/// The state of the endpoints of a monitored socket, by endpoint, kept up
+/// to date by a `Monitor` set up with `Monitor::record_endpoints`. Clones
+/// share the same state.
+#[derive(Clone, Debug, Default)]
+pub struct EndpointStats {
+    endpoints: Arc<Mutex<BTreeMap<String, EndpointState>>>,
+}
+
+impl EndpointStats {
+    /// Create empty endpoint statistics.
+    pub fn new() -> EndpointStats {
+        EndpointStats::default()
+    }
+
+    /// Returns the state of the given endpoint, if it had any event.
+    pub fn get(&self, endpoint: &str) -> Option<EndpointState> {
+        self.endpoints.lock().unwrap().get(endpoint).cloned()
+    }
+
+    /// Returns the state of every endpoint that had an event.
+    pub fn snapshot(&self) -> BTreeMap<String, EndpointState> {
+        self.endpoints.lock().unwrap().clone()
+    }
+
+    /// Update the state of the endpoint an event is about.
+    pub fn record(&self, event: &MonitorEvent) {
+        if event.event == Event::MonitorStopped {
+            return;
+        }
+        let mut endpoints = self.endpoints.lock().unwrap();
+        let state = endpoints
+            .entry(event.endpoint.clone())
+            .or_insert_with(EndpointState::default);
+        match event.event {
+            Event::Connected | Event::Accepted => {
+                if state.connections == 0 {
+                    state.connected_since = Some(Instant::now());
+                }
+                state.connections += 1;
+            }
+            Event::Disconnected => {
+                state.connections = state.connections.saturating_sub(1);
+                if state.connections == 0 {
+                    state.connected_since = None;
+                }
+            }
+            Event::ConnectRetried => state.retries += 1,
+            Event::HandshakeFailedNoDetail | Event::HandshakeFailedProtocol | Event::HandshakeFailedAuth => {
+                state.last_handshake_error = Some(event.clone())
+            }
+            _ => {}
+        }
+    }
+}
+
+/// A `Stream` of the events of a monitored socket. The stream ends after
+/// the `MonitorStopped` event.
+pub struct Monitor {
+    socket: Socket,
+    endpoint: String,
+    stopped: bool,
+    metrics: Option<Arc<Metrics>>,
+    stats: Option<EndpointStats>,
+}
+
+impl Monitor {
+    /// Start monitoring a socket, for the selected `events`, such as
+    /// `EVENT_ALL`. The monitor should be created before the socket binds
+    /// or connects, so no event is missed.
+    pub fn new(context: &Context, socket: &Socket, events: i32, handle: &Handle) -> io::Result<Monitor> {
+        let endpoint = format!(
+            "inproc://zmq-tokio-monitor-{}",
+            NEXT_MONITOR.fetch_add(1, Ordering::Relaxed)
+        );
+        socket.monitor(&endpoint, events)?;
+        let pair = context.socket(PAIR, handle)?;
+        pair.connect(&endpoint)?;
+        Ok(Monitor {
+            socket: pair,
+            endpoint,
+            stopped: false,
+            metrics: None,
+            stats: None,
+        })
+    }
+
+    /// Count every `ConnectRetried` event as a reconnection in the given
+    /// metrics, such as those of the monitored socket, from
+    /// `Socket::metrics_handle`.
+    pub fn record_reconnects(&mut self, metrics: Arc<Metrics>) {
+        self.metrics = Some(metrics);
+    }
+
+    /// Keep the state of every endpoint of the monitored socket in the
+    /// given statistics, as events are read. Monitor every event, with
+    /// `EVENT_ALL`, for complete statistics.
+    pub fn record_endpoints(&mut self, stats: EndpointStats) {
+        self.stats = Some(stats);
+    }
+
+    /// Returns the `inproc` endpoint the events are published on.
+    pub fn endpoint(&self) -> &str {
+        &self.endpoint
+    }
+}
+
+impl Stream for Monitor {
+    type Item = MonitorEvent;
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        if self.stopped {
+            return Ok(Async::Ready(None));
+        }
+        let msgs = {
+            let mut stream = &self.socket;
+            match try_ready!(stream.poll()) {
+                Some(msgs) => msgs,
+                None => return Ok(Async::Ready(None)),
+            }
+        };
+        if msgs.len() != 2 || msgs[0].len() != 6 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "invalid monitor event",
+            ));
+        }
+        let raw = u16::from(msgs[0][0]) | u16::from(msgs[0][1]) << 8;
+        let value = msgs[0][2..6]
+            .iter()
+            .rev()
+            .fold(0, |acc, b| (acc << 8) | u32::from(*b));
+        let event = Event::from_raw(raw);
+        if event == Event::MonitorStopped {
+            self.stopped = true;
+        }
+        if event == Event::ConnectRetried {
+            if let Some(ref metrics) = self.metrics {
+                metrics.record_reconnect();
+            }
+        }
+        let event = MonitorEvent {
+            event,
+            value,
+            endpoint: String::from_utf8_lossy(&msgs[1]).into_owned(),
+        };
+        if let Some(ref stats) = self.stats {
+            stats.record(&event);
+        }
+        Ok(Async::Ready(Some(event)))
+    }
+}
@@ -0,0 +1,48 @@
+//! MessagePack codec for sockets.
+//!
+//! Enabled with the `msgpack` feature.
+use std::io;
+use std::marker::PhantomData;
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use rmp_serde;
+use zmq::Message;
+
+use super::Multipart;
+use super::codec::{single_frame, MessageCodec};
+
+/// Codec for values of type `T`, encoded as one-part MessagePack messages.
+/// Use it with `Socket::framed_with`.
+pub struct MsgPackCodec<T> {
+    _item: PhantomData<T>,
+}
+
+impl<T> MsgPackCodec<T> {
+    pub fn new() -> MsgPackCodec<T> {
+        MsgPackCodec { _item: PhantomData }
+    }
+}
+
+impl<T> Default for MsgPackCodec<T> {
+    fn default() -> MsgPackCodec<T> {
+        MsgPackCodec::new()
+    }
+}
+
+impl<T> MessageCodec for MsgPackCodec<T>
+where
+    T: Serialize + DeserializeOwned,
+{
+    type Item = T;
+
+    fn encode(&mut self, item: T) -> io::Result<Multipart> {
+        let bytes = rmp_serde::to_vec(&item).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        Ok(vec![Message::from(bytes)])
+    }
+
+    fn decode(&mut self, msgs: Multipart) -> io::Result<T> {
+        let msg = single_frame(msgs)?;
+        rmp_serde::from_slice(&msg).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
@@ -0,0 +1,119 @@
+//! Snapshots of socket options, and groups of related options.
+use std::time::Duration;
+
+#[cfg(feature = "serde")]
+use serde::{Serialize, Serializer};
+#[cfg(feature = "serde")]
+use serde::ser::SerializeStruct;
+
+/// The options of a socket at some point, for logging its configuration.
+/// This is returned by `Socket::dump_options`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SocketOptionsSnapshot {
+    /// The type of the socket, such as `"PUB"`.
+    pub socket_type: String,
+    /// The endpoints the socket is bound or connected to.
+    pub endpoints: Vec<String>,
+    /// The high-water mark for outbound messages.
+    pub sndhwm: i32,
+    /// The high-water mark for inbound messages.
+    pub rcvhwm: i32,
+    /// The linger period, in milliseconds, or -1 to linger forever.
+    pub linger: i32,
+    /// The identity of the socket, as seen by `ROUTER` peers.
+    pub identity: String,
+    /// The security mechanism, such as `"NULL"` or `"CURVE"`.
+    pub mechanism: String,
+    /// The number of subscriptions of a `SUB` or `XSUB` socket.
+    pub subscriptions: usize,
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for SocketOptionsSnapshot {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("SocketOptionsSnapshot", 8)?;
+        state.serialize_field("socket_type", &self.socket_type)?;
+        state.serialize_field("endpoints", &self.endpoints)?;
+        state.serialize_field("sndhwm", &self.sndhwm)?;
+        state.serialize_field("rcvhwm", &self.rcvhwm)?;
+        state.serialize_field("linger", &self.linger)?;
+        state.serialize_field("identity", &self.identity)?;
+        state.serialize_field("mechanism", &self.mechanism)?;
+        state.serialize_field("subscriptions", &self.subscriptions)?;
+        state.end()
+    }
+}
+
+/// The TCP options of a socket: keepalives, which keep idle connections
+/// open through stateful firewalls and NATs, and the type-of-service of its
+/// packets, for DSCP-marked networks. Unset options keep their current
+/// value.
+///
+/// These are set with `Socket::set_tcp_options`, or with
+/// `SocketBuilder::tcp_options`, before connecting, as they apply to new
+/// connections.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TcpOptions {
+    /// Whether `SO_KEEPALIVE` is enabled, or `None` for the OS default.
+    pub keepalive: Option<bool>,
+    /// The idle time before keepalive probes are sent, to the second.
+    pub keepalive_idle: Option<Duration>,
+    /// The number of unanswered probes after which a connection is dropped.
+    pub keepalive_cnt: Option<u32>,
+    /// The interval between keepalive probes, to the second.
+    pub keepalive_intvl: Option<Duration>,
+    /// The type-of-service field of outgoing IP packets.
+    pub tos: Option<u8>,
+}
+
+impl TcpOptions {
+    /// Options with keepalives enabled, probing idle connections every
+    /// `interval`.
+    pub fn keepalive(interval: Duration) -> TcpOptions {
+        TcpOptions {
+            keepalive: Some(true),
+            keepalive_idle: Some(interval),
+            keepalive_intvl: Some(interval),
+            ..TcpOptions::default()
+        }
+    }
+
+    // The raw values of the options, with `-1` for the OS defaults.
+    pub(crate) fn keepalive_raw(&self) -> Option<i32> {
+        self.keepalive.map(|enabled| if enabled { 1 } else { 0 })
+    }
+
+    pub(crate) fn from_raw(keepalive: i32, idle: i32, cnt: i32, intvl: i32, tos: i32) -> TcpOptions {
+        let secs = |value: i32| if value < 0 { None } else { Some(Duration::from_secs(value as u64)) };
+        TcpOptions {
+            keepalive: match keepalive {
+                0 => Some(false),
+                1 => Some(true),
+                _ => None,
+            },
+            keepalive_idle: secs(idle),
+            keepalive_cnt: if cnt < 0 { None } else { Some(cnt as u32) },
+            keepalive_intvl: secs(intvl),
+            tos: Some(tos as u8),
+        }
+    }
+}
+
+/// The options of a socket over the `pgm://` and `epgm://` multicast
+/// transports, such as for market data distribution. Unset options keep
+/// their current value.
+///
+/// These are set with `Socket::set_multicast_options`, or with
+/// `SocketBuilder::multicast_options`, before binding or connecting.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MulticastOptions {
+    /// The maximum send rate, in kilobits per second.
+    pub rate: Option<u32>,
+    /// How long data is kept for receivers that recover from a loss, to the
+    /// millisecond.
+    pub recovery_ivl: Option<Duration>,
+    /// The time-to-live of multicast packets, in hops.
+    pub hops: Option<u32>,
+    /// The largest transport data unit, in bytes.
+    pub maxtpdu: Option<u32>,
+}
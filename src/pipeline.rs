@@ -0,0 +1,309 @@
+//! Divide-and-conquer pipelines over `PUSH` and `PULL` sockets.
+//!
+//! A `Ventilator` pushes jobs to workers, every `Worker` pulls jobs and
+//! pushes their results, and a `SinkCollector` pulls the results. The
+//! ventilator tells the collector when a batch starts, and how many jobs it
+//! had once it ends, so the collector knows when every result arrived. The
+//! collector may then tell the workers to stop, over `PUB` and `SUB`
+//! sockets.
+//!
+//! Control messages start with the `CONTROL` frame, followed by the kind
+//! of message, `START` or `STOP`. The `STOP` message of the ventilator
+//! carries the number of jobs, as an 8-byte big-endian number.
+use std::collections::VecDeque;
+use std::io;
+
+use futures::{Async, AsyncSink, Future, Poll, Sink, Stream};
+use zmq::Message;
+
+use super::{Multipart, Socket};
+use super::gap::sequence_frame;
+use super::sink::flush;
+
+/// First frame of control messages.
+pub const CONTROL: &[u8] = b"\x00PIPELINE";
+
+/// Kind of control message starting a batch.
+pub const START: &[u8] = b"START";
+
+/// Kind of control message ending a batch, or stopping workers.
+pub const STOP: &[u8] = b"STOP";
+
+fn control(kind: &[u8]) -> Multipart {
+    vec![Message::from_slice(CONTROL), Message::from_slice(kind)]
+}
+
+fn is_control(msgs: &[Message]) -> bool {
+    msgs.len() >= 2 && &msgs[0][..] == CONTROL
+}
+
+/// A Future that pushes every job of a stream to the workers, only taking
+/// a job from the stream once the socket can send it. It resolves into the
+/// number of jobs, once the stream ends.
+pub struct Ventilator<S> {
+    socket: Socket,
+    jobs: S,
+    collector: Option<Socket>,
+    outgoing: VecDeque<Multipart>,
+    buffered: Option<Multipart>,
+    count: u64,
+    done: bool,
+}
+
+impl<S> Ventilator<S>
+where
+    S: Stream<Item = Multipart, Error = io::Error>,
+{
+    /// Create a new ventilator, pushing jobs through a bound `PUSH` socket.
+    pub fn new(socket: Socket, jobs: S) -> Ventilator<S> {
+        Ventilator {
+            socket,
+            jobs,
+            collector: None,
+            outgoing: VecDeque::new(),
+            buffered: None,
+            count: 0,
+            done: false,
+        }
+    }
+
+    /// Signal the start and the end of the batch to a `SinkCollector`,
+    /// through a `PUSH` socket connected to it.
+    pub fn signal(mut self, collector: Socket) -> Ventilator<S> {
+        self.outgoing.push_back(control(START));
+        self.collector = Some(collector);
+        self
+    }
+
+    /// Returns the number of jobs pushed so far.
+    pub fn pushed(&self) -> u64 {
+        self.count
+    }
+
+    fn flush_control(&mut self) -> Poll<(), io::Error> {
+        match self.collector {
+            Some(ref collector) => flush(collector, &mut self.outgoing),
+            None => Ok(Async::Ready(())),
+        }
+    }
+}
+
+impl<S> Future for Ventilator<S>
+where
+    S: Stream<Item = Multipart, Error = io::Error>,
+{
+    type Item = u64;
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        // the start signal goes out before any job.
+        try_ready!(self.flush_control());
+        while !self.done {
+            let job = match self.buffered.take() {
+                Some(job) => job,
+                None => match try_ready!(self.jobs.poll()) {
+                    Some(job) => job,
+                    None => {
+                        self.done = true;
+                        let mut stop = control(STOP);
+                        stop.push(sequence_frame(self.count));
+                        self.outgoing.push_back(stop);
+                        break;
+                    }
+                },
+            };
+            let mut sink = &self.socket;
+            if let AsyncSink::NotReady(job) = sink.start_send(job)? {
+                self.buffered = Some(job);
+                return Ok(Async::NotReady);
+            }
+            self.count += 1;
+        }
+        try_ready!(self.flush_control());
+        Ok(Async::Ready(self.count))
+    }
+}
+
+/// A Future that pulls jobs, and pushes the result of the handler for each
+/// of them. It runs until the jobs socket fails, or it receives `STOP` on
+/// its control socket.
+pub struct Worker<F> {
+    jobs: Socket,
+    results: Socket,
+    handler: F,
+    control: Option<Socket>,
+    outgoing: VecDeque<Multipart>,
+    handled: u64,
+}
+
+impl<F> Worker<F>
+where
+    F: FnMut(Multipart) -> io::Result<Multipart>,
+{
+    /// Create a new worker, pulling jobs from a `PULL` socket, and pushing
+    /// results to a `PUSH` socket. An error of the handler fails the worker.
+    pub fn new(jobs: Socket, results: Socket, handler: F) -> Worker<F> {
+        Worker {
+            jobs,
+            results,
+            handler,
+            control: None,
+            outgoing: VecDeque::new(),
+            handled: 0,
+        }
+    }
+
+    /// Stop once a `STOP` control message arrives on the given `SUB`
+    /// socket, which is subscribed to control messages.
+    pub fn control(mut self, socket: Socket) -> io::Result<Worker<F>> {
+        socket.set_subscribe(CONTROL)?;
+        self.control = Some(socket);
+        Ok(self)
+    }
+
+    /// Returns the number of jobs handled so far.
+    pub fn handled(&self) -> u64 {
+        self.handled
+    }
+
+    fn poll_control(&mut self) -> Poll<(), io::Error> {
+        let mut stream = match self.control {
+            Some(ref socket) => socket,
+            None => return Ok(Async::NotReady),
+        };
+        loop {
+            match try_ready!(stream.poll()) {
+                Some(ref msgs) if is_control(msgs) && &msgs[1][..] == STOP => {
+                    return Ok(Async::Ready(()));
+                }
+                Some(_) => {}
+                None => return Ok(Async::NotReady),
+            }
+        }
+    }
+}
+
+impl<F> Future for Worker<F>
+where
+    F: FnMut(Multipart) -> io::Result<Multipart>,
+{
+    type Item = ();
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        if let Async::Ready(()) = self.poll_control()? {
+            return Ok(Async::Ready(()));
+        }
+        loop {
+            // a job is only pulled once the previous result went out.
+            try_ready!(flush(&self.results, &mut self.outgoing));
+            let job = {
+                let mut stream = &self.jobs;
+                match try_ready!(stream.poll()) {
+                    Some(job) => job,
+                    None => return Ok(Async::Ready(())),
+                }
+            };
+            let result = (self.handler)(job)?;
+            self.handled += 1;
+            self.outgoing.push_back(result);
+        }
+    }
+}
+
+/// A `Stream` of the results pulled from the workers. Once the ventilator
+/// signalled the end of the batch, the stream ends after every result of
+/// the batch.
+pub struct SinkCollector {
+    socket: Socket,
+    control: Option<Socket>,
+    outgoing: VecDeque<Multipart>,
+    started: bool,
+    expected: Option<u64>,
+    received: u64,
+    finished: bool,
+}
+
+impl SinkCollector {
+    /// Create a new collector, pulling results from a bound `PULL` socket,
+    /// to which the ventilator also connects.
+    pub fn new(socket: Socket) -> SinkCollector {
+        SinkCollector {
+            socket,
+            control: None,
+            outgoing: VecDeque::new(),
+            started: false,
+            expected: None,
+            received: 0,
+            finished: false,
+        }
+    }
+
+    /// Publish `STOP` to the workers through a bound `PUB` socket, once
+    /// every result of the batch arrived.
+    pub fn stop_workers(mut self, socket: Socket) -> SinkCollector {
+        self.control = Some(socket);
+        self
+    }
+
+    /// Returns true once the ventilator signalled the start of the batch.
+    pub fn started(&self) -> bool {
+        self.started
+    }
+
+    /// Returns the number of results received so far.
+    pub fn received(&self) -> u64 {
+        self.received
+    }
+
+    fn handle_control(&mut self, msgs: &[Message]) {
+        // results may overtake the start signal, so it resets nothing.
+        if &msgs[1][..] == START {
+            self.started = true;
+        } else if &msgs[1][..] == STOP && msgs.len() == 3 && msgs[2].len() == 8 {
+            let count = msgs[2].iter().fold(0, |acc, b| (acc << 8) | u64::from(*b));
+            self.expected = Some(count);
+        } else {
+            warn!("dropping unknown pipeline control message");
+        }
+    }
+
+    fn is_complete(&self) -> bool {
+        self.expected.map_or(false, |expected| self.received >= expected)
+    }
+}
+
+impl Stream for SinkCollector {
+    type Item = Multipart;
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        loop {
+            if self.is_complete() && !self.finished {
+                self.finished = true;
+                if self.control.is_some() {
+                    self.outgoing.push_back(control(STOP));
+                }
+            }
+            if self.finished {
+                if let Some(ref socket) = self.control {
+                    try_ready!(flush(socket, &mut self.outgoing));
+                }
+                return Ok(Async::Ready(None));
+            }
+            let msgs = {
+                let mut stream = &self.socket;
+                match try_ready!(stream.poll()) {
+                    Some(msgs) => msgs,
+                    None => return Ok(Async::Ready(None)),
+                }
+            };
+            if is_control(&msgs) {
+                self.handle_control(&msgs);
+                continue;
+            }
+            self.received += 1;
+            return Ok(Async::Ready(Some(msgs)));
+        }
+    }
+}
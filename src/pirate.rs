@@ -0,0 +1,290 @@
+//! Paranoid Pirate queue, workers, and clients.
+//!
+//! Following the Paranoid Pirate pattern of the ØMQ guide, a
+//! `PirateQueue` is a load-balancing broker whose workers, connected with
+//! `DEALER` sockets, exchange heartbeats with it. Workers that miss too
+//! many heartbeats are expired, and the queue reports workers coming up and
+//! going down as a `Stream` of `WorkerEvent`s. A `PirateClient` retries
+//! requests that get no reply in time, since a request handed to a dead
+//! worker is lost.
+//!
+//! Workers send `WORKER_READY` when they start, `PIRATE_HEARTBEAT` while
+//! idle, and reply to each request with the client envelope the request
+//! came with, followed by the reply.
+use std::collections::{HashMap, VecDeque};
+use std::io;
+use std::time::{Duration, Instant};
+
+use futures::{Async, AsyncSink, Future, Poll, Sink, Stream};
+use tokio_core::reactor::{Handle, Interval, Timeout};
+use zmq::Message;
+
+use super::{Multipart, Socket};
+use super::load_balancer::{LoadBalancer, WORKER_READY};
+
+/// Heartbeat exchanged between the queue and its workers.
+pub const PIRATE_HEARTBEAT: &[u8] = b"\x02";
+
+/// Heartbeat settings of a `PirateQueue`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PirateConfig {
+    /// Interval between heartbeats.
+    pub interval: Duration,
+    /// Number of missed heartbeats after which a worker is expired.
+    pub liveness: u32,
+}
+
+impl Default for PirateConfig {
+    fn default() -> PirateConfig {
+        PirateConfig {
+            interval: Duration::from_secs(1),
+            liveness: 3,
+        }
+    }
+}
+
+/// A change in the workers of a `PirateQueue`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum WorkerEvent {
+    /// A worker registered, with its id.
+    Up(Vec<u8>),
+    /// A worker was expired, with its id.
+    Down(Vec<u8>),
+}
+
+/// A Paranoid Pirate queue between bound frontend and backend `ROUTER`
+/// sockets. It runs while polled as a `Stream` of `WorkerEvent`s, and ends
+/// when either socket fails.
+pub struct PirateQueue {
+    broker: LoadBalancer,
+    config: PirateConfig,
+    heartbeat: Interval,
+    expiry: HashMap<Vec<u8>, Instant>,
+    events: VecDeque<WorkerEvent>,
+}
+
+impl PirateQueue {
+    /// Create a new queue for bound frontend and backend `ROUTER` sockets.
+    pub fn new(frontend: Socket, backend: Socket, config: PirateConfig, handle: &Handle) -> io::Result<PirateQueue> {
+        Ok(PirateQueue {
+            broker: LoadBalancer::undelimited(frontend, backend),
+            config,
+            heartbeat: Interval::new(config.interval, handle)?,
+            expiry: HashMap::new(),
+            events: VecDeque::new(),
+        })
+    }
+
+    /// A reference to the underlying broker.
+    pub fn broker(&self) -> &LoadBalancer {
+        &self.broker
+    }
+
+    fn expiry(&self) -> Instant {
+        Instant::now() + self.config.interval * self.config.liveness
+    }
+
+    fn handle_worker(&mut self, msgs: Multipart) {
+        let (worker, body) = match self.broker.split_worker_message(msgs) {
+            Some(parts) => parts,
+            None => {
+                warn!("dropping worker message with an invalid envelope");
+                return;
+            }
+        };
+        let known = self.broker.has_worker(&worker);
+        let expiry = self.expiry();
+        if body.len() == 1 && &body[0][..] == PIRATE_HEARTBEAT {
+            // heartbeats only keep known workers alive.
+            if known {
+                self.expiry.insert(worker, expiry);
+            }
+            return;
+        }
+        let ready = body.len() == 1 && &body[0][..] == WORKER_READY;
+        if !ready {
+            self.broker.forward_reply(body);
+        }
+        if !known {
+            debug!("worker is up");
+            self.events.push_back(WorkerEvent::Up(worker.clone()));
+        }
+        self.expiry.insert(worker.clone(), expiry);
+        self.broker.worker_ready(&worker);
+    }
+
+    // Expires silent workers, and sends heartbeats to the idle ones.
+    fn send_heartbeats(&mut self) {
+        let now = Instant::now();
+        let expired: Vec<Vec<u8>> = self.expiry
+            .iter()
+            .filter(|&(_, expiry)| *expiry < now)
+            .map(|(worker, _)| worker.clone())
+            .collect();
+        for worker in expired {
+            debug!("worker is down");
+            self.expiry.remove(&worker);
+            self.broker.remove_worker(&worker);
+            self.events.push_back(WorkerEvent::Down(worker));
+        }
+        let idle: Vec<Vec<u8>> = self.broker.idle().iter().cloned().collect();
+        for worker in idle {
+            self.broker
+                .send_to_worker(&worker, vec![Message::from_slice(PIRATE_HEARTBEAT)]);
+        }
+    }
+
+    fn poll_backend(&mut self) -> Poll<(), io::Error> {
+        loop {
+            let msgs = {
+                let mut stream = self.broker.backend();
+                match stream.poll()? {
+                    Async::Ready(Some(msgs)) => msgs,
+                    Async::Ready(None) => return Ok(Async::Ready(())),
+                    Async::NotReady => return Ok(Async::NotReady),
+                }
+            };
+            self.handle_worker(msgs);
+        }
+    }
+}
+
+impl Stream for PirateQueue {
+    type Item = WorkerEvent;
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        while let Async::Ready(Some(())) = self.heartbeat.poll()? {
+            self.send_heartbeats();
+        }
+        if let Async::Ready(()) = self.poll_backend()? {
+            return Ok(Async::Ready(None));
+        }
+        if let Async::Ready(()) = self.broker.poll_frontend()? {
+            return Ok(Async::Ready(None));
+        }
+        self.broker.flush()?;
+        match self.events.pop_front() {
+            Some(event) => Ok(Async::Ready(Some(event))),
+            None => Ok(Async::NotReady),
+        }
+    }
+}
+
+/// A client of a `PirateQueue`, or of any `REP`-style server, sending
+/// requests over a `DEALER` socket.
+///
+/// Requests are resent when no reply arrives within the timeout, up to the
+/// configured number of retries. Since replies carry no request id, only
+/// one request should be outstanding at a time.
+pub struct PirateClient {
+    socket: Socket,
+    handle: Handle,
+    timeout: Duration,
+    retries: usize,
+}
+
+impl PirateClient {
+    /// Create a new client for a connected `DEALER` socket. By default,
+    /// requests time out after 2.5 seconds, and are retried 3 times.
+    pub fn new(socket: Socket, handle: &Handle) -> PirateClient {
+        PirateClient {
+            socket,
+            handle: handle.clone(),
+            timeout: Duration::from_millis(2500),
+            retries: 3,
+        }
+    }
+
+    /// A reference to the underlying `DEALER` socket.
+    pub fn get_ref(&self) -> &Socket {
+        &self.socket
+    }
+
+    /// Set the time to wait for a reply, before retrying a request.
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    /// Set the number of times a request is resent, before giving up.
+    pub fn set_retries(&mut self, retries: usize) {
+        self.retries = retries;
+    }
+
+    /// Send a request, returning a future that resolves into the reply.
+    pub fn send<I, T>(&self, body: I) -> PirateCall
+    where
+        I: IntoIterator<Item = T>,
+        T: AsRef<[u8]>,
+    {
+        let mut frames = vec![Vec::new()];
+        frames.extend(body.into_iter().map(|f| f.as_ref().to_vec()));
+        PirateCall {
+            client: self,
+            frames,
+            retries: self.retries,
+            timer: None,
+        }
+    }
+}
+
+/// A Future that resolves into the reply to a request made with
+/// `PirateClient`. This is returned by `PirateClient::send`.
+pub struct PirateCall<'a> {
+    client: &'a PirateClient,
+    frames: Vec<Vec<u8>>,
+    retries: usize,
+    timer: Option<Timeout>,
+}
+
+impl<'a> PirateCall<'a> {
+    fn send_request(&mut self) -> Poll<(), io::Error> {
+        let msgs = self.frames.iter().map(|f| Message::from_slice(f)).collect();
+        let mut sink = &self.client.socket;
+        if let AsyncSink::NotReady(_) = sink.start_send(msgs)? {
+            return Ok(Async::NotReady);
+        }
+        self.timer = Some(Timeout::new(self.client.timeout, &self.client.handle)?);
+        Ok(Async::Ready(()))
+    }
+}
+
+impl<'a> Future for PirateCall<'a> {
+    type Item = Multipart;
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        loop {
+            if self.timer.is_none() {
+                try_ready!(self.send_request());
+            }
+            loop {
+                let mut msgs = {
+                    let mut stream = &self.client.socket;
+                    match stream.poll()? {
+                        Async::Ready(Some(msgs)) => msgs,
+                        _ => break,
+                    }
+                };
+                if msgs.is_empty() || !msgs[0].is_empty() {
+                    warn!("dropping reply with an invalid envelope");
+                    continue;
+                }
+                return Ok(Async::Ready(msgs.split_off(1)));
+            }
+            match self.timer.as_mut().unwrap().poll()? {
+                Async::NotReady => return Ok(Async::NotReady),
+                Async::Ready(()) => {}
+            }
+            if self.retries == 0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    "no reply from the server",
+                ));
+            }
+            warn!("no reply from the server, retrying");
+            self.retries -= 1;
+            self.timer = None;
+        }
+    }
+}
@@ -1,16 +1,16 @@
-//! Trait implementations for `tokio_core::reactor::PollEvented`.
+//! Trait implementations for reactor registrations, such as
+//! `tokio_core::reactor::PollEvented`.
 use std::io;
 
-use futures::Async;
-use tokio_core::reactor::PollEvented;
-use zmq::{Message, Sendable};
-use zmq_mio;
+use futures::{task, Async};
+use zmq::{Message, Sendable, POLLIN, POLLOUT};
 
 use super::{SocketRecv, SocketSend};
+use super::reactor::Registration;
 
-/// This implementation uses `PollEvented<_>` polling mechanism to properly send messages with
-/// tokio.
-impl SocketSend for PollEvented<zmq_mio::Socket> {
+/// This implementation uses the polling mechanism of the reactor to properly send messages
+/// with tokio.
+impl<R: Registration> SocketSend for R {
     fn send<T>(&self, msg: T, flags: i32) -> io::Result<()>
     where
         T: Sendable,
@@ -19,9 +19,7 @@ impl SocketSend for PollEvented<zmq_mio::Socket> {
             return Err(io::ErrorKind::WouldBlock.into());
         }
         let r = self.get_ref().send(msg, flags);
-        if is_wouldblock(&r) {
-            self.need_write();
-        }
+        rearm_write(self, &r);
         return r;
     }
 
@@ -34,16 +32,26 @@ impl SocketSend for PollEvented<zmq_mio::Socket> {
             return Err(io::ErrorKind::WouldBlock.into());
         }
         let r = self.get_ref().send_multipart(iter, flags);
-        if is_wouldblock(&r) {
-            self.need_write();
+        rearm_write(self, &r);
+        return r;
+    }
+
+    fn send_zero_copy<T>(&self, data: T, flags: i32) -> io::Result<()>
+    where
+        T: AsRef<[u8]> + Send + 'static,
+    {
+        if let Async::NotReady = self.poll_write() {
+            return Err(io::ErrorKind::WouldBlock.into());
         }
+        let r = self.get_ref().send_zero_copy(data, flags);
+        rearm_write(self, &r);
         return r;
     }
 }
 
-/// This implementation uses `PollEvented<_>` polling mechanism to properly receive messages with
-/// tokio.
-impl SocketRecv for PollEvented<zmq_mio::Socket> {
+/// This implementation uses the polling mechanism of the reactor to properly receive messages
+/// with tokio.
+impl<R: Registration> SocketRecv for R {
     /// Return true if there are more frames of a multipart message to receive.
     fn get_rcvmore(&self) -> io::Result<bool> {
         let r = self.get_ref().get_rcvmore();
@@ -57,9 +65,7 @@ impl SocketRecv for PollEvented<zmq_mio::Socket> {
             return Err(io::ErrorKind::WouldBlock.into());
         }
         let r = self.get_ref().recv(buf, flags);
-        if is_wouldblock(&r) {
-            self.need_read();
-        }
+        rearm_read(self, &r);
         return r;
     }
 
@@ -71,9 +77,7 @@ impl SocketRecv for PollEvented<zmq_mio::Socket> {
             return Err(io::ErrorKind::WouldBlock.into());
         }
         let r = self.get_ref().recv_into(buf, flags);
-        if is_wouldblock(&r) {
-            self.need_read();
-        }
+        rearm_read(self, &r);
         return r;
     }
 
@@ -83,9 +87,7 @@ impl SocketRecv for PollEvented<zmq_mio::Socket> {
             return Err(io::ErrorKind::WouldBlock.into());
         }
         let r = self.get_ref().recv_msg(flags);
-        if is_wouldblock(&r) {
-            self.need_read();
-        }
+        rearm_read(self, &r);
         return r;
     }
 
@@ -95,9 +97,7 @@ impl SocketRecv for PollEvented<zmq_mio::Socket> {
             return Err(io::ErrorKind::WouldBlock.into());
         }
         let r = self.get_ref().recv_bytes(flags);
-        if is_wouldblock(&r) {
-            self.need_read();
-        }
+        rearm_read(self, &r);
         return r;
     }
 
@@ -110,9 +110,7 @@ impl SocketRecv for PollEvented<zmq_mio::Socket> {
             return Err(io::ErrorKind::WouldBlock.into());
         }
         let r = self.get_ref().recv_string(flags);
-        if is_wouldblock(&r) {
-            self.need_read();
-        }
+        rearm_read(self, &r);
         return r;
     }
 
@@ -126,11 +124,55 @@ impl SocketRecv for PollEvented<zmq_mio::Socket> {
             return Err(io::ErrorKind::WouldBlock.into());
         }
         let r = self.get_ref().recv_multipart(flags);
-        if is_wouldblock(&r) {
-            self.need_read();
-        }
+        rearm_read(self, &r);
         return r;
     }
+
+    /// Returns the `ZMQ_MAXMSGSIZE` of the socket, as cached by
+    /// `zmq_mio::Socket::set_maxmsgsize`.
+    fn max_message_size(&self) -> io::Result<i64> {
+        Ok(self.get_ref().cached_maxmsgsize())
+    }
+}
+
+// The ØMQ file descriptor is edge-triggered, and only signals that
+// `ZMQ_EVENTS` may have changed. Every operation on the socket may consume
+// an edge, so the events are rechecked after each one: readiness that is
+// gone is re-armed with `need_read`, and readiness that showed up without
+// an edge wakes the current task up.
+//
+// Since receiving processes the pending commands of the socket, it may also
+// consume the edge for outgoing messages, such as the one of a peer
+// reading below the high-water mark, and sending may consume the edge for
+// incoming messages. So the task is also woken up when the socket turned
+// ready in the other direction.
+fn rearm_read<R: Registration, T>(io: &R, r: &io::Result<T>) {
+    let events = match io.get_ref().get_events() {
+        Ok(events) => events,
+        Err(_) => return,
+    };
+    let readable = events.contains(POLLIN);
+    if !readable || is_wouldblock(r) {
+        io.need_read();
+    }
+    if readable && is_wouldblock(r) || r.is_ok() && events.contains(POLLOUT) {
+        task::current().notify();
+    }
+}
+
+// Same as `rearm_read`, for sending.
+fn rearm_write<R: Registration, T>(io: &R, r: &io::Result<T>) {
+    let events = match io.get_ref().get_events() {
+        Ok(events) => events,
+        Err(_) => return,
+    };
+    let writable = events.contains(POLLOUT);
+    if !writable || is_wouldblock(r) {
+        io.need_write();
+    }
+    if writable && is_wouldblock(r) || r.is_ok() && events.contains(POLLIN) {
+        task::current().notify();
+    }
 }
 
 // Convenience function to check if messaging will block or not.
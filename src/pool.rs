@@ -0,0 +1,203 @@
+//! Buffer pools, for receiving without allocating.
+//!
+//! Receiving a frame usually allocates a buffer for it, such as the
+//! `Vec<u8>` of `recv_bytes`. High-rate consumers can receive into buffers
+//! taken from a `MessagePool` instead, with `Socket::recv_pooled`, or
+//! `SocketFramed::pooled`. A `PooledBuffer` goes back to its pool when it is
+//! dropped, so once the pool is warm, receiving allocates nothing.
+//!
+//! Every buffer of a pool has the same capacity, which bounds the frames it
+//! can receive: larger frames are dropped, and reported as an
+//! `error::MessageTooLarge` error.
+use std::fmt;
+use std::io;
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use futures::{Async, Future, Poll};
+
+use super::{Socket, SocketRecv};
+use super::error::MessageTooLarge;
+use super::future::check_received_size;
+
+/// A pool of reusable byte buffers. Cloning a pool yields a handle to the
+/// same set of buffers.
+#[derive(Clone)]
+pub struct MessagePool {
+    inner: Arc<PoolInner>,
+}
+
+struct PoolInner {
+    free: Mutex<Vec<Vec<u8>>>,
+    max_size: usize,
+    buffer_size: usize,
+    created: AtomicUsize,
+    reused: AtomicUsize,
+}
+
+impl MessagePool {
+    /// Create a pool that keeps at most `max_size` idle buffers, each able
+    /// to receive frames of up to `buffer_size` bytes.
+    pub fn new(max_size: usize, buffer_size: usize) -> MessagePool {
+        MessagePool {
+            inner: Arc::new(PoolInner {
+                free: Mutex::new(Vec::with_capacity(max_size)),
+                max_size,
+                buffer_size,
+                created: AtomicUsize::new(0),
+                reused: AtomicUsize::new(0),
+            }),
+        }
+    }
+
+    /// Take an empty buffer from the pool, allocating a new one if the pool
+    /// is empty.
+    pub fn take(&self) -> PooledBuffer {
+        let buf = match self.inner.free.lock().unwrap().pop() {
+            Some(buf) => {
+                self.inner.reused.fetch_add(1, Ordering::Relaxed);
+                buf
+            }
+            None => {
+                self.inner.created.fetch_add(1, Ordering::Relaxed);
+                Vec::with_capacity(self.inner.buffer_size)
+            }
+        };
+        PooledBuffer {
+            buf,
+            pool: self.clone(),
+        }
+    }
+
+    // Returns a buffer to the pool, or drops it if the pool is full.
+    fn give(&self, mut buf: Vec<u8>) {
+        let mut free = self.inner.free.lock().unwrap();
+        if free.len() < self.inner.max_size {
+            buf.clear();
+            free.push(buf);
+        }
+    }
+
+    /// The largest frame that the buffers of the pool can receive, in bytes.
+    pub fn buffer_size(&self) -> usize {
+        self.inner.buffer_size
+    }
+
+    /// Number of idle buffers in the pool.
+    pub fn idle(&self) -> usize {
+        self.inner.free.lock().unwrap().len()
+    }
+
+    /// Number of buffers that had to be allocated because the pool was empty.
+    pub fn created(&self) -> usize {
+        self.inner.created.load(Ordering::Relaxed)
+    }
+
+    /// Number of buffers that were taken from the pool instead of allocated.
+    pub fn reused(&self) -> usize {
+        self.inner.reused.load(Ordering::Relaxed)
+    }
+
+    // Receives a frame into a buffer of the pool.
+    pub(crate) fn recv<S: SocketRecv>(&self, socket: &S) -> io::Result<PooledBuffer> {
+        let mut buf = self.take();
+        let limit = self.inner.buffer_size;
+        // the capacity is already there, so this doesn't allocate.
+        buf.buf.resize(limit, 0);
+        let size = SocketRecv::recv_into(socket, &mut buf.buf, 0)?;
+        if size > limit {
+            return Err(MessageTooLarge {
+                limit: limit as i64,
+                size,
+            }.into());
+        }
+        check_received_size(socket, size)?;
+        buf.buf.truncate(size);
+        Ok(buf)
+    }
+}
+
+impl fmt::Debug for MessagePool {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("MessagePool")
+            .field("buffer_size", &self.inner.buffer_size)
+            .field("idle", &self.idle())
+            .finish()
+    }
+}
+
+/// A byte buffer taken from a `MessagePool`, which it goes back to when
+/// dropped.
+pub struct PooledBuffer {
+    buf: Vec<u8>,
+    pool: MessagePool,
+}
+
+impl PooledBuffer {
+    /// Take the bytes out of the buffer, which then doesn't go back to the
+    /// pool.
+    pub fn into_vec(mut self) -> Vec<u8> {
+        ::std::mem::replace(&mut self.buf, Vec::new())
+    }
+}
+
+impl Deref for PooledBuffer {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.buf
+    }
+}
+
+impl DerefMut for PooledBuffer {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        &mut self.buf
+    }
+}
+
+impl fmt::Debug for PooledBuffer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("PooledBuffer").field(&self.buf).finish()
+    }
+}
+
+impl Drop for PooledBuffer {
+    fn drop(&mut self) {
+        // buffers taken out with `into_vec` have no capacity left.
+        if self.buf.capacity() > 0 {
+            let buf = ::std::mem::replace(&mut self.buf, Vec::new());
+            self.pool.give(buf);
+        }
+    }
+}
+
+/// A Future that receives a frame into a buffer taken from a pool.
+/// This is returned by `Socket::recv_pooled`
+pub struct ReceivePooled<'a> {
+    socket: &'a Socket,
+    pool: MessagePool,
+}
+
+impl<'a> ReceivePooled<'a> {
+    pub fn new(socket: &'a Socket, pool: &MessagePool) -> ReceivePooled<'a> {
+        ReceivePooled {
+            socket,
+            pool: pool.clone(),
+        }
+    }
+}
+
+impl<'a> Future for ReceivePooled<'a> {
+    type Item = PooledBuffer;
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        // a buffer that would block goes straight back to the pool.
+        match self.pool.recv(self.socket) {
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => Ok(Async::NotReady),
+            Err(e) => Err(e),
+            Ok(buf) => Ok(Async::Ready(buf)),
+        }
+    }
+}
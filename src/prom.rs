@@ -0,0 +1,160 @@
+//! Prometheus export of socket metrics.
+//!
+//! A `SocketCollector` is registered once with a `prometheus::Registry`, and
+//! reads the counters of every socket added to it on each scrape. Sockets
+//! must have metrics enabled, see the `metrics` module.
+use std::io;
+use std::sync::{Arc, Mutex};
+
+use prometheus::{CounterVec, Opts};
+use prometheus::core::{Collector, Desc};
+use prometheus::proto::MetricFamily;
+
+use super::Socket;
+use super::metrics::{Metrics, MetricsSnapshot};
+
+const LABELS: &[&str] = &["endpoint", "socket_type"];
+
+struct Source {
+    endpoint: String,
+    socket_type: String,
+    metrics: Arc<Metrics>,
+}
+
+// One counter per field of `MetricsSnapshot`.
+struct Counters {
+    messages_sent: CounterVec,
+    bytes_sent: CounterVec,
+    messages_received: CounterVec,
+    bytes_received: CounterVec,
+    eagain: CounterVec,
+    hwm_stalls: CounterVec,
+    reconnects: CounterVec,
+}
+
+impl Counters {
+    fn new(namespace: &str) -> io::Result<Counters> {
+        let counter = |name: &str, help: &str| {
+            CounterVec::new(Opts::new(name, help).namespace(namespace.to_string()), LABELS)
+                .map_err(to_io_error)
+        };
+        Ok(Counters {
+            messages_sent: counter("messages_sent_total", "Multi-part messages sent.")?,
+            bytes_sent: counter("bytes_sent_total", "Bytes sent, over every frame.")?,
+            messages_received: counter("messages_received_total", "Multi-part messages received.")?,
+            bytes_received: counter("bytes_received_total", "Bytes received, over every frame.")?,
+            eagain: counter("eagain_total", "Sends and receives that failed with EAGAIN.")?,
+            hwm_stalls: counter("hwm_stalls_total", "Sends that failed with EAGAIN.")?,
+            reconnects: counter("reconnects_total", "Reconnections reported by a monitor.")?,
+        })
+    }
+
+    fn all(&self) -> [&CounterVec; 7] {
+        [
+            &self.messages_sent,
+            &self.bytes_sent,
+            &self.messages_received,
+            &self.bytes_received,
+            &self.eagain,
+            &self.hwm_stalls,
+            &self.reconnects,
+        ]
+    }
+
+    fn set(&self, labels: &[&str], snapshot: &MetricsSnapshot) {
+        let values = [
+            snapshot.messages_sent,
+            snapshot.bytes_sent,
+            snapshot.messages_received,
+            snapshot.bytes_received,
+            snapshot.eagain,
+            snapshot.hwm_stalls,
+            snapshot.reconnects,
+        ];
+        for (counter, value) in self.all().iter().zip(values.iter()) {
+            counter.with_label_values(labels).inc_by(*value as f64);
+        }
+    }
+}
+
+/// A `prometheus::core::Collector` for the metrics of sockets, labelled by
+/// endpoint and socket type. Clones share the same sockets.
+#[derive(Clone)]
+pub struct SocketCollector {
+    counters: Arc<Counters>,
+    sources: Arc<Mutex<Vec<Source>>>,
+}
+
+impl SocketCollector {
+    /// Create a collector, whose metrics are prefixed with the given
+    /// namespace, such as `"zmq"`.
+    pub fn new(namespace: &str) -> io::Result<SocketCollector> {
+        Ok(SocketCollector {
+            counters: Arc::new(Counters::new(namespace)?),
+            sources: Arc::new(Mutex::new(Vec::new())),
+        })
+    }
+
+    /// Export the metrics of a socket, labelled with the given endpoint.
+    /// Fails when the socket doesn't record metrics. The metrics keep being
+    /// exported after the socket is dropped, until `remove` is called.
+    pub fn add(&self, socket: &Socket, endpoint: &str) -> io::Result<()> {
+        let metrics = socket.metrics_handle().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "socket metrics are not enabled")
+        })?;
+        let socket_type = format!("{:?}", socket.get_socket_type()?);
+        self.sources.lock().unwrap().push(Source {
+            endpoint: endpoint.to_string(),
+            socket_type,
+            metrics,
+        });
+        Ok(())
+    }
+
+    /// Stop exporting the metrics of the sockets labelled with the given
+    /// endpoint.
+    pub fn remove(&self, endpoint: &str) {
+        self.sources
+            .lock()
+            .unwrap()
+            .retain(|source| source.endpoint != endpoint);
+    }
+
+    /// Register this collector with a registry.
+    pub fn register(&self, registry: &::prometheus::Registry) -> io::Result<()> {
+        registry
+            .register(Box::new(self.clone()))
+            .map_err(to_io_error)
+    }
+}
+
+impl Collector for SocketCollector {
+    fn desc(&self) -> Vec<&Desc> {
+        let mut descs = Vec::new();
+        for counter in self.counters.all().iter() {
+            let counter: &CounterVec = *counter;
+            descs.extend(counter.desc());
+        }
+        descs
+    }
+
+    fn collect(&self) -> Vec<MetricFamily> {
+        let sources = self.sources.lock().unwrap();
+        for counter in self.counters.all().iter() {
+            counter.reset();
+        }
+        for source in sources.iter() {
+            let labels = [&source.endpoint[..], &source.socket_type[..]];
+            self.counters.set(&labels, &source.metrics.snapshot());
+        }
+        self.counters
+            .all()
+            .iter()
+            .flat_map(|counter| counter.collect())
+            .collect()
+    }
+}
+
+fn to_io_error(e: ::prometheus::Error) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, e.to_string())
+}
@@ -0,0 +1,117 @@
+//! Protocol Buffers codec for sockets, using `prost`.
+//!
+//! Enabled with the `prost` feature.
+use std::io;
+use std::marker::PhantomData;
+
+use futures::{Async, Future, Poll};
+use prost;
+use zmq::Message;
+
+use super::{Multipart, Socket};
+use super::codec::{single_frame, MessageCodec};
+use super::future::{ReceiveMessage, SendMessage};
+
+/// Codec for protobuf messages of type `M`, encoded as one-part messages.
+/// Use it with `Socket::framed_with`.
+pub struct ProstCodec<M> {
+    _item: PhantomData<M>,
+}
+
+impl<M> ProstCodec<M> {
+    pub fn new() -> ProstCodec<M> {
+        ProstCodec { _item: PhantomData }
+    }
+}
+
+impl<M> Default for ProstCodec<M> {
+    fn default() -> ProstCodec<M> {
+        ProstCodec::new()
+    }
+}
+
+impl<M> MessageCodec for ProstCodec<M>
+where
+    M: prost::Message + Default,
+{
+    type Item = M;
+
+    fn encode(&mut self, item: M) -> io::Result<Multipart> {
+        Ok(vec![Message::from(encode(&item)?)])
+    }
+
+    fn decode(&mut self, msgs: Multipart) -> io::Result<M> {
+        decode(&single_frame(msgs)?)
+    }
+}
+
+/// A Future that sends a protobuf message.
+/// This is returned by `Socket::send_proto`
+pub struct SendProto<'a> {
+    inner: Result<SendMessage<'a>, Option<io::Error>>,
+}
+
+impl<'a> SendProto<'a> {
+    pub fn new<M: prost::Message>(socket: &'a Socket, item: &M) -> SendProto<'a> {
+        let inner = match encode(item) {
+            Ok(bytes) => Ok(SendMessage::new(socket, Message::from(bytes))),
+            Err(e) => Err(Some(e)),
+        };
+        SendProto { inner }
+    }
+}
+
+impl<'a> Future for SendProto<'a> {
+    type Item = ();
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        match self.inner {
+            Ok(ref mut send) => send.poll(),
+            Err(ref mut e) => Err(e.take().expect("polled SendProto after completion")),
+        }
+    }
+}
+
+/// A Future that receives and decodes a protobuf message.
+/// This is returned by `Socket::recv_proto`
+pub struct ReceiveProto<'a, M> {
+    inner: ReceiveMessage<'a>,
+    _item: PhantomData<M>,
+}
+
+impl<'a, M> ReceiveProto<'a, M>
+where
+    M: prost::Message + Default,
+{
+    pub fn new(socket: &'a Socket) -> ReceiveProto<'a, M> {
+        ReceiveProto {
+            inner: ReceiveMessage::new(socket),
+            _item: PhantomData,
+        }
+    }
+}
+
+impl<'a, M> Future for ReceiveProto<'a, M>
+where
+    M: prost::Message + Default,
+{
+    type Item = M;
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        let msg = try_ready!(self.inner.poll());
+        Ok(Async::Ready(decode(&msg)?))
+    }
+}
+
+fn encode<M: prost::Message>(item: &M) -> io::Result<Vec<u8>> {
+    let mut buf = Vec::with_capacity(item.encoded_len());
+    item.encode(&mut buf)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    Ok(buf)
+}
+
+fn decode<M: prost::Message + Default>(bytes: &[u8]) -> io::Result<M> {
+    M::decode(bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
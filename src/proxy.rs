@@ -0,0 +1,103 @@
+//! Forwarding messages between two sockets, like `zmq_proxy`.
+//!
+//! A `Proxy` moves every message received on its frontend to its backend,
+//! and back, such as between the `ROUTER` and `DEALER` sockets of a broker,
+//! or the `XSUB` and `XPUB` sockets of a forwarder. A socket isn't read
+//! while messages wait to go out through the other one, so a slow peer
+//! pushes back on the other side.
+use std::collections::VecDeque;
+use std::io;
+
+use futures::{Async, Future, Poll, Stream};
+
+use super::{Multipart, Socket};
+use super::shutdown::Drain;
+use super::sink::flush;
+
+/// Forward messages between `frontend` and `backend`.
+pub fn proxy(frontend: Socket, backend: Socket) -> Proxy {
+    Proxy {
+        frontend,
+        backend,
+        to_frontend: VecDeque::new(),
+        to_backend: VecDeque::new(),
+    }
+}
+
+/// A Future that forwards messages between two sockets. It resolves once
+/// either socket stops yielding messages. This is returned by `proxy`.
+pub struct Proxy {
+    frontend: Socket,
+    backend: Socket,
+    to_frontend: VecDeque<Multipart>,
+    to_backend: VecDeque<Multipart>,
+}
+
+impl Proxy {
+    /// References to the frontend and backend sockets.
+    pub fn get_ref(&self) -> (&Socket, &Socket) {
+        (&self.frontend, &self.backend)
+    }
+
+    /// Consumes the proxy, returning the frontend and backend sockets.
+    pub fn into_inner(self) -> (Socket, Socket) {
+        (self.frontend, self.backend)
+    }
+}
+
+// Reads a message from `from` into `queue`, if the queue is empty. Returns
+// `None` once the socket stops yielding messages.
+fn forward(from: &Socket, queue: &mut VecDeque<Multipart>) -> io::Result<Option<bool>> {
+    if !queue.is_empty() {
+        return Ok(Some(false));
+    }
+    let mut stream = from;
+    match stream.poll()? {
+        Async::Ready(Some(msgs)) => {
+            queue.push_back(msgs);
+            Ok(Some(true))
+        }
+        Async::Ready(None) => Ok(None),
+        Async::NotReady => Ok(Some(false)),
+    }
+}
+
+impl Future for Proxy {
+    type Item = ();
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        loop {
+            flush(&self.backend, &mut self.to_backend)?;
+            flush(&self.frontend, &mut self.to_frontend)?;
+            let (from_frontend, from_backend) = match (
+                forward(&self.frontend, &mut self.to_backend)?,
+                forward(&self.backend, &mut self.to_frontend)?,
+            ) {
+                (Some(a), Some(b)) => (a, b),
+                _ => return Ok(Async::Ready(())),
+            };
+            if !from_frontend && !from_backend {
+                return Ok(Async::NotReady);
+            }
+        }
+    }
+}
+
+/// Once shut down, no message is read, while the messages already read are
+/// forwarded.
+impl Drain for Proxy {
+    fn poll_drain(&mut self) -> Poll<(), io::Error> {
+        flush(&self.backend, &mut self.to_backend)?;
+        flush(&self.frontend, &mut self.to_frontend)?;
+        if self.to_backend.is_empty() && self.to_frontend.is_empty() {
+            Ok(Async::Ready(()))
+        } else {
+            Ok(Async::NotReady)
+        }
+    }
+
+    fn sockets(&self) -> Vec<&Socket> {
+        vec![&self.frontend, &self.backend]
+    }
+}
@@ -0,0 +1,84 @@
+//! Reactor integration.
+//!
+//! The readiness logic of sockets, which turns the edge-triggered ØMQ file
+//! descriptor into `WouldBlock` errors and task wake-ups, only relies on the
+//! `Registration` trait. `SocketSend` and `SocketRecv` are implemented for
+//! every registration, so the streams, sinks and futures built on them work
+//! with any reactor implementing `Reactor`.
+//!
+//! `tokio_core` is the reactor supported out of the box, with `Handle`
+//! registering sockets as `PollEvented`s. Other reactors, or a mock one for
+//! tests, plug in by implementing both traits.
+use std::io;
+
+use futures::Async;
+use tokio_core::reactor::{Handle, PollEvented};
+use zmq_mio;
+
+/// A reactor that sockets can be registered with.
+pub trait Reactor {
+    /// The registration of a socket with this reactor.
+    type Registration: Registration;
+
+    /// Register a socket, to be notified of its readiness.
+    fn register(&self, socket: zmq_mio::Socket) -> io::Result<Self::Registration>;
+
+    /// Deregister a socket, returning it.
+    fn deregister(&self, registration: Self::Registration) -> io::Result<()>;
+}
+
+/// A socket registered with a reactor.
+///
+/// The `poll_*` methods register the current task for readiness when the
+/// socket is not ready, and `need_*` clear the readiness, so the current
+/// task is notified on the next edge.
+pub trait Registration {
+    /// A reference to the registered socket.
+    fn get_ref(&self) -> &zmq_mio::Socket;
+
+    /// Returns whether the socket may be readable.
+    fn poll_read(&self) -> Async<()>;
+
+    /// Returns whether the socket may be writable.
+    fn poll_write(&self) -> Async<()>;
+
+    /// Clear the read readiness, until the next edge.
+    fn need_read(&self);
+
+    /// Clear the write readiness, until the next edge.
+    fn need_write(&self);
+}
+
+impl Reactor for Handle {
+    type Registration = PollEvented<zmq_mio::Socket>;
+
+    fn register(&self, socket: zmq_mio::Socket) -> io::Result<Self::Registration> {
+        PollEvented::new(socket, self)
+    }
+
+    fn deregister(&self, registration: Self::Registration) -> io::Result<()> {
+        registration.deregister(self)
+    }
+}
+
+impl Registration for PollEvented<zmq_mio::Socket> {
+    fn get_ref(&self) -> &zmq_mio::Socket {
+        PollEvented::get_ref(self)
+    }
+
+    fn poll_read(&self) -> Async<()> {
+        PollEvented::poll_read(self)
+    }
+
+    fn poll_write(&self) -> Async<()> {
+        PollEvented::poll_write(self)
+    }
+
+    fn need_read(&self) {
+        PollEvented::need_read(self)
+    }
+
+    fn need_write(&self) {
+        PollEvented::need_write(self)
+    }
+}
@@ -0,0 +1,214 @@
+//! Reconnection policies, and supervision of failing sockets.
+//!
+//! A `ReconnectPolicy` configures the native reconnection of a socket, with
+//! `Socket::set_reconnect_policy`. ØMQ doubles the reconnection interval
+//! after every failed attempt, but applies no jitter, so many peers of a
+//! flapping endpoint reconnect at the same time.
+//!
+//! When a socket fails for good, a `Supervisor` recreates it, waiting for an
+//! exponential backoff with jitter between attempts.
+use std::io;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use futures::{Async, Future, Poll, Stream};
+use tokio_core::reactor::{Handle, Timeout};
+
+use super::{Multipart, Socket};
+
+/// Reconnection intervals, with exponential backoff and jitter.
+#[derive(Clone, Copy, Debug)]
+pub struct ReconnectPolicy {
+    initial: Duration,
+    max: Duration,
+    jitter: f64,
+    max_attempts: Option<u32>,
+}
+
+impl Default for ReconnectPolicy {
+    /// Start at 100 milliseconds, backing off up to 30 seconds, with 20%
+    /// jitter and unlimited attempts.
+    fn default() -> ReconnectPolicy {
+        ReconnectPolicy {
+            initial: Duration::from_millis(100),
+            max: Duration::from_secs(30),
+            jitter: 0.2,
+            max_attempts: None,
+        }
+    }
+}
+
+impl ReconnectPolicy {
+    /// Create a new policy, backing off from the `initial` interval up to
+    /// the `max` interval.
+    pub fn new(initial: Duration, max: Duration) -> ReconnectPolicy {
+        ReconnectPolicy {
+            initial,
+            max,
+            ..ReconnectPolicy::default()
+        }
+    }
+
+    /// Set the fraction of each interval that is randomized, between `0.0`
+    /// and `1.0`.
+    pub fn jitter(mut self, jitter: f64) -> ReconnectPolicy {
+        self.jitter = jitter.max(0.0).min(1.0);
+        self
+    }
+
+    /// Set the number of consecutive attempts after which a `Supervisor`
+    /// gives up.
+    pub fn max_attempts(mut self, attempts: u32) -> ReconnectPolicy {
+        self.max_attempts = Some(attempts);
+        self
+    }
+
+    /// Returns the initial interval.
+    pub fn initial(&self) -> Duration {
+        self.initial
+    }
+
+    /// Returns the maximum interval.
+    pub fn max(&self) -> Duration {
+        self.max
+    }
+
+    /// Returns the interval to wait before the given attempt, counting
+    /// from zero, without jitter.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 1u32.checked_shl(attempt.min(31)).unwrap_or(u32::max_value());
+        match self.initial.checked_mul(factor) {
+            Some(delay) if delay < self.max => delay,
+            _ => self.max,
+        }
+    }
+
+    // Applies jitter to the backoff, spreading it over
+    // `[backoff * (1 - jitter), backoff]`.
+    fn delay(&self, attempt: u32, seed: &mut u64) -> Duration {
+        let backoff = self.backoff(attempt);
+        if self.jitter == 0.0 {
+            return backoff;
+        }
+        let millis = super::duration_to_millis(backoff) as f64;
+        let random = next_random(seed) as f64 / u64::max_value() as f64;
+        let jittered = millis * (1.0 - self.jitter * random);
+        Duration::from_millis(jittered as u64)
+    }
+}
+
+/// A `Stream` of the messages received on a socket, that recreates the
+/// socket when it fails, waiting for the backoff of its `ReconnectPolicy`
+/// between attempts.
+///
+/// The socket is created by the `factory` closure, which should also bind
+/// or connect it. The stream fails once the maximum number of attempts is
+/// reached, with the last error.
+pub struct Supervisor<F> {
+    factory: F,
+    policy: ReconnectPolicy,
+    handle: Handle,
+    socket: Option<Socket>,
+    delay: Option<Timeout>,
+    attempts: u32,
+    seed: u64,
+}
+
+impl<F> Supervisor<F>
+where
+    F: FnMut() -> io::Result<Socket>,
+{
+    /// Create a new supervisor. The socket is created when the stream is
+    /// first polled.
+    pub fn new(policy: ReconnectPolicy, handle: &Handle, factory: F) -> Supervisor<F> {
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs() ^ u64::from(d.subsec_nanos()))
+            .unwrap_or(0);
+        Supervisor {
+            factory,
+            policy,
+            handle: handle.clone(),
+            socket: None,
+            delay: None,
+            attempts: 0,
+            seed: seed | 1,
+        }
+    }
+
+    /// A reference to the current socket, if any.
+    pub fn get_ref(&self) -> Option<&Socket> {
+        self.socket.as_ref()
+    }
+
+    /// Returns the number of consecutive failed attempts.
+    pub fn attempts(&self) -> u32 {
+        self.attempts
+    }
+
+    // Drops the failed socket, and schedules the next attempt.
+    fn fail(&mut self, e: io::Error) -> io::Result<()> {
+        self.socket = None;
+        if let Some(max) = self.policy.max_attempts {
+            if self.attempts >= max {
+                return Err(e);
+            }
+        }
+        let delay = self.policy.delay(self.attempts, &mut self.seed);
+        warn!("socket failed: {}, recreating in {:?}", e, delay);
+        self.attempts += 1;
+        self.delay = Some(Timeout::new(delay, &self.handle)?);
+        Ok(())
+    }
+}
+
+impl<F> Stream for Supervisor<F>
+where
+    F: FnMut() -> io::Result<Socket>,
+{
+    type Item = Multipart;
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        loop {
+            if let Some(ref mut delay) = self.delay {
+                try_ready!(delay.poll());
+            }
+            self.delay = None;
+            if self.socket.is_none() {
+                match (self.factory)() {
+                    Ok(socket) => {
+                        socket.set_reconnect_policy(self.policy)?;
+                        self.socket = Some(socket);
+                    }
+                    Err(e) => {
+                        self.fail(e)?;
+                        continue;
+                    }
+                }
+            }
+            let polled = {
+                let mut stream = self.socket.as_ref().unwrap();
+                stream.poll()
+            };
+            match polled {
+                Ok(Async::Ready(Some(msgs))) => {
+                    self.attempts = 0;
+                    return Ok(Async::Ready(Some(msgs)));
+                }
+                Ok(Async::Ready(None)) => return Ok(Async::Ready(None)),
+                Ok(Async::NotReady) => return Ok(Async::NotReady),
+                Err(e) => self.fail(e)?,
+            }
+        }
+    }
+}
+
+// xorshift64, which is plenty for spreading reconnection attempts.
+fn next_random(seed: &mut u64) -> u64 {
+    let mut x = *seed;
+    x ^= x << 13;
+    x ^= x >> 7;
+    x ^= x << 17;
+    *seed = x;
+    x
+}
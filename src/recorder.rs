@@ -0,0 +1,250 @@
+//! Recording and replaying message traffic.
+//!
+//! A `Recorder` writes timestamped multipart-messages to a file, and a
+//! `Replayer` reads them back as a `Stream`, with their original timing,
+//! scaled timing, or as fast as possible. This is handy for reproducing the
+//! behaviour of a subscriber with a feed captured in production.
+//!
+//! The file starts with the magic bytes `ZTREC\x01`, followed by one record
+//! per message. A record is the timestamp, in microseconds since the UNIX
+//! epoch, as a big-endian `u64`, then the number of frames as a big-endian
+//! `u32`, then every frame, as its length as a big-endian `u32` followed by
+//! its bytes.
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use futures::{Async, AsyncSink, Future, Poll, Sink, StartSend, Stream};
+use tokio_core::reactor::{Handle, Timeout};
+
+use super::{Message, Multipart};
+
+/// Magic bytes at the start of a recording.
+pub const MAGIC: &[u8] = b"ZTREC\x01";
+
+/// Writes timestamped messages to a recording. It is also a `Sink` of
+/// multipart-messages, which never blocks.
+pub struct Recorder<W: Write> {
+    writer: W,
+}
+
+impl Recorder<BufWriter<File>> {
+    /// Create a recording at the given path, replacing any existing file.
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<Recorder<BufWriter<File>>> {
+        Recorder::new(BufWriter::new(File::create(path)?))
+    }
+}
+
+impl<W: Write> Recorder<W> {
+    /// Start a recording on the given writer, writing its magic bytes.
+    pub fn new(mut writer: W) -> io::Result<Recorder<W>> {
+        writer.write_all(MAGIC)?;
+        Ok(Recorder { writer })
+    }
+
+    /// Record a message, timestamped with the current time.
+    pub fn record(&mut self, msgs: &[Message]) -> io::Result<()> {
+        let timestamp = to_micros(SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default());
+        self.record_at(timestamp, msgs)
+    }
+
+    /// Record a message with the given timestamp, in microseconds since the
+    /// UNIX epoch.
+    pub fn record_at(&mut self, timestamp: u64, msgs: &[Message]) -> io::Result<()> {
+        self.writer.write_all(&encode_u64(timestamp))?;
+        self.writer.write_all(&encode_u32(msgs.len() as u32))?;
+        for msg in msgs {
+            self.writer.write_all(&encode_u32(msg.len() as u32))?;
+            self.writer.write_all(msg)?;
+        }
+        Ok(())
+    }
+
+    /// Flush the underlying writer.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    /// A mutable reference to the underlying writer.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.writer
+    }
+
+    /// Consumes the recorder, returning the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl<W: Write> Sink for Recorder<W> {
+    type SinkItem = Multipart;
+    type SinkError = io::Error;
+
+    fn start_send(&mut self, item: Multipart) -> StartSend<Self::SinkItem, Self::SinkError> {
+        self.record(&item)?;
+        Ok(AsyncSink::Ready)
+    }
+
+    fn poll_complete(&mut self) -> Poll<(), Self::SinkError> {
+        self.writer.flush()?;
+        Ok(Async::Ready(()))
+    }
+}
+
+/// How a `Replayer` spaces the messages it yields.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Timing {
+    /// Keep the original intervals between messages.
+    Original,
+    /// Divide the original intervals by the given factor, so `2.0` replays
+    /// twice as fast.
+    Scaled(f64),
+    /// Yield every message right away.
+    Immediate,
+}
+
+/// A `Stream` of the messages of a recording, yielded with the given
+/// `Timing`.
+pub struct Replayer<R: Read> {
+    reader: R,
+    timing: Timing,
+    handle: Handle,
+    // The time replay started, and the timestamp of the first record.
+    start: Option<(Instant, u64)>,
+    pending: Option<Multipart>,
+    delay: Option<Timeout>,
+}
+
+impl Replayer<BufReader<File>> {
+    /// Open the recording at the given path.
+    pub fn open<P: AsRef<Path>>(path: P, timing: Timing, handle: &Handle) -> io::Result<Replayer<BufReader<File>>> {
+        Replayer::new(BufReader::new(File::open(path)?), timing, handle)
+    }
+}
+
+impl<R: Read> Replayer<R> {
+    /// Replay the recording read from the given reader, checking its magic
+    /// bytes.
+    pub fn new(mut reader: R, timing: Timing, handle: &Handle) -> io::Result<Replayer<R>> {
+        let mut magic = [0u8; 6];
+        reader.read_exact(&mut magic)?;
+        if &magic[..] != MAGIC {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "not a recording"));
+        }
+        Ok(Replayer {
+            reader,
+            timing,
+            handle: handle.clone(),
+            start: None,
+            pending: None,
+            delay: None,
+        })
+    }
+
+    // Returns when a record with the given timestamp is due.
+    fn due(&mut self, timestamp: u64) -> Option<Instant> {
+        let (start, first) = *self.start.get_or_insert((Instant::now(), timestamp));
+        let offset = timestamp.saturating_sub(first);
+        let offset = match self.timing {
+            Timing::Original => offset,
+            Timing::Scaled(factor) if factor > 0.0 => (offset as f64 / factor) as u64,
+            Timing::Scaled(_) | Timing::Immediate => return None,
+        };
+        Some(start + from_micros(offset))
+    }
+}
+
+impl<R: Read> Stream for Replayer<R> {
+    type Item = Multipart;
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        if let Some(ref mut delay) = self.delay {
+            try_ready!(delay.poll());
+        }
+        self.delay = None;
+        if let Some(msgs) = self.pending.take() {
+            return Ok(Async::Ready(Some(msgs)));
+        }
+        let (timestamp, msgs) = match read_record(&mut self.reader)? {
+            Some(record) => record,
+            None => return Ok(Async::Ready(None)),
+        };
+        match self.due(timestamp) {
+            Some(due) if due > Instant::now() => {
+                let mut delay = Timeout::new_at(due, &self.handle)?;
+                // registers the task, the timeout can't be ready yet.
+                if let Async::Ready(()) = delay.poll()? {
+                    return Ok(Async::Ready(Some(msgs)));
+                }
+                self.delay = Some(delay);
+                self.pending = Some(msgs);
+                Ok(Async::NotReady)
+            }
+            _ => Ok(Async::Ready(Some(msgs))),
+        }
+    }
+}
+
+/// Read the next record of a recording, after its magic bytes. Returns
+/// `None` at the end of the recording.
+pub fn read_record<R: Read>(reader: &mut R) -> io::Result<Option<(u64, Multipart)>> {
+    let mut timestamp = [0u8; 8];
+    // an end of file is only clean before a record.
+    let mut read = 0;
+    while read < timestamp.len() {
+        match reader.read(&mut timestamp[read..]) {
+            Ok(0) if read == 0 => return Ok(None),
+            Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+            Ok(n) => read += n,
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    let frames = read_u32(reader)?;
+    let mut msgs = Vec::with_capacity(frames as usize);
+    for _ in 0..frames {
+        let len = read_u32(reader)? as usize;
+        let mut frame = vec![0u8; len];
+        reader.read_exact(&mut frame)?;
+        msgs.push(Message::from_slice(&frame));
+    }
+    Ok(Some((decode_u64(&timestamp), msgs)))
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> io::Result<u32> {
+    let mut buf = [0u8; 4];
+    reader.read_exact(&mut buf)?;
+    Ok(buf.iter().fold(0, |acc, b| (acc << 8) | u32::from(*b)))
+}
+
+fn decode_u64(buf: &[u8; 8]) -> u64 {
+    buf.iter().fold(0, |acc, b| (acc << 8) | u64::from(*b))
+}
+
+fn encode_u32(value: u32) -> [u8; 4] {
+    let mut buf = [0u8; 4];
+    for (idx, byte) in buf.iter_mut().enumerate() {
+        *byte = (value >> (8 * (3 - idx))) as u8;
+    }
+    buf
+}
+
+fn encode_u64(value: u64) -> [u8; 8] {
+    let mut buf = [0u8; 8];
+    for (idx, byte) in buf.iter_mut().enumerate() {
+        *byte = (value >> (8 * (7 - idx))) as u8;
+    }
+    buf
+}
+
+fn to_micros(d: Duration) -> u64 {
+    d.as_secs()
+        .saturating_mul(1_000_000)
+        .saturating_add(u64::from(d.subsec_nanos() / 1_000))
+}
+
+fn from_micros(micros: u64) -> Duration {
+    Duration::new(micros / 1_000_000, (micros % 1_000_000) as u32 * 1_000)
+}
@@ -0,0 +1,371 @@
+//! At-least-once delivery over `DEALER` and `ROUTER` sockets.
+//!
+//! Every message carries a sequence number, and is acknowledged by the
+//! receiving side. Messages that aren't acknowledged in time are sent
+//! again, and duplicates are suppressed on the receiving side, so each
+//! message is yielded once, as long as the peers keep their sessions.
+//!
+//! Data messages are sent as `[DATA, sequence, body...]`, and
+//! acknowledgements as `[ACK, sequence]`, where the sequence is an 8-byte
+//! big-endian number, starting from 1. On a `ROUTER` socket, both are
+//! prefixed with the routing id of the peer.
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::io;
+use std::time::{Duration, Instant};
+
+use futures::{Async, AsyncSink, Poll, Sink, StartSend, Stream};
+use tokio_core::reactor::{Handle, Interval};
+use zmq::Message;
+
+use super::{Multipart, RoutingId, Socket};
+use super::sink::flush;
+
+/// Frame type of data messages.
+pub const DATA: u8 = 0x01;
+
+/// Frame type of acknowledgements.
+pub const ACK: u8 = 0x02;
+
+/// Configuration of a reliable channel.
+#[derive(Clone, Copy, Debug)]
+pub struct ReliableConfig {
+    /// Maximum number of unacknowledged messages per peer, after which the
+    /// sink stops accepting messages.
+    pub window: usize,
+    /// Time to wait for an acknowledgement, before sending a message again.
+    pub retransmit: Duration,
+}
+
+impl Default for ReliableConfig {
+    /// A window of 64 messages, retransmitted after 500 milliseconds.
+    fn default() -> ReliableConfig {
+        ReliableConfig {
+            window: 64,
+            retransmit: Duration::from_millis(500),
+        }
+    }
+}
+
+// Delivery state shared with a single peer.
+#[derive(Default)]
+struct Session {
+    next_seq: u64,
+    unacked: BTreeMap<u64, (Vec<Vec<u8>>, Instant)>,
+    // every sequence up to `delivered` has been received, as well as the
+    // ones in `ahead`.
+    delivered: u64,
+    ahead: BTreeSet<u64>,
+}
+
+impl Session {
+    fn send(&mut self, body: Multipart) -> (u64, Vec<Vec<u8>>) {
+        self.next_seq += 1;
+        let frames: Vec<Vec<u8>> = body.iter().map(|m| m.to_vec()).collect();
+        self.unacked
+            .insert(self.next_seq, (frames.clone(), Instant::now()));
+        (self.next_seq, frames)
+    }
+
+    fn ack(&mut self, seq: u64) {
+        self.unacked.remove(&seq);
+    }
+
+    // Records a received sequence, returning false for duplicates.
+    fn accept(&mut self, seq: u64) -> bool {
+        if seq <= self.delivered || !self.ahead.insert(seq) {
+            return false;
+        }
+        while self.ahead.remove(&(self.delivered + 1)) {
+            self.delivered += 1;
+        }
+        true
+    }
+
+    // Returns the messages due for retransmission.
+    fn due(&mut self, timeout: Duration) -> Vec<(u64, Vec<Vec<u8>>)> {
+        let now = Instant::now();
+        let mut due = Vec::new();
+        for (seq, &mut (ref frames, ref mut sent)) in &mut self.unacked {
+            if now.duration_since(*sent) >= timeout {
+                *sent = now;
+                due.push((*seq, frames.clone()));
+            }
+        }
+        due
+    }
+}
+
+// The channel behind `ReliableDealer` and `ReliableRouter`. With a `DEALER`
+// socket, there is a single session, keyed by an empty peer id.
+struct Channel {
+    socket: Socket,
+    router: bool,
+    config: ReliableConfig,
+    sessions: HashMap<Vec<u8>, Session>,
+    inbox: VecDeque<(Vec<u8>, Multipart)>,
+    outgoing: VecDeque<Multipart>,
+    timer: Interval,
+}
+
+impl Channel {
+    fn new(socket: Socket, router: bool, config: ReliableConfig, handle: &Handle) -> io::Result<Channel> {
+        if config.window == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "window must be non-zero",
+            ));
+        }
+        Ok(Channel {
+            socket,
+            router,
+            config,
+            sessions: HashMap::new(),
+            inbox: VecDeque::new(),
+            outgoing: VecDeque::new(),
+            timer: Interval::new(config.retransmit, handle)?,
+        })
+    }
+
+    fn frame(&self, peer: &[u8], kind: u8, seq: u64, body: Vec<Vec<u8>>) -> Multipart {
+        let mut msgs = Vec::with_capacity(body.len() + 3);
+        if self.router {
+            msgs.push(Message::from_slice(peer));
+        }
+        msgs.push(Message::from_slice(&[kind]));
+        msgs.push(Message::from_slice(&encode_seq(seq)));
+        msgs.extend(body.iter().map(|f| Message::from_slice(f)));
+        msgs
+    }
+
+    fn unacked(&self, peer: &[u8]) -> usize {
+        self.sessions.get(peer).map(|s| s.unacked.len()).unwrap_or(0)
+    }
+
+    fn start_send(&mut self, peer: Vec<u8>, body: Multipart) -> StartSend<Multipart, io::Error> {
+        self.poll_socket()?;
+        if self.unacked(&peer) >= self.config.window {
+            return Ok(AsyncSink::NotReady(body));
+        }
+        let (seq, frames) = self.sessions
+            .entry(peer.clone())
+            .or_insert_with(Session::default)
+            .send(body);
+        let msgs = self.frame(&peer, DATA, seq, frames);
+        self.outgoing.push_back(msgs);
+        flush(&self.socket, &mut self.outgoing)?;
+        Ok(AsyncSink::Ready)
+    }
+
+    fn poll_complete(&mut self) -> Poll<(), io::Error> {
+        self.poll_socket()?;
+        try_ready!(flush(&self.socket, &mut self.outgoing));
+        if self.sessions.values().any(|s| !s.unacked.is_empty()) {
+            Ok(Async::NotReady)
+        } else {
+            Ok(Async::Ready(()))
+        }
+    }
+
+    fn poll_recv(&mut self) -> Poll<Option<(Vec<u8>, Multipart)>, io::Error> {
+        self.poll_socket()?;
+        match self.inbox.pop_front() {
+            Some(item) => Ok(Async::Ready(Some(item))),
+            None => Ok(Async::NotReady),
+        }
+    }
+
+    // Retransmits due messages, and reads every incoming message, handling
+    // acknowledgements, and queueing new data in the inbox.
+    fn poll_socket(&mut self) -> io::Result<()> {
+        while let Async::Ready(Some(())) = self.timer.poll()? {
+            let mut due = Vec::new();
+            for (peer, session) in &mut self.sessions {
+                for (seq, frames) in session.due(self.config.retransmit) {
+                    due.push((peer.clone(), seq, frames));
+                }
+            }
+            for (peer, seq, frames) in due {
+                trace!("retransmitting message {}", seq);
+                let msgs = self.frame(&peer, DATA, seq, frames);
+                self.outgoing.push_back(msgs);
+            }
+        }
+        loop {
+            let mut msgs = {
+                let mut stream = &self.socket;
+                match stream.poll()? {
+                    Async::Ready(Some(msgs)) => msgs,
+                    _ => break,
+                }
+            };
+            let peer = if self.router && !msgs.is_empty() {
+                msgs.remove(0).to_vec()
+            } else {
+                Vec::new()
+            };
+            if msgs.len() < 2 || msgs[0].len() != 1 {
+                warn!("dropping invalid reliable message");
+                continue;
+            }
+            let seq = match decode_seq(&msgs[1]) {
+                Some(seq) => seq,
+                None => {
+                    warn!("dropping reliable message with an invalid sequence");
+                    continue;
+                }
+            };
+            match msgs[0][0] {
+                ACK => {
+                    if let Some(session) = self.sessions.get_mut(&peer) {
+                        session.ack(seq);
+                    }
+                }
+                DATA => {
+                    let fresh = self.sessions
+                        .entry(peer.clone())
+                        .or_insert_with(Session::default)
+                        .accept(seq);
+                    let ack = self.frame(&peer, ACK, seq, Vec::new());
+                    self.outgoing.push_back(ack);
+                    if fresh {
+                        let body = msgs.split_off(2);
+                        self.inbox.push_back((peer, body));
+                    }
+                }
+                _ => warn!("dropping reliable message of an unknown type"),
+            }
+        }
+        flush(&self.socket, &mut self.outgoing)?;
+        Ok(())
+    }
+}
+
+/// A reliable channel over a `DEALER` socket. It is a `Sink` and a `Stream`
+/// of multipart-messages.
+///
+/// The sink stops accepting messages while the window is full, and
+/// `poll_complete` resolves once every message is acknowledged. Both
+/// halves must be polled for acknowledgements to flow.
+pub struct ReliableDealer {
+    channel: Channel,
+}
+
+impl ReliableDealer {
+    /// Create a new reliable channel, for a connected `DEALER` socket. Fails
+    /// with `InvalidInput` when the window of the configuration is zero.
+    pub fn new(socket: Socket, config: ReliableConfig, handle: &Handle) -> io::Result<ReliableDealer> {
+        Ok(ReliableDealer {
+            channel: Channel::new(socket, false, config, handle)?,
+        })
+    }
+
+    /// A reference to the underlying socket.
+    pub fn get_ref(&self) -> &Socket {
+        &self.channel.socket
+    }
+
+    /// Returns the number of messages waiting for an acknowledgement.
+    pub fn unacked(&self) -> usize {
+        self.channel.unacked(&[])
+    }
+}
+
+impl Stream for ReliableDealer {
+    type Item = Multipart;
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        let item = try_ready!(self.channel.poll_recv());
+        Ok(Async::Ready(item.map(|(_, body)| body)))
+    }
+}
+
+impl Sink for ReliableDealer {
+    type SinkItem = Multipart;
+    type SinkError = io::Error;
+
+    fn start_send(&mut self, item: Multipart) -> StartSend<Multipart, Self::SinkError> {
+        self.channel.start_send(Vec::new(), item)
+    }
+
+    fn poll_complete(&mut self) -> Poll<(), Self::SinkError> {
+        self.channel.poll_complete()
+    }
+}
+
+/// A reliable channel over a `ROUTER` socket, keeping a session for every
+/// peer. It is a `Sink` and a `Stream` of multipart-messages, paired with
+/// the `RoutingId` of their peer.
+pub struct ReliableRouter {
+    channel: Channel,
+}
+
+impl ReliableRouter {
+    /// Create a new reliable channel, for a bound `ROUTER` socket. Fails
+    /// with `InvalidInput` when the window of the configuration is zero.
+    pub fn new(socket: Socket, config: ReliableConfig, handle: &Handle) -> io::Result<ReliableRouter> {
+        Ok(ReliableRouter {
+            channel: Channel::new(socket, true, config, handle)?,
+        })
+    }
+
+    /// A reference to the underlying socket.
+    pub fn get_ref(&self) -> &Socket {
+        &self.channel.socket
+    }
+
+    /// Returns the number of messages waiting for an acknowledgement from
+    /// the given peer.
+    pub fn unacked(&self, peer: &RoutingId) -> usize {
+        self.channel.unacked(peer.as_bytes())
+    }
+
+    /// Forget the session of a peer, dropping its unacknowledged messages.
+    pub fn remove_peer(&mut self, peer: &RoutingId) {
+        self.channel.sessions.remove(peer.as_bytes());
+    }
+}
+
+impl Stream for ReliableRouter {
+    type Item = (RoutingId, Multipart);
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        let item = try_ready!(self.channel.poll_recv());
+        Ok(Async::Ready(
+            item.map(|(peer, body)| (RoutingId::from(peer), body)),
+        ))
+    }
+}
+
+impl Sink for ReliableRouter {
+    type SinkItem = (RoutingId, Multipart);
+    type SinkError = io::Error;
+
+    fn start_send(&mut self, item: Self::SinkItem) -> StartSend<Self::SinkItem, Self::SinkError> {
+        let (peer, body) = item;
+        match self.channel.start_send(peer.to_vec(), body)? {
+            AsyncSink::Ready => Ok(AsyncSink::Ready),
+            AsyncSink::NotReady(body) => Ok(AsyncSink::NotReady((peer, body))),
+        }
+    }
+
+    fn poll_complete(&mut self) -> Poll<(), Self::SinkError> {
+        self.channel.poll_complete()
+    }
+}
+
+fn encode_seq(seq: u64) -> [u8; 8] {
+    let mut buf = [0u8; 8];
+    for (idx, b) in buf.iter_mut().enumerate() {
+        *b = (seq >> (56 - 8 * idx)) as u8;
+    }
+    buf
+}
+
+fn decode_seq(frame: &[u8]) -> Option<u64> {
+    if frame.len() != 8 {
+        return None;
+    }
+    Some(frame.iter().fold(0, |acc, b| (acc << 8) | u64::from(*b)))
+}
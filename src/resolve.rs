@@ -0,0 +1,156 @@
+//! Asynchronous name resolution for `tcp` endpoints.
+//!
+//! libzmq resolves hostnames with a blocking lookup inside `zmq_connect`,
+//! which stalls the reactor for as long as DNS takes. `ConnectResolved`
+//! looks the hostname up on a `CpuPool` instead, and connects to the
+//! resolved address once it is known.
+use std::io;
+use std::net::{IpAddr, ToSocketAddrs};
+
+use futures::{Async, Future, Poll};
+use futures_cpupool::{CpuFuture, CpuPool};
+
+use super::{Endpoint, Socket};
+
+/// Returns true if the endpoint has a hostname that needs resolving.
+pub fn needs_resolution(endpoint: &Endpoint) -> bool {
+    match *endpoint {
+        Endpoint::Tcp { ref host, .. } => host != "*" && parse_ip(host).is_none(),
+        _ => false,
+    }
+}
+
+/// Resolve the hostname of a `tcp` endpoint on the given pool, into every
+/// address it maps to. Other endpoints resolve to themselves.
+pub fn resolve(endpoint: Endpoint, pool: &CpuPool) -> Resolve {
+    if !needs_resolution(&endpoint) {
+        return Resolve {
+            state: ResolveState::Done(Some(vec![endpoint])),
+        };
+    }
+    let lookup = pool.spawn_fn(move || {
+        let (source, host, port) = match endpoint {
+            Endpoint::Tcp { source, host, port } => (source, host, port),
+            _ => unreachable!(),
+        };
+        let addrs = (&host[..], port).to_socket_addrs()?;
+        let endpoints = addrs
+            .map(|addr| match Endpoint::from(addr) {
+                Endpoint::Tcp { host, port, .. } => Endpoint::Tcp {
+                    source: source.clone(),
+                    host,
+                    port,
+                },
+                other => other,
+            })
+            .collect::<Vec<_>>();
+        if endpoints.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("no addresses found for {}", host),
+            ));
+        }
+        Ok(endpoints)
+    });
+    Resolve {
+        state: ResolveState::Lookup(lookup),
+    }
+}
+
+enum ResolveState {
+    Lookup(CpuFuture<Vec<Endpoint>, io::Error>),
+    Done(Option<Vec<Endpoint>>),
+}
+
+/// A Future that resolves into the endpoints a `tcp` hostname maps to.
+/// This is returned by `resolve`.
+pub struct Resolve {
+    state: ResolveState,
+}
+
+impl Future for Resolve {
+    type Item = Vec<Endpoint>;
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        match self.state {
+            ResolveState::Lookup(ref mut lookup) => lookup.poll(),
+            ResolveState::Done(ref mut endpoints) => Ok(Async::Ready(
+                endpoints.take().expect("cannot poll Resolve twice"),
+            )),
+        }
+    }
+}
+
+/// A Future that connects a socket once the hostname of its endpoint is
+/// resolved, resolving into the endpoint it connected to.
+/// This is returned by `Socket::connect_resolved`.
+///
+/// Like libzmq, the socket connects to the first resolved address. IPv6
+/// addresses are skipped unless IPv6 is enabled on the socket.
+pub struct ConnectResolved<'a> {
+    socket: &'a Socket,
+    resolve: Option<Resolve>,
+    error: Option<io::Error>,
+}
+
+impl<'a> ConnectResolved<'a> {
+    pub fn new(socket: &'a Socket, endpoint: io::Result<Endpoint>, pool: &CpuPool) -> ConnectResolved<'a> {
+        match endpoint {
+            Ok(endpoint) => ConnectResolved {
+                socket,
+                resolve: Some(resolve(endpoint, pool)),
+                error: None,
+            },
+            Err(e) => ConnectResolved {
+                socket,
+                resolve: None,
+                error: Some(e),
+            },
+        }
+    }
+}
+
+impl<'a> Future for ConnectResolved<'a> {
+    type Item = Endpoint;
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        if let Some(e) = self.error.take() {
+            return Err(e);
+        }
+        let endpoints = match self.resolve {
+            Some(ref mut resolve) => try_ready!(resolve.poll()),
+            None => panic!("cannot poll ConnectResolved twice"),
+        };
+        self.resolve = None;
+        let ipv6 = self.socket.is_ipv6()?;
+        let endpoint = endpoints
+            .into_iter()
+            .find(|endpoint| ipv6 || !is_ipv6_endpoint(endpoint))
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no IPv4 addresses found"))?;
+        self.socket.connect(&endpoint)?;
+        Ok(Async::Ready(endpoint))
+    }
+}
+
+// Parses a host as an IP address, allowing IPv6 addresses in brackets.
+fn parse_ip(host: &str) -> Option<IpAddr> {
+    let host = if host.starts_with('[') && host.ends_with(']') {
+        &host[1..host.len() - 1]
+    } else {
+        host
+    };
+    host.parse().ok()
+}
+
+fn is_ipv6_endpoint(endpoint: &Endpoint) -> bool {
+    match *endpoint {
+        Endpoint::Tcp { ref host, .. } => match parse_ip(host) {
+            Some(IpAddr::V6(_)) => true,
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
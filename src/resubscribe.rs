@@ -0,0 +1,81 @@
+//! Re-applying the subscriptions of `SUB` sockets when they connect.
+//!
+//! `SUB` sockets send their subscriptions to every publisher they connect
+//! to, but a restarted broker, or an intermediary that only forwards the
+//! first subscription to a topic, may still lose track of them. A
+//! `Resubscriber` monitors the socket, and re-applies every tracked
+//! subscription, from `Socket::subscriptions`, each time it connects.
+use std::io;
+
+use futures::{Async, Poll, Stream};
+use tokio_core::reactor::Handle;
+
+use super::{Context, Multipart, Socket};
+use super::monitor::{Event, Monitor};
+
+/// Items yielded by a `Resubscriber`.
+#[derive(Debug)]
+pub enum SubEvent {
+    /// A message was received.
+    Message(Multipart),
+    /// The socket connected to the given endpoint, and its subscriptions
+    /// were re-applied.
+    Restored(String),
+}
+
+/// A `Stream` of the messages of a `SUB` socket, which re-applies its
+/// subscriptions after every connection. This is returned by
+/// `Socket::auto_resubscribe`.
+pub struct Resubscriber {
+    socket: Socket,
+    monitor: Option<Monitor>,
+}
+
+impl Resubscriber {
+    /// Monitor the connections of a `SUB` socket, in the given context. It
+    /// should be created before the socket connects, so no connection is
+    /// missed.
+    pub fn new(context: &Context, socket: Socket, handle: &Handle) -> io::Result<Resubscriber> {
+        let events = i32::from(Event::Connected.to_raw());
+        let monitor = Monitor::new(context, &socket, events, handle)?;
+        Ok(Resubscriber {
+            socket,
+            monitor: Some(monitor),
+        })
+    }
+
+    /// A reference to the underlying socket.
+    pub fn get_ref(&self) -> &Socket {
+        &self.socket
+    }
+
+    /// Consumes the stream, returning the underlying socket, which stays
+    /// monitored.
+    pub fn into_inner(self) -> Socket {
+        self.socket
+    }
+}
+
+impl Stream for Resubscriber {
+    type Item = SubEvent;
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        let event = match self.monitor {
+            Some(ref mut monitor) => monitor.poll()?,
+            None => Async::NotReady,
+        };
+        match event {
+            Async::Ready(Some(ref event)) if event.event == Event::Connected => {
+                self.socket.resubscribe()?;
+                debug!("subscriptions restored for {}", event.endpoint);
+                return Ok(Async::Ready(Some(SubEvent::Restored(event.endpoint.clone()))));
+            }
+            Async::Ready(None) => self.monitor = None,
+            _ => {}
+        }
+        let mut stream = &self.socket;
+        let msgs = try_ready!(stream.poll());
+        Ok(Async::Ready(msgs.map(SubEvent::Message)))
+    }
+}
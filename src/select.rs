@@ -0,0 +1,147 @@
+//! Waiting on several sockets at once, like `zmq_poll`.
+//!
+//! A `PollSet` holds tagged socket references, and is a `Stream` of the
+//! messages received on any of them, paired with the tag of their socket.
+//! Sockets are polled in turn, starting after the one that yielded last,
+//! so a busy socket can't starve the others. `merge_streams` does the same
+//! for owned sockets, tagging messages with the index of their socket.
+use std::io;
+
+use futures::{Async, Future, Poll, Stream};
+
+use super::{Multipart, Socket};
+
+/// A set of tagged sockets, yielding the messages received on any of them.
+pub struct PollSet<'a, T> {
+    sockets: Vec<(T, &'a Socket)>,
+    next: usize,
+}
+
+impl<'a, T: Clone> PollSet<'a, T> {
+    /// Create a new, empty set.
+    pub fn new() -> PollSet<'a, T> {
+        PollSet {
+            sockets: Vec::new(),
+            next: 0,
+        }
+    }
+
+    /// Add a socket to the set, with the tag that identifies its messages.
+    pub fn add(&mut self, tag: T, socket: &'a Socket) -> &mut PollSet<'a, T> {
+        self.sockets.push((tag, socket));
+        self
+    }
+
+    /// Returns the number of sockets in the set.
+    pub fn len(&self) -> usize {
+        self.sockets.len()
+    }
+
+    /// Returns true if the set has no sockets.
+    pub fn is_empty(&self) -> bool {
+        self.sockets.is_empty()
+    }
+
+    /// Returns a future that resolves into the next message received on any
+    /// of the sockets.
+    pub fn select<'b>(&'b mut self) -> Select<'b, 'a, T> {
+        Select { set: self }
+    }
+}
+
+impl<'a, T: Clone> Default for PollSet<'a, T> {
+    fn default() -> PollSet<'a, T> {
+        PollSet::new()
+    }
+}
+
+impl<'a, T: Clone> Stream for PollSet<'a, T> {
+    type Item = (T, Multipart);
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        let (idx, msgs) = match try_ready!(poll_fairly(&self.sockets, &mut self.next, |&(_, socket)| socket)) {
+            Some(item) => item,
+            None => return Ok(Async::Ready(None)),
+        };
+        Ok(Async::Ready(Some((self.sockets[idx].0.clone(), msgs))))
+    }
+}
+
+/// A Future that resolves into the next message received on a `PollSet`,
+/// paired with the tag of its socket. This is returned by `PollSet::select`.
+pub struct Select<'b, 'a: 'b, T: 'b> {
+    set: &'b mut PollSet<'a, T>,
+}
+
+impl<'b, 'a, T: Clone> Future for Select<'b, 'a, T> {
+    type Item = (T, Multipart);
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        match try_ready!(self.set.poll()) {
+            Some(item) => Ok(Async::Ready(item)),
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "no sockets to select from",
+            )),
+        }
+    }
+}
+
+/// Merge the messages of several sockets, such as `SUB` sockets for
+/// different upstreams, into one stream, pairing each message with the
+/// index of its socket. Sockets are polled fairly, like with `PollSet`.
+pub fn merge_streams(sockets: Vec<Socket>) -> MergedStreams {
+    MergedStreams { sockets, next: 0 }
+}
+
+/// A `Stream` of the messages received on any of several sockets, paired
+/// with the index of their socket. This is returned by `merge_streams`.
+pub struct MergedStreams {
+    sockets: Vec<Socket>,
+    next: usize,
+}
+
+impl MergedStreams {
+    /// A reference to the merged sockets.
+    pub fn get_ref(&self) -> &[Socket] {
+        &self.sockets
+    }
+
+    /// Consumes the stream, returning the merged sockets.
+    pub fn into_inner(self) -> Vec<Socket> {
+        self.sockets
+    }
+}
+
+impl Stream for MergedStreams {
+    type Item = (usize, Multipart);
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        poll_fairly(&self.sockets, &mut self.next, |socket| socket)
+    }
+}
+
+// Polls the sockets of the entries in turn, starting at `next`, returning
+// the first message received with the index of its entry. `next` moves past
+// that entry, so it is polled last the next time.
+fn poll_fairly<E, F>(entries: &[E], next: &mut usize, socket: F) -> Poll<Option<(usize, Multipart)>, io::Error>
+where
+    F: Fn(&E) -> &Socket,
+{
+    let len = entries.len();
+    if len == 0 {
+        return Ok(Async::Ready(None));
+    }
+    for offset in 0..len {
+        let idx = (*next + offset) % len;
+        let mut stream = socket(&entries[idx]);
+        if let Async::Ready(Some(msgs)) = stream.poll()? {
+            *next = idx + 1;
+            return Ok(Async::Ready(Some((idx, msgs))));
+        }
+    }
+    Ok(Async::NotReady)
+}
@@ -0,0 +1,301 @@
+//! `tower-service` integration, for request-reply over `DEALER` and `ROUTER`
+//! sockets.
+//!
+//! Enabled with the `tower-service` feature.
+//!
+//! Requests carry an envelope made of the empty delimiter, followed by a
+//! frame with an 8-byte request id, and then the request body. Replies echo
+//! the envelope back, so a `Client` can match replies to requests even when
+//! they arrive out of order. `serve` takes care of the envelope on the
+//! server side.
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::io;
+
+use futures::{Async, Future, Poll, Stream};
+use futures::sync::{mpsc, oneshot};
+use tokio_core::reactor::Handle;
+use tower_service::Service;
+use zmq::Message;
+
+use super::{Multipart, Socket};
+use super::shutdown::Drain;
+use super::sink::flush;
+
+type Request = (Multipart, oneshot::Sender<io::Result<Multipart>>);
+
+/// A `Service` that sends requests over a `DEALER` socket, and resolves
+/// into their replies. Cloning a client yields a handle to the same socket.
+#[derive(Clone)]
+pub struct Client {
+    requests: mpsc::UnboundedSender<Request>,
+}
+
+impl Client {
+    /// Create a new client for a connected `DEALER` socket. The socket is
+    /// driven by a task spawned on the given reactor handle, which stops
+    /// once every clone of the client is dropped and all replies are in.
+    pub fn new(socket: Socket, handle: &Handle) -> Client {
+        let (tx, rx) = mpsc::unbounded();
+        let driver = ClientDriver::new(socket, rx);
+        handle.spawn(driver.map_err(|e| error!("service client failed: {}", e)));
+        Client { requests: tx }
+    }
+}
+
+impl Service for Client {
+    type Request = Multipart;
+    type Response = Multipart;
+    type Error = io::Error;
+    type Future = ResponseFuture;
+
+    fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+        Ok(Async::Ready(()))
+    }
+
+    fn call(&mut self, req: Multipart) -> ResponseFuture {
+        let (tx, rx) = oneshot::channel();
+        // when the driver is gone, `tx` is dropped and the future fails.
+        let _ = self.requests.unbounded_send((req, tx));
+        ResponseFuture { rx }
+    }
+}
+
+/// A Future that resolves into the reply to a request made with `Client`.
+pub struct ResponseFuture {
+    rx: oneshot::Receiver<io::Result<Multipart>>,
+}
+
+impl Future for ResponseFuture {
+    type Item = Multipart;
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        match self.rx.poll() {
+            Ok(Async::Ready(Ok(reply))) => Ok(Async::Ready(reply)),
+            Ok(Async::Ready(Err(e))) => Err(e),
+            Ok(Async::NotReady) => Ok(Async::NotReady),
+            Err(_) => Err(io::Error::new(
+                io::ErrorKind::BrokenPipe,
+                "service client has stopped",
+            )),
+        }
+    }
+}
+
+// Task that owns the `DEALER` socket of a `Client`.
+struct ClientDriver {
+    socket: Socket,
+    requests: mpsc::UnboundedReceiver<Request>,
+    requests_done: bool,
+    next_id: u64,
+    pending: HashMap<u64, oneshot::Sender<io::Result<Multipart>>>,
+    outgoing: VecDeque<Multipart>,
+}
+
+impl ClientDriver {
+    fn new(socket: Socket, requests: mpsc::UnboundedReceiver<Request>) -> ClientDriver {
+        ClientDriver {
+            socket,
+            requests,
+            requests_done: false,
+            next_id: 0,
+            pending: HashMap::new(),
+            outgoing: VecDeque::new(),
+        }
+    }
+
+    fn poll_requests(&mut self) {
+        while !self.requests_done {
+            match self.requests.poll() {
+                Ok(Async::Ready(Some((body, tx)))) => {
+                    let id = self.next_id;
+                    self.next_id = self.next_id.wrapping_add(1);
+                    let mut msgs = vec![Message::new(), encode_id(id)];
+                    msgs.extend(body);
+                    self.outgoing.push_back(msgs);
+                    self.pending.insert(id, tx);
+                }
+                Ok(Async::NotReady) => break,
+                Ok(Async::Ready(None)) | Err(_) => self.requests_done = true,
+            }
+        }
+    }
+
+    fn dispatch(&mut self, mut msgs: Multipart) {
+        let id = if msgs.len() >= 2 && msgs[0].is_empty() {
+            decode_id(&msgs[1])
+        } else {
+            None
+        };
+        match id.and_then(|id| self.pending.remove(&id)) {
+            Some(tx) => {
+                let body = msgs.split_off(2);
+                let _ = tx.send(Ok(body));
+            }
+            None => warn!("dropping reply without a matching request"),
+        }
+    }
+
+    fn poll_socket(&mut self) -> Poll<(), io::Error> {
+        self.poll_requests();
+        try_ready!(flush(&self.socket, &mut self.outgoing));
+        loop {
+            let mut stream = &self.socket;
+            match stream.poll()? {
+                Async::Ready(Some(msgs)) => self.dispatch(msgs),
+                _ => break,
+            }
+        }
+        if self.requests_done && self.pending.is_empty() {
+            Ok(Async::Ready(()))
+        } else {
+            Ok(Async::NotReady)
+        }
+    }
+}
+
+impl Future for ClientDriver {
+    type Item = ();
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        match self.poll_socket() {
+            Err(e) => {
+                for (_, tx) in self.pending.drain() {
+                    let _ = tx.send(Err(io::Error::new(e.kind(), e.to_string())));
+                }
+                Err(e)
+            }
+            r => r,
+        }
+    }
+}
+
+/// Serve requests received on a `ROUTER` socket with the given `Service`,
+/// sending back its responses. Returns a `Future` that runs until the socket
+/// fails.
+///
+/// Requests that the service fails to handle are logged and dropped.
+pub fn serve<S>(socket: Socket, service: S) -> Serve<S>
+where
+    S: Service<Request = Multipart, Response = Multipart>,
+    S::Error: fmt::Display,
+{
+    Serve {
+        socket,
+        service,
+        in_flight: Vec::new(),
+        outgoing: VecDeque::new(),
+    }
+}
+
+/// A Future that serves requests on a `ROUTER` socket. This is returned by
+/// `serve`.
+pub struct Serve<S: Service> {
+    socket: Socket,
+    service: S,
+    in_flight: Vec<(Multipart, S::Future)>,
+    outgoing: VecDeque<Multipart>,
+}
+
+impl<S> Serve<S>
+where
+    S: Service<Request = Multipart, Response = Multipart>,
+    S::Error: fmt::Display,
+{
+    fn poll_requests(&mut self) -> Poll<(), io::Error> {
+        loop {
+            match self.service.poll_ready() {
+                Ok(Async::Ready(())) => {}
+                Ok(Async::NotReady) => return Ok(Async::NotReady),
+                Err(e) => return Err(io::Error::new(io::ErrorKind::Other, e.to_string())),
+            }
+            let mut stream = &self.socket;
+            let mut msgs = match try_ready!(stream.poll()) {
+                Some(msgs) => msgs,
+                None => return Ok(Async::Ready(())),
+            };
+            // the envelope ends with the request id, right after the delimiter.
+            match msgs.iter().position(|m| m.is_empty()) {
+                Some(idx) if msgs.len() > idx + 1 => {
+                    let body = msgs.split_off(idx + 2);
+                    let fut = self.service.call(body);
+                    self.in_flight.push((msgs, fut));
+                }
+                _ => warn!("dropping request without a valid envelope"),
+            }
+        }
+    }
+
+    fn poll_in_flight(&mut self) {
+        let mut idx = 0;
+        while idx < self.in_flight.len() {
+            match self.in_flight[idx].1.poll() {
+                Ok(Async::NotReady) => idx += 1,
+                Ok(Async::Ready(response)) => {
+                    let (mut envelope, _) = self.in_flight.swap_remove(idx);
+                    envelope.extend(response);
+                    self.outgoing.push_back(envelope);
+                }
+                Err(e) => {
+                    let _ = self.in_flight.swap_remove(idx);
+                    warn!("service failed to handle a request: {}", e);
+                }
+            }
+        }
+    }
+}
+
+impl<S> Future for Serve<S>
+where
+    S: Service<Request = Multipart, Response = Multipart>,
+    S::Error: fmt::Display,
+{
+    type Item = ();
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        self.poll_requests()?;
+        self.poll_in_flight();
+        flush(&self.socket, &mut self.outgoing)?;
+        Ok(Async::NotReady)
+    }
+}
+
+/// Once shut down, no request is read, while the responses to the requests
+/// in flight are sent back.
+impl<S> Drain for Serve<S>
+where
+    S: Service<Request = Multipart, Response = Multipart>,
+    S::Error: fmt::Display,
+{
+    fn poll_drain(&mut self) -> Poll<(), io::Error> {
+        self.poll_in_flight();
+        flush(&self.socket, &mut self.outgoing)?;
+        if self.in_flight.is_empty() && self.outgoing.is_empty() {
+            Ok(Async::Ready(()))
+        } else {
+            Ok(Async::NotReady)
+        }
+    }
+
+    fn sockets(&self) -> Vec<&Socket> {
+        vec![&self.socket]
+    }
+}
+
+fn encode_id(id: u64) -> Message {
+    let mut buf = [0u8; 8];
+    for (idx, b) in buf.iter_mut().enumerate() {
+        *b = (id >> (56 - 8 * idx)) as u8;
+    }
+    Message::from_slice(&buf)
+}
+
+fn decode_id(frame: &[u8]) -> Option<u64> {
+    if frame.len() != 8 {
+        return None;
+    }
+    Some(frame.iter().fold(0, |acc, b| (acc << 8) | u64::from(*b)))
+}
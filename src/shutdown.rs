@@ -0,0 +1,102 @@
+//! Graceful shutdown of serving loops.
+//!
+//! The loops of this crate, such as `service::serve`, `proxy::proxy`, the
+//! `mdp::Broker`, and the `load_balancer::LoadBalancer`, run until their
+//! sockets fail. They implement `Drain`, so `Drain::with_shutdown` can tie
+//! them to a shutdown signal, such as a `oneshot::Receiver`. Once the signal
+//! fires, the loop stops taking new work, finishes the work in flight,
+//! flushes its outgoing messages, and closes its sockets with a linger
+//! period, before resolving.
+use std::io;
+
+use futures::{Async, Future, Poll};
+
+use super::Socket;
+
+/// Default linger period of the sockets of a loop that shut down, in
+/// milliseconds.
+pub const DEFAULT_LINGER: i32 = 1000;
+
+/// A loop that can be drained before shutting down.
+pub trait Drain: Future<Item = (), Error = io::Error> {
+    /// Polls the loop while it shuts down: no new work is taken, while the
+    /// work in flight is finished, and outgoing messages are flushed. This
+    /// resolves once nothing is left to do.
+    fn poll_drain(&mut self) -> Poll<(), io::Error>;
+
+    /// References to the sockets of the loop, which are closed once it is
+    /// drained.
+    fn sockets(&self) -> Vec<&Socket>;
+
+    /// Run the loop until it ends, or until `signal` fires, resolving or
+    /// failing, after which the loop is drained.
+    fn with_shutdown<S: Future>(self, signal: S) -> Graceful<Self, S>
+    where
+        Self: Sized,
+    {
+        Graceful {
+            inner: Some(self),
+            signal,
+            draining: false,
+            linger: DEFAULT_LINGER,
+        }
+    }
+}
+
+/// A Future that runs a loop until a shutdown signal fires, and then
+/// drains it. This is returned by `Drain::with_shutdown`.
+pub struct Graceful<F, S> {
+    inner: Option<F>,
+    signal: S,
+    draining: bool,
+    linger: i32,
+}
+
+impl<F, S> Graceful<F, S> {
+    /// Set the linger period of the sockets of the loop once drained, in
+    /// milliseconds. `DEFAULT_LINGER` is used by default.
+    pub fn linger(mut self, linger: i32) -> Self {
+        self.linger = linger;
+        self
+    }
+
+    /// Returns true once the shutdown signal has fired.
+    pub fn is_draining(&self) -> bool {
+        self.draining
+    }
+}
+
+impl<F, S> Future for Graceful<F, S>
+where
+    F: Drain,
+    S: Future,
+{
+    type Item = ();
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        let linger = self.linger;
+        {
+            let inner = match self.inner {
+                Some(ref mut inner) => inner,
+                None => panic!("cannot poll Graceful twice"),
+            };
+            if !self.draining {
+                match self.signal.poll() {
+                    Ok(Async::NotReady) => return inner.poll(),
+                    _ => {
+                        debug!("shutting down");
+                        self.draining = true;
+                    }
+                }
+            }
+            try_ready!(inner.poll_drain());
+            for socket in inner.sockets() {
+                socket.set_linger(linger)?;
+            }
+        }
+        // dropping the loop closes its sockets.
+        self.inner = None;
+        Ok(Async::Ready(()))
+    }
+}
@@ -0,0 +1,57 @@
+//! Graceful termination on `SIGINT` and `SIGTERM`.
+//!
+//! With the `tokio-signal` feature, `terminated` is a shutdown signal that
+//! fires on the first `SIGINT` or `SIGTERM` received by the process, so
+//! services exit cleanly, draining their loops, instead of being killed, or
+//! calling `process::exit`:
+//!
+//! ```rust,ignore
+//! let server = proxy(frontend, backend);
+//! core.run(signal::until_terminated(server, &handle))?;
+//! ```
+use std::io;
+
+use futures::{Future, Poll, Stream};
+use tokio_core::reactor::Handle;
+use tokio_signal;
+use tokio_signal::unix::{Signal, SIGTERM};
+
+use super::shutdown::{Drain, Graceful};
+
+/// Returns a `Future` that resolves once the process receives `SIGINT` or
+/// `SIGTERM`.
+pub fn terminated(handle: &Handle) -> Terminated {
+    let interrupt = tokio_signal::ctrl_c(handle).flatten_stream();
+    let terminate = Signal::new(SIGTERM, handle)
+        .flatten_stream()
+        .map(|_| ());
+    let first = interrupt
+        .select(terminate)
+        .into_future()
+        .map(|_| ())
+        .map_err(|(e, _)| e);
+    Terminated {
+        inner: Box::new(first),
+    }
+}
+
+/// Run a loop until it ends, or until the process receives `SIGINT` or
+/// `SIGTERM`, after which the loop is drained.
+pub fn until_terminated<F: Drain>(serving: F, handle: &Handle) -> Graceful<F, Terminated> {
+    serving.with_shutdown(terminated(handle))
+}
+
+/// A Future that resolves once the process receives `SIGINT` or `SIGTERM`.
+/// This is returned by `terminated`.
+pub struct Terminated {
+    inner: Box<Future<Item = (), Error = io::Error>>,
+}
+
+impl Future for Terminated {
+    type Item = ();
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        self.inner.poll()
+    }
+}
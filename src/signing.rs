@@ -0,0 +1,131 @@
+//! Signing and verification of messages with ed25519.
+//!
+//! CURVE encrypts and authenticates each hop, but a broker relaying the
+//! messages can still alter them. A `SignedCodec` appends a signature frame
+//! to every outgoing message, and verifies and strips it from every
+//! incoming message, rejecting messages that aren't signed by a trusted
+//! key. This authenticates messages end-to-end.
+//!
+//! The signature covers every frame, each prefixed with its length as a
+//! big-endian `u32`, so frame boundaries can't be changed either.
+//!
+//! Enabled with the `ed25519` feature.
+use std::convert::TryFrom;
+use std::io;
+
+use ed25519_dalek::{Keypair, PublicKey, Signature, Signer, Verifier, SIGNATURE_LENGTH};
+use zmq::Message;
+
+use super::Multipart;
+use super::codec::{MessageCodec, MultipartCodec};
+
+/// A codec that signs the messages encoded by an inner codec, and verifies
+/// messages before the inner codec decodes them. Use it with
+/// `Socket::framed_with`.
+pub struct SignedCodec<C = MultipartCodec> {
+    inner: C,
+    keypair: Option<Keypair>,
+    trusted: Vec<PublicKey>,
+}
+
+impl SignedCodec<MultipartCodec> {
+    /// Sign raw multi-part messages with the given key pair. Only messages
+    /// signed with this key pair are accepted, until more keys are trusted.
+    pub fn new(keypair: Keypair) -> SignedCodec<MultipartCodec> {
+        SignedCodec::wrap(MultipartCodec, Some(keypair))
+    }
+
+    /// Verify raw multi-part messages, accepting those signed by any of the
+    /// given keys. Encoding fails, since there is no key pair to sign with.
+    pub fn verify_only(trusted: Vec<PublicKey>) -> SignedCodec<MultipartCodec> {
+        let mut codec = SignedCodec::wrap(MultipartCodec, None);
+        codec.trusted = trusted;
+        codec
+    }
+}
+
+impl<C: MessageCodec> SignedCodec<C> {
+    /// Sign the messages of an inner codec with the given key pair, if any,
+    /// trusting its public key.
+    pub fn wrap(inner: C, keypair: Option<Keypair>) -> SignedCodec<C> {
+        let trusted = keypair.iter().map(|keypair| keypair.public).collect();
+        SignedCodec {
+            inner,
+            keypair,
+            trusted,
+        }
+    }
+
+    /// Accept messages signed with the given public key.
+    pub fn trust(&mut self, key: PublicKey) -> &mut SignedCodec<C> {
+        if !self.trusted.contains(&key) {
+            self.trusted.push(key);
+        }
+        self
+    }
+
+    /// Stop accepting messages signed with the given public key.
+    pub fn distrust(&mut self, key: &PublicKey) -> &mut SignedCodec<C> {
+        self.trusted.retain(|trusted| trusted != key);
+        self
+    }
+
+    /// A reference to the inner codec.
+    pub fn get_ref(&self) -> &C {
+        &self.inner
+    }
+
+    /// A mutable reference to the inner codec.
+    pub fn get_mut(&mut self) -> &mut C {
+        &mut self.inner
+    }
+}
+
+impl<C: MessageCodec> MessageCodec for SignedCodec<C> {
+    type Item = C::Item;
+
+    fn encode(&mut self, item: C::Item) -> io::Result<Multipart> {
+        let mut msgs = self.inner.encode(item)?;
+        let signature = {
+            let keypair = self.keypair.as_ref().ok_or_else(|| {
+                io::Error::new(io::ErrorKind::InvalidInput, "no key pair to sign with")
+            })?;
+            keypair.sign(&signed_bytes(&msgs))
+        };
+        msgs.push(Message::from_slice(&signature.to_bytes()));
+        Ok(msgs)
+    }
+
+    fn decode(&mut self, mut msgs: Multipart) -> io::Result<C::Item> {
+        let signature = match msgs.pop() {
+            Some(ref frame) if frame.len() == SIGNATURE_LENGTH => {
+                Signature::try_from(&frame[..]).map_err(|_| invalid_signature())?
+            }
+            _ => return Err(invalid_signature()),
+        };
+        let bytes = signed_bytes(&msgs);
+        if !self.trusted
+            .iter()
+            .any(|key| key.verify(&bytes, &signature).is_ok())
+        {
+            return Err(invalid_signature());
+        }
+        self.inner.decode(msgs)
+    }
+}
+
+fn invalid_signature() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "invalid message signature")
+}
+
+// The bytes covered by the signature of a message.
+fn signed_bytes(msgs: &[Message]) -> Vec<u8> {
+    let len = msgs.iter().map(|msg| msg.len() + 4).sum();
+    let mut bytes = Vec::with_capacity(len);
+    for msg in msgs {
+        let n = msg.len() as u32;
+        bytes.extend_from_slice(&[(n >> 24) as u8, (n >> 16) as u8, (n >> 8) as u8, n as u8]);
+        bytes.extend_from_slice(msg);
+    }
+    bytes
+}
@@ -1,4 +1,5 @@
 //! Sinks for sockets.
+use std::collections::VecDeque;
 use std::io;
 use std::ops::Deref;
 
@@ -6,7 +7,8 @@ use futures::{Async, AsyncSink, Poll, Sink, StartSend};
 use tokio_io::AsyncWrite;
 use zmq;
 
-use super::SocketSend;
+use super::{Multipart, Socket, SocketSend};
+use super::error::with_peer;
 
 /// Single-message sink for sockets.
 pub struct MessageSink<'a, T: 'a> {
@@ -89,3 +91,63 @@ where
         Ok(Async::Ready(()))
     }
 }
+
+/// Multipart-message sink for a borrowed `Socket`, complementing its `Stream`
+/// implementation.
+///
+/// Frames are sent natively, using `zmq::SNDMORE` for every frame but the
+/// last. When the first frame would block, the whole message is handed back
+/// as `AsyncSink::NotReady`, and the task is notified once the socket becomes
+/// writable. ØMQ never blocks on the remaining frames of a message.
+impl<'a> Sink for &'a Socket {
+    type SinkItem = Multipart;
+    type SinkError = io::Error;
+
+    fn start_send(&mut self, item: Multipart) -> StartSend<Multipart, Self::SinkError> {
+        let last = match item.len() {
+            0 => return Ok(AsyncSink::Ready),
+            n => n - 1,
+        };
+        self.check_conflate(item.len())?;
+        let flags = if last > 0 { zmq::SNDMORE } else { 0 };
+        match SocketSend::send(*self, item[0].deref(), flags) {
+            Err(e) => {
+                if e.kind() == io::ErrorKind::WouldBlock {
+                    if let Some(ref metrics) = self.metrics {
+                        metrics.record_send_blocked();
+                    }
+                    return Ok(AsyncSink::NotReady(item));
+                } else {
+                    return Err(with_peer(e, &item[0]));
+                }
+            }
+            Ok(_) => {}
+        }
+        // the remaining frames never block once the first one is queued,
+        // but sending the last one may consume an edge of the socket.
+        for (idx, part) in item.iter().enumerate().skip(1) {
+            let flags = if idx < last { zmq::SNDMORE } else { 0 };
+            SocketSend::send(*self, part.deref(), flags)?;
+        }
+        if let Some(ref metrics) = self.metrics {
+            metrics.record_sent(&item);
+        }
+        Ok(AsyncSink::Ready)
+    }
+
+    fn poll_complete(&mut self) -> Poll<(), Self::SinkError> {
+        Ok(Async::Ready(()))
+    }
+}
+
+// Sends queued messages until the socket would block.
+pub(crate) fn flush(socket: &Socket, outgoing: &mut VecDeque<Multipart>) -> Poll<(), io::Error> {
+    let mut sink = socket;
+    while let Some(msgs) = outgoing.pop_front() {
+        if let AsyncSink::NotReady(msgs) = sink.start_send(msgs)? {
+            outgoing.push_front(msgs);
+            return Ok(Async::NotReady);
+        }
+    }
+    Ok(Async::Ready(()))
+}
@@ -0,0 +1,239 @@
+//! Spilling subscribers, which spool messages to disk.
+//!
+//! A `SUB` socket drops messages once its high-water mark is reached, which
+//! happens as soon as the application stalls for a moment on a busy feed.
+//! `spill` splits a stream of messages into a `Spill` future, to spawn on
+//! the reactor, which keeps reading the stream, and a `Spilled` stream for
+//! the application. Messages wait in memory, and go to a file once too many
+//! of them are waiting, so the socket is always drained. The application
+//! gets every message in order.
+//!
+//! The file uses the format of `recorder`, and is truncated every time the
+//! application catches up with it.
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufReader, BufWriter, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::rc::Rc;
+
+use futures::{task, Async, Future, Poll, Stream};
+use futures::task::Task;
+
+use super::Multipart;
+use super::recorder::{read_record, Recorder, MAGIC};
+
+/// Counters of a spilling subscriber.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SpillStats {
+    /// Messages that were written to the file.
+    pub spilled: u64,
+    /// Messages that were dropped because the file was full.
+    pub dropped: u64,
+}
+
+struct Spool {
+    memory: VecDeque<Multipart>,
+    max_memory: usize,
+    writer: Recorder<BufWriter<File>>,
+    reader: BufReader<File>,
+    // records and bytes written to the file, and not read yet.
+    disk_records: u64,
+    disk_bytes: u64,
+    max_disk_bytes: u64,
+    stats: SpillStats,
+    done: bool,
+    error: Option<io::Error>,
+    consumer: Option<Task>,
+}
+
+impl Spool {
+    fn push(&mut self, msgs: Multipart) -> io::Result<()> {
+        // once messages are on disk, newer ones follow them there.
+        if self.disk_records == 0 && self.memory.len() < self.max_memory {
+            self.memory.push_back(msgs);
+            return Ok(());
+        }
+        let size = record_size(&msgs);
+        if self.disk_bytes + size > self.max_disk_bytes {
+            self.stats.dropped += 1;
+            return Ok(());
+        }
+        self.writer.record_at(0, &msgs)?;
+        self.disk_records += 1;
+        self.disk_bytes += size;
+        self.stats.spilled += 1;
+        Ok(())
+    }
+
+    fn pop(&mut self) -> io::Result<Option<Multipart>> {
+        if let Some(msgs) = self.memory.pop_front() {
+            return Ok(Some(msgs));
+        }
+        if self.disk_records == 0 {
+            return Ok(None);
+        }
+        self.writer.flush()?;
+        let msgs = match read_record(&mut self.reader)? {
+            Some((_, msgs)) => msgs,
+            None => return Err(io::ErrorKind::UnexpectedEof.into()),
+        };
+        self.disk_records -= 1;
+        self.disk_bytes -= record_size(&msgs);
+        if self.disk_records == 0 {
+            self.reset()?;
+        }
+        Ok(Some(msgs))
+    }
+
+    // Empties the file, once every record was read.
+    fn reset(&mut self) -> io::Result<()> {
+        self.writer.flush()?;
+        {
+            let file = self.writer.get_mut().get_mut();
+            file.set_len(0)?;
+            file.seek(SeekFrom::Start(0))?;
+        }
+        self.writer.get_mut().write_all(MAGIC)?;
+        self.reader.seek(SeekFrom::Start(MAGIC.len() as u64))?;
+        Ok(())
+    }
+
+    fn notify(&mut self) {
+        if let Some(task) = self.consumer.take() {
+            task.notify();
+        }
+    }
+}
+
+fn record_size(msgs: &Multipart) -> u64 {
+    12 + msgs.iter().map(|msg| 4 + msg.len() as u64).sum::<u64>()
+}
+
+/// Split a stream into a `Spill` future, reading it, and a `Spilled` stream
+/// of its messages. Up to `max_memory` messages wait in memory, and up to
+/// `max_disk_bytes` bytes of messages in the file at the given path, which
+/// is replaced. Messages that don't fit are dropped.
+pub fn spill<S, P>(inner: S, path: P, max_memory: usize, max_disk_bytes: u64) -> io::Result<(Spill<S>, Spilled)>
+where
+    S: Stream<Item = Multipart, Error = io::Error>,
+    P: AsRef<Path>,
+{
+    // separate handles, with their own offsets, for writing and reading.
+    let file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(path.as_ref())?;
+    let writer = Recorder::new(BufWriter::new(file))?;
+    let mut reader = BufReader::new(File::open(path.as_ref())?);
+    reader.seek(SeekFrom::Start(MAGIC.len() as u64))?;
+    let spool = Rc::new(RefCell::new(Spool {
+        memory: VecDeque::new(),
+        max_memory,
+        writer,
+        reader,
+        disk_records: 0,
+        disk_bytes: 0,
+        max_disk_bytes,
+        stats: SpillStats::default(),
+        done: false,
+        error: None,
+        consumer: None,
+    }));
+    Ok((
+        Spill {
+            inner,
+            spool: spool.clone(),
+        },
+        Spilled { spool },
+    ))
+}
+
+/// A Future that reads every message of a stream into a spool, until the
+/// stream ends. It should be spawned on the reactor. This is returned by
+/// `spill`.
+pub struct Spill<S> {
+    inner: S,
+    spool: Rc<RefCell<Spool>>,
+}
+
+impl<S> Spill<S> {
+    /// Returns the counters of the spool.
+    pub fn stats(&self) -> SpillStats {
+        self.spool.borrow().stats
+    }
+}
+
+impl<S> Future for Spill<S>
+where
+    S: Stream<Item = Multipart, Error = io::Error>,
+{
+    type Item = ();
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        loop {
+            let polled = self.inner.poll();
+            let mut spool = self.spool.borrow_mut();
+            let result = match polled {
+                Ok(Async::Ready(Some(msgs))) => spool.push(msgs),
+                Ok(Async::Ready(None)) => {
+                    spool.done = true;
+                    spool.notify();
+                    return Ok(Async::Ready(()));
+                }
+                Ok(Async::NotReady) => return Ok(Async::NotReady),
+                Err(e) => Err(e),
+            };
+            spool.notify();
+            if let Err(e) = result {
+                // the consumer fails with the error, after the spooled messages.
+                let reported = io::Error::new(e.kind(), e.to_string());
+                spool.error = Some(e);
+                spool.done = true;
+                return Err(reported);
+            }
+        }
+    }
+}
+
+/// A `Stream` of the messages read by a `Spill`, in order. It ends once the
+/// `Spill` resolved and every message was yielded, or fails after every
+/// message when the `Spill` failed. This is returned by `spill`.
+pub struct Spilled {
+    spool: Rc<RefCell<Spool>>,
+}
+
+impl Spilled {
+    /// Returns the number of messages waiting, in memory and on disk.
+    pub fn pending(&self) -> u64 {
+        let spool = self.spool.borrow();
+        spool.memory.len() as u64 + spool.disk_records
+    }
+
+    /// Returns the counters of the spool.
+    pub fn stats(&self) -> SpillStats {
+        self.spool.borrow().stats
+    }
+}
+
+impl Stream for Spilled {
+    type Item = Multipart;
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        let mut spool = self.spool.borrow_mut();
+        if let Some(msgs) = spool.pop()? {
+            return Ok(Async::Ready(Some(msgs)));
+        }
+        if let Some(e) = spool.error.take() {
+            return Err(e);
+        }
+        if spool.done {
+            return Ok(Async::Ready(None));
+        }
+        spool.consumer = Some(task::current());
+        Ok(Async::NotReady)
+    }
+}
@@ -5,7 +5,9 @@ use futures::{Async, Poll, Stream};
 use tokio_io::{AsyncRead, AsyncWrite};
 use zmq;
 
-use super::{SocketRecv, SocketSend};
+use super::{Multipart, Socket, SocketRecv, SocketSend};
+use super::error::is_recoverable;
+use super::future::{check_message_size, check_received_size, recv_batch, recv_multipart_msgs};
 
 /// Single-message stream for sockets.
 pub struct MessageStream<'a, T: 'a> {
@@ -38,7 +40,10 @@ where
                     Err(e)
                 }
             }
-            Ok(_) => Ok(Async::Ready(Some(buf))),
+            Ok(_) => {
+                check_received_size(self.socket, buf.len())?;
+                Ok(Async::Ready(Some(buf)))
+            }
         }
     }
 }
@@ -65,7 +70,7 @@ where
     type Error = io::Error;
 
     fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
-        match SocketRecv::recv_multipart(self.socket, 0) {
+        match recv_multipart_msgs(self.socket) {
             Err(e) => {
                 if e.kind() == io::ErrorKind::WouldBlock {
                     Ok(Async::NotReady)
@@ -73,13 +78,123 @@ where
                     Err(e)
                 }
             }
-            Ok(vecs) => {
-                let msgs = vecs.iter().map(|v| {
-                    v.into()
-                }).collect();
-                Ok(Async::Ready(Some(msgs)))
+            Ok(msgs) => Ok(Async::Ready(Some(msgs))),
+        }
+    }
+}
+
+
+/// Batched multipart-message stream for sockets. Every item holds up to
+/// `max` messages, drained on a single readiness notification.
+pub struct MultipartBatchStream<'a, T: 'a> {
+    socket: &'a T,
+    max: usize,
+}
+
+impl<'a, T> MultipartBatchStream<'a, T>
+where
+    T: AsyncRead + SocketRecv + 'a,
+{
+    pub fn new(socket: &'a T, max: usize) -> MultipartBatchStream<'a, T> {
+        assert!(max > 0, "batch size must be non-zero");
+        MultipartBatchStream { socket, max }
+    }
+}
+
+impl<'a, T> Stream for MultipartBatchStream<'a, T>
+where
+    T: AsyncRead + SocketRecv + 'a,
+{
+    type Item = Vec<Multipart>;
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        let batch = try_ready!(recv_batch(self.socket, self.max));
+        Ok(Async::Ready(Some(batch)))
+    }
+}
+
+/// A stream yielding the items of another stream as `Ok`, and its
+/// recoverable errors as `Err`, instead of ending on them. Other errors
+/// still end the stream. See `error::is_recoverable`.
+pub struct ResultStream<S> {
+    stream: S,
+}
+
+impl<S> ResultStream<S>
+where
+    S: Stream<Error = io::Error>,
+{
+    pub fn new(stream: S) -> ResultStream<S> {
+        ResultStream { stream }
+    }
+
+    /// Consumes the stream, returning the inner stream.
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+}
+
+impl<S> Stream for ResultStream<S>
+where
+    S: Stream<Error = io::Error>,
+{
+    type Item = Result<S::Item, io::Error>;
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        match self.stream.poll() {
+            Ok(Async::Ready(item)) => Ok(Async::Ready(item.map(Ok))),
+            Ok(Async::NotReady) => Ok(Async::NotReady),
+            Err(e) => {
+                if is_recoverable(&e) {
+                    Ok(Async::Ready(Some(Err(e))))
+                } else {
+                    Err(e)
+                }
             }
         }
     }
 }
 
+/// Multipart-message stream for a borrowed `Socket`, yielding every incoming
+/// message without going through `framed()`.
+///
+/// Frames are received natively into `zmq::Message`s, one at a time, so no
+/// intermediate byte-vectors or fixed-size buffers are involved.
+impl<'a> Stream for &'a Socket {
+    type Item = Multipart;
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        let mut first = zmq::Message::new();
+        match SocketRecv::recv(*self, &mut first, 0) {
+            Err(e) => {
+                if e.kind() == io::ErrorKind::WouldBlock {
+                    if let Some(ref metrics) = self.metrics {
+                        metrics.record_recv_blocked();
+                    }
+                    return Ok(Async::NotReady);
+                } else {
+                    return Err(e);
+                }
+            }
+            Ok(_) => {}
+        }
+        // ØMQ delivers multi-part messages atomically, so once the first
+        // frame has arrived, the remaining ones are ready to be received.
+        let mut more = first.get_more();
+        let mut msgs = vec![first];
+        while more {
+            let mut part = zmq::Message::new();
+            self.registration()?.get_ref().recv(&mut part, 0)?;
+            more = part.get_more();
+            msgs.push(part);
+        }
+        if let Some(ref metrics) = self.metrics {
+            metrics.record_received(&msgs);
+        }
+        check_message_size(*self, &msgs)?;
+        Ok(Async::Ready(Some(msgs)))
+    }
+}
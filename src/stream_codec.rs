@@ -0,0 +1,170 @@
+//! `tokio_io` codecs over the peers of a `STREAM` socket.
+//!
+//! Enabled with the `bytes` feature.
+//!
+//! Every peer of a `STREAM` socket is a plain TCP byte-stream. A
+//! `StreamFramed` keeps a read buffer per peer, and decodes it with a
+//! `tokio_io::codec::Decoder`, such as `length_delimited`, so that
+//! frames split over several reads, or several frames in one read, are
+//! handled like on a `TcpStream`. Outgoing items are encoded with the
+//! matching `Encoder`, and sent to the given peer.
+use std::collections::{HashMap, VecDeque};
+use std::io;
+
+use bytes::BytesMut;
+use futures::{Async, AsyncSink, Poll, Sink, StartSend, Stream};
+use tokio_io::codec::{Decoder, Encoder};
+use zmq::Message;
+
+use super::{Multipart, RoutingId, Socket};
+use super::sink::flush;
+use super::tcp::{StreamEvent, StreamListener};
+
+/// Items yielded by a `StreamFramed`.
+#[derive(Debug)]
+pub enum PeerEvent<T> {
+    /// A peer has connected.
+    Connected(RoutingId),
+    /// A frame was decoded from the data of a peer.
+    Frame(RoutingId, T),
+    /// A peer has disconnected.
+    Disconnected(RoutingId),
+}
+
+/// A transport for `STREAM` sockets, decoding the byte-stream of every peer
+/// with a codec. It is a `Stream` of `PeerEvent`s, and a `Sink` of items to
+/// encode, paired with the peer they go to.
+pub struct StreamFramed<C> {
+    listener: StreamListener,
+    codec: C,
+    buffers: HashMap<RoutingId, BytesMut>,
+    // The peer whose buffer may hold more frames.
+    pending: Option<RoutingId>,
+    ready: VecDeque<PeerEvent<<C as Decoder>::Item>>,
+    outgoing: VecDeque<Multipart>,
+}
+
+impl<C> StreamFramed<C>
+where
+    C: Decoder<Error = io::Error>,
+{
+    /// Wrap a `STREAM` socket, decoding the data of its peers with `codec`.
+    pub fn new(socket: Socket, codec: C) -> StreamFramed<C> {
+        StreamFramed {
+            listener: StreamListener::new(socket),
+            codec,
+            buffers: HashMap::new(),
+            pending: None,
+            ready: VecDeque::new(),
+            outgoing: VecDeque::new(),
+        }
+    }
+
+    /// A reference to the underlying socket.
+    pub fn get_ref(&self) -> &Socket {
+        self.listener.get_ref()
+    }
+
+    /// A reference to the codec.
+    pub fn codec(&self) -> &C {
+        &self.codec
+    }
+
+    /// A mutable reference to the codec.
+    pub fn codec_mut(&mut self) -> &mut C {
+        &mut self.codec
+    }
+
+    /// The peers that are currently connected.
+    pub fn peers(&self) -> Vec<RoutingId> {
+        self.listener.peers()
+    }
+
+    // Decodes the next frame buffered for the pending peer, if any.
+    fn decode_pending(&mut self) -> io::Result<Option<PeerEvent<C::Item>>> {
+        let peer = match self.pending.take() {
+            Some(peer) => peer,
+            None => return Ok(None),
+        };
+        let frame = match self.buffers.get_mut(&peer) {
+            Some(buf) => self.codec.decode(buf)?,
+            None => None,
+        };
+        Ok(frame.map(|frame| {
+            self.pending = Some(peer.clone());
+            PeerEvent::Frame(peer, frame)
+        }))
+    }
+
+    // Decodes what is left in the buffer of a disconnected peer.
+    fn disconnect(&mut self, peer: RoutingId) -> io::Result<()> {
+        if let Some(mut buf) = self.buffers.remove(&peer) {
+            while !buf.is_empty() {
+                match self.codec.decode_eof(&mut buf)? {
+                    Some(frame) => self.ready.push_back(PeerEvent::Frame(peer.clone(), frame)),
+                    None => break,
+                }
+            }
+        }
+        self.ready.push_back(PeerEvent::Disconnected(peer));
+        Ok(())
+    }
+}
+
+impl<C> Stream for StreamFramed<C>
+where
+    C: Decoder<Error = io::Error>,
+{
+    type Item = PeerEvent<C::Item>;
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        loop {
+            if let Some(event) = self.ready.pop_front() {
+                return Ok(Async::Ready(Some(event)));
+            }
+            if let Some(event) = self.decode_pending()? {
+                return Ok(Async::Ready(Some(event)));
+            }
+            match try_ready!(self.listener.poll()) {
+                Some(StreamEvent::Connected(peer)) => {
+                    self.buffers.insert(peer.clone(), BytesMut::new());
+                    return Ok(Async::Ready(Some(PeerEvent::Connected(peer))));
+                }
+                Some(StreamEvent::Data(peer, data)) => {
+                    self.buffers
+                        .entry(peer.clone())
+                        .or_insert_with(BytesMut::new)
+                        .extend_from_slice(&data);
+                    self.pending = Some(peer);
+                }
+                Some(StreamEvent::Disconnected(peer)) => self.disconnect(peer)?,
+                None => return Ok(Async::Ready(None)),
+            }
+        }
+    }
+}
+
+impl<C> Sink for StreamFramed<C>
+where
+    C: Decoder<Error = io::Error> + Encoder<Error = io::Error>,
+{
+    type SinkItem = (RoutingId, <C as Encoder>::Item);
+    type SinkError = io::Error;
+
+    fn start_send(&mut self, item: Self::SinkItem) -> StartSend<Self::SinkItem, Self::SinkError> {
+        if !self.outgoing.is_empty() && self.poll_complete()?.is_not_ready() {
+            return Ok(AsyncSink::NotReady(item));
+        }
+        let (peer, item) = item;
+        let mut buf = BytesMut::new();
+        self.codec.encode(item, &mut buf)?;
+        self.outgoing
+            .push_back(vec![Message::from_slice(&peer), Message::from_slice(&buf)]);
+        Ok(AsyncSink::Ready)
+    }
+
+    fn poll_complete(&mut self) -> Poll<(), Self::SinkError> {
+        flush(self.listener.get_ref(), &mut self.outgoing)
+    }
+}
@@ -0,0 +1,170 @@
+//! Subscription messages of `XPUB` and `XSUB` sockets.
+//!
+//! `XSUB` sockets subscribe by sending a message whose first byte is 1,
+//! followed by the prefix, and unsubscribe with a first byte of 0. `XPUB`
+//! sockets receive those messages from their subscribers. Pub/sub
+//! intermediaries forward them upstream, and may send data messages along,
+//! which can be told apart with `classify`.
+//!
+//! As subscribers that join late miss what was published before,
+//! `Socket::wait_for_subscribers` counts the subscriptions an `XPUB` socket
+//! receives, until enough subscribers joined.
+use std::io;
+use std::time::Duration;
+
+use futures::{Async, Future, Poll, Stream};
+use tokio_core::reactor::{Handle, Timeout};
+use zmq::Message;
+
+use super::{Multipart, Socket};
+
+/// Returns the message subscribing an `XSUB` socket to a prefix.
+pub fn subscribe_message(prefix: &[u8]) -> Message {
+    control_message(1, prefix)
+}
+
+/// Returns the message unsubscribing an `XSUB` socket from a prefix.
+pub fn unsubscribe_message(prefix: &[u8]) -> Message {
+    control_message(0, prefix)
+}
+
+fn control_message(kind: u8, prefix: &[u8]) -> Message {
+    let mut bytes = Vec::with_capacity(prefix.len() + 1);
+    bytes.push(kind);
+    bytes.extend_from_slice(prefix);
+    Message::from_slice(&bytes)
+}
+
+/// A message sent upstream by a subscriber.
+#[derive(Debug)]
+pub enum SubscriptionMessage {
+    /// A subscription to the prefix.
+    Subscribe(Vec<u8>),
+    /// An unsubscription from the prefix.
+    Unsubscribe(Vec<u8>),
+    /// Any other message, such as data sent upstream.
+    Data(Multipart),
+}
+
+impl SubscriptionMessage {
+    /// Classify a message. Only single-frame messages starting with 0 or 1
+    /// are subscription messages.
+    pub fn parse(msgs: Multipart) -> SubscriptionMessage {
+        if msgs.len() == 1 {
+            match msgs[0].first() {
+                Some(&1) => return SubscriptionMessage::Subscribe(msgs[0][1..].to_vec()),
+                Some(&0) => return SubscriptionMessage::Unsubscribe(msgs[0][1..].to_vec()),
+                _ => {}
+            }
+        }
+        SubscriptionMessage::Data(msgs)
+    }
+
+    /// Returns the message back, for forwarding it.
+    pub fn into_multipart(self) -> Multipart {
+        match self {
+            SubscriptionMessage::Subscribe(prefix) => vec![subscribe_message(&prefix)],
+            SubscriptionMessage::Unsubscribe(prefix) => vec![unsubscribe_message(&prefix)],
+            SubscriptionMessage::Data(msgs) => msgs,
+        }
+    }
+}
+
+/// Classify every message of a stream, such as that of an `XPUB` socket.
+pub fn classify<S>(inner: S) -> Classify<S>
+where
+    S: Stream<Item = Multipart, Error = io::Error>,
+{
+    Classify { inner }
+}
+
+/// A `Stream` adapter yielding `SubscriptionMessage`s. This is returned by
+/// `classify`.
+pub struct Classify<S> {
+    inner: S,
+}
+
+impl<S> Classify<S> {
+    /// A reference to the wrapped stream.
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+
+    /// Consumes the adapter, returning the wrapped stream.
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S> Stream for Classify<S>
+where
+    S: Stream<Item = Multipart, Error = io::Error>,
+{
+    type Item = SubscriptionMessage;
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        let msgs = try_ready!(self.inner.poll());
+        Ok(Async::Ready(msgs.map(SubscriptionMessage::parse)))
+    }
+}
+
+/// A Future that resolves into the number of subscribers of an `XPUB`
+/// socket, once enough of them joined. It fails with `TimedOut` when they
+/// don't join in time. This is returned by `Socket::wait_for_subscribers`.
+///
+/// Every subscription counts as a subscriber, and every unsubscription as
+/// one leaving, so subscribers should subscribe to a single prefix. The
+/// subscription messages are consumed, and data messages are dropped.
+pub struct WaitForSubscribers<'a> {
+    socket: &'a Socket,
+    wanted: usize,
+    subscribers: usize,
+    timeout: Timeout,
+}
+
+impl<'a> WaitForSubscribers<'a> {
+    pub fn new(socket: &'a Socket, wanted: usize, timeout: Duration, handle: &Handle) -> io::Result<WaitForSubscribers<'a>> {
+        socket.set_xpub_verbose(true)?;
+        Ok(WaitForSubscribers {
+            socket,
+            wanted,
+            subscribers: 0,
+            timeout: Timeout::new(timeout, handle)?,
+        })
+    }
+}
+
+impl<'a> Future for WaitForSubscribers<'a> {
+    type Item = usize;
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        while self.subscribers < self.wanted {
+            let mut stream = self.socket;
+            match stream.poll()? {
+                Async::Ready(Some(msgs)) => match SubscriptionMessage::parse(msgs) {
+                    SubscriptionMessage::Subscribe(_) => self.subscribers += 1,
+                    SubscriptionMessage::Unsubscribe(_) => {
+                        self.subscribers = self.subscribers.saturating_sub(1)
+                    }
+                    SubscriptionMessage::Data(_) => {}
+                },
+                Async::Ready(None) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "socket closed before subscribers joined",
+                    ))
+                }
+                Async::NotReady => {
+                    try_ready!(self.timeout.poll());
+                    return Err(io::Error::new(
+                        io::ErrorKind::TimedOut,
+                        format!("{} of {} subscribers joined", self.subscribers, self.wanted),
+                    ));
+                }
+            }
+        }
+        Ok(Async::Ready(self.subscribers))
+    }
+}
@@ -0,0 +1,117 @@
+//! Capturing message traffic.
+//!
+//! A `Tap` wraps a `Stream` or a `Sink` of multipart-messages, and sends a
+//! copy of every message that goes through it to a capture socket, like the
+//! capture socket of `zmq_proxy`. The capture socket is typically a `PUB`
+//! or `PUSH` socket, read by a debugging or auditing tool.
+//!
+//! Capturing never slows the wrapped traffic down: copies that the capture
+//! socket can't accept right away are dropped, and counted.
+use std::io;
+
+use futures::{Async, AsyncSink, Poll, Sink, StartSend, Stream};
+
+use super::{Message, Multipart, Socket};
+use super::error::TrySendError;
+
+/// Wrap a `Stream` or a `Sink`, capturing its messages on the given socket.
+pub fn tap<S>(inner: S, capture: Socket) -> Tap<S> {
+    Tap::new(inner, capture)
+}
+
+/// A `Stream` or `Sink` adapter that copies every message to a capture
+/// socket. This is returned by `tap`.
+pub struct Tap<S> {
+    inner: S,
+    capture: Socket,
+    dropped: u64,
+}
+
+impl<S> Tap<S> {
+    pub fn new(inner: S, capture: Socket) -> Tap<S> {
+        Tap {
+            inner,
+            capture,
+            dropped: 0,
+        }
+    }
+
+    /// A reference to the wrapped stream or sink.
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+
+    /// A mutable reference to the wrapped stream or sink.
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+
+    /// Returns the number of copies dropped because the capture socket
+    /// couldn't accept them.
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    /// Consumes the tap, returning the wrapped stream or sink, and the
+    /// capture socket.
+    pub fn into_inner(self) -> (S, Socket) {
+        (self.inner, self.capture)
+    }
+
+    fn capture(&mut self, msgs: Multipart) -> io::Result<()> {
+        match self.capture.try_send(msgs) {
+            Ok(()) => Ok(()),
+            Err(TrySendError::Full(_)) => {
+                self.dropped += 1;
+                debug!("capture socket is full, dropping copy");
+                Ok(())
+            }
+            Err(TrySendError::Io(e)) => Err(e),
+        }
+    }
+}
+
+// Copies every frame of a message.
+fn copy(msgs: &[Message]) -> Multipart {
+    msgs.iter().map(|msg| Message::from_slice(msg)).collect()
+}
+
+impl<S> Stream for Tap<S>
+where
+    S: Stream<Item = Multipart, Error = io::Error>,
+{
+    type Item = Multipart;
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        let msgs = match try_ready!(self.inner.poll()) {
+            Some(msgs) => msgs,
+            None => return Ok(Async::Ready(None)),
+        };
+        self.capture(copy(&msgs))?;
+        Ok(Async::Ready(Some(msgs)))
+    }
+}
+
+impl<S> Sink for Tap<S>
+where
+    S: Sink<SinkItem = Multipart, SinkError = io::Error>,
+{
+    type SinkItem = Multipart;
+    type SinkError = io::Error;
+
+    fn start_send(&mut self, item: Multipart) -> StartSend<Self::SinkItem, Self::SinkError> {
+        let copied = copy(&item);
+        match self.inner.start_send(item)? {
+            AsyncSink::Ready => {
+                self.capture(copied)?;
+                Ok(AsyncSink::Ready)
+            }
+            AsyncSink::NotReady(item) => Ok(AsyncSink::NotReady(item)),
+        }
+    }
+
+    fn poll_complete(&mut self) -> Poll<(), Self::SinkError> {
+        self.inner.poll_complete()
+    }
+}
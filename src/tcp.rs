@@ -0,0 +1,192 @@
+//! Raw TCP interoperability, with `STREAM` sockets.
+//!
+//! A `STREAM` socket talks plain TCP to non-ØMQ peers. Every message it
+//! receives is made of the peer's identity followed by a frame of data. A
+//! zero-length data frame signals that the peer connected, the first time,
+//! or disconnected, afterwards.
+use std::collections::HashSet;
+use std::io;
+
+use futures::{Async, Future, Poll, Stream};
+
+use super::{Multipart, RoutingId, Socket};
+use super::future::SendMultipartMessage;
+
+/// Events received on a `STREAM` socket.
+#[derive(Clone, Debug, PartialEq)]
+pub enum StreamEvent {
+    /// A peer has connected.
+    Connected(RoutingId),
+    /// A peer has sent some data.
+    Data(RoutingId, Vec<u8>),
+    /// A peer has disconnected.
+    Disconnected(RoutingId),
+}
+
+/// A `STREAM` socket that accepts raw TCP connections, yielding the events of
+/// all of its peers as a `Stream`.
+pub struct StreamListener {
+    socket: Socket,
+    peers: HashSet<RoutingId>,
+}
+
+impl StreamListener {
+    /// Create a new listener for a `STREAM` socket.
+    pub fn new(socket: Socket) -> StreamListener {
+        StreamListener {
+            socket,
+            peers: HashSet::new(),
+        }
+    }
+
+    /// A reference to the underlying socket.
+    pub fn get_ref(&self) -> &Socket {
+        &self.socket
+    }
+
+    /// Returns true if the given peer is connected.
+    pub fn is_connected(&self, peer: &RoutingId) -> bool {
+        self.peers.contains(peer)
+    }
+
+    /// The peers that are currently connected.
+    pub fn peers(&self) -> Vec<RoutingId> {
+        self.peers.iter().cloned().collect()
+    }
+
+    /// Sends data to a peer. Returns a `Future`.
+    pub fn send<T: Into<Vec<u8>>>(&self, peer: &RoutingId, data: T) -> SendMultipartMessage {
+        self.socket.send_multipart(vec![peer.to_vec(), data.into()])
+    }
+
+    /// Closes the connection to a peer. Returns a `Future`.
+    pub fn disconnect(&self, peer: &RoutingId) -> SendMultipartMessage {
+        self.socket.send_multipart(vec![peer.to_vec(), Vec::new()])
+    }
+
+    fn decode(&mut self, mut msgs: Multipart) -> io::Result<StreamEvent> {
+        if msgs.len() != 2 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("expected identity and data frames, got {} parts", msgs.len()),
+            ));
+        }
+        let data = msgs.pop().unwrap();
+        let peer = RoutingId::from(&msgs[0][..]);
+        if !data.is_empty() {
+            return Ok(StreamEvent::Data(peer, data.to_vec()));
+        }
+        if self.peers.insert(peer.clone()) {
+            Ok(StreamEvent::Connected(peer))
+        } else {
+            self.peers.remove(&peer);
+            Ok(StreamEvent::Disconnected(peer))
+        }
+    }
+}
+
+impl Stream for StreamListener {
+    type Item = StreamEvent;
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        let msgs = {
+            let mut stream = &self.socket;
+            try_ready!(stream.poll())
+        };
+        match msgs {
+            Some(msgs) => Ok(Async::Ready(Some(self.decode(msgs)?))),
+            None => Ok(Async::Ready(None)),
+        }
+    }
+}
+
+/// Connects a `STREAM` socket to a raw TCP endpoint. Returns a `Future` that
+/// resolves into a `StreamClient` once the connection is established.
+pub fn connect(socket: Socket, endpoint: &str) -> io::Result<StreamConnect> {
+    socket.connect(endpoint)?;
+    Ok(StreamConnect {
+        listener: Some(StreamListener::new(socket)),
+    })
+}
+
+/// A Future that resolves into a `StreamClient`. This is returned by `connect`.
+pub struct StreamConnect {
+    listener: Option<StreamListener>,
+}
+
+impl Future for StreamConnect {
+    type Item = StreamClient;
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        loop {
+            let event = {
+                let listener = self.listener.as_mut().expect("polled StreamConnect after completion");
+                try_ready!(listener.poll())
+            };
+            match event {
+                Some(StreamEvent::Connected(peer)) => {
+                    let listener = self.listener.take().unwrap();
+                    return Ok(Async::Ready(StreamClient { listener, peer }));
+                }
+                Some(event) => debug!("ignoring event before connecting: {:?}", event),
+                None => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "socket closed before connecting",
+                    ))
+                }
+            }
+        }
+    }
+}
+
+/// A raw TCP connection, made with a `STREAM` socket. It is a `Stream` of
+/// the data received from the peer, which ends when the peer disconnects.
+pub struct StreamClient {
+    listener: StreamListener,
+    peer: RoutingId,
+}
+
+impl StreamClient {
+    /// The identity of the peer.
+    pub fn peer(&self) -> &RoutingId {
+        &self.peer
+    }
+
+    /// A reference to the underlying socket.
+    pub fn get_ref(&self) -> &Socket {
+        self.listener.get_ref()
+    }
+
+    /// Sends data to the peer. Returns a `Future`.
+    pub fn send<T: Into<Vec<u8>>>(&self, data: T) -> SendMultipartMessage {
+        self.listener.send(&self.peer, data)
+    }
+
+    /// Closes the connection. Returns a `Future`.
+    pub fn close(&self) -> SendMultipartMessage {
+        self.listener.disconnect(&self.peer)
+    }
+}
+
+impl Stream for StreamClient {
+    type Item = Vec<u8>;
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        loop {
+            match try_ready!(self.listener.poll()) {
+                Some(StreamEvent::Data(ref peer, ref data)) if *peer == self.peer => {
+                    return Ok(Async::Ready(Some(data.clone())));
+                }
+                Some(StreamEvent::Disconnected(ref peer)) if *peer == self.peer => {
+                    return Ok(Async::Ready(None));
+                }
+                Some(event) => debug!("ignoring event from another peer: {:?}", event),
+                None => return Ok(Async::Ready(None)),
+            }
+        }
+    }
+}
@@ -0,0 +1,428 @@
+//! Helpers for testing code built on this crate.
+//!
+//! A `MockSocket` is an in-memory socket implementing `SocketSend` and
+//! `SocketRecv`, so the streams, sinks and futures built on those traits
+//! can be unit-tested without real ØMQ endpoints. Its readiness is
+//! scriptable: it can fail with `WouldBlock` a number of times before
+//! delivering, to exercise the `NotReady` paths of a future chain.
+//!
+//! For integration tests, `pair` and `tcp_pair` create two real sockets
+//! connected to each other, and `loopback` creates two connected ends that
+//! don't involve libzmq at all. Messages sent on a loopback end are
+//! available on the other end right away, so tests built on it don't depend
+//! on connection timing, such as slow-joining subscribers.
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::io::{self, Read, Write};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering, ATOMIC_USIZE_INIT};
+
+use futures::{task, Async, AsyncSink, Poll, Sink, StartSend, Stream};
+use futures::task::Task;
+use tokio_core::reactor::Handle;
+use tokio_io::{AsyncRead, AsyncWrite};
+use zmq::{self, Message, Sendable, SocketType};
+
+use super::{Context, Multipart, Socket, SocketRecv, SocketSend};
+
+static NEXT_PAIR: AtomicUsize = ATOMIC_USIZE_INIT;
+
+/// Create two sockets of a new context, the first bound to a unique
+/// `inproc` endpoint, and the second connected to it. A `SUB` socket is
+/// subscribed to every message.
+pub fn pair(bound: SocketType, connected: SocketType, handle: &Handle) -> io::Result<(Socket, Socket)> {
+    let endpoint = format!("inproc://pair-{}", NEXT_PAIR.fetch_add(1, Ordering::SeqCst));
+    let ctx = Context::new();
+    let a = new_socket(&ctx, bound, handle)?;
+    let b = new_socket(&ctx, connected, handle)?;
+    a.bind(&endpoint)?;
+    b.connect(&endpoint)?;
+    Ok((a, b))
+}
+
+/// Same as `pair`, over `tcp`, with the first socket bound to an ephemeral
+/// port of the loopback interface.
+pub fn tcp_pair(bound: SocketType, connected: SocketType, handle: &Handle) -> io::Result<(Socket, Socket)> {
+    let ctx = Context::new();
+    let a = new_socket(&ctx, bound, handle)?;
+    let b = new_socket(&ctx, connected, handle)?;
+    let addr = a.bind_ephemeral("tcp://127.0.0.1")?;
+    b.connect(addr)?;
+    Ok((a, b))
+}
+
+fn new_socket(ctx: &Context, typ: SocketType, handle: &Handle) -> io::Result<Socket> {
+    let socket = ctx.socket(typ, handle)?;
+    if typ == zmq::SUB {
+        socket.set_subscribe(b"")?;
+    }
+    Ok(socket)
+}
+
+#[derive(Default)]
+struct MockState {
+    incoming: VecDeque<Vec<Vec<u8>>>,
+    rcvmore: bool,
+    sent: Vec<Vec<Vec<u8>>>,
+    partial: Vec<Vec<u8>>,
+    recv_blocks: usize,
+    send_blocks: usize,
+    closed: bool,
+    recv_task: Option<Task>,
+    send_task: Option<Task>,
+    // turns `Sendable`s into bytes, which only libzmq can do.
+    loopback: Option<(zmq::Socket, zmq::Socket, zmq::Context)>,
+}
+
+/// An in-memory socket, receiving scripted messages and recording the
+/// messages sent through it.
+///
+/// Operations that would block register the current task, which is
+/// notified when the socket can make progress, so a `MockSocket` must be
+/// used from within a task, such as a future run by `Future::wait`.
+#[derive(Default)]
+pub struct MockSocket {
+    state: RefCell<MockState>,
+}
+
+impl MockSocket {
+    /// Create a new mock socket, with nothing to receive.
+    pub fn new() -> MockSocket {
+        MockSocket::default()
+    }
+
+    /// Queue a multipart-message to be received.
+    pub fn push<I, T>(&self, msgs: I)
+    where
+        I: IntoIterator<Item = T>,
+        T: AsRef<[u8]>,
+    {
+        let mut state = self.state.borrow_mut();
+        state
+            .incoming
+            .push_back(msgs.into_iter().map(|m| m.as_ref().to_vec()).collect());
+        if let Some(task) = state.recv_task.take() {
+            task.notify();
+        }
+    }
+
+    /// Fail the next `n` receive operations with `WouldBlock`, before
+    /// delivering the queued messages.
+    pub fn block_recv(&self, n: usize) {
+        self.state.borrow_mut().recv_blocks = n;
+    }
+
+    /// Fail the next `n` send operations with `WouldBlock`, before
+    /// accepting messages.
+    pub fn block_send(&self, n: usize) {
+        self.state.borrow_mut().send_blocks = n;
+    }
+
+    /// Close the socket, so every further operation fails with
+    /// `BrokenPipe`, as if the socket had failed.
+    pub fn close(&self) {
+        let mut state = self.state.borrow_mut();
+        state.closed = true;
+        for task in state.recv_task.take().into_iter().chain(state.send_task.take()) {
+            task.notify();
+        }
+    }
+
+    /// Returns the number of queued messages, not yet received.
+    pub fn pending(&self) -> usize {
+        self.state.borrow().incoming.len()
+    }
+
+    /// Returns the multipart-messages sent so far, oldest first.
+    pub fn sent(&self) -> Vec<Vec<Vec<u8>>> {
+        self.state.borrow().sent.clone()
+    }
+
+    /// Returns and forgets the multipart-messages sent so far.
+    pub fn take_sent(&self) -> Vec<Vec<Vec<u8>>> {
+        let mut state = self.state.borrow_mut();
+        ::std::mem::replace(&mut state.sent, Vec::new())
+    }
+
+    // Returns the next frame, or fails as scripted.
+    fn next_frame(&self) -> io::Result<Vec<u8>> {
+        let mut state = self.state.borrow_mut();
+        if state.closed {
+            return Err(io::ErrorKind::BrokenPipe.into());
+        }
+        if state.recv_blocks > 0 {
+            state.recv_blocks -= 1;
+            // scripted blocks end on their own, so poll again right away.
+            task::current().notify();
+            return Err(io::ErrorKind::WouldBlock.into());
+        }
+        if state.incoming.is_empty() {
+            state.recv_task = Some(task::current());
+            return Err(io::ErrorKind::WouldBlock.into());
+        }
+        let mut msgs = state.incoming.pop_front().unwrap();
+        let frame = if msgs.is_empty() { Vec::new() } else { msgs.remove(0) };
+        state.rcvmore = !msgs.is_empty();
+        if state.rcvmore {
+            state.incoming.push_front(msgs);
+        }
+        Ok(frame)
+    }
+
+    // Fails as scripted, when the socket cannot send.
+    fn poll_send(&self) -> io::Result<()> {
+        let mut state = self.state.borrow_mut();
+        if state.closed {
+            return Err(io::ErrorKind::BrokenPipe.into());
+        }
+        if state.send_blocks > 0 {
+            state.send_blocks -= 1;
+            task::current().notify();
+            return Err(io::ErrorKind::WouldBlock.into());
+        }
+        Ok(())
+    }
+
+    // Records a sent frame.
+    fn record(&self, frame: Vec<u8>, flags: i32) {
+        let mut state = self.state.borrow_mut();
+        state.partial.push(frame);
+        if flags & zmq::SNDMORE == 0 {
+            let msgs = ::std::mem::replace(&mut state.partial, Vec::new());
+            state.sent.push(msgs);
+        }
+    }
+
+    fn sendable_bytes<T: Sendable>(&self, msg: T) -> io::Result<Vec<u8>> {
+        let mut state = self.state.borrow_mut();
+        if state.loopback.is_none() {
+            let ctx = zmq::Context::new();
+            let tx = ctx.socket(zmq::PAIR)?;
+            let rx = ctx.socket(zmq::PAIR)?;
+            rx.bind("inproc://mock")?;
+            tx.connect("inproc://mock")?;
+            state.loopback = Some((tx, rx, ctx));
+        }
+        let (ref tx, ref rx, _) = *state.loopback.as_ref().unwrap();
+        tx.send(msg, 0)?;
+        Ok(rx.recv_bytes(0)?)
+    }
+}
+
+impl SocketSend for MockSocket {
+    fn send<T>(&self, msg: T, flags: i32) -> io::Result<()>
+    where
+        T: Sendable,
+    {
+        self.poll_send()?;
+        let frame = self.sendable_bytes(msg)?;
+        self.record(frame, flags);
+        Ok(())
+    }
+
+    fn send_multipart<I, T>(&self, iter: I, flags: i32) -> io::Result<()>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<Message>,
+    {
+        self.poll_send()?;
+        let mut frames: Vec<Vec<u8>> = iter.into_iter().map(|m| m.into().to_vec()).collect();
+        let last = frames.pop().unwrap_or_default();
+        for frame in frames {
+            self.record(frame, flags | zmq::SNDMORE);
+        }
+        self.record(last, flags);
+        Ok(())
+    }
+
+    fn send_zero_copy<T>(&self, data: T, flags: i32) -> io::Result<()>
+    where
+        T: AsRef<[u8]> + Send + 'static,
+    {
+        self.poll_send()?;
+        self.record(data.as_ref().to_vec(), flags);
+        Ok(())
+    }
+}
+
+impl SocketRecv for MockSocket {
+    fn get_rcvmore(&self) -> io::Result<bool> {
+        Ok(self.state.borrow().rcvmore)
+    }
+
+    fn recv(&self, buf: &mut Message, _flags: i32) -> io::Result<()> {
+        *buf = Message::from_slice(&self.next_frame()?);
+        Ok(())
+    }
+
+    fn recv_into(&self, buf: &mut [u8], _flags: i32) -> io::Result<usize> {
+        let frame = self.next_frame()?;
+        let len = ::std::cmp::min(buf.len(), frame.len());
+        buf[..len].copy_from_slice(&frame[..len]);
+        Ok(frame.len())
+    }
+
+    fn recv_msg(&self, _flags: i32) -> io::Result<Message> {
+        Ok(Message::from_slice(&self.next_frame()?))
+    }
+
+    fn recv_bytes(&self, _flags: i32) -> io::Result<Vec<u8>> {
+        self.next_frame()
+    }
+
+    fn recv_string(&self, _flags: i32) -> io::Result<Result<String, Vec<u8>>> {
+        Ok(String::from_utf8(self.next_frame()?).map_err(|e| e.into_bytes()))
+    }
+
+    fn recv_multipart(&self, _flags: i32) -> io::Result<Vec<Vec<u8>>> {
+        let mut frames = vec![self.next_frame()?];
+        while self.state.borrow().rcvmore {
+            frames.push(self.next_frame()?);
+        }
+        Ok(frames)
+    }
+}
+
+impl Read for MockSocket {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        SocketRecv::recv_into(&*self, buf, 0)
+    }
+}
+
+impl Write for MockSocket {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.poll_send()?;
+        self.record(buf.to_vec(), 0);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl AsyncRead for MockSocket {}
+
+impl AsyncWrite for MockSocket {
+    fn shutdown(&mut self) -> Poll<(), io::Error> {
+        Ok(().into())
+    }
+}
+
+/// Create two connected loopback ends, each holding at most `hwm` incoming
+/// messages, or any number of them when `hwm` is 0.
+pub fn loopback(hwm: usize) -> (Loopback, Loopback) {
+    let a = Arc::new(Mutex::new(Channel::new(hwm)));
+    let b = Arc::new(Mutex::new(Channel::new(hwm)));
+    (
+        Loopback {
+            incoming: a.clone(),
+            outgoing: b.clone(),
+        },
+        Loopback {
+            incoming: b,
+            outgoing: a,
+        },
+    )
+}
+
+// Messages going one way between loopback ends.
+struct Channel {
+    queue: VecDeque<Multipart>,
+    hwm: usize,
+    closed: bool,
+    recv_task: Option<Task>,
+    send_task: Option<Task>,
+}
+
+impl Channel {
+    fn new(hwm: usize) -> Channel {
+        Channel {
+            queue: VecDeque::new(),
+            hwm,
+            closed: false,
+            recv_task: None,
+            send_task: None,
+        }
+    }
+
+    fn close(&mut self) {
+        self.closed = true;
+        for task in self.recv_task.take().into_iter().chain(self.send_task.take()) {
+            task.notify();
+        }
+    }
+}
+
+/// One end of a loopback connection, created by `loopback`.
+///
+/// Like `&Socket`, it is a `Stream` and a `Sink` of multipart-messages.
+/// Sending holds back with `AsyncSink::NotReady` while the other end has
+/// `hwm` messages waiting, and fails with `BrokenPipe` once the other end
+/// is dropped. The stream ends once the other end is dropped, and every
+/// message it sent was received.
+pub struct Loopback {
+    incoming: Arc<Mutex<Channel>>,
+    outgoing: Arc<Mutex<Channel>>,
+}
+
+impl Loopback {
+    /// Returns the number of messages waiting to be received on this end.
+    pub fn pending(&self) -> usize {
+        self.incoming.lock().unwrap().queue.len()
+    }
+}
+
+impl Stream for Loopback {
+    type Item = Multipart;
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        let mut channel = self.incoming.lock().unwrap();
+        match channel.queue.pop_front() {
+            Some(msgs) => {
+                if let Some(task) = channel.send_task.take() {
+                    task.notify();
+                }
+                Ok(Async::Ready(Some(msgs)))
+            }
+            None if channel.closed => Ok(Async::Ready(None)),
+            None => {
+                channel.recv_task = Some(task::current());
+                Ok(Async::NotReady)
+            }
+        }
+    }
+}
+
+impl Sink for Loopback {
+    type SinkItem = Multipart;
+    type SinkError = io::Error;
+
+    fn start_send(&mut self, item: Multipart) -> StartSend<Self::SinkItem, Self::SinkError> {
+        let mut channel = self.outgoing.lock().unwrap();
+        if channel.closed {
+            return Err(io::ErrorKind::BrokenPipe.into());
+        }
+        if channel.hwm > 0 && channel.queue.len() >= channel.hwm {
+            channel.send_task = Some(task::current());
+            return Ok(AsyncSink::NotReady(item));
+        }
+        channel.queue.push_back(item);
+        if let Some(task) = channel.recv_task.take() {
+            task.notify();
+        }
+        Ok(AsyncSink::Ready)
+    }
+
+    fn poll_complete(&mut self) -> Poll<(), Self::SinkError> {
+        Ok(Async::Ready(()))
+    }
+}
+
+impl Drop for Loopback {
+    fn drop(&mut self) {
+        self.incoming.lock().unwrap().close();
+        self.outgoing.lock().unwrap().close();
+    }
+}
@@ -0,0 +1,179 @@
+//! Rate limiting for sinks.
+//!
+//! A `Throttle` wraps a `Sink` of multipart-messages, and holds messages
+//! back while they would exceed a rate of messages or bytes per second.
+//! Rates are enforced with token buckets, which allow bursts of up to one
+//! second worth of traffic. This smooths a bursty publisher out before it
+//! overwhelms slow subscribers, which would drop messages at their
+//! high-water mark.
+use std::cmp;
+use std::io;
+use std::time::{Duration, Instant};
+
+use futures::{Async, AsyncSink, Future, Poll, Sink, StartSend};
+use tokio_core::reactor::{Handle, Timeout};
+
+use super::Multipart;
+
+// A token bucket, holding at most one second worth of tokens.
+struct Bucket {
+    rate: f64,
+    tokens: f64,
+    last: Instant,
+}
+
+impl Bucket {
+    fn new(rate: f64) -> Bucket {
+        Bucket {
+            rate,
+            tokens: rate,
+            last: Instant::now(),
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now - self.last;
+        let elapsed = elapsed.as_secs() as f64 + f64::from(elapsed.subsec_nanos()) / 1e9;
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.rate);
+        self.last = now;
+    }
+
+    // Returns how long to wait until `cost` tokens are available. Costs
+    // larger than the bucket only need a full bucket, so they can pass.
+    fn wait(&self, cost: f64) -> Option<Duration> {
+        let needed = cost.min(self.rate);
+        if self.tokens >= needed {
+            None
+        } else {
+            let secs = (needed - self.tokens) / self.rate;
+            Some(Duration::new(secs as u64, (secs.fract() * 1e9) as u32))
+        }
+    }
+
+    fn take(&mut self, cost: f64) {
+        self.tokens -= cost;
+    }
+}
+
+/// A `Sink` adapter that limits the rate of messages going into the
+/// wrapped sink.
+pub struct Throttle<S> {
+    inner: S,
+    handle: Handle,
+    messages: Option<Bucket>,
+    bytes: Option<Bucket>,
+    delay: Option<Timeout>,
+}
+
+impl<S> Throttle<S>
+where
+    S: Sink<SinkItem = Multipart, SinkError = io::Error>,
+{
+    /// Wrap a sink, without any limit until one is set.
+    pub fn new(inner: S, handle: &Handle) -> Throttle<S> {
+        Throttle {
+            inner,
+            handle: handle.clone(),
+            messages: None,
+            bytes: None,
+            delay: None,
+        }
+    }
+
+    /// Limit the number of multipart-messages sent per second.
+    pub fn messages_per_sec(mut self, rate: u32) -> Throttle<S> {
+        assert!(rate > 0, "rate must be non-zero");
+        self.messages = Some(Bucket::new(f64::from(rate)));
+        self
+    }
+
+    /// Limit the number of bytes sent per second, over every frame.
+    pub fn bytes_per_sec(mut self, rate: u64) -> Throttle<S> {
+        assert!(rate > 0, "rate must be non-zero");
+        self.bytes = Some(Bucket::new(rate as f64));
+        self
+    }
+
+    /// A reference to the wrapped sink.
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+
+    /// A mutable reference to the wrapped sink.
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+
+    /// Consumes the throttle, returning the wrapped sink.
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+
+    // Returns how long to wait before sending a message of `len` bytes.
+    fn wait(&mut self, len: usize) -> Option<Duration> {
+        let now = Instant::now();
+        let mut wait = None;
+        if let Some(ref mut bucket) = self.messages {
+            bucket.refill(now);
+            wait = longest(wait, bucket.wait(1.0));
+        }
+        if let Some(ref mut bucket) = self.bytes {
+            bucket.refill(now);
+            wait = longest(wait, bucket.wait(len as f64));
+        }
+        wait
+    }
+
+    fn take(&mut self, len: usize) {
+        if let Some(ref mut bucket) = self.messages {
+            bucket.take(1.0);
+        }
+        if let Some(ref mut bucket) = self.bytes {
+            bucket.take(len as f64);
+        }
+    }
+}
+
+impl<S> Sink for Throttle<S>
+where
+    S: Sink<SinkItem = Multipart, SinkError = io::Error>,
+{
+    type SinkItem = Multipart;
+    type SinkError = io::Error;
+
+    fn start_send(&mut self, item: Multipart) -> StartSend<Self::SinkItem, Self::SinkError> {
+        if let Some(ref mut delay) = self.delay {
+            if let Async::NotReady = delay.poll()? {
+                return Ok(AsyncSink::NotReady(item));
+            }
+        }
+        self.delay = None;
+        let len = item.iter().map(|msg| msg.len()).sum();
+        if let Some(wait) = self.wait(len) {
+            let mut delay = Timeout::new(wait, &self.handle)?;
+            // registers the task for the end of the delay.
+            if let Async::NotReady = delay.poll()? {
+                self.delay = Some(delay);
+                return Ok(AsyncSink::NotReady(item));
+            }
+        }
+        match self.inner.start_send(item)? {
+            AsyncSink::Ready => {
+                self.take(len);
+                Ok(AsyncSink::Ready)
+            }
+            AsyncSink::NotReady(item) => Ok(AsyncSink::NotReady(item)),
+        }
+    }
+
+    fn poll_complete(&mut self) -> Poll<(), Self::SinkError> {
+        self.inner.poll_complete()
+    }
+}
+
+fn longest(a: Option<Duration>, b: Option<Duration>) -> Option<Duration> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(cmp::max(a, b)),
+        (a, b) => a.or(b),
+    }
+}
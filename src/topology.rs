@@ -0,0 +1,199 @@
+//! Wiring several sockets from one configuration.
+//!
+//! A `TopologyConfig` names the sockets of a process, each described by a
+//! `SocketConfig`, and the proxies to run between them. `Topology::start`
+//! creates every socket, spawns the proxies on the reactor, and hands the
+//! other sockets over by name. Shutting the topology down stops the proxies.
+use std::collections::BTreeMap;
+#[cfg(feature = "serde")]
+use std::fmt;
+use std::io;
+
+use futures::{Async, Future, Poll};
+use futures::future::Either;
+use futures::sync::oneshot;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Deserializer};
+#[cfg(feature = "serde")]
+use serde::de::{self, MapAccess, Visitor};
+use tokio_core::reactor::Handle;
+
+use super::{Context, Socket};
+use super::config::SocketConfig;
+use super::proxy::proxy;
+
+/// A proxy between two named sockets.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProxyConfig {
+    /// The name of the frontend socket.
+    pub frontend: String,
+    /// The name of the backend socket.
+    pub backend: String,
+}
+
+/// The sockets of a process, by name, and the proxies between them.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TopologyConfig {
+    /// The sockets, by name.
+    pub sockets: BTreeMap<String, SocketConfig>,
+    /// The proxies. Each socket can be part of one proxy at most.
+    pub proxies: Vec<ProxyConfig>,
+}
+
+/// The sockets of a running topology, by name, except for those taken over
+/// by its proxies.
+pub struct Topology {
+    sockets: BTreeMap<String, Socket>,
+    stop: Vec<oneshot::Sender<()>>,
+    done: Vec<oneshot::Receiver<io::Result<()>>>,
+}
+
+impl Topology {
+    /// Create the configured sockets in `context`, and spawn the proxies on
+    /// the given reactor handle.
+    pub fn start(config: &TopologyConfig, context: &Context, handle: &Handle) -> io::Result<Topology> {
+        let mut sockets = BTreeMap::new();
+        for (name, socket) in &config.sockets {
+            sockets.insert(name.clone(), context.socket_from_config(socket, handle)?);
+        }
+        let mut topology = Topology {
+            sockets,
+            stop: Vec::new(),
+            done: Vec::new(),
+        };
+        for proxy in &config.proxies {
+            let frontend = topology.take_for_proxy(&proxy.frontend)?;
+            let backend = topology.take_for_proxy(&proxy.backend)?;
+            topology.spawn_proxy(frontend, backend, handle);
+        }
+        Ok(topology)
+    }
+
+    fn take_for_proxy(&mut self, name: &str) -> io::Result<Socket> {
+        self.sockets.remove(name).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("proxy socket {} is not configured, or already proxied", name),
+            )
+        })
+    }
+
+    fn spawn_proxy(&mut self, frontend: Socket, backend: Socket, handle: &Handle) {
+        let (stop_tx, stop_rx) = oneshot::channel();
+        let (done_tx, done_rx) = oneshot::channel();
+        let task = proxy(frontend, backend).select2(stop_rx).then(move |result| {
+            let result = match result {
+                Err(Either::A((e, _))) => Err(e),
+                _ => Ok(()),
+            };
+            if let Err(ref e) = result {
+                error!("proxy failed: {}", e);
+            }
+            let _ = done_tx.send(result);
+            Ok::<(), ()>(())
+        });
+        handle.spawn(task);
+        self.stop.push(stop_tx);
+        self.done.push(done_rx);
+    }
+
+    /// A reference to the socket with the given name.
+    pub fn get(&self, name: &str) -> Option<&Socket> {
+        self.sockets.get(name)
+    }
+
+    /// Take the socket with the given name out of the topology.
+    pub fn take(&mut self, name: &str) -> Option<Socket> {
+        self.sockets.remove(name)
+    }
+
+    /// The names of the sockets left in the topology.
+    pub fn names(&self) -> Vec<String> {
+        self.sockets.keys().cloned().collect()
+    }
+
+    /// Take every socket left in the topology, by name.
+    pub fn take_all(&mut self) -> BTreeMap<String, Socket> {
+        ::std::mem::replace(&mut self.sockets, BTreeMap::new())
+    }
+
+    /// Stop the proxies, and close the sockets left in the topology.
+    /// Returns a `Future` that resolves once every proxy has stopped,
+    /// failing with the first error of a proxy, if any.
+    pub fn shutdown(self) -> Shutdown {
+        for stop in self.stop {
+            let _ = stop.send(());
+        }
+        Shutdown { done: self.done }
+    }
+}
+
+/// A Future that resolves once the proxies of a topology have stopped.
+/// This is returned by `Topology::shutdown`.
+pub struct Shutdown {
+    done: Vec<oneshot::Receiver<io::Result<()>>>,
+}
+
+impl Future for Shutdown {
+    type Item = ();
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        while let Some(mut done) = self.done.pop() {
+            match done.poll() {
+                Ok(Async::NotReady) => {
+                    self.done.push(done);
+                    return Ok(Async::NotReady);
+                }
+                Ok(Async::Ready(result)) => result?,
+                // the proxy task was dropped with the reactor.
+                Err(_) => {}
+            }
+        }
+        Ok(Async::Ready(()))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for ProxyConfig {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<ProxyConfig, D::Error> {
+        let mut map = BTreeMap::<String, String>::deserialize(deserializer)?;
+        let mut take = |key: &'static str| {
+            map.remove(key)
+                .ok_or_else(|| <D::Error as de::Error>::missing_field(key))
+        };
+        Ok(ProxyConfig {
+            frontend: take("frontend")?,
+            backend: take("backend")?,
+        })
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for TopologyConfig {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<TopologyConfig, D::Error> {
+        struct TopologyConfigVisitor;
+
+        impl<'de> Visitor<'de> for TopologyConfigVisitor {
+            type Value = TopologyConfig;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a topology configuration")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<TopologyConfig, A::Error> {
+                let mut config = TopologyConfig::default();
+                while let Some(key) = map.next_key::<String>()? {
+                    match key.as_str() {
+                        "sockets" => config.sockets = map.next_value()?,
+                        "proxies" => config.proxies = map.next_value()?,
+                        key => return Err(de::Error::unknown_field(key, &["sockets", "proxies"])),
+                    }
+                }
+                Ok(config)
+            }
+        }
+
+        deserializer.deserialize_map(TopologyConfigVisitor)
+    }
+}
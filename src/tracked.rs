@@ -0,0 +1,126 @@
+//! `PUSH` sockets that know their peers.
+//!
+//! A `PUSH` socket without peers queues messages until a `PULL` socket
+//! connects, and then hands them all to that first worker, before the
+//! others had a chance to connect. A `TrackedPush` monitors its socket,
+//! counts the connected peers, and doesn't accept messages while there are
+//! none.
+//!
+//! Peers are seen through monitor events, which libzmq only reports for
+//! connection-oriented transports, such as `tcp` and `ipc`.
+use std::collections::{HashMap, VecDeque};
+use std::io;
+
+use futures::{Async, AsyncSink, Poll, Sink, StartSend, Stream};
+use tokio_core::reactor::Handle;
+
+use super::{Context, Multipart, Socket};
+use super::monitor::{Event, Monitor, MonitorEvent};
+
+// Events kept for the stream, dropping the oldest ones beyond this.
+const MAX_PENDING_EVENTS: usize = 64;
+
+/// Changes in the peers of a `TrackedPush`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PeerEvent {
+    /// A peer connected, on the given endpoint.
+    Connected(String),
+    /// A peer disconnected, from the given endpoint.
+    Disconnected(String),
+}
+
+/// A `Sink` for a `PUSH` socket that holds messages back while no peer is
+/// connected, and a `Stream` of its `PeerEvent`s.
+pub struct TrackedPush {
+    socket: Socket,
+    monitor: Monitor,
+    // connected peers, by file descriptor, with their endpoint.
+    peers: HashMap<u32, String>,
+    events: VecDeque<PeerEvent>,
+}
+
+impl TrackedPush {
+    /// Monitor a `PUSH` socket, in the given context. It should be created
+    /// before the socket binds or connects, so no peer is missed.
+    pub fn new(context: &Context, socket: Socket, handle: &Handle) -> io::Result<TrackedPush> {
+        let events = [Event::Connected, Event::Accepted, Event::Disconnected]
+            .iter()
+            .fold(0, |acc, event| acc | i32::from(event.to_raw()));
+        let monitor = Monitor::new(context, &socket, events, handle)?;
+        Ok(TrackedPush {
+            socket,
+            monitor,
+            peers: HashMap::new(),
+            events: VecDeque::new(),
+        })
+    }
+
+    /// A reference to the underlying socket.
+    pub fn get_ref(&self) -> &Socket {
+        &self.socket
+    }
+
+    /// Returns the number of connected peers, as of the last poll.
+    pub fn connected_peers(&self) -> usize {
+        self.peers.len()
+    }
+
+    // Applies every pending monitor event to the peers.
+    fn poll_monitor(&mut self) -> io::Result<()> {
+        while let Async::Ready(Some(event)) = self.monitor.poll()? {
+            if let Some(event) = self.track(event) {
+                if self.events.len() == MAX_PENDING_EVENTS {
+                    self.events.pop_front();
+                }
+                self.events.push_back(event);
+            }
+        }
+        Ok(())
+    }
+
+    fn track(&mut self, event: MonitorEvent) -> Option<PeerEvent> {
+        match event.event {
+            Event::Connected | Event::Accepted => {
+                self.peers.insert(event.value, event.endpoint.clone());
+                Some(PeerEvent::Connected(event.endpoint))
+            }
+            Event::Disconnected => self.peers
+                .remove(&event.value)
+                .map(|_| PeerEvent::Disconnected(event.endpoint)),
+            _ => None,
+        }
+    }
+}
+
+impl Sink for TrackedPush {
+    type SinkItem = Multipart;
+    type SinkError = io::Error;
+
+    fn start_send(&mut self, item: Multipart) -> StartSend<Self::SinkItem, Self::SinkError> {
+        self.poll_monitor()?;
+        if self.peers.is_empty() {
+            // the monitor wakes the task up once a peer connects.
+            return Ok(AsyncSink::NotReady(item));
+        }
+        let mut sink = &self.socket;
+        sink.start_send(item)
+    }
+
+    fn poll_complete(&mut self) -> Poll<(), Self::SinkError> {
+        let mut sink = &self.socket;
+        sink.poll_complete()
+    }
+}
+
+impl Stream for TrackedPush {
+    type Item = PeerEvent;
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        self.poll_monitor()?;
+        match self.events.pop_front() {
+            Some(event) => Ok(Async::Ready(Some(event))),
+            None => Ok(Async::NotReady),
+        }
+    }
+}
@@ -1,12 +1,15 @@
 //! Tokio transports for sockets.
 use std::io;
-use std::ops::{Deref, DerefMut};
+use std::ops::Deref;
 
-use futures::{Async, AsyncSink, Poll, Sink, StartSend, Stream};
+use futures::{task, Async, AsyncSink, Poll, Sink, StartSend, Stream};
 use tokio_io::{AsyncRead, AsyncWrite};
 use zmq;
 
 use super::{SocketRecv, SocketSend};
+use super::future::{check_received_size, recv_multipart_msgs};
+use super::pool::{MessagePool, PooledBuffer};
+use super::stream::ResultStream;
 
 /// Tokio transport for one-part messages.
 pub struct MessageTransport<'a, T: 'a> {
@@ -66,7 +69,10 @@ where
                     Err(e)
                 }
             }
-            Ok(_) => Ok(Async::Ready(Some(buf))),
+            Ok(_) => {
+                check_received_size(self.socket, buf.len())?;
+                Ok(Async::Ready(Some(buf)))
+            }
         }
     }
 }
@@ -121,7 +127,7 @@ where
     type Error = io::Error;
 
     fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
-        match SocketRecv::recv_multipart(self.socket, 0) {
+        match recv_multipart_msgs(self.socket) {
             Err(e) => {
                 if e.kind() == io::ErrorKind::WouldBlock {
                     Ok(Async::NotReady)
@@ -129,19 +135,19 @@ where
                     Err(e)
                 }
             }
-            Ok(vecs) => {
-                let msgs = vecs.iter().map(|v| {
-                    v.into()
-                }).collect();
-                Ok(Async::Ready(Some(msgs)))
-            }
+            Ok(msgs) => Ok(Async::Ready(Some(msgs))),
         }
     }
 }
 
 /// A custom transport type for `Socket`.
+///
+/// Messages are received straight into a `zmq::Message`, whatever their
+/// size, without going through an intermediate buffer.
 pub struct SocketFramed<T> {
     socket: T,
+    poll_limit: Option<usize>,
+    polled: usize,
 }
 
 impl<T> SocketFramed<T>
@@ -149,7 +155,63 @@ where
     T: AsyncRead + AsyncWrite,
 {
     pub fn new(socket: T) -> Self {
-        SocketFramed { socket: socket }
+        SocketFramed {
+            socket: socket,
+            poll_limit: None,
+            polled: 0,
+        }
+    }
+
+    /// Limit the number of consecutive messages the stream yields before
+    /// handing control back to the reactor, so that a busy socket can't
+    /// starve other tasks running on the same `Core`. The task is notified
+    /// right away, so it gets polled again on the next reactor turn.
+    ///
+    /// No limit is set by default.
+    pub fn with_poll_limit(mut self, limit: usize) -> Self {
+        assert!(limit > 0, "poll limit must be non-zero");
+        self.poll_limit = Some(limit);
+        self
+    }
+
+    /// Consumes the transport, returning a stream of `Result`s, which goes
+    /// on after recoverable errors, such as interrupted calls, leaving the
+    /// application to decide when to give up.
+    pub fn into_result_stream(self) -> ResultStream<Self>
+    where
+        T: SocketRecv,
+    {
+        ResultStream::new(self)
+    }
+
+    /// Consumes the transport, returning a stream that receives frames into
+    /// buffers taken from the given pool, instead of allocating a message
+    /// for each of them. Frames larger than the buffers of the pool fail
+    /// with `error::MessageTooLarge`.
+    pub fn pooled(self, pool: &MessagePool) -> PooledSocketFramed<T>
+    where
+        T: SocketRecv,
+    {
+        PooledSocketFramed {
+            framed: self,
+            pool: pool.clone(),
+        }
+    }
+}
+
+impl<T> SocketFramed<T> {
+    // Returns true once the stream must yield to the reactor, as set by
+    // `with_poll_limit`, notifying the task to be polled again.
+    fn poll_limit_reached(&mut self) -> bool {
+        if let Some(limit) = self.poll_limit {
+            if self.polled >= limit {
+                trace!("SocketFramed::poll() yielding after {} messages", self.polled);
+                self.polled = 0;
+                task::current().notify();
+                return true;
+            }
+        }
+        false
     }
 }
 
@@ -185,24 +247,63 @@ where
 // TODO: Make this generic using a codec
 impl<T> Stream for SocketFramed<T>
 where
-    T: AsyncRead,
+    T: AsyncRead + SocketRecv,
 {
     type Item = zmq::Message;
     type Error = io::Error;
 
     fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
-        let mut buf = zmq::Message::with_capacity(1024);
         trace!("SocketFramed::poll()");
-        match self.socket.read(buf.deref_mut()) {
+        if self.poll_limit_reached() {
+            return Ok(Async::NotReady);
+        }
+        match SocketRecv::recv_msg(&self.socket, 0) {
+            Err(e) => {
+                if e.kind() == io::ErrorKind::WouldBlock {
+                    self.polled = 0;
+                    Ok(Async::NotReady)
+                } else {
+                    Err(e)
+                }
+            }
+            Ok(msg) => {
+                self.polled += 1;
+                check_received_size(&self.socket, msg.len())?;
+                Ok(Async::Ready(Some(msg)))
+            }
+        }
+    }
+}
+
+/// A stream of frames received into pooled buffers. This is returned by
+/// `SocketFramed::pooled`
+pub struct PooledSocketFramed<T> {
+    framed: SocketFramed<T>,
+    pool: MessagePool,
+}
+
+impl<T> Stream for PooledSocketFramed<T>
+where
+    T: AsyncRead + SocketRecv,
+{
+    type Item = PooledBuffer;
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        if self.framed.poll_limit_reached() {
+            return Ok(Async::NotReady);
+        }
+        match self.pool.recv(&self.framed.socket) {
             Err(e) => {
                 if e.kind() == io::ErrorKind::WouldBlock {
+                    self.framed.polled = 0;
                     Ok(Async::NotReady)
                 } else {
                     Err(e)
                 }
             }
-            Ok(c) => {
-                buf = zmq::Message::from_slice(&buf[..c]);
+            Ok(buf) => {
+                self.framed.polled += 1;
                 Ok(Async::Ready(Some(buf)))
             }
         }
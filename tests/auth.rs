@@ -0,0 +1,21 @@
+extern crate zmq_tokio;
+
+use std::net::IpAddr;
+
+use zmq_tokio::auth::IpRange;
+
+#[test]
+fn ip_ranges_match_prefixes() {
+    let addr = |text: &str| text.parse::<IpAddr>().unwrap();
+    let range = IpRange::parse("10.0.0.0/8").unwrap();
+    assert!(range.contains(&addr("10.1.2.3")));
+    assert!(!range.contains(&addr("11.0.0.1")));
+    assert!(!range.contains(&addr("::1")));
+
+    let single = IpRange::parse("192.168.1.7").unwrap();
+    assert!(single.contains(&addr("192.168.1.7")));
+    assert!(!single.contains(&addr("192.168.1.8")));
+
+    assert!(IpRange::parse("0.0.0.0/0").unwrap().contains(&addr("8.8.8.8")));
+    assert!(IpRange::parse("10.0.0.0/33").is_err());
+}
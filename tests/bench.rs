@@ -0,0 +1,49 @@
+extern crate tokio_core;
+extern crate zmq_tokio;
+
+use std::time::Duration;
+
+use tokio_core::reactor::Core;
+use zmq_tokio::{testing, PAIR};
+use zmq_tokio::bench::{throughput, Histogram, LatencyProbe, ThroughputConfig};
+
+macro_rules! t {
+    ($e:expr) => (match $e {
+        Ok(e) => e,
+        Err(e) => panic!("{} failed with {:?}", stringify!($e), e),
+    })
+}
+
+#[test]
+fn histogram_percentiles() {
+    let samples = (1..101).map(Duration::from_millis).collect();
+    let histogram = Histogram::new(samples);
+    assert_eq!(histogram.len(), 100);
+    assert_eq!(histogram.min(), Duration::from_millis(1));
+    assert_eq!(histogram.p50(), Duration::from_millis(50));
+    assert_eq!(histogram.p99(), Duration::from_millis(99));
+    assert_eq!(histogram.p999(), Duration::from_millis(100));
+}
+
+#[test]
+fn latency_probe_collects_samples() {
+    let mut core = t!(Core::new());
+    let (client, echo) = t!(testing::pair(PAIR, PAIR, &core.handle()));
+    let histogram = t!(core.run(LatencyProbe::new(client, echo, 64, 10)));
+    assert_eq!(histogram.len(), 10);
+    assert!(histogram.min() <= histogram.max());
+}
+
+#[test]
+fn throughput_reports_rates() {
+    let mut core = t!(Core::new());
+    let config = ThroughputConfig {
+        size: 10,
+        count: 1000,
+        ..ThroughputConfig::default()
+    };
+    let report = t!(core.run(t!(throughput(config, &core.handle()))));
+    assert_eq!(report.messages, 1000);
+    assert_eq!(report.bytes, 10_000);
+    assert!(report.messages_per_sec() > 0.0);
+}
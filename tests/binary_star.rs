@@ -0,0 +1,99 @@
+extern crate futures;
+extern crate tokio_core;
+extern crate zmq_tokio;
+
+use std::io;
+use std::thread;
+use std::time::Duration;
+
+use futures::{future, Async, Future, Poll, Stream};
+use tokio_core::reactor::{Core, Timeout};
+use zmq_tokio::{Context, Message, DEALER};
+use zmq_tokio::binary_star::{BinaryStar, BinaryStarEvent, State};
+
+macro_rules! t {
+    ($e:expr) => (match $e {
+        Ok(e) => e,
+        Err(e) => panic!("{} failed with {:?}", stringify!($e), e),
+    })
+}
+
+// A primary and a backup server, publishing their states to each other.
+fn pair(ctx: &Context, name: &str, core: &Core) -> (BinaryStar, BinaryStar) {
+    let endpoint = |side: &str, socket: &str| format!("inproc://bstar-{}-{}-{}", name, side, socket);
+    let primary = t!(BinaryStar::new(
+        ctx,
+        true,
+        &endpoint("primary", "state"),
+        &endpoint("backup", "state"),
+        &endpoint("primary", "voter"),
+        &core.handle(),
+    ));
+    let backup = t!(BinaryStar::new(
+        ctx,
+        false,
+        &endpoint("backup", "state"),
+        &endpoint("primary", "state"),
+        &endpoint("backup", "voter"),
+        &core.handle(),
+    ));
+    (primary, backup)
+}
+
+// Polls the servers until they reach the given states.
+fn settle(core: &mut Core, servers: &mut [&mut BinaryStar], states: &[State]) {
+    let settled = future::poll_fn(|| -> Poll<(), io::Error> {
+        for server in servers.iter_mut() {
+            while let Async::Ready(Some(_)) = server.poll()? {}
+        }
+        if servers.iter().map(|server| server.state()).eq(states.iter().cloned()) {
+            Ok(Async::Ready(()))
+        } else {
+            Ok(Async::NotReady)
+        }
+    });
+    let timeout = t!(Timeout::new(Duration::from_secs(10), &core.handle()));
+    match core.run(settled.select2(timeout)) {
+        Ok(future::Either::A(_)) => {}
+        Ok(future::Either::B(_)) => panic!("servers never reached {:?}", states),
+        Err(future::Either::A((e, _))) | Err(future::Either::B((e, _))) => panic!("failed with {:?}", e),
+    }
+}
+
+#[test]
+fn primary_becomes_active_once_backup_is_up() {
+    let mut core = t!(Core::new());
+    let ctx = Context::new();
+    let (mut primary, mut backup) = pair(&ctx, "handshake", &core);
+    assert_eq!((primary.state(), backup.state()), (State::Primary, State::Backup));
+
+    settle(&mut core, &mut [&mut primary, &mut backup], &[State::Active, State::Passive]);
+}
+
+#[test]
+fn backup_takes_over_after_primary_expires() {
+    let mut core = t!(Core::new());
+    let ctx = Context::new();
+    let (mut primary, mut backup) = pair(&ctx, "failover", &core);
+    settle(&mut core, &mut [&mut primary, &mut backup], &[State::Active, State::Passive]);
+
+    // the primary goes away, and the backup stops hearing from it.
+    drop(primary);
+    settle(&mut core, &mut [&mut backup], &[State::Passive]);
+    thread::sleep(Duration::from_millis(2500));
+
+    let client = t!(ctx.socket(DEALER, &core.handle()));
+    t!(client.connect("inproc://bstar-failover-backup-voter"));
+    t!(core.run(client.send(Message::from_slice(b"hello"))));
+
+    let events = t!(core.run(backup.by_ref().take(2).collect()));
+    match events[0] {
+        BinaryStarEvent::Active => {}
+        ref event => panic!("expected failover, got {:?}", event),
+    }
+    match events[1] {
+        BinaryStarEvent::Request(ref msgs) => assert_eq!(msgs[1].as_str(), Some("hello")),
+        ref event => panic!("expected the request, got {:?}", event),
+    }
+    assert_eq!(backup.state(), State::Active);
+}
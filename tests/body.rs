@@ -0,0 +1,58 @@
+extern crate futures;
+extern crate tokio_core;
+extern crate zmq_tokio;
+
+use std::io;
+
+use futures::{stream, Future, Stream};
+use tokio_core::reactor::Core;
+use zmq_tokio::{testing, PAIR, PULL, PUSH};
+
+macro_rules! t {
+    ($e:expr) => (match $e {
+        Ok(e) => e,
+        Err(e) => panic!("{} failed with {:?}", stringify!($e), e),
+    })
+}
+
+#[test]
+fn bodies_are_split_into_frames() {
+    let mut core = t!(Core::new());
+    let (sender, receiver) = t!(testing::pair(PAIR, PAIR, &core.handle()));
+
+    let body = stream::iter_ok::<_, io::Error>(vec![vec![1; 10], vec![2; 3]]);
+    let send = sender.send_stream_body_with_frame_size(body, 4);
+    let (_, chunks) = t!(core.run(send.join(receiver.recv_stream_body().collect())));
+    let sizes: Vec<usize> = chunks.iter().map(|chunk| chunk.len()).collect();
+    assert_eq!(sizes, vec![4, 4, 4, 1]);
+
+    let mut expected = vec![1; 10];
+    expected.extend_from_slice(&[2; 3]);
+    assert_eq!(chunks.concat(), expected);
+}
+
+#[test]
+fn empty_bodies_end_with_one_frame() {
+    let mut core = t!(Core::new());
+    let (sender, receiver) = t!(testing::pair(PAIR, PAIR, &core.handle()));
+
+    let send = sender.send_stream_body(stream::empty::<Vec<u8>, io::Error>());
+    let (_, chunks) = t!(core.run(send.join(receiver.recv_stream_body().collect())));
+    assert_eq!(chunks, vec![Vec::<u8>::new()]);
+
+    // the next body starts afresh.
+    let send = sender.send_stream_body(stream::once::<_, io::Error>(Ok(b"next".to_vec())));
+    let (_, chunks) = t!(core.run(send.join(receiver.recv_stream_body().collect())));
+    assert_eq!(chunks, vec![b"next".to_vec()]);
+}
+
+#[test]
+fn bodies_need_pair_sockets() {
+    let mut core = t!(Core::new());
+    let (push, pull) = t!(testing::pair(PUSH, PULL, &core.handle()));
+
+    let send = push.send_stream_body(stream::empty::<Vec<u8>, io::Error>());
+    assert_eq!(core.run(send).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+    let recv = pull.recv_stream_body().collect();
+    assert_eq!(core.run(recv).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+}
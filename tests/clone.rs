@@ -0,0 +1,85 @@
+extern crate futures;
+extern crate tokio_core;
+extern crate zmq_tokio;
+
+use std::io;
+use std::time::Duration;
+
+use futures::{future, Async, Future, Poll, Stream};
+use tokio_core::reactor::{Core, Timeout};
+use zmq_tokio::Context;
+use zmq_tokio::clone::{Change, CloneClient, CloneServer};
+
+macro_rules! t {
+    ($e:expr) => (match $e {
+        Ok(e) => e,
+        Err(e) => panic!("{} failed with {:?}", stringify!($e), e),
+    })
+}
+
+fn change(key: &str, value: Option<&str>) -> Change {
+    Change {
+        key: key.as_bytes().to_vec(),
+        value: value.map(|value| value.as_bytes().to_vec()),
+    }
+}
+
+// Polls the server while waiting for the next change of the client.
+fn next(core: &mut Core, server: &mut CloneServer, client: &mut CloneClient) -> Change {
+    let next = future::poll_fn(|| -> Poll<Option<Change>, io::Error> {
+        if let Async::Ready(()) = server.poll()? {
+            panic!("server stopped");
+        }
+        client.poll()
+    });
+    let timeout = t!(Timeout::new(Duration::from_secs(10), &core.handle()));
+    match core.run(next.select2(timeout)) {
+        Ok(future::Either::A((Some(change), _))) => change,
+        Ok(future::Either::A((None, _))) => panic!("client stopped"),
+        Ok(future::Either::B(_)) => panic!("no change arrived"),
+        Err(future::Either::A((e, _))) | Err(future::Either::B((e, _))) => panic!("failed with {:?}", e),
+    }
+}
+
+#[test]
+fn late_clients_get_the_snapshot_then_updates() {
+    let mut core = t!(Core::new());
+    let ctx = Context::new();
+    let mut server = t!(CloneServer::new(
+        &ctx,
+        "inproc://clone-late-snapshot",
+        "inproc://clone-late-publisher",
+        &core.handle(),
+    ));
+    t!(server.set(b"a", b"1"));
+    t!(server.set(b"b", b"2"));
+    t!(server.set(b"a", b"3"));
+
+    // nobody was subscribed to these updates, so they come with the snapshot.
+    let mut client = t!(CloneClient::new(
+        &ctx,
+        "inproc://clone-late-snapshot",
+        "inproc://clone-late-publisher",
+        b"",
+        &core.handle(),
+    ));
+    let mut snapshot = vec![
+        next(&mut core, &mut server, &mut client),
+        next(&mut core, &mut server, &mut client),
+    ];
+    snapshot.sort_by(|a, b| a.key.cmp(&b.key));
+    assert_eq!(snapshot, vec![change("a", Some("3")), change("b", Some("2"))]);
+
+    // the client is synced once the snapshot ends.
+    t!(server.set(b"c", b"4"));
+    assert_eq!(next(&mut core, &mut server, &mut client), change("c", Some("4")));
+    assert!(client.is_synced());
+    assert_eq!(client.sequence(), 4);
+
+    t!(server.remove(b"a"));
+    assert_eq!(next(&mut core, &mut server, &mut client), change("a", None));
+    assert_eq!(client.sequence(), 5);
+    assert_eq!(client.get(b"a"), None);
+    assert_eq!(client.get(b"b"), Some(&b"2"[..]));
+    assert_eq!(client.get(b"c"), Some(&b"4"[..]));
+}
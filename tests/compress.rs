@@ -0,0 +1,75 @@
+extern crate zmq;
+extern crate zmq_tokio;
+
+use std::io;
+
+use zmq::Message;
+use zmq_tokio::codec::MessageCodec;
+use zmq_tokio::compress::{CompressedCodec, Compression};
+
+macro_rules! t {
+    ($e:expr) => (match $e {
+        Ok(e) => e,
+        Err(e) => panic!("{} failed with {:?}", stringify!($e), e),
+    })
+}
+
+// Run-length encoding of frames repeating a single byte.
+struct Repeat;
+
+impl Compression for Repeat {
+    fn compress(&mut self, data: &[u8]) -> io::Result<Vec<u8>> {
+        Ok(vec![data[0], data.len() as u8])
+    }
+
+    fn decompress(&mut self, data: &[u8], max_size: usize) -> io::Result<Vec<u8>> {
+        if data[1] as usize > max_size {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "too large"));
+        }
+        Ok(vec![data[0]; data[1] as usize])
+    }
+}
+
+fn roundtrip<A: Compression>(codec: &mut CompressedCodec<A>, frame: &[u8]) -> io::Result<Vec<u8>> {
+    let msgs = codec.encode(vec![Message::from_slice(frame)])?;
+    let msgs = codec.decode(msgs)?;
+    Ok(msgs[0].to_vec())
+}
+
+#[test]
+fn decompression_is_bounded() {
+    let mut codec = CompressedCodec::new(Repeat).threshold(0);
+    assert_eq!(t!(roundtrip(&mut codec, &[7; 100])), vec![7; 100]);
+
+    let mut codec = CompressedCodec::new(Repeat)
+        .threshold(0)
+        .max_decompressed_size(10);
+    let err = roundtrip(&mut codec, &[7; 100]).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+}
+
+#[cfg(feature = "zstd")]
+#[test]
+fn zstd_frames_are_bounded() {
+    use zmq_tokio::compress::Zstd;
+
+    let mut codec = CompressedCodec::new(Zstd::default());
+    assert_eq!(t!(roundtrip(&mut codec, &[0; 4096])), vec![0; 4096]);
+
+    let mut codec = CompressedCodec::new(Zstd::default()).max_decompressed_size(4095);
+    let err = roundtrip(&mut codec, &[0; 4096]).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+}
+
+#[cfg(feature = "lz4")]
+#[test]
+fn lz4_frames_are_bounded() {
+    use zmq_tokio::compress::Lz4;
+
+    let mut codec = CompressedCodec::new(Lz4);
+    assert_eq!(t!(roundtrip(&mut codec, &[0; 4096])), vec![0; 4096]);
+
+    let mut codec = CompressedCodec::new(Lz4).max_decompressed_size(4095);
+    let err = roundtrip(&mut codec, &[0; 4096]).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+}
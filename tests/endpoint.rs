@@ -0,0 +1,197 @@
+extern crate futures_cpupool;
+extern crate tokio_core;
+extern crate zmq;
+extern crate zmq_tokio;
+
+use std::io;
+
+use futures_cpupool::CpuPool;
+use tokio_core::reactor::Core;
+use zmq_tokio::{Context, PAIR};
+use zmq_tokio::{Endpoint, MulticastOptions};
+use zmq_tokio::endpoint::parse_tcp_endpoint;
+
+macro_rules! t {
+    ($e:expr) => (match $e {
+        Ok(e) => e,
+        Err(e) => panic!("{} failed with {:?}", stringify!($e), e),
+    })
+}
+
+#[test]
+fn parses_tcp_endpoints() {
+    let addr = t!(parse_tcp_endpoint("tcp://127.0.0.1:5555"));
+    assert_eq!(addr.port(), 5555);
+    let addr = t!(parse_tcp_endpoint("tcp://[::1]:5556"));
+    assert_eq!(addr.port(), 5556);
+    assert!(parse_tcp_endpoint("ipc:///tmp/socket").is_err());
+    assert!(parse_tcp_endpoint("tcp://127.0.0.1").is_err());
+}
+
+#[test]
+fn parses_and_displays_endpoints() {
+    for endpoint in &[
+        "tcp://127.0.0.1:5555",
+        "tcp://[::1]:5555",
+        "tcp://10.0.0.1;192.168.1.1:5555",
+        "ipc:///tmp/socket",
+        "inproc://workers",
+        "epgm://eth0;239.192.1.1:5555",
+        "vmci://*:5555",
+        "udp://eth0;239.0.0.1:5555",
+    ] {
+        let parsed: Endpoint = t!(endpoint.parse());
+        assert_eq!(parsed.to_string(), *endpoint);
+    }
+
+    let wildcard: Endpoint = t!("tcp://*:*".parse());
+    assert!(wildcard.is_wildcard_port());
+    assert_eq!(wildcard, Endpoint::tcp("*", 0));
+
+    assert!("127.0.0.1:5555".parse::<Endpoint>().is_err());
+    assert!("tcp://127.0.0.1".parse::<Endpoint>().is_err());
+    assert!("tcp://127.0.0.1:70000".parse::<Endpoint>().is_err());
+    assert!("tcp://::1:5555".parse::<Endpoint>().is_err());
+    assert!("pgm://239.192.1.1:5555".parse::<Endpoint>().is_err());
+    assert!("foo://bar".parse::<Endpoint>().is_err());
+}
+
+#[test]
+fn bind_rejects_malformed_endpoints() {
+    let core = t!(Core::new());
+    let ctx = Context::new();
+    let socket = t!(ctx.socket(PAIR, &core.handle()));
+
+    assert!(socket.bind("tcp://127.0.0.1").is_err());
+    t!(socket.bind(Endpoint::inproc("endpoint-test")));
+}
+
+#[test]
+fn bind_ephemeral_reports_assigned_port() {
+    let core = t!(Core::new());
+    let ctx = Context::new();
+    let socket = t!(ctx.socket(PAIR, &core.handle()));
+
+    let addr = t!(socket.bind_ephemeral("tcp://127.0.0.1"));
+    assert!(addr.port() != 0);
+
+    let endpoint = format!("tcp://{}", addr);
+    t!(socket.unbind(&endpoint));
+}
+
+#[test]
+fn bind_all_reports_failed_endpoints() {
+    let core = t!(Core::new());
+    let ctx = Context::new();
+    let socket = t!(ctx.socket(PAIR, &core.handle()));
+
+    let bound = t!(socket.bind_all(&["inproc://bind-all-a", "inproc://bind-all-b"]));
+    assert_eq!(bound.len(), 2);
+
+    let other = t!(ctx.socket(PAIR, &core.handle()));
+    let err = other
+        .bind_all(&["inproc://bind-all-a", "inproc://bind-all-c", "tcp://nowhere"])
+        .unwrap_err();
+    assert_eq!(err.succeeded, vec![Endpoint::inproc("bind-all-c")]);
+    assert_eq!(err.failed.len(), 2);
+    assert_eq!(err.failed[0].0, "inproc://bind-all-a");
+}
+
+#[test]
+fn connect_resolved_uses_resolved_address() {
+    let mut core = t!(Core::new());
+    let ctx = Context::new();
+    let pool = CpuPool::new(1);
+    let server = t!(ctx.socket(PAIR, &core.handle()));
+    let client = t!(ctx.socket(PAIR, &core.handle()));
+
+    let addr = t!(server.bind_ephemeral("tcp://127.0.0.1"));
+    let endpoint = format!("tcp://localhost:{}", addr.port());
+    let connected = t!(core.run(client.connect_resolved(endpoint, &pool)));
+    assert_eq!(connected, Endpoint::tcp("127.0.0.1", addr.port()));
+}
+
+#[test]
+fn dump_options_reports_endpoints_and_subscriptions() {
+    let core = t!(Core::new());
+    let ctx = Context::new();
+    let socket = t!(ctx.socket(zmq_tokio::SUB, &core.handle()));
+    t!(socket.bind("inproc://dump-options"));
+    t!(socket.set_subscribe(b"a"));
+    t!(socket.set_subscribe(b"b"));
+    t!(socket.set_unsubscribe(b"a"));
+
+    let options = t!(socket.dump_options());
+    assert_eq!(options.socket_type, "SUB");
+    assert_eq!(options.endpoints, vec!["inproc://dump-options".to_string()]);
+    assert_eq!(options.subscriptions, 1);
+    assert_eq!(options.mechanism, "NULL");
+}
+
+#[test]
+fn from_zmq_wraps_configured_sockets() {
+    let core = t!(Core::new());
+    let ctx = zmq::Context::new();
+    let raw = t!(ctx.socket(zmq::PAIR));
+    t!(raw.set_linger(42));
+
+    let socket = t!(zmq_tokio::Socket::from_zmq(raw, &core.handle()));
+    assert_eq!(t!(socket.get_linger()), 42);
+}
+
+#[test]
+fn into_inner_returns_the_wrapped_socket() {
+    let core = t!(Core::new());
+    let ctx = zmq::Context::new();
+    let raw = t!(ctx.socket(zmq::PAIR));
+    t!(raw.set_linger(42));
+
+    let socket = t!(zmq_tokio::Socket::from_zmq(raw, &core.handle()));
+    t!(socket.set_sndhwm(7));
+    let raw = t!(socket.into_inner());
+    assert_eq!(t!(raw.get_linger()), 42);
+    assert_eq!(t!(raw.get_sndhwm()), 7);
+
+    // the returned socket can be wrapped again.
+    let socket = t!(zmq_tokio::Socket::from_zmq(raw, &core.handle()));
+    assert_eq!(t!(socket.get_sndhwm()), 7);
+}
+
+#[test]
+fn endpoint_query_sets_options() {
+    let core = t!(Core::new());
+    let ctx = Context::new();
+    let socket = t!(ctx.socket(PAIR, &core.handle()));
+    t!(socket.bind("inproc://query-options?sndhwm=1000&linger=0&rcvtimeo=500"));
+    assert_eq!(t!(socket.get_sndhwm()), 1000);
+    assert_eq!(t!(socket.get_linger()), 0);
+    assert_eq!(t!(socket.get_rcvtimeo()), 500);
+    assert_eq!(socket.endpoints(), vec!["inproc://query-options".to_string()]);
+
+    let err = socket.connect("inproc://other?bogus=1").unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+}
+
+#[test]
+fn multicast_endpoints_need_pgm_support() {
+    let core = t!(Core::new());
+    let ctx = Context::new();
+
+    let endpoint = Endpoint::epgm("eth0", "239.192.1.1", 5555);
+    assert_eq!(endpoint.to_string(), "epgm://eth0;239.192.1.1:5555");
+    assert_eq!(t!("epgm://eth0;239.192.1.1:5555".parse::<Endpoint>()), endpoint);
+
+    let publisher = t!(ctx.socket(zmq_tokio::PUB, &core.handle()));
+    t!(publisher.set_multicast_options(&MulticastOptions {
+        rate: Some(1000),
+        hops: Some(4),
+        ..MulticastOptions::default()
+    }));
+    let options = t!(publisher.multicast_options());
+    assert_eq!((options.rate, options.hops), (Some(1000), Some(4)));
+
+    if zmq_tokio::has("pgm") != Some(true) {
+        let err = publisher.connect(endpoint).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+}
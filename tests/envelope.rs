@@ -0,0 +1,63 @@
+extern crate futures;
+extern crate tokio_core;
+extern crate zmq_tokio;
+
+use futures::Future;
+use tokio_core::reactor::Core;
+use zmq_tokio::{error, Context, RoutingId, DEALER, ROUTER};
+
+macro_rules! t {
+    ($e:expr) => (match $e {
+        Ok(e) => e,
+        Err(e) => panic!("{} failed with {:?}", stringify!($e), e),
+    })
+}
+
+const TEST_ADDR: &str = "inproc://envelope";
+
+#[test]
+fn dealer_request_round_trips_through_router() {
+    let mut core = t!(Core::new());
+    let handle = core.handle();
+    let ctx = Context::new();
+
+    let router = t!(ctx.socket(ROUTER, &handle));
+    t!(router.bind(TEST_ADDR));
+
+    let dealer = t!(ctx.socket(DEALER, &handle));
+    t!(dealer.set_identity(b"dealer-1"));
+    t!(dealer.connect(TEST_ADDR));
+
+    let exchange = dealer
+        .send_request(vec!["ping"])
+        .and_then(|_| router.recv_from())
+        .and_then(|(id, msgs)| {
+            assert_eq!(id.as_bytes(), b"dealer-1");
+            assert_eq!(msgs.len(), 1);
+            assert_eq!(msgs[0].as_str(), Some("ping"));
+            router.send_to(&id, vec!["pong"])
+        })
+        .and_then(|_| dealer.recv_reply());
+
+    let reply = t!(core.run(exchange));
+    assert_eq!(reply.len(), 1);
+    assert_eq!(reply[0].as_str(), Some("pong"));
+}
+
+#[test]
+fn sending_to_unknown_peer_names_it() {
+    let mut core = t!(Core::new());
+    let handle = core.handle();
+    let ctx = Context::new();
+
+    let router = t!(ctx.socket(ROUTER, &handle));
+    t!(router.set_router_mandatory(true));
+    t!(router.bind("inproc://envelope-unreachable"));
+
+    let peer = RoutingId::from("nobody");
+    let e = core.run(router.send_to(&peer, vec!["hello"])).unwrap_err();
+    assert!(error::is_host_unreachable(&e));
+    assert!(!error::is_recoverable(&e));
+    let unreachable = error::host_unreachable(&e).expect("a HostUnreachable error");
+    assert_eq!(unreachable.peer.as_bytes(), b"nobody");
+}
@@ -0,0 +1,31 @@
+extern crate futures;
+extern crate zmq;
+extern crate zmq_tokio;
+
+use std::io;
+
+use futures::{Async, Stream};
+use futures::stream::poll_fn;
+use zmq_tokio::error::is_recoverable;
+use zmq_tokio::stream::ResultStream;
+
+#[test]
+fn result_streams_go_on_after_recoverable_errors() {
+    assert!(is_recoverable(&io::Error::from(zmq::Error::EMSGSIZE)));
+    assert!(!is_recoverable(&io::Error::from(zmq::Error::ETERM)));
+
+    let mut polls = 0;
+    let inner = poll_fn(move || {
+        polls += 1;
+        match polls {
+            1 => Err(io::Error::new(io::ErrorKind::Interrupted, "interrupted")),
+            2 => Ok(Async::Ready(Some(2))),
+            _ => Err(io::Error::new(io::ErrorKind::Other, "fatal")),
+        }
+    });
+    let mut results = ResultStream::new(inner).wait();
+    let interrupted = results.next().unwrap().unwrap();
+    assert_eq!(interrupted.unwrap_err().kind(), io::ErrorKind::Interrupted);
+    assert_eq!(results.next().unwrap().unwrap().unwrap(), 2);
+    assert!(results.next().unwrap().is_err());
+}
@@ -0,0 +1,60 @@
+extern crate futures;
+extern crate tokio_core;
+extern crate zmq_tokio;
+
+use std::thread;
+use std::time::Duration;
+
+use futures::{future, Future, Stream};
+use tokio_core::reactor::Core;
+use zmq_tokio::{Context, Message, Socket, REP};
+use zmq_tokio::freelance::{FreelanceClient, PING, PONG};
+
+macro_rules! t {
+    ($e:expr) => (match $e {
+        Ok(e) => e,
+        Err(e) => panic!("{} failed with {:?}", stringify!($e), e),
+    })
+}
+
+// Answers pings, and echoes requests.
+fn serve<'a>(rep: &'a Socket) -> Box<Future<Item = (), Error = ()> + 'a> {
+    let replies = rep.map(|msgs| {
+        if &msgs[0][..] == PING {
+            vec![Message::from_slice(PONG)]
+        } else {
+            msgs
+        }
+    });
+    Box::new(replies.forward(rep).map(|_| ()).map_err(|e| panic!("server failed with {:?}", e)))
+}
+
+fn call(core: &mut Core, client: &mut FreelanceClient, rep: &Socket, body: &str) -> Vec<Message> {
+    match core.run(client.call(vec![body]).select2(serve(rep))) {
+        Ok(future::Either::A((reply, _))) => reply,
+        Ok(future::Either::B(_)) => panic!("server stopped"),
+        Err(future::Either::A((e, _))) => panic!("call failed with {:?}", e),
+        Err(future::Either::B(_)) => unreachable!(),
+    }
+}
+
+#[test]
+fn idle_clients_still_reach_their_server() {
+    let mut core = t!(Core::new());
+    let handle = core.handle();
+    let ctx = Context::new();
+
+    let rep = t!(ctx.socket(REP, &handle));
+    t!(rep.bind("inproc://freelance-idle"));
+    let mut client = t!(FreelanceClient::new(&ctx, vec!["inproc://freelance-idle"], &handle));
+
+    let reply = call(&mut core, &mut client, &rep, "first");
+    assert_eq!(reply[0].as_str(), Some("first"));
+
+    // no pings are sent while idle, for longer than servers are presumed
+    // alive, which must not cost the only attempt of the next request.
+    thread::sleep(Duration::from_millis(2500));
+    let reply = call(&mut core, &mut client, &rep, "second");
+    assert_eq!(reply[0].as_str(), Some("second"));
+    assert_eq!(client.live_servers(), vec!["inproc://freelance-idle"]);
+}
@@ -0,0 +1,38 @@
+extern crate futures;
+extern crate zmq_tokio;
+
+use futures::{Future, Sink, Stream};
+use zmq_tokio::Message;
+use zmq_tokio::gap::{GapDetector, SeqPub, Sequenced};
+use zmq_tokio::testing;
+
+macro_rules! t {
+    ($e:expr) => (match $e {
+        Ok(e) => e,
+        Err(e) => panic!("{} failed with {:?}", stringify!($e), e),
+    })
+}
+
+#[test]
+fn seq_pub_numbers_each_topic() {
+    let (a, b) = testing::loopback(0);
+    let msg = |topic: &str| vec![Message::from_slice(topic.as_bytes()), Message::from_slice(b"data")];
+
+    let mut publisher = SeqPub::new(a);
+    for topic in &["a", "b", "a"] {
+        publisher = t!(publisher.send(msg(topic)).wait());
+    }
+    assert_eq!(publisher.sent(b"a"), 2);
+    assert_eq!(publisher.sent(b"b"), 1);
+    drop(publisher);
+
+    let received = t!(GapDetector::new(b, 1).per_topic().collect().wait());
+    let sequences: Vec<u64> = received
+        .into_iter()
+        .map(|item| match item {
+            Sequenced::Message(msg) => msg.sequence,
+            Sequenced::Gap(gap) => panic!("unexpected gap {:?}", gap),
+        })
+        .collect();
+    assert_eq!(sequences, vec![0, 0, 1]);
+}
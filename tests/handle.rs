@@ -0,0 +1,69 @@
+extern crate futures;
+extern crate tokio_core;
+extern crate zmq_tokio;
+
+use std::io;
+use std::time::Duration;
+
+use futures::{future, Future, Stream};
+use tokio_core::reactor::{Core, Timeout};
+use zmq_tokio::{testing, Context, Message, DEALER, PULL, PUSH, ROUTER};
+use zmq_tokio::handle::SocketHandle;
+use zmq_tokio::monitor::{Event, Monitor, EVENT_ALL};
+
+macro_rules! t {
+    ($e:expr) => (match $e {
+        Ok(e) => e,
+        Err(e) => panic!("{} failed with {:?}", stringify!($e), e),
+    })
+}
+
+#[test]
+fn failed_calls_leave_the_handle_running() {
+    let mut core = t!(Core::new());
+    let (pull, push) = t!(testing::pair(PULL, PUSH, &core.handle()));
+    t!(push.set_conflate(true));
+    let handle = SocketHandle::spawn(push, &core.handle());
+
+    // conflating sockets refuse multipart-messages.
+    let multipart = vec![Message::from_slice(b"two"), Message::from_slice(b"parts")];
+    let err = core.run(handle.send(multipart)).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+
+    t!(core.run(handle.send(vec![Message::from_slice(b"still running")])));
+    let msg = t!(core.run(pull.recv()));
+    assert_eq!(msg.as_str(), Some("still running"));
+}
+
+#[test]
+fn unanswered_requests_dont_outlive_the_handle() {
+    let mut core = t!(Core::new());
+    let ctx = Context::new();
+
+    let router = t!(ctx.socket(ROUTER, &core.handle()));
+    t!(router.bind("inproc://handle-unanswered"));
+    let dealer = t!(ctx.socket(DEALER, &core.handle()));
+    let monitor = t!(Monitor::new(&ctx, &dealer, EVENT_ALL, &core.handle()));
+    t!(dealer.connect("inproc://handle-unanswered"));
+    let handle = SocketHandle::spawn(dealer, &core.handle());
+
+    let response = handle.request(vec![Message::from_slice(b"hello")]);
+    let request = t!(core.run(router.recv_multipart()));
+    assert_eq!(request[1].as_str(), Some("hello"));
+
+    // the request is never answered, so the socket is only closed once
+    // both the handle and the response are dropped.
+    drop(handle);
+    drop(response);
+    let stopped = monitor
+        .skip_while(|event| Ok(event.event != Event::MonitorStopped))
+        .into_future()
+        .map_err(|(e, _)| e);
+    let timeout = t!(Timeout::new(Duration::from_secs(5), &core.handle()));
+    match core.run(stopped.select2(timeout)) {
+        Ok(future::Either::A(_)) => {}
+        Ok(future::Either::B(_)) => panic!("socket handle kept running"),
+        Err(future::Either::A((e, _))) => panic!("monitor failed with {:?}", e),
+        Err(future::Either::B((e, _))) => panic!("timeout failed with {:?}", e),
+    }
+}
@@ -0,0 +1,144 @@
+extern crate futures;
+extern crate tokio_core;
+extern crate zmq_tokio;
+
+use std::thread;
+use std::time::{Duration, Instant};
+
+use futures::{future, Async, Future, Poll, Stream};
+use futures::sync::oneshot;
+use tokio_core::reactor::{Core, Handle, Timeout};
+use zmq_tokio::{Context, Message, DEALER, ROUTER};
+use zmq_tokio::mdp::{Broker, MdClient, MdWorker, MDPW_WORKER};
+
+macro_rules! t {
+    ($e:expr) => (match $e {
+        Ok(e) => e,
+        Err(e) => panic!("{} failed with {:?}", stringify!($e), e),
+    })
+}
+
+// Runs a broker bound to the endpoint, until the returned sender is used
+// or dropped.
+fn spawn_broker(ctx: &Context, endpoint: &str, handle: &Handle) -> oneshot::Sender<()> {
+    let router = t!(ctx.socket(ROUTER, handle));
+    // the endpoint of a previous broker is released once its socket is
+    // reaped.
+    let mut bound = router.bind(endpoint);
+    for _ in 0..100 {
+        if bound.is_ok() {
+            break;
+        }
+        thread::sleep(Duration::from_millis(10));
+        bound = router.bind(endpoint);
+    }
+    t!(bound);
+    let broker = t!(Broker::new(router, handle));
+    let (stop, stopped) = oneshot::channel();
+    handle.spawn(broker.select2(stopped).map(|_| ()).map_err(|_| ()));
+    stop
+}
+
+// Echoes every request of the worker.
+fn spawn_echo(mut worker: MdWorker, handle: &Handle) {
+    let serve = future::poll_fn(move || -> Poll<(), ()> {
+        loop {
+            let request = match worker.poll() {
+                Ok(Async::Ready(Some(request))) => request,
+                Ok(Async::Ready(None)) => return Ok(Async::Ready(())),
+                Ok(Async::NotReady) => return Ok(Async::NotReady),
+                Err(e) => panic!("worker failed with {:?}", e),
+            };
+            let body = request.body().iter().map(|msg| Message::from_slice(msg)).collect();
+            t!(worker.reply(&request, body));
+        }
+    });
+    handle.spawn(serve);
+}
+
+fn client(ctx: &Context, endpoint: &str, handle: &Handle) -> MdClient {
+    let dealer = t!(ctx.socket(DEALER, handle));
+    t!(dealer.connect(endpoint));
+    MdClient::new(dealer, handle)
+}
+
+fn call(core: &mut Core, client: &MdClient, service: &str, body: &str) -> String {
+    let reply = t!(core.run(client.send(service.as_bytes(), vec![body])));
+    assert_eq!(reply.len(), 1);
+    reply[0].as_str().unwrap().to_string()
+}
+
+// Asks the broker about the service until the reply is the expected code.
+fn wait_for_service(core: &mut Core, client: &MdClient, service: &str, code: &str) {
+    let deadline = Instant::now() + Duration::from_secs(15);
+    while call(core, client, "mmi.service", service) != code {
+        assert!(Instant::now() < deadline, "service {} never replied {}", service, code);
+        t!(core.run(t!(Timeout::new(Duration::from_millis(200), &core.handle()))));
+    }
+}
+
+#[test]
+fn requests_go_through_the_broker() {
+    let mut core = t!(Core::new());
+    let handle = core.handle();
+    let ctx = Context::new();
+    let _broker = spawn_broker(&ctx, "inproc://mdp-roundtrip", &handle);
+    spawn_echo(t!(MdWorker::new(&ctx, "inproc://mdp-roundtrip", b"echo", &handle)), &handle);
+    let client = client(&ctx, "inproc://mdp-roundtrip", &handle);
+
+    assert_eq!(call(&mut core, &client, "echo", "hello"), "hello");
+    assert_eq!(call(&mut core, &client, "echo", "again"), "again");
+}
+
+#[test]
+fn mmi_service_reports_registered_services() {
+    let mut core = t!(Core::new());
+    let handle = core.handle();
+    let ctx = Context::new();
+    let _broker = spawn_broker(&ctx, "inproc://mdp-mmi", &handle);
+    let client = client(&ctx, "inproc://mdp-mmi", &handle);
+
+    assert_eq!(call(&mut core, &client, "mmi.service", "echo"), "404");
+    spawn_echo(t!(MdWorker::new(&ctx, "inproc://mdp-mmi", b"echo", &handle)), &handle);
+    wait_for_service(&mut core, &client, "echo", "200");
+    assert_eq!(call(&mut core, &client, "mmi.service", "other"), "404");
+    assert_eq!(call(&mut core, &client, "mmi.unknown", "echo"), "501");
+}
+
+#[test]
+fn silent_workers_expire() {
+    let mut core = t!(Core::new());
+    let handle = core.handle();
+    let ctx = Context::new();
+    let _broker = spawn_broker(&ctx, "inproc://mdp-expiry", &handle);
+    let client = client(&ctx, "inproc://mdp-expiry", &handle);
+
+    // a worker that registers, and never sends a heartbeat.
+    let worker = t!(ctx.socket(DEALER, &handle));
+    t!(worker.connect("inproc://mdp-expiry"));
+    let ready = vec![&b""[..], MDPW_WORKER, &[0x01][..], &b"silent"[..]];
+    t!(core.run(worker.send_multipart(ready)));
+
+    wait_for_service(&mut core, &client, "silent", "200");
+    wait_for_service(&mut core, &client, "silent", "404");
+}
+
+#[test]
+fn workers_reconnect_to_a_new_broker() {
+    let mut core = t!(Core::new());
+    let handle = core.handle();
+    let ctx = Context::new();
+    let broker = spawn_broker(&ctx, "inproc://mdp-reconnect", &handle);
+    spawn_echo(t!(MdWorker::new(&ctx, "inproc://mdp-reconnect", b"echo", &handle)), &handle);
+    let first = client(&ctx, "inproc://mdp-reconnect", &handle);
+    assert_eq!(call(&mut core, &first, "echo", "hello"), "hello");
+
+    // the worker stops hearing from the broker, and registers with the
+    // one that replaces it.
+    t!(broker.send(()).map_err(|_| "broker already stopped"));
+    t!(core.run(t!(Timeout::new(Duration::from_millis(100), &handle))));
+    let _broker = spawn_broker(&ctx, "inproc://mdp-reconnect", &handle);
+    let second = client(&ctx, "inproc://mdp-reconnect", &handle);
+    wait_for_service(&mut core, &second, "echo", "200");
+    assert_eq!(call(&mut core, &second, "echo", "again"), "again");
+}
@@ -0,0 +1,40 @@
+extern crate futures;
+extern crate tokio_core;
+extern crate zmq_tokio;
+
+use futures::Stream;
+use tokio_core::reactor::Core;
+use zmq_tokio::{Context, PULL, PUSH};
+use zmq_tokio::monitor::{EndpointStats, Event, Monitor, EVENT_ALL};
+
+macro_rules! t {
+    ($e:expr) => (match $e {
+        Ok(e) => e,
+        Err(e) => panic!("{} failed with {:?}", stringify!($e), e),
+    })
+}
+
+#[test]
+fn endpoint_stats_follow_connections() {
+    let mut core = t!(Core::new());
+    let ctx = Context::new();
+
+    let pull = t!(ctx.socket(PULL, &core.handle()));
+    t!(pull.bind("tcp://127.0.0.1:*"));
+    let endpoint = pull.endpoints()[0].clone();
+
+    let push = t!(ctx.socket(PUSH, &core.handle()));
+    let mut monitor = t!(Monitor::new(&ctx, &push, EVENT_ALL, &core.handle()));
+    let stats = EndpointStats::new();
+    monitor.record_endpoints(stats.clone());
+    t!(push.connect(&endpoint));
+
+    let connected = monitor
+        .skip_while(|event| Ok(event.event != Event::Connected))
+        .into_future();
+    t!(core.run(connected).map_err(|(e, _)| e));
+    let state = stats.get(&endpoint).unwrap();
+    assert_eq!(state.connections, 1);
+    assert!(state.connected_since.is_some());
+    assert_eq!(state.last_handshake_error, None);
+}
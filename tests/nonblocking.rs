@@ -0,0 +1,58 @@
+extern crate futures;
+extern crate tokio_core;
+extern crate zmq_tokio;
+
+use futures::{future, Async, Future};
+use tokio_core::reactor::Core;
+use zmq_tokio::{testing, Context, Message, PULL, PUSH};
+use zmq_tokio::error::TrySendError;
+
+macro_rules! t {
+    ($e:expr) => (match $e {
+        Ok(e) => e,
+        Err(e) => panic!("{} failed with {:?}", stringify!($e), e),
+    })
+}
+
+#[test]
+fn try_send_hands_back_messages_that_would_block() {
+    let mut core = t!(Core::new());
+    let handle = core.handle();
+    let ctx = Context::new();
+    // a `PUSH` socket without peers can't send.
+    let push = t!(ctx.socket(PUSH, &handle));
+
+    let sent = core.run(future::lazy(|| {
+        future::ok::<_, ()>(push.try_send(vec![Message::from_slice(b"job")]))
+    }));
+    match sent {
+        Ok(Err(TrySendError::Full(msgs))) => assert_eq!(&msgs[0][..], b"job"),
+        other => panic!("unexpected result {:?}", other.map(|r| r.is_ok())),
+    }
+}
+
+#[test]
+fn try_recv_mixes_with_pending_receives() {
+    let mut core = t!(Core::new());
+    let handle = core.handle();
+    let (push, pull) = t!(testing::pair(PUSH, PULL, &handle));
+
+    let empty = t!(core.run(future::lazy(|| pull.try_recv())));
+    assert!(empty.is_none());
+
+    // a pending receive is woken up after `try_recv` found nothing.
+    let (first, _) = t!(core.run(pull.recv_multipart().join(push.send("a"))));
+    assert_eq!(first[0].as_str(), Some("a"));
+
+    // `try_recv` wakes the task up once a message arrives.
+    let wait = future::poll_fn(|| match pull.try_recv()? {
+        Some(msgs) => Ok(Async::Ready(msgs)),
+        None => Ok(Async::NotReady),
+    });
+    let (second, _) = t!(core.run(wait.join(push.send("b"))));
+    assert_eq!(second[0].as_str(), Some("b"));
+
+    // and receives stay readable after `try_recv` drained the socket.
+    let (third, _) = t!(core.run(pull.recv_multipart().join(push.send("c"))));
+    assert_eq!(third[0].as_str(), Some("c"));
+}
@@ -0,0 +1,226 @@
+extern crate futures;
+extern crate tokio_core;
+extern crate zmq_tokio;
+
+use std::io;
+use std::time::Duration;
+
+use futures::{future, Future, Sink, Stream};
+use tokio_core::reactor::Core;
+use zmq_tokio::{testing, Context, Message, TcpOptions, DEALER, PULL, PUSH, REQ, ROUTER};
+use zmq_tokio::config::SocketConfig;
+use zmq_tokio::error;
+use zmq_tokio::monitor::{Event, Monitor, EVENT_ALL};
+
+macro_rules! t {
+    ($e:expr) => (match $e {
+        Ok(e) => e,
+        Err(e) => panic!("{} failed with {:?}", stringify!($e), e),
+    })
+}
+
+#[test]
+fn conflate_is_validated() {
+    let core = t!(Core::new());
+    let ctx = Context::new();
+
+    let req = t!(ctx.socket(REQ, &core.handle()));
+    let err = req.set_conflate(true).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    assert!(!t!(req.is_conflate()));
+
+    let (push, _pull) = t!(testing::pair(PUSH, PULL, &core.handle()));
+    t!(push.set_conflate(true));
+    assert!(t!(push.is_conflate()));
+
+    let msg = vec![Message::from_slice(b"a"), Message::from_slice(b"b")];
+    let mut sink = &push;
+    let err = sink.start_send(msg).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+
+    let msg = vec![Message::from_slice(b"a")];
+    assert!(t!(sink.start_send(msg)).is_ready());
+}
+
+#[test]
+fn outbound_queue_full_without_peers() {
+    let core = t!(Core::new());
+    let ctx = Context::new();
+
+    let push = t!(ctx.socket(PUSH, &core.handle()));
+    t!(push.set_sndhwm(1));
+    assert_eq!(t!(push.get_sndhwm()), 1);
+    t!(push.bind("inproc://outbound-queue-full"));
+    assert!(t!(push.outbound_queue_full()));
+
+    let pull = t!(ctx.socket(PULL, &core.handle()));
+    t!(pull.connect("inproc://outbound-queue-full"));
+    assert!(!t!(push.outbound_queue_full()));
+}
+
+#[test]
+fn socket_builder_applies_options() {
+    let core = t!(Core::new());
+    let ctx = Context::new();
+
+    let pull = t!(ctx.socket_builder(PULL)
+        .rcvhwm(10)
+        .linger(0)
+        .bind("inproc://socket-builder")
+        .build(&core.handle()));
+    assert_eq!(t!(pull.get_rcvhwm()), 10);
+    assert_eq!(t!(pull.get_linger()), 0);
+    assert_eq!(pull.endpoints(), vec!["inproc://socket-builder".to_string()]);
+
+    let err = ctx.socket_builder(REQ).conflate(true).build(&core.handle()).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+}
+
+#[test]
+fn tcp_options_round_trip() {
+    let core = t!(Core::new());
+    let ctx = Context::new();
+
+    let mut options = TcpOptions::keepalive(Duration::from_secs(30));
+    options.keepalive_cnt = Some(4);
+    options.tos = Some(0x28);
+    let push = t!(ctx.socket_builder(PUSH).tcp_options(options).build(&core.handle()));
+    assert_eq!(t!(push.tcp_options()), options);
+}
+
+#[test]
+fn socket_from_config() {
+    let core = t!(Core::new());
+    let ctx = Context::new();
+
+    let mut config = SocketConfig::new("pull");
+    config.bind.push("inproc://socket-config".to_string());
+    config.options.insert("rcvhwm".to_string(), "20".to_string());
+    let pull = t!(ctx.socket_from_config(&config, &core.handle()));
+    assert_eq!(t!(pull.get_rcvhwm()), 20);
+    assert_eq!(pull.endpoints(), vec!["inproc://socket-config".to_string()]);
+
+    let err = ctx.socket_from_config(&SocketConfig::new("bogus"), &core.handle()).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+}
+
+#[test]
+fn message_size_limits_apply_to_frames() {
+    let mut core = t!(Core::new());
+    let ctx = Context::new();
+
+    // the frames fit the limit, so the message is delivered over both
+    // transports, even though they add up to more.
+    for transport in &["inproc", "tcp"] {
+        let push = t!(ctx.socket(PUSH, &core.handle()));
+        let pull = t!(ctx.socket(PULL, &core.handle()));
+        t!(pull.set_maxmsgsize(8));
+        let endpoint = match *transport {
+            "inproc" => {
+                t!(push.bind("inproc://oversized"));
+                "inproc://oversized".to_string()
+            }
+            _ => format!("tcp://{}", t!(push.bind_ephemeral("tcp://127.0.0.1"))),
+        };
+        t!(pull.connect(&endpoint));
+
+        let large = vec![&b"first"[..], &b"second"[..]];
+        t!(core.run(push.send_multipart(large)));
+
+        let msgs = t!(core.run(pull.recv_multipart()));
+        assert_eq!(msgs.len(), 2);
+        assert_eq!(&msgs[1][..], b"second");
+    }
+}
+
+#[test]
+fn oversized_frames_are_reported_over_inproc() {
+    // over other transports, ØMQ disconnects peers sending a larger frame.
+    let mut core = t!(Core::new());
+    let (push, pull) = t!(testing::pair(PUSH, PULL, &core.handle()));
+    t!(pull.set_maxmsgsize(8));
+
+    t!(core.run(push.send(Message::from_slice(b"too large"))));
+    t!(core.run(push.send(Message::from_slice(b"small"))));
+    t!(core.run(push.send(Message::from_slice(b"too large"))));
+    t!(core.run(push.send_multipart(vec![&b"small"[..], &b"too large"[..]])));
+
+    let err = core.run(pull.recv()).unwrap_err();
+    assert!(error::message_too_large(&err).is_some());
+    assert_eq!(t!(core.run(pull.recv())).as_str(), Some("small"));
+    let err = core.run(future::lazy(|| pull.try_recv())).unwrap_err();
+    assert!(error::message_too_large(&err).is_some());
+    let err = core.run(pull.recv_multipart()).unwrap_err();
+    let too_large = error::message_too_large(&err).unwrap();
+    assert_eq!((too_large.limit, too_large.size), (8, 9));
+}
+
+#[test]
+fn oversized_frames_disconnect_tcp_peers() {
+    let mut core = t!(Core::new());
+    let ctx = Context::new();
+
+    let pull = t!(ctx.socket(PULL, &core.handle()));
+    t!(pull.set_maxmsgsize(8));
+    let endpoint = format!("tcp://{}", t!(pull.bind_ephemeral("tcp://127.0.0.1")));
+    let monitor = t!(Monitor::new(&ctx, &pull, EVENT_ALL, &core.handle()));
+
+    let push = t!(ctx.socket(PUSH, &core.handle()));
+    t!(push.connect(&endpoint));
+    t!(core.run(push.send(Message::from_slice(b"too large"))));
+
+    let disconnected = monitor
+        .skip_while(|event| Ok(event.event != Event::Disconnected))
+        .into_future();
+    let (event, _) = t!(core.run(disconnected).map_err(|(e, _)| e));
+    assert_eq!(event.unwrap().event, Event::Disconnected);
+}
+
+#[test]
+fn mandatory_routing_names_the_unreachable_peer() {
+    let mut core = t!(Core::new());
+    let ctx = Context::new();
+
+    let dealer = t!(ctx.socket(DEALER, &core.handle()));
+    let err = dealer.set_router_mandatory(true).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+
+    let router = t!(ctx.socket(ROUTER, &core.handle()));
+    t!(router.set_router_mandatory(true));
+    let err = core.run(router.send_multipart(vec![&b"nobody"[..], &b"hello"[..]])).unwrap_err();
+    assert!(error::is_host_unreachable(&err));
+    assert_eq!(&error::host_unreachable(&err).unwrap().peer[..], b"nobody");
+}
+
+#[test]
+fn relaxed_requests_are_resent() {
+    let mut core = t!(Core::new());
+    let handle = core.handle();
+    let (req, router) = t!(testing::pair(REQ, ROUTER, &handle));
+    t!(req.set_req_relaxed(true));
+    t!(req.set_req_correlate(true));
+
+    let request = t!(req.request(vec![&b"ping"[..]], Duration::from_millis(50), 1, &handle));
+    // the first attempt goes unanswered, the second one is replied to.
+    let server = router.incoming_multipart().take(2).collect().and_then(|attempts| {
+        let envelope = attempts[1].iter().take_while(|frame| !frame.is_empty()).count() + 1;
+        let mut reply: Vec<Vec<u8>> = attempts[1][..envelope].iter().map(|frame| frame.to_vec()).collect();
+        reply.push(b"pong".to_vec());
+        router.send_multipart(reply)
+    });
+    let (reply, ()) = t!(core.run(request.join(server)));
+    assert_eq!(&reply[0][..], b"pong");
+}
+
+#[test]
+fn context_options_apply_to_sockets() {
+    let core = t!(Core::new());
+    let ctx = t!(Context::builder().max_sockets(1).ipv6(true).build());
+
+    let first = t!(ctx.socket(PUSH, &core.handle()));
+    assert!(t!(first.is_ipv6()));
+    assert!(ctx.socket(PULL, &core.handle()).is_err());
+
+    drop(first);
+    t!(ctx.socket(PULL, &core.handle()));
+}
@@ -0,0 +1,52 @@
+extern crate futures;
+extern crate tokio_core;
+extern crate zmq_tokio;
+
+use std::io;
+
+use futures::{stream, Future, Stream};
+use tokio_core::reactor::Core;
+use zmq_tokio::{Context, Message, PULL, PUSH};
+use zmq_tokio::pipeline::{SinkCollector, Ventilator, Worker};
+
+macro_rules! t {
+    ($e:expr) => (match $e {
+        Ok(e) => e,
+        Err(e) => panic!("{} failed with {:?}", stringify!($e), e),
+    })
+}
+
+#[test]
+fn pipeline_collects_every_result() {
+    let mut core = t!(Core::new());
+    let handle = core.handle();
+    let ctx = Context::new();
+
+    let collector = t!(ctx.socket(PULL, &handle));
+    t!(collector.bind("inproc://pipeline-results"));
+    let jobs = t!(ctx.socket(PUSH, &handle));
+    t!(jobs.bind("inproc://pipeline-jobs"));
+    let signal = t!(ctx.socket(PUSH, &handle));
+    t!(signal.connect("inproc://pipeline-results"));
+    let worker_jobs = t!(ctx.socket(PULL, &handle));
+    t!(worker_jobs.connect("inproc://pipeline-jobs"));
+    let worker_results = t!(ctx.socket(PUSH, &handle));
+    t!(worker_results.connect("inproc://pipeline-results"));
+
+    let work = (0..10u8).map(|i| Ok(vec![Message::from_slice(&[i])]));
+    let ventilator = Ventilator::new(jobs, stream::iter_result::<_, _, io::Error>(work)).signal(signal);
+    let worker = Worker::new(worker_jobs, worker_results, |mut job| {
+        let doubled = job[0][0] * 2;
+        job[0] = Message::from_slice(&[doubled]);
+        Ok(job)
+    });
+    handle.spawn(ventilator.map(|_| ()).map_err(|e| panic!("ventilator failed: {}", e)));
+    handle.spawn(worker.map_err(|e| panic!("worker failed: {}", e)));
+
+    let mut results: Vec<u8> = t!(core.run(SinkCollector::new(collector).collect()))
+        .into_iter()
+        .map(|msgs| msgs[0][0])
+        .collect();
+    results.sort();
+    assert_eq!(results, (0..10u8).map(|i| i * 2).collect::<Vec<_>>());
+}
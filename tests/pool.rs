@@ -0,0 +1,61 @@
+extern crate futures;
+extern crate tokio_core;
+extern crate zmq_tokio;
+
+use futures::{Future, Stream};
+use tokio_core::reactor::Core;
+use zmq_tokio::{testing, PULL, PUSH};
+use zmq_tokio::error;
+use zmq_tokio::pool::MessagePool;
+
+macro_rules! t {
+    ($e:expr) => (match $e {
+        Ok(e) => e,
+        Err(e) => panic!("{} failed with {:?}", stringify!($e), e),
+    })
+}
+
+#[test]
+fn pooled_buffers_are_reused() {
+    let mut core = t!(Core::new());
+    let (push, pull) = t!(testing::pair(PUSH, PULL, &core.handle()));
+    let pool = MessagePool::new(4, 64);
+
+    for i in 0..10u8 {
+        let (buf, _) = t!(core.run(pull.recv_pooled(&pool).join(push.send(&[i][..]))));
+        assert_eq!(&*buf, &[i]);
+    }
+    assert_eq!(pool.created(), 1);
+    assert_eq!(pool.idle(), 1);
+}
+
+#[test]
+fn pooled_frames_are_bounded_by_the_buffer_size() {
+    let mut core = t!(Core::new());
+    let (push, pull) = t!(testing::pair(PUSH, PULL, &core.handle()));
+    let pool = MessagePool::new(4, 4);
+
+    t!(core.run(push.send(&b"too large"[..])));
+    let err = core.run(pull.recv_pooled(&pool)).unwrap_err();
+    let too_large = error::message_too_large(&err).expect("not a MessageTooLarge error");
+    assert_eq!(too_large.limit, 4);
+    assert_eq!(too_large.size, 9);
+    // the buffer went back to the pool.
+    assert_eq!(pool.idle(), 1);
+}
+
+#[test]
+fn framed_streams_receive_into_pooled_buffers() {
+    let mut core = t!(Core::new());
+    let (push, pull) = t!(testing::pair(PUSH, PULL, &core.handle()));
+    let pool = MessagePool::new(4, 64);
+
+    for i in 0..3u8 {
+        t!(core.run(push.send(&[i][..])));
+    }
+    let frames = t!(core.run(pull.framed().pooled(&pool).take(3).collect()));
+    let received: Vec<u8> = frames.iter().map(|buf| buf[0]).collect();
+    assert_eq!(received, vec![0, 1, 2]);
+    drop(frames);
+    assert_eq!(pool.idle(), 3);
+}
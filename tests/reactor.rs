@@ -0,0 +1,132 @@
+extern crate futures;
+extern crate tokio_core;
+extern crate zmq_mio;
+extern crate zmq_tokio;
+
+use std::io;
+use std::thread;
+use std::time::Duration;
+
+use futures::{future, stream, Async, Future, Stream};
+use tokio_core::reactor::{Core, Timeout};
+use zmq_tokio::{testing, Context, Message, SocketRecv, SocketSend, PAIR, PULL, PUSH};
+use zmq_tokio::reactor::Registration;
+
+macro_rules! t {
+    ($e:expr) => (match $e {
+        Ok(e) => e,
+        Err(e) => panic!("{} failed with {:?}", stringify!($e), e),
+    })
+}
+
+// A registration that always reports readiness, leaving it to the socket
+// to fail with `WouldBlock`.
+struct AlwaysReady(zmq_mio::Socket);
+
+impl Registration for AlwaysReady {
+    fn get_ref(&self) -> &zmq_mio::Socket {
+        &self.0
+    }
+
+    fn poll_read(&self) -> Async<()> {
+        Async::Ready(())
+    }
+
+    fn poll_write(&self) -> Async<()> {
+        Async::Ready(())
+    }
+
+    fn need_read(&self) {}
+
+    fn need_write(&self) {}
+}
+
+#[test]
+fn sends_and_receives_with_a_custom_registration() {
+    let ctx = zmq_mio::Context::new();
+    let sender = AlwaysReady(t!(ctx.socket(PAIR)));
+    let receiver = AlwaysReady(t!(ctx.socket(PAIR)));
+    t!(receiver.get_ref().bind("inproc://custom-registration"));
+    t!(sender.get_ref().connect("inproc://custom-registration"));
+
+    // readiness checks may notify the current task, so run within one.
+    let received = future::lazy(|| {
+        t!(SocketSend::send(&sender, "hello", 0));
+        future::ok::<_, ()>(t!(receiver.recv_string(0)))
+    }).wait();
+    assert_eq!(received, Ok(Ok("hello".to_string())));
+}
+
+#[test]
+fn sockets_move_between_reactors() {
+    let mut core = t!(Core::new());
+    let (mut push, pull) = t!(testing::pair(PUSH, PULL, &core.handle()));
+
+    t!(push.deregister());
+    assert!(!push.is_registered());
+    assert!(push.deregister().is_err());
+    // options can still be set while deregistered.
+    t!(push.set_linger(1000));
+
+    let worker = thread::spawn(move || {
+        let mut core = t!(Core::new());
+        t!(push.register(&core.handle()));
+        assert!(push.register(&core.handle()).is_err());
+        t!(core.run(push.send("moved")));
+    });
+    let msg = t!(core.run(pull.recv()));
+    assert_eq!(msg.as_str(), Some("moved"));
+    t!(worker.join());
+}
+
+#[test]
+fn futures_of_deregistered_sockets_fail() {
+    let mut core = t!(Core::new());
+    let (mut push, pull) = t!(testing::pair(PUSH, PULL, &core.handle()));
+
+    t!(push.deregister());
+    let err = core.run(push.send("lost")).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::NotConnected);
+    let err = core.run(push.incoming().into_future()).map_err(|(e, _)| e).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::NotConnected);
+
+    t!(push.register(&core.handle()));
+    t!(core.run(push.send("found")));
+    let msg = t!(core.run(pull.recv()));
+    assert_eq!(msg.as_str(), Some("found"));
+}
+
+#[test]
+fn sending_and_receiving_in_one_task_keeps_up_under_load() {
+    const COUNT: usize = 10_000;
+    let mut core = t!(Core::new());
+    let handle = core.handle();
+    let ctx = Context::new();
+
+    // with the smallest high-water marks, sends keep blocking, and receives
+    // keep consuming the edges that unblock them.
+    let client = t!(ctx.socket(PAIR, &handle));
+    let echo = t!(ctx.socket(PAIR, &handle));
+    for socket in &[&client, &echo] {
+        t!(socket.set_sndhwm(1));
+        t!(socket.set_rcvhwm(1));
+    }
+    t!(echo.bind("inproc://under-load"));
+    t!(client.connect("inproc://under-load"));
+
+    let requests = stream::iter_ok::<_, io::Error>(0..COUNT)
+        .map(|i| vec![Message::from_slice(i.to_string().as_bytes())]);
+    let exchange = client
+        .send_all(requests)
+        .join(echo.send_all((&echo).take(COUNT as u64)))
+        .join((&client).take(COUNT as u64).collect());
+    let timeout = t!(Timeout::new(Duration::from_secs(30), &handle));
+    let replies = match core.run(exchange.select2(timeout)) {
+        Ok(future::Either::A(((_, replies), _))) => replies,
+        Ok(future::Either::B(_)) => panic!("exchange hung"),
+        Err(future::Either::A((e, _))) => panic!("exchange failed with {:?}", e),
+        Err(future::Either::B((e, _))) => panic!("timeout failed with {:?}", e),
+    };
+    assert_eq!(replies.len(), COUNT);
+    assert_eq!(replies[COUNT - 1][0].as_str(), Some("9999"));
+}
@@ -0,0 +1,93 @@
+extern crate futures;
+extern crate tokio_core;
+extern crate zmq_tokio;
+
+use std::io;
+use std::time::Duration;
+
+use futures::{future, Future, Sink, Stream};
+use tokio_core::reactor::Core;
+use zmq_tokio::{testing, Message, DEALER, ROUTER};
+use zmq_tokio::reliable::{ReliableConfig, ReliableDealer, ReliableRouter, ACK, DATA};
+
+macro_rules! t {
+    ($e:expr) => (match $e {
+        Ok(e) => e,
+        Err(e) => panic!("{} failed with {:?}", stringify!($e), e),
+    })
+}
+
+const FIRST: &[u8] = &[0, 0, 0, 0, 0, 0, 0, 1];
+const SECOND: &[u8] = &[0, 0, 0, 0, 0, 0, 0, 2];
+
+fn config() -> ReliableConfig {
+    ReliableConfig {
+        window: 4,
+        retransmit: Duration::from_millis(50),
+    }
+}
+
+#[test]
+fn zero_windows_are_refused() {
+    let core = t!(Core::new());
+    let (_, dealer) = t!(testing::pair(ROUTER, DEALER, &core.handle()));
+    let config = ReliableConfig {
+        window: 0,
+        ..ReliableConfig::default()
+    };
+    let err = ReliableDealer::new(dealer, config, &core.handle()).err().unwrap();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+}
+
+#[test]
+fn unacknowledged_messages_are_sent_again() {
+    let mut core = t!(Core::new());
+    let (router, dealer) = t!(testing::pair(ROUTER, DEALER, &core.handle()));
+    let mut dealer = t!(ReliableDealer::new(dealer, config(), &core.handle()));
+
+    t!(core.run(future::lazy(|| dealer.start_send(vec![Message::from_slice(b"hello")]))));
+    let first = t!(core.run(router.recv_multipart()));
+    assert_eq!(&first[1][..], &[DATA]);
+    assert_eq!(&first[2][..], FIRST);
+
+    // the acknowledgement is dropped, so the message comes again.
+    let resent = match core.run(future::poll_fn(|| dealer.poll_complete()).select2(router.recv_multipart())) {
+        Ok(future::Either::B((msgs, _))) => msgs,
+        Ok(future::Either::A(_)) => panic!("completed without an acknowledgement"),
+        Err(future::Either::A((e, _))) | Err(future::Either::B((e, _))) => panic!("failed with {:?}", e),
+    };
+    assert_eq!(&resent[2][..], FIRST);
+    assert_eq!(resent[3].as_str(), Some("hello"));
+    assert_eq!(dealer.unacked(), 1);
+
+    let ack = vec![first[0].to_vec(), vec![ACK], FIRST.to_vec()];
+    t!(core.run(router.send_multipart(ack)));
+    t!(core.run(future::poll_fn(|| dealer.poll_complete())));
+    assert_eq!(dealer.unacked(), 0);
+}
+
+#[test]
+fn duplicates_are_yielded_once() {
+    let mut core = t!(Core::new());
+    let (router, dealer) = t!(testing::pair(ROUTER, DEALER, &core.handle()));
+    let mut router = t!(ReliableRouter::new(router, config(), &core.handle()));
+
+    let hello = vec![&[DATA][..], FIRST, &b"hello"[..]];
+    t!(core.run(dealer.send_multipart(hello.clone())));
+    t!(core.run(dealer.send_multipart(hello)));
+    t!(core.run(dealer.send_multipart(vec![&[DATA][..], SECOND, &b"world"[..]])));
+
+    let received = t!(core.run(router.by_ref().take(2).collect()));
+    let bodies: Vec<_> = received
+        .iter()
+        .map(|&(_, ref body)| body[0].as_str().unwrap().to_string())
+        .collect();
+    assert_eq!(bodies, vec!["hello", "world"]);
+
+    // every copy is acknowledged, so the sender stops sending it again.
+    for seq in &[FIRST, FIRST, SECOND] {
+        let ack = t!(core.run(dealer.recv_multipart()));
+        assert_eq!(&ack[0][..], &[ACK]);
+        assert_eq!(&ack[1][..], *seq);
+    }
+}
@@ -0,0 +1,78 @@
+extern crate futures;
+extern crate tokio_core;
+extern crate zmq_tokio;
+
+use std::time::Duration;
+
+use futures::{future, Future, Stream};
+use futures::sync::oneshot;
+use tokio_core::reactor::Core;
+use zmq_tokio::{testing, Context, Message, PULL, PUSH};
+use zmq_tokio::proxy::proxy;
+use zmq_tokio::shutdown::Drain;
+
+macro_rules! t {
+    ($e:expr) => (match $e {
+        Ok(e) => e,
+        Err(e) => panic!("{} failed with {:?}", stringify!($e), e),
+    })
+}
+
+#[test]
+fn proxies_stop_on_shutdown() {
+    let mut core = t!(Core::new());
+    let handle = core.handle();
+    let (input, frontend) = t!(testing::pair(PUSH, PULL, &handle));
+    let (backend, output) = t!(testing::pair(PUSH, PULL, &handle));
+
+    let (stop_tx, stop_rx) = oneshot::channel::<()>();
+    let (done_tx, done_rx) = oneshot::channel();
+    let proxy = proxy(frontend, backend).with_shutdown(stop_rx).linger(0);
+    handle.spawn(proxy.then(move |result| {
+        let _ = done_tx.send(result.is_ok());
+        Ok(())
+    }));
+
+    t!(core.run(input.send(Message::from_slice(b"job"))));
+    let received = output.incoming_multipart().into_future().map_err(|(e, _)| e);
+    let (msgs, _) = t!(core.run(received));
+    assert_eq!(&msgs.unwrap()[0][..], b"job");
+
+    stop_tx.send(()).unwrap();
+    assert!(t!(core.run(done_rx)));
+}
+
+#[test]
+fn terminate_shuts_down_open_sockets() {
+    let mut core = t!(Core::new());
+    let handle = core.handle();
+    let ctx = Context::new();
+    let pull = t!(ctx.socket(PULL, &handle));
+    t!(pull.bind("inproc://terminate-open"));
+
+    // the task waiting on the socket fails once the context is destroyed,
+    // dropping the socket.
+    let (done_tx, done_rx) = oneshot::channel();
+    handle.spawn(future::poll_fn(move || pull.recv().poll()).then(move |result| {
+        let _ = done_tx.send(result.is_err());
+        Ok(())
+    }));
+
+    let terminate = ctx.terminate(Some(Duration::from_millis(0)));
+    let (_, failed) = t!(core.run(terminate.join(done_rx.map_err(|_| unreachable!()))));
+    assert!(failed);
+    assert!(ctx.socket(PULL, &handle).is_err());
+}
+
+#[test]
+fn closing_keeps_messages_queued_over_inproc() {
+    let mut core = t!(Core::new());
+    let handle = core.handle();
+    let (push, pull) = t!(testing::pair(PUSH, PULL, &handle));
+
+    t!(core.run(push.send(Message::from_slice(b"last"))));
+    t!(core.run(push.close(Some(Duration::from_millis(1000)))));
+
+    let msg = t!(core.run(pull.recv()));
+    assert_eq!(msg.as_str(), Some("last"));
+}
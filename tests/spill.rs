@@ -0,0 +1,37 @@
+extern crate futures;
+extern crate zmq_tokio;
+
+use std::env;
+
+use futures::{Future, Sink, Stream};
+use zmq_tokio::Message;
+use zmq_tokio::spill::spill;
+use zmq_tokio::testing;
+
+macro_rules! t {
+    ($e:expr) => (match $e {
+        Ok(e) => e,
+        Err(e) => panic!("{} failed with {:?}", stringify!($e), e),
+    })
+}
+
+#[test]
+fn spilled_messages_are_replayed_in_order() {
+    let (mut tx, rx) = testing::loopback(0);
+    for i in 0..5 {
+        tx = t!(tx.send(vec![Message::from_slice(format!("{}", i).as_bytes())]).wait());
+    }
+    drop(tx);
+
+    let path = env::temp_dir().join("zmq-tokio-spill-test");
+    let (spiller, spilled) = t!(spill(rx, &path, 2, 1024));
+    t!(spiller.wait());
+    assert_eq!(spilled.stats().spilled, 3);
+    assert_eq!(spilled.pending(), 5);
+
+    let received: Vec<String> = t!(spilled.collect().wait())
+        .into_iter()
+        .map(|msgs| msgs[0].as_str().unwrap().to_string())
+        .collect();
+    assert_eq!(received, vec!["0", "1", "2", "3", "4"]);
+}
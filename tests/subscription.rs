@@ -0,0 +1,79 @@
+extern crate futures;
+extern crate tokio_core;
+extern crate zmq_tokio;
+
+use std::io;
+use std::time::Duration;
+
+use futures::Stream;
+use tokio_core::reactor::Core;
+use zmq_tokio::{testing, XPUB, XSUB};
+use zmq_tokio::subscription::{classify, SubscriptionMessage};
+
+macro_rules! t {
+    ($e:expr) => (match $e {
+        Ok(e) => e,
+        Err(e) => panic!("{} failed with {:?}", stringify!($e), e),
+    })
+}
+
+#[test]
+fn xsub_subscriptions_reach_xpub() {
+    let mut core = t!(Core::new());
+    let (xpub, xsub) = t!(testing::pair(XPUB, XSUB, &core.handle()));
+
+    t!(core.run(xsub.send_subscription(b"topic")));
+    t!(core.run(xsub.send_unsubscription(b"topic")));
+    let incoming = classify(&xpub).take(2).collect();
+    let msgs = t!(core.run(incoming));
+    match (&msgs[0], &msgs[1]) {
+        (&SubscriptionMessage::Subscribe(ref a), &SubscriptionMessage::Unsubscribe(ref b)) => {
+            assert_eq!(&a[..], b"topic");
+            assert_eq!(&b[..], b"topic");
+        }
+        other => panic!("unexpected messages {:?}", other),
+    }
+}
+
+#[test]
+fn inverted_subscriptions_block_topics() {
+    let core = t!(Core::new());
+    let ctx = zmq_tokio::Context::new();
+    let sub = t!(ctx.socket(zmq_tokio::SUB, &core.handle()));
+    t!(sub.set_subscribe(b"debug"));
+    assert!(t!(sub.accepts_topic(b"debug.verbose")));
+    assert!(!t!(sub.accepts_topic(b"info")));
+
+    t!(sub.set_invert_matching(true));
+    assert!(t!(sub.is_invert_matching()));
+    assert!(!t!(sub.accepts_topic(b"debug.verbose")));
+    assert!(t!(sub.accepts_topic(b"info")));
+}
+
+#[test]
+fn resubscribing_keeps_subscriptions() {
+    let core = t!(Core::new());
+    let ctx = zmq_tokio::Context::new();
+    let sub = t!(ctx.socket(zmq_tokio::SUB, &core.handle()));
+    t!(sub.set_subscribe(b"a"));
+    t!(sub.set_subscribe(b"b"));
+    t!(sub.resubscribe());
+    assert_eq!(sub.subscriptions(), vec![b"a".to_vec(), b"b".to_vec()]);
+    t!(sub.set_unsubscribe(b"a"));
+    assert!(!t!(sub.accepts_topic(b"a")));
+}
+
+#[test]
+fn waiting_for_subscribers_counts_subscriptions() {
+    let mut core = t!(Core::new());
+    let handle = core.handle();
+    let (xpub, xsub) = t!(testing::pair(XPUB, XSUB, &handle));
+
+    t!(core.run(xsub.send_subscription(b"topic")));
+    let wait = t!(xpub.wait_for_subscribers(1, Duration::from_secs(1), &handle));
+    assert_eq!(t!(core.run(wait)), 1);
+
+    let wait = t!(xpub.wait_for_subscribers(1, Duration::from_millis(50), &handle));
+    let err = core.run(wait).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+}
@@ -0,0 +1,112 @@
+extern crate futures;
+extern crate tokio_core;
+extern crate zmq_tokio;
+
+use futures::{Future, Sink, Stream};
+use tokio_core::reactor::Core;
+use zmq_tokio::{PULL, PUSH};
+use zmq_tokio::sink::MultipartMessageSink;
+use zmq_tokio::stream::MessageStream;
+use zmq_tokio::testing::{self, MockSocket};
+
+macro_rules! t {
+    ($e:expr) => (match $e {
+        Ok(e) => e,
+        Err(e) => panic!("{} failed with {:?}", stringify!($e), e),
+    })
+}
+
+#[test]
+fn mock_socket_delivers_after_scripted_blocks() {
+    let socket = MockSocket::new();
+    socket.push(&["hello"]);
+    socket.block_recv(3);
+    let (msg, _) = t!(MessageStream::new(&socket).into_future().map_err(|(e, _)| e).wait());
+    assert_eq!(msg.unwrap().as_str(), Some("hello"));
+    assert_eq!(socket.pending(), 0);
+}
+
+#[test]
+fn mock_socket_records_sent_messages() {
+    let socket = MockSocket::new();
+    socket.block_send(2);
+    let msgs = vec![b"a".to_vec(), b"b".to_vec()];
+    t!(MultipartMessageSink::new(&socket).send(msgs.clone()).wait());
+    assert_eq!(socket.take_sent(), vec![msgs]);
+    assert!(socket.sent().is_empty());
+}
+
+#[test]
+fn pairs_are_connected() {
+    let mut core = t!(Core::new());
+    let handle = core.handle();
+    for &tcp in &[false, true] {
+        let (pull, push) = if tcp {
+            t!(testing::tcp_pair(PULL, PUSH, &handle))
+        } else {
+            t!(testing::pair(PULL, PUSH, &handle))
+        };
+        t!(core.run(push.send("ping")));
+        let msg = t!(core.run(pull.recv()));
+        assert_eq!(msg.as_str(), Some("ping"));
+    }
+}
+
+#[test]
+fn loopback_applies_backpressure() {
+    let (mut a, b) = testing::loopback(1);
+    let msg = || vec![zmq_tokio::Message::from_slice(b"frame")];
+
+    // the second message waits for the first one to be received.
+    a = t!(a.send(msg()).wait());
+    let mut pending = a.send(msg());
+    assert!(t!(futures::future::lazy(|| pending.poll()).wait()).is_not_ready());
+
+    let (received, b) = t!(b.into_future().map_err(|(e, _)| e).wait());
+    assert_eq!(received.unwrap().len(), 1);
+    t!(pending.wait());
+    drop(b);
+}
+
+#[test]
+fn wait_events_resolves_once_readable() {
+    use zmq_tokio::events::{READABLE, WRITABLE};
+
+    let mut core = t!(Core::new());
+    let (pull, push) = t!(testing::pair(PULL, PUSH, &core.handle()));
+    assert!(!t!(pull.events()).is_readable());
+
+    t!(core.run(push.send("ping")));
+    let events = t!(core.run(pull.wait_events(READABLE)));
+    assert!(events.contains(READABLE));
+    assert!(!events.contains(READABLE | WRITABLE));
+}
+
+#[test]
+fn merged_streams_tag_messages_with_their_socket() {
+    use zmq_tokio::select::merge_streams;
+
+    let mut core = t!(Core::new());
+    let (pull_a, push_a) = t!(testing::pair(PULL, PUSH, &core.handle()));
+    let (pull_b, push_b) = t!(testing::pair(PULL, PUSH, &core.handle()));
+    t!(core.run(push_b.send("b")));
+    t!(core.run(push_a.send("a")));
+
+    let merged = merge_streams(vec![pull_a, pull_b]).take(2).collect();
+    let mut received: Vec<(usize, String)> = t!(core.run(merged))
+        .into_iter()
+        .map(|(idx, msgs)| (idx, msgs[0].as_str().unwrap().to_string()))
+        .collect();
+    received.sort();
+    assert_eq!(received, vec![(0, "a".to_string()), (1, "b".to_string())]);
+}
+
+#[test]
+fn write_vectored_sends_frames() {
+    let mut core = t!(Core::new());
+    let (push, pull) = t!(testing::pair(PUSH, PULL, &core.handle()));
+    assert_eq!(t!(push.write_vectored(&[&b"head"[..], &b"body"[..]])), 8);
+    let (msgs, _) = t!(core.run(pull.incoming_multipart().into_future().map_err(|(e, _)| e)));
+    let frames: Vec<Vec<u8>> = msgs.unwrap().iter().map(|m| m.to_vec()).collect();
+    assert_eq!(frames, vec![b"head".to_vec(), b"body".to_vec()]);
+}
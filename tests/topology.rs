@@ -0,0 +1,50 @@
+extern crate futures;
+extern crate tokio_core;
+extern crate zmq_tokio;
+
+use futures::{Future, Stream};
+use tokio_core::reactor::Core;
+use zmq_tokio::{Context, Message};
+use zmq_tokio::config::SocketConfig;
+use zmq_tokio::topology::{ProxyConfig, Topology, TopologyConfig};
+
+macro_rules! t {
+    ($e:expr) => (match $e {
+        Ok(e) => e,
+        Err(e) => panic!("{} failed with {:?}", stringify!($e), e),
+    })
+}
+
+fn socket(typ: &str, bind: &[&str], connect: &[&str]) -> SocketConfig {
+    let mut config = SocketConfig::new(typ);
+    config.bind = bind.iter().map(|e| e.to_string()).collect();
+    config.connect = connect.iter().map(|e| e.to_string()).collect();
+    config
+}
+
+#[test]
+fn topology_proxies_between_sockets() {
+    let mut core = t!(Core::new());
+    let ctx = Context::new();
+
+    let mut config = TopologyConfig::default();
+    config.sockets.insert("frontend".into(), socket("PULL", &["inproc://topology-in"], &[]));
+    config.sockets.insert("backend".into(), socket("PUSH", &["inproc://topology-out"], &[]));
+    config.sockets.insert("producer".into(), socket("PUSH", &[], &["inproc://topology-in"]));
+    config.sockets.insert("consumer".into(), socket("PULL", &[], &["inproc://topology-out"]));
+    config.proxies.push(ProxyConfig {
+        frontend: "frontend".into(),
+        backend: "backend".into(),
+    });
+
+    let mut topology = t!(Topology::start(&config, &ctx, &core.handle()));
+    assert_eq!(topology.names(), vec!["consumer".to_string(), "producer".to_string()]);
+
+    let producer = topology.take("producer").unwrap();
+    let consumer = topology.take("consumer").unwrap();
+    t!(core.run(producer.send(Message::from_slice(b"through"))));
+    let (msgs, _) = t!(core.run(consumer.incoming_multipart().into_future().map_err(|(e, _)| e)));
+    assert_eq!(&msgs.unwrap()[0][..], b"through");
+
+    t!(core.run(topology.shutdown()));
+}
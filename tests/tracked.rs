@@ -0,0 +1,34 @@
+extern crate futures;
+extern crate tokio_core;
+extern crate zmq_tokio;
+
+use futures::{Future, Sink, Stream};
+use tokio_core::reactor::Core;
+use zmq_tokio::{Context, Message, PULL, PUSH};
+use zmq_tokio::tracked::TrackedPush;
+
+macro_rules! t {
+    ($e:expr) => (match $e {
+        Ok(e) => e,
+        Err(e) => panic!("{} failed with {:?}", stringify!($e), e),
+    })
+}
+
+#[test]
+fn tracked_push_waits_for_a_peer() {
+    let mut core = t!(Core::new());
+    let ctx = Context::new();
+
+    let push = t!(ctx.socket(PUSH, &core.handle()));
+    let tracked = t!(TrackedPush::new(&ctx, push, &core.handle()));
+    t!(tracked.get_ref().bind("tcp://127.0.0.1:*"));
+    assert_eq!(tracked.connected_peers(), 0);
+
+    let pull = t!(ctx.socket(PULL, &core.handle()));
+    t!(pull.connect(&tracked.get_ref().endpoints()[0]));
+
+    let tracked = t!(core.run(tracked.send(vec![Message::from_slice(b"first")])));
+    assert_eq!(tracked.connected_peers(), 1);
+    let (msgs, _) = t!(core.run(pull.incoming_multipart().into_future().map_err(|(e, _)| e)));
+    assert_eq!(&msgs.unwrap()[0][..], b"first");
+}
@@ -99,15 +99,22 @@
 extern crate log;
 extern crate mio;
 extern crate zmq;
+extern crate zmq_sys;
 
+use std::cell::Cell;
 use std::io;
 use std::io::{Read, Write};
 use std::fmt;
+use std::mem;
+use std::os::raw::{c_int, c_void};
 use std::os::unix::io::RawFd;
 
 use mio::unix::EventedFd;
 use mio::{PollOpt, Ready, Token};
 
+// Socket options that `zmq` doesn't wrap.
+const ZMQ_INVERT_MATCHING: c_int = 74;
+
 /// Wrapper for ØMQ context.
 #[derive(Clone, Default)]
 pub struct Context {
@@ -135,6 +142,17 @@ impl Context {
         Ok(Socket::new(try!(self.inner.socket(typ))))
     }
 
+    /// Set the number of ØMQ I/O threads. Must be called before creating
+    /// any sockets.
+    pub fn set_io_threads(&self, threads: i32) -> io::Result<()> {
+        self.inner.set_io_threads(threads).map_err(|e| e.into())
+    }
+
+    /// Get the number of ØMQ I/O threads.
+    pub fn get_io_threads(&self) -> io::Result<i32> {
+        self.inner.get_io_threads().map_err(|e| e.into())
+    }
+
     /// Try to destroy the underlying context. This is different than the destructor;
     /// the destructor will loop when zmq_ctx_destroy returns EINTR.
     pub fn destroy(&mut self) -> io::Result<()> {
@@ -150,48 +168,573 @@ impl Context {
 // mio integration, should probably be put into its own crate eventually
 /// Asynchronous ØMQ socket.
 pub struct Socket {
-    inner: zmq::Socket,
+    // `None` in the shell left behind by `detach`.
+    inner: Option<zmq::Socket>,
+    // Raw handle of `inner`, for calls that `zmq::Socket` does not wrap.
+    raw: *mut c_void,
+    // File descriptor of the detached socket, so the shell can still be
+    // deregistered.
+    detached_fd: Option<RawFd>,
+    // `ZMQ_MAXMSGSIZE`, as last set through this socket, so receives can
+    // check it without a `getsockopt`.
+    maxmsgsize: Cell<i64>,
 }
 
 impl fmt::Debug for Socket {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "Socket<{:?}>", self.inner.get_socket_type())
+        match self.inner {
+            Some(ref inner) => write!(f, "Socket<{:?}>", inner.get_socket_type()),
+            None => write!(f, "Socket<detached>"),
+        }
     }
 }
 
 impl Socket {
     /// Create a new event-wrapped ØMQ socket. Takes an existing `zmq::Socket`
     /// instance as an only argument.
-    pub fn new(socket: zmq::Socket) -> Self {
-        Socket { inner: socket }
+    pub fn new(mut socket: zmq::Socket) -> Self {
+        let raw = socket.as_mut_ptr();
+        let maxmsgsize = socket.get_maxmsgsize().unwrap_or(-1);
+        Socket {
+            inner: Some(socket),
+            raw,
+            detached_fd: None,
+            maxmsgsize: Cell::new(maxmsgsize),
+        }
+    }
+
+    /// Move the underlying ØMQ socket into a new `Socket`, leaving this one
+    /// as an empty shell.
+    ///
+    /// This is meant for deregistering the socket from a `mio::Poll` that
+    /// drops what it deregisters: the shell, which still knows the file
+    /// descriptor, is deregistered, while the returned `Socket` can be
+    /// registered again. The shell must not be used for anything else.
+    pub fn detach(&mut self) -> io::Result<Socket> {
+        let fd = try!(self.as_raw_fd());
+        let inner = match self.inner.take() {
+            Some(inner) => inner,
+            None => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Other,
+                    "socket is already detached",
+                ))
+            }
+        };
+        self.detached_fd = Some(fd);
+        Ok(Socket {
+            inner: Some(inner),
+            raw: self.raw,
+            detached_fd: None,
+            maxmsgsize: self.maxmsgsize.clone(),
+        })
     }
 
     /// Returns an `io::Result` with the raw socket file-descriptor.
     pub fn as_raw_fd(&self) -> io::Result<RawFd> {
-        let fd = try!(self.inner.get_fd());
+        let fd = match self.inner {
+            Some(ref inner) => try!(inner.get_fd()),
+            None => self.detached_fd.expect("detached socket without a file descriptor"),
+        };
         trace!("socket raw FD: {}", fd);
         Ok(fd)
     }
 
     /// Returns a reference to the underlying `zmq::Socket`.
     /// Useful for setting socket options at runtime.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the socket was detached.
     pub fn get_ref(&self) -> &zmq::Socket {
-        &self.inner
+        self.inner.as_ref().expect("socket is detached")
+    }
+
+    /// Consumes the socket, returning the underlying `zmq::Socket`. Fails
+    /// on the shell left by `detach`.
+    pub fn into_inner(mut self) -> io::Result<zmq::Socket> {
+        self.inner.take().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::Other, "socket is detached")
+        })
+    }
+
+    /// Returns the raw `void *` handle of the underlying libzmq socket, for
+    /// passing to C code. The handle is only valid while this socket lives.
+    pub fn as_raw_socket(&self) -> *mut c_void {
+        self.raw
     }
 
     /// Bind the socket to the given address.
     pub fn bind(&self, address: &str) -> io::Result<()> {
-        self.inner.bind(address).map_err(|e| e.into())
+        self.get_ref().bind(address).map_err(|e| e.into())
     }
 
     /// Connect the socket to the given address.
     pub fn connect(&self, address: &str) -> io::Result<()> {
-        self.inner.connect(address).map_err(|e| e.into())
+        self.get_ref().connect(address).map_err(|e| e.into())
+    }
+
+    /// Unbind the socket from the given endpoint.
+    pub fn unbind(&self, endpoint: &str) -> io::Result<()> {
+        self.get_ref().unbind(endpoint).map_err(|e| e.into())
+    }
+
+    /// Get the last endpoint the socket was bound or connected to.
+    ///
+    /// If the endpoint is not valid UTF-8, it is returned as the
+    /// original `Vec` in the `Err` part of the inner result.
+    pub fn get_last_endpoint(&self) -> io::Result<Result<String, Vec<u8>>> {
+        self.get_ref().get_last_endpoint().map_err(|e| e.into())
+    }
+
+    /// Set the identity of the socket, as seen by `ROUTER` peers.
+    pub fn set_identity(&self, identity: &[u8]) -> io::Result<()> {
+        self.get_ref().set_identity(identity).map_err(|e| e.into())
+    }
+
+    /// Get the identity of the socket.
+    pub fn get_identity(&self) -> io::Result<Vec<u8>> {
+        self.get_ref().get_identity().map_err(|e| e.into())
+    }
+
+    /// Make a `ROUTER` socket report unroutable messages as errors,
+    /// instead of silently dropping them.
+    pub fn set_router_mandatory(&self, value: bool) -> io::Result<()> {
+        self.get_ref().set_router_mandatory(value).map_err(|e| e.into())
+    }
+
+    /// Enable or disable IPv6 on the socket.
+    pub fn set_ipv6(&self, value: bool) -> io::Result<()> {
+        self.get_ref().set_ipv6(value).map_err(|e| e.into())
+    }
+
+    /// Return true if IPv6 is enabled on the socket.
+    pub fn is_ipv6(&self) -> io::Result<bool> {
+        self.get_ref().is_ipv6().map_err(|e| e.into())
+    }
+
+    /// Set the linger period of the socket, in milliseconds. A value of `-1`
+    /// means an infinite period.
+    pub fn set_linger(&self, value: i32) -> io::Result<()> {
+        self.get_ref().set_linger(value).map_err(|e| e.into())
+    }
+
+    /// Get the linger period of the socket, in milliseconds.
+    pub fn get_linger(&self) -> io::Result<i32> {
+        self.get_ref().get_linger().map_err(|e| e.into())
+    }
+
+    /// Set the interval between ZMTP heartbeats sent by the socket, in milliseconds.
+    /// A value of `0` disables heartbeats.
+    pub fn set_heartbeat_ivl(&self, value: i32) -> io::Result<()> {
+        self.get_ref().set_heartbeat_ivl(value).map_err(|e| e.into())
+    }
+
+    /// Get the interval between ZMTP heartbeats sent by the socket, in milliseconds.
+    pub fn get_heartbeat_ivl(&self) -> io::Result<i32> {
+        self.get_ref().get_heartbeat_ivl().map_err(|e| e.into())
+    }
+
+    /// Set how long the socket waits for traffic after sending a ZMTP heartbeat,
+    /// before timing out the connection, in milliseconds.
+    pub fn set_heartbeat_timeout(&self, value: i32) -> io::Result<()> {
+        self.get_ref().set_heartbeat_timeout(value).map_err(|e| e.into())
+    }
+
+    /// Get the ZMTP heartbeat timeout of the socket, in milliseconds.
+    pub fn get_heartbeat_timeout(&self) -> io::Result<i32> {
+        self.get_ref().get_heartbeat_timeout().map_err(|e| e.into())
+    }
+
+    /// Set the time-to-live advertised to the peers of the socket, in milliseconds.
+    /// Peers time out the connection if they receive no traffic within this period.
+    pub fn set_heartbeat_ttl(&self, value: i32) -> io::Result<()> {
+        self.get_ref().set_heartbeat_ttl(value).map_err(|e| e.into())
+    }
+
+    /// Get the ZMTP heartbeat time-to-live of the socket, in milliseconds.
+    pub fn get_heartbeat_ttl(&self) -> io::Result<i32> {
+        self.get_ref().get_heartbeat_ttl().map_err(|e| e.into())
+    }
+
+    /// Set the initial interval the socket waits before reconnecting, in milliseconds.
+    /// A value of `-1` disables reconnection.
+    pub fn set_reconnect_ivl(&self, value: i32) -> io::Result<()> {
+        self.get_ref().set_reconnect_ivl(value).map_err(|e| e.into())
+    }
+
+    /// Get the initial interval the socket waits before reconnecting, in milliseconds.
+    pub fn get_reconnect_ivl(&self) -> io::Result<i32> {
+        self.get_ref().get_reconnect_ivl().map_err(|e| e.into())
+    }
+
+    /// Set the maximum interval the socket waits before reconnecting, in milliseconds.
+    /// The interval doubles after each failed attempt, up to this maximum. A value of `0` keeps it fixed.
+    pub fn set_reconnect_ivl_max(&self, value: i32) -> io::Result<()> {
+        self.get_ref().set_reconnect_ivl_max(value).map_err(|e| e.into())
+    }
+
+    /// Get the maximum interval the socket waits before reconnecting, in milliseconds.
+    pub fn get_reconnect_ivl_max(&self) -> io::Result<i32> {
+        self.get_ref().get_reconnect_ivl_max().map_err(|e| e.into())
+    }
+
+    /// Start monitoring the socket, publishing the selected `events` on a
+    /// `PAIR` socket bound to the given `inproc` endpoint.
+    pub fn monitor(&self, endpoint: &str, events: i32) -> io::Result<()> {
+        self.get_ref().monitor(endpoint, events).map_err(|e| e.into())
+    }
+
+    /// Get the `ZMQ_EVENTS` of the socket, telling whether it is currently
+    /// readable or writable.
+    pub fn get_events(&self) -> io::Result<zmq::PollEvents> {
+        self.get_ref().get_events().map_err(|e| e.into())
+    }
+
+    /// Set whether the socket acts as a server for the PLAIN security mechanism.
+    pub fn set_plain_server(&self, value: bool) -> io::Result<()> {
+        self.get_ref().set_plain_server(value).map_err(|e| e.into())
+    }
+
+    /// Returns true if the socket acts as a server for the PLAIN security mechanism.
+    pub fn is_plain_server(&self) -> io::Result<bool> {
+        self.get_ref().is_plain_server().map_err(|e| e.into())
+    }
+
+    /// Set the username used by the socket for the PLAIN security mechanism.
+    /// Setting `None` resets the socket to the NULL mechanism.
+    pub fn set_plain_username(&self, value: Option<&str>) -> io::Result<()> {
+        self.get_ref().set_plain_username(value).map_err(|e| e.into())
+    }
+
+    /// Get the username used by the socket for the PLAIN security mechanism.
+    pub fn get_plain_username(&self) -> io::Result<Result<String, Vec<u8>>> {
+        self.get_ref().get_plain_username().map_err(|e| e.into())
+    }
+
+    /// Set the password used by the socket for the PLAIN security mechanism.
+    /// Setting `None` resets the socket to the NULL mechanism.
+    pub fn set_plain_password(&self, value: Option<&str>) -> io::Result<()> {
+        self.get_ref().set_plain_password(value).map_err(|e| e.into())
+    }
+
+    /// Get the password used by the socket for the PLAIN security mechanism.
+    pub fn get_plain_password(&self) -> io::Result<Result<String, Vec<u8>>> {
+        self.get_ref().get_plain_password().map_err(|e| e.into())
+    }
+
+    /// Set the ZAP domain of the socket, passed to the authenticator in every request.
+    pub fn set_zap_domain(&self, value: &str) -> io::Result<()> {
+        self.get_ref().set_zap_domain(value).map_err(|e| e.into())
+    }
+
+    /// Get the ZAP domain of the socket.
+    pub fn get_zap_domain(&self) -> io::Result<Result<String, Vec<u8>>> {
+        self.get_ref().get_zap_domain().map_err(|e| e.into())
+    }
+
+    /// Set whether the socket acts as a server for the GSSAPI security mechanism.
+    pub fn set_gssapi_server(&self, value: bool) -> io::Result<()> {
+        self.get_ref().set_gssapi_server(value).map_err(|e| e.into())
+    }
+
+    /// Returns true if the socket acts as a server for the GSSAPI security mechanism.
+    pub fn is_gssapi_server(&self) -> io::Result<bool> {
+        self.get_ref().is_gssapi_server().map_err(|e| e.into())
+    }
+
+    /// Set the name of the principal for which GSSAPI credentials of the socket are retrieved.
+    pub fn set_gssapi_principal(&self, value: &str) -> io::Result<()> {
+        self.get_ref().set_gssapi_principal(value).map_err(|e| e.into())
+    }
+
+    /// Get the name of the principal for which GSSAPI credentials of the socket are retrieved.
+    pub fn get_gssapi_principal(&self) -> io::Result<Result<String, Vec<u8>>> {
+        self.get_ref().get_gssapi_principal().map_err(|e| e.into())
+    }
+
+    /// Set the name of the principal of the GSSAPI server the socket connects to.
+    pub fn set_gssapi_service_principal(&self, value: &str) -> io::Result<()> {
+        self.get_ref().set_gssapi_service_principal(value).map_err(|e| e.into())
+    }
+
+    /// Get the name of the principal of the GSSAPI server the socket connects to.
+    pub fn get_gssapi_service_principal(&self) -> io::Result<Result<String, Vec<u8>>> {
+        self.get_ref().get_gssapi_service_principal().map_err(|e| e.into())
+    }
+
+    /// Set whether the socket disables the encryption of GSSAPI connections.
+    pub fn set_gssapi_plaintext(&self, value: bool) -> io::Result<()> {
+        self.get_ref().set_gssapi_plaintext(value).map_err(|e| e.into())
+    }
+
+    /// Returns true if the socket disables the encryption of GSSAPI connections.
+    pub fn is_gssapi_plaintext(&self) -> io::Result<bool> {
+        self.get_ref().is_gssapi_plaintext().map_err(|e| e.into())
+    }
+
+    /// Set whether the socket acts as a server for the CURVE security mechanism.
+    pub fn set_curve_server(&self, value: bool) -> io::Result<()> {
+        self.get_ref().set_curve_server(value).map_err(|e| e.into())
+    }
+
+    /// Returns true if the socket acts as a server for the CURVE security mechanism.
+    pub fn is_curve_server(&self) -> io::Result<bool> {
+        self.get_ref().is_curve_server().map_err(|e| e.into())
+    }
+
+    /// Set the long-term public key of the socket, for the CURVE security mechanism.
+    pub fn set_curve_publickey(&self, value: &[u8]) -> io::Result<()> {
+        self.get_ref().set_curve_publickey(value).map_err(|e| e.into())
+    }
+
+    /// Get the long-term public key of the socket, for the CURVE security mechanism.
+    pub fn get_curve_publickey(&self) -> io::Result<Vec<u8>> {
+        self.get_ref().get_curve_publickey().map_err(|e| e.into())
+    }
+
+    /// Set the long-term secret key of the socket, for the CURVE security mechanism.
+    pub fn set_curve_secretkey(&self, value: &[u8]) -> io::Result<()> {
+        self.get_ref().set_curve_secretkey(value).map_err(|e| e.into())
+    }
+
+    /// Get the long-term secret key of the socket, for the CURVE security mechanism.
+    pub fn get_curve_secretkey(&self) -> io::Result<Vec<u8>> {
+        self.get_ref().get_curve_secretkey().map_err(|e| e.into())
+    }
+
+    /// Set the long-term public key of the server the socket connects to, for the CURVE security mechanism.
+    pub fn set_curve_serverkey(&self, value: &[u8]) -> io::Result<()> {
+        self.get_ref().set_curve_serverkey(value).map_err(|e| e.into())
+    }
+
+    /// Get the long-term public key of the server the socket connects to, for the CURVE security mechanism.
+    pub fn get_curve_serverkey(&self) -> io::Result<Vec<u8>> {
+        self.get_ref().get_curve_serverkey().map_err(|e| e.into())
+    }
+
+    /// Set the high-water mark for outbound messages of the socket.
+    pub fn set_sndhwm(&self, value: i32) -> io::Result<()> {
+        self.get_ref().set_sndhwm(value).map_err(|e| e.into())
+    }
+
+    /// Get the high-water mark for outbound messages of the socket.
+    pub fn get_sndhwm(&self) -> io::Result<i32> {
+        self.get_ref().get_sndhwm().map_err(|e| e.into())
+    }
+
+    /// Set the high-water mark for inbound messages of the socket.
+    pub fn set_rcvhwm(&self, value: i32) -> io::Result<()> {
+        self.get_ref().set_rcvhwm(value).map_err(|e| e.into())
+    }
+
+    /// Get the high-water mark for inbound messages of the socket.
+    pub fn get_rcvhwm(&self) -> io::Result<i32> {
+        self.get_ref().get_rcvhwm().map_err(|e| e.into())
+    }
+
+    /// Get the security mechanism of the socket.
+    pub fn get_mechanism(&self) -> io::Result<zmq::Mechanism> {
+        self.get_ref().get_mechanism().map_err(|e| e.into())
+    }
+
+    /// Set whether the socket inverts the matching of subscriptions. Inverted
+    /// `SUB` sockets receive the messages matching none of their
+    /// subscriptions, and inverted `PUB` and `XPUB` sockets send messages to
+    /// the subscribers with no matching subscription. Every `PUB` and `SUB`
+    /// socket of a topology must agree on this option.
+    pub fn set_invert_matching(&self, value: bool) -> io::Result<()> {
+        self.set_int_option(ZMQ_INVERT_MATCHING, value as c_int)
+    }
+
+    /// Returns true if the socket inverts the matching of subscriptions.
+    pub fn is_invert_matching(&self) -> io::Result<bool> {
+        self.get_int_option(ZMQ_INVERT_MATCHING).map(|value| value != 0)
+    }
+
+    /// Set whether the socket keeps only the last message in its queues, `ZMQ_CONFLATE`.
+    pub fn set_conflate(&self, value: bool) -> io::Result<()> {
+        self.get_ref().set_conflate(value).map_err(|e| e.into())
+    }
+
+    /// Returns true if the socket keeps only the last message in its queues.
+    pub fn is_conflate(&self) -> io::Result<bool> {
+        self.get_ref().is_conflate().map_err(|e| e.into())
+    }
+
+    /// Set the timeout of blocking receives on the socket, in milliseconds, `ZMQ_RCVTIMEO`.
+    pub fn set_rcvtimeo(&self, value: i32) -> io::Result<()> {
+        self.get_ref().set_rcvtimeo(value).map_err(|e| e.into())
+    }
+
+    /// Get the timeout of blocking receives on the socket, in milliseconds.
+    pub fn get_rcvtimeo(&self) -> io::Result<i32> {
+        self.get_ref().get_rcvtimeo().map_err(|e| e.into())
+    }
+
+    /// Set the timeout of blocking sends on the socket, in milliseconds, `ZMQ_SNDTIMEO`.
+    pub fn set_sndtimeo(&self, value: i32) -> io::Result<()> {
+        self.get_ref().set_sndtimeo(value).map_err(|e| e.into())
+    }
+
+    /// Get the timeout of blocking sends on the socket, in milliseconds.
+    pub fn get_sndtimeo(&self) -> io::Result<i32> {
+        self.get_ref().get_sndtimeo().map_err(|e| e.into())
+    }
+
+    /// Set whether the socket passes every subscription message upstream, instead of only new ones, `ZMQ_XPUB_VERBOSE`.
+    pub fn set_xpub_verbose(&self, value: bool) -> io::Result<()> {
+        self.get_ref().set_xpub_verbose(value).map_err(|e| e.into())
+    }
+
+    /// Set the largest message the socket accepts, in bytes, `ZMQ_MAXMSGSIZE`. Peers sending larger messages are disconnected. `-1` means no limit.
+    pub fn set_maxmsgsize(&self, value: i64) -> io::Result<()> {
+        try!(self.get_ref().set_maxmsgsize(value));
+        self.maxmsgsize.set(value);
+        Ok(())
+    }
+
+    /// Get the largest message the socket accepts, in bytes.
+    pub fn get_maxmsgsize(&self) -> io::Result<i64> {
+        self.get_ref().get_maxmsgsize().map_err(|e| e.into())
+    }
+
+    /// The largest message the socket accepts, in bytes, as set with
+    /// `set_maxmsgsize`, or as found when the socket was wrapped. Unlike
+    /// `get_maxmsgsize`, this doesn't call into ØMQ, so it misses changes
+    /// made through `get_ref`.
+    pub fn cached_maxmsgsize(&self) -> i64 {
+        self.maxmsgsize.get()
+    }
+
+    /// Set whether the socket enables `SO_KEEPALIVE` on its TCP connections, `ZMQ_TCP_KEEPALIVE`: `1` to enable, `0` to disable, `-1` for the OS default.
+    pub fn set_tcp_keepalive(&self, value: i32) -> io::Result<()> {
+        self.get_ref().set_tcp_keepalive(value).map_err(|e| e.into())
+    }
+
+    /// Get whether the socket enables `SO_KEEPALIVE` on its TCP connections.
+    pub fn get_tcp_keepalive(&self) -> io::Result<i32> {
+        self.get_ref().get_tcp_keepalive().map_err(|e| e.into())
+    }
+
+    /// Set the idle time before the socket sends TCP keepalive probes, in seconds, `ZMQ_TCP_KEEPALIVE_IDLE`. `-1` is the OS default.
+    pub fn set_tcp_keepalive_idle(&self, value: i32) -> io::Result<()> {
+        self.get_ref().set_tcp_keepalive_idle(value).map_err(|e| e.into())
+    }
+
+    /// Get the idle time before the socket sends TCP keepalive probes, in seconds.
+    pub fn get_tcp_keepalive_idle(&self) -> io::Result<i32> {
+        self.get_ref().get_tcp_keepalive_idle().map_err(|e| e.into())
+    }
+
+    /// Set the number of unanswered TCP keepalive probes after which the socket drops a connection, `ZMQ_TCP_KEEPALIVE_CNT`. `-1` is the OS default.
+    pub fn set_tcp_keepalive_cnt(&self, value: i32) -> io::Result<()> {
+        self.get_ref().set_tcp_keepalive_cnt(value).map_err(|e| e.into())
+    }
+
+    /// Get the number of unanswered TCP keepalive probes after which the socket drops a connection.
+    pub fn get_tcp_keepalive_cnt(&self) -> io::Result<i32> {
+        self.get_ref().get_tcp_keepalive_cnt().map_err(|e| e.into())
+    }
+
+    /// Set the interval between the TCP keepalive probes of the socket, in seconds, `ZMQ_TCP_KEEPALIVE_INTVL`. `-1` is the OS default.
+    pub fn set_tcp_keepalive_intvl(&self, value: i32) -> io::Result<()> {
+        self.get_ref().set_tcp_keepalive_intvl(value).map_err(|e| e.into())
+    }
+
+    /// Get the interval between the TCP keepalive probes of the socket, in seconds.
+    pub fn get_tcp_keepalive_intvl(&self) -> io::Result<i32> {
+        self.get_ref().get_tcp_keepalive_intvl().map_err(|e| e.into())
+    }
+
+    /// Set the type-of-service field of the IP packets sent by the socket, such as a DSCP marking, `ZMQ_TOS`.
+    pub fn set_tos(&self, value: i32) -> io::Result<()> {
+        self.get_ref().set_tos(value).map_err(|e| e.into())
+    }
+
+    /// Get the type-of-service field of the IP packets sent by the socket.
+    pub fn get_tos(&self) -> io::Result<i32> {
+        self.get_ref().get_tos().map_err(|e| e.into())
+    }
+
+    /// Set whether the socket only queues messages for completed connections, `ZMQ_IMMEDIATE`, so that messages are not queued for peers that are not connected yet.
+    pub fn set_immediate(&self, value: bool) -> io::Result<()> {
+        self.get_ref().set_immediate(value).map_err(|e| e.into())
+    }
+
+    /// Return true if the socket only queues messages for completed connections.
+    pub fn is_immediate(&self) -> io::Result<bool> {
+        self.get_ref().is_immediate().map_err(|e| e.into())
+    }
+
+    /// Make the socket send an empty message to every `ROUTER` peer it connects to, `ZMQ_PROBE_ROUTER`, so the peer learns its routing id.
+    pub fn set_probe_router(&self, value: bool) -> io::Result<()> {
+        self.get_ref().set_probe_router(value).map_err(|e| e.into())
+    }
+
+    /// Make a `ROUTER` socket hand the routing id of a peer over to a new connection with the same id, instead of refusing it, `ZMQ_ROUTER_HANDOVER`.
+    pub fn set_router_handover(&self, value: bool) -> io::Result<()> {
+        self.get_ref().set_router_handover(value).map_err(|e| e.into())
+    }
+
+    /// Make a `REQ` socket accept a new request before the reply to the previous one arrived, `ZMQ_REQ_RELAXED`.
+    pub fn set_req_relaxed(&self, value: bool) -> io::Result<()> {
+        self.get_ref().set_req_relaxed(value).map_err(|e| e.into())
+    }
+
+    /// Make a `REQ` socket tag its requests with an id, and drop the replies that do not match the last request, `ZMQ_REQ_CORRELATE`.
+    pub fn set_req_correlate(&self, value: bool) -> io::Result<()> {
+        self.get_ref().set_req_correlate(value).map_err(|e| e.into())
+    }
+
+    /// Set the maximum send rate of the socket over multicast transports, in kilobits per second, `ZMQ_RATE`.
+    pub fn set_rate(&self, value: i32) -> io::Result<()> {
+        self.get_ref().set_rate(value).map_err(|e| e.into())
+    }
+
+    /// Get the maximum send rate of the socket over multicast transports, in kilobits per second.
+    pub fn get_rate(&self) -> io::Result<i32> {
+        self.get_ref().get_rate().map_err(|e| e.into())
+    }
+
+    /// Set how long the socket keeps multicast data for receivers that recover from a loss, in milliseconds, `ZMQ_RECOVERY_IVL`.
+    pub fn set_recovery_ivl(&self, value: i32) -> io::Result<()> {
+        self.get_ref().set_recovery_ivl(value).map_err(|e| e.into())
+    }
+
+    /// Get how long the socket keeps multicast data for receivers that recover from a loss, in milliseconds.
+    pub fn get_recovery_ivl(&self) -> io::Result<i32> {
+        self.get_ref().get_recovery_ivl().map_err(|e| e.into())
+    }
+
+    /// Set the time-to-live of the multicast packets sent by the socket, in hops, `ZMQ_MULTICAST_HOPS`.
+    pub fn set_multicast_hops(&self, value: i32) -> io::Result<()> {
+        self.get_ref().set_multicast_hops(value).map_err(|e| e.into())
+    }
+
+    /// Get the time-to-live of the multicast packets sent by the socket, in hops.
+    pub fn get_multicast_hops(&self) -> io::Result<i32> {
+        self.get_ref().get_multicast_hops().map_err(|e| e.into())
+    }
+
+    /// Set the largest transport data unit the socket sends over multicast transports, in bytes, `ZMQ_MULTICAST_MAXTPDU`.
+    pub fn set_multicast_maxtpdu(&self, value: i32) -> io::Result<()> {
+        self.get_ref().set_multicast_maxtpdu(value).map_err(|e| e.into())
+    }
+
+    /// Get the largest transport data unit the socket sends over multicast transports, in bytes.
+    pub fn get_multicast_maxtpdu(&self) -> io::Result<i32> {
+        self.get_ref().get_multicast_maxtpdu().map_err(|e| e.into())
     }
 
     /// Subscribe this socket to the given `prefix`.
     pub fn set_subscribe(&self, prefix: &[u8]) -> io::Result<()> {
-        self.inner.set_subscribe(prefix).map_err(|e| e.into())
+        self.get_ref().set_subscribe(prefix).map_err(|e| e.into())
+    }
+
+    /// Unsubscribe this socket from the given `prefix`.
+    pub fn set_unsubscribe(&self, prefix: &[u8]) -> io::Result<()> {
+        self.get_ref().set_unsubscribe(prefix).map_err(|e| e.into())
     }
 
     /// Send a message.
@@ -208,7 +751,7 @@ impl Socket {
     where
         T: zmq::Sendable,
     {
-        let r = self.inner.send(item, zmq::DONTWAIT | flags).map_err(|e| e.into());
+        let r = self.get_ref().send(item, zmq::DONTWAIT | flags).map_err(|e| e.into());
         r
     }
 
@@ -229,15 +772,53 @@ impl Socket {
         I: IntoIterator<Item = T>,
         T: Into<zmq::Message>,
     {
-        let r = self.inner
+        let r = self.get_ref()
             .send_multipart(iter, zmq::DONTWAIT | flags)
             .map_err(|e| e.into());
         r
     }
 
+    /// Send a message without copying its payload. The message takes
+    /// ownership of `data`, which is dropped once ØMQ is done with it.
+    ///
+    /// This is cheap for reference-counted buffers, such as `Arc<[u8]>`,
+    /// since sending the same payload on several sockets only clones the
+    /// reference, never the bytes.
+    ///
+    /// Any flags set will be combined with `zmq::DONTWAIT`, which is
+    /// needed for non-blocking mode. The internal `zmq::Error::EAGAIN`
+    /// is automatically translated to `io::ErrorKind::WouldBlock`,
+    /// which you MUST handle without failing. Note that `data` is dropped
+    /// when sending fails.
+    pub fn send_zero_copy<T>(&self, data: T, flags: i32) -> io::Result<()>
+    where
+        T: AsRef<[u8]> + Send + 'static,
+    {
+        let data = Box::new(data);
+        let (ptr, len) = {
+            let bytes = (*data).as_ref();
+            (bytes.as_ptr() as *mut c_void, bytes.len())
+        };
+        let hint = Box::into_raw(data) as *mut c_void;
+        unsafe {
+            let mut msg: zmq_sys::zmq_msg_t = mem::zeroed();
+            let rc = zmq_sys::zmq_msg_init_data(&mut msg, ptr, len, Some(free_boxed::<T>), hint);
+            if rc == -1 {
+                drop(Box::from_raw(hint as *mut T));
+                return Err(last_error());
+            }
+            if zmq_sys::zmq_msg_send(&mut msg, self.raw, zmq::DONTWAIT | flags) == -1 {
+                let e = last_error();
+                zmq_sys::zmq_msg_close(&mut msg);
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+
     /// Return true if there are more frames of a multipart message to receive.
     pub fn get_rcvmore(&self) -> io::Result<bool> {
-        let r = self.inner.get_rcvmore().map_err(|e| e.into());
+        let r = self.get_ref().get_rcvmore().map_err(|e| e.into());
         r
     }
 
@@ -252,7 +833,7 @@ impl Socket {
     /// is automatically translated to `io::ErrorKind::WouldBlock`,
     /// which you MUST handle without failing.
     pub fn recv(&self, msg: &mut zmq::Message, flags: i32) -> io::Result<()> {
-        let r = self.inner
+        let r = self.get_ref()
             .recv(msg, zmq::DONTWAIT | flags)
             .map_err(|e| e.into());
         r
@@ -267,7 +848,7 @@ impl Socket {
     /// is automatically translated to `io::ErrorKind::WouldBlock`,
     /// which you MUST handle without failing.
     pub fn recv_into(&self, msg: &mut [u8], flags: i32) -> io::Result<usize> {
-        let r = self.inner
+        let r = self.get_ref()
             .recv_into(msg, zmq::DONTWAIT | flags)
             .map_err(|e| e.into());
         r
@@ -280,7 +861,7 @@ impl Socket {
     /// is automatically translated to `io::ErrorKind::WouldBlock`,
     /// which you MUST handle without failing.
     pub fn recv_msg(&self, flags: i32) -> io::Result<zmq::Message> {
-        let r = self.inner
+        let r = self.get_ref()
             .recv_msg(zmq::DONTWAIT | flags)
             .map_err(|e| e.into());
         r
@@ -293,7 +874,7 @@ impl Socket {
     /// is automatically translated to `io::ErrorKind::WouldBlock`,
     /// which you MUST handle without failing.
     pub fn recv_bytes(&self, flags: i32) -> io::Result<Vec<u8>> {
-        let r = self.inner
+        let r = self.get_ref()
             .recv_bytes(zmq::DONTWAIT | flags)
             .map_err(|e| e.into());
         r
@@ -309,7 +890,7 @@ impl Socket {
     /// is automatically translated to `io::ErrorKind::WouldBlock`,
     /// which you MUST handle without failing.
     pub fn recv_string(&self, flags: i32) -> io::Result<Result<String, Vec<u8>>> {
-        let r = self.inner
+        let r = self.get_ref()
             .recv_string(zmq::DONTWAIT | flags)
             .map_err(|e| e.into());
         r
@@ -326,7 +907,7 @@ impl Socket {
     /// is automatically translated to `io::ErrorKind::WouldBlock`,
     /// which you MUST handle without failing.
     pub fn recv_multipart(&self, flags: i32) -> io::Result<Vec<Vec<u8>>> {
-        let r = self.inner
+        let r = self.get_ref()
             .recv_multipart(zmq::DONTWAIT | flags)
             .map_err(|e| e.into());
         r
@@ -341,6 +922,58 @@ impl Socket {
 
 unsafe impl Send for Socket {}
 
+impl Socket {
+    // Sets an integer option with `zmq_setsockopt`.
+    fn set_int_option(&self, option: c_int, value: c_int) -> io::Result<()> {
+        set_int_sockopt(self.raw, option, value)
+    }
+
+    // Gets an integer option with `zmq_getsockopt`.
+    fn get_int_option(&self, option: c_int) -> io::Result<c_int> {
+        let mut value: c_int = 0;
+        let mut size = mem::size_of::<c_int>();
+        let rc = unsafe {
+            zmq_sys::zmq_getsockopt(
+                self.raw,
+                option,
+                &mut value as *mut c_int as *mut c_void,
+                &mut size,
+            )
+        };
+        if rc == -1 {
+            return Err(last_error());
+        }
+        Ok(value)
+    }
+}
+
+// Free function for messages created by `Socket::send_zero_copy`.
+unsafe extern "C" fn free_boxed<T>(_data: *mut c_void, hint: *mut c_void) {
+    drop(Box::from_raw(hint as *mut T));
+}
+
+// Sets an integer option on a raw socket handle with `zmq_setsockopt`.
+fn set_int_sockopt(raw: *mut c_void, option: c_int, value: c_int) -> io::Result<()> {
+    let rc = unsafe {
+        zmq_sys::zmq_setsockopt(
+            raw,
+            option,
+            &value as *const c_int as *const c_void,
+            mem::size_of::<c_int>(),
+        )
+    };
+    if rc == -1 {
+        return Err(last_error());
+    }
+    Ok(())
+}
+
+// Returns the last ØMQ error of the calling thread.
+fn last_error() -> io::Error {
+    let errno = unsafe { zmq_sys::zmq_errno() };
+    zmq::Error::from_raw(errno).into()
+}
+
 /// This implementation is meant for asynchronous `Read`. It might fail
 /// if not handled via polling.
 impl Read for Socket {
@@ -420,6 +1053,20 @@ mod tests {
         (bound, connected)
     }
 
+    #[test]
+    fn detached_sockets_stay_open() {
+        let (mut receiver, mut sender) = get_async_test_pair();
+        let mut detached = sender.detach().unwrap();
+        drop(sender);
+
+        let sent = detached.write(TEST_STR).unwrap();
+        assert_eq!(sent, TEST_STR.len());
+
+        let mut buf = vec![0; TEST_BUFFER_SIZE];
+        let recvd = receiver.read(&mut buf).unwrap();
+        assert_eq!(&buf[..recvd], TEST_STR);
+    }
+
     #[test]
     fn socket_sends_and_receives_a_byte_buffer() {
         let (mut receiver, mut sender) = get_async_test_pair();